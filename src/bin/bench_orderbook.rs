@@ -35,12 +35,35 @@ mod parser {
             qty: i64,
             maker_order_id: Option<u64>,
         },
+        TradingStatus {},
+        Level {
+            instr: u32,
+            side: Side,
+            level: u8,
+            px: i64,
+            qty: i64,
+            orders: i32,
+            action: LevelAction,
+            implied: bool,
+            #[allow(dead_code)] // set by the orderbook tests, never read by the book
+            exch_ts_ns: Option<u64>,
+        },
         Heartbeat,
     }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LevelAction {
+        New,
+        Change,
+        Delete,
+        DeleteThru,
+        DeleteFrom,
+        Overlay,
+    }
 }
 
 use crate::orderbook::OrderBook;
-use crate::parser::{Event, Side};
+use crate::parser::{Event, LevelAction, Side};
 
 fn parse_arg_usize(args: &[String], idx: usize, default: usize) -> usize {
     args.get(idx)
@@ -148,6 +171,27 @@ fn main() {
     // Exercise rarely-used variants once to ensure full enum coverage in this bench
     // without impacting the measured hot path.
     book.apply(&Event::Heartbeat);
+    book.apply(&Event::TradingStatus {});
+    for action in [
+        LevelAction::New,
+        LevelAction::Change,
+        LevelAction::Overlay,
+        LevelAction::Delete,
+        LevelAction::DeleteThru,
+        LevelAction::DeleteFrom,
+    ] {
+        book.apply(&Event::Level {
+            instr: 0,
+            side: Side::Bid,
+            level: 1,
+            px: 100,
+            qty: 1,
+            orders: 1,
+            action,
+            implied: false,
+            exch_ts_ns: None,
+        });
+    }
     let _ = book.apply(&Event::Trade {
         instr: 0,
         qty: 1,
@@ -2,14 +2,30 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread;
 
+#[path = "../chaos.rs"]
+mod chaos;
+#[allow(dead_code)] // merge control payloads only
+#[path = "../codec_raw.rs"]
+mod codec_raw;
 #[path = "../config.rs"]
 mod config;
+#[path = "../decode_stats.rs"]
+mod decode_stats;
+#[allow(dead_code)] // snapshot channel templates only
 #[path = "../decoder_eobi.rs"]
 mod decoder_eobi;
 #[path = "../decoder_fast.rs"]
 mod decoder_fast;
 #[path = "../decoder_itch.rs"]
 mod decoder_itch;
+#[path = "../decoder_mdp3.rs"]
+mod decoder_mdp3;
+#[path = "../decoder_pitch.rs"]
+mod decoder_pitch;
+#[path = "../dup_verify.rs"]
+mod dup_verify;
+#[path = "../line_race.rs"]
+mod line_race;
 #[path = "../merge.rs"]
 mod merge;
 #[path = "../metrics.rs"]
@@ -22,8 +38,12 @@ mod parser;
 mod pool;
 #[path = "../recovery.rs"]
 mod recovery;
+#[path = "../recovery_journal.rs"]
+mod recovery_journal;
 #[path = "../rx.rs"]
 mod rx;
+#[path = "../rx_uring.rs"]
+mod rx_uring;
 #[path = "../spsc.rs"]
 mod spsc;
 #[path = "../util.rs"]
@@ -44,6 +64,14 @@ fn main() -> anyhow::Result<()> {
         .nth(1)
         .map(PathBuf::from)
         .unwrap_or_else(|| PathBuf::from("config.toml"));
+    // Decode every Nth packet to sample exchange -> RX latency (0 = off). PITCH stamps
+    // depend on the last Time message decoded, so they are exact only with 1.
+    let exch_sample_every: u64 = match std::env::args().nth(2) {
+        Some(n) => n
+            .parse()
+            .map_err(|_| anyhow::anyhow!("exchange latency sample rate must be a number"))?,
+        None => 0,
+    };
     let cfg = AppConfig::from_file(&cfg_path)?;
 
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
@@ -61,10 +89,18 @@ fn main() -> anyhow::Result<()> {
     // Touch metrics that are otherwise unused in this minimal bin
     metrics::inc_decode_msgs(0);
     metrics::set_live_orders(0);
+    metrics::set_stale_instruments(0);
     metrics::inc_ws_clients(0);
     metrics::inc_out_frames();
     metrics::inc_out_bytes(0);
     metrics::inc_dropped_clients();
+    metrics::set_snapshot_feed_joined(false);
+    metrics::set_snapshot_feed_buffered(0);
+    metrics::inc_snapshot_feed_evicted();
+    metrics::inc_snapshot_feed_instrument("rebuilt");
+    metrics::set_glimpse_held(0);
+    metrics::inc_glimpse_evicted();
+    metrics::inc_glimpse_snapshot("applied");
 
     let shutdown = Arc::new(BarrierFlag::default());
     {
@@ -84,132 +120,169 @@ fn main() -> anyhow::Result<()> {
     // Touch recovery paths to avoid dead code in that module
     let (_rc_cli, _rc_handle) = recovery::spawn_logger();
     let q_recovery_touch = Arc::new(SpscQueue::new(64));
+    let injector_cfg = match cfg.recovery.as_ref() {
+        Some(r) => {
+            recovery::InjectorCfg::from_cfg(r, None, "0", 1, cfg.parser.session_utc_offset_s)
+        }
+        None => recovery::InjectorCfg::new(vec!["127.0.0.1:9".into()], "0"),
+    };
     let (rc2_cli, _rc2_handle) =
-        recovery::spawn_tcp_injector("127.0.0.1:9", q_recovery_touch.clone(), pool.clone(), None);
+        recovery::spawn_tcp_injector(injector_cfg, q_recovery_touch.clone(), pool.clone());
     rc2_cli.notify_gap(1, 1);
 
-    // Queues
-    let a_workers = cfg.channels.a.workers.unwrap_or(1).max(1);
-    let b_workers = cfg.channels.b.workers.unwrap_or(1).max(1);
-    let q_rx_a_list: Vec<Arc<SpscQueue<Pkt>>> = (0..a_workers)
-        .map(|_| Arc::new(SpscQueue::new(cfg.general.rx_queue_capacity)))
-        .collect();
-    let q_rx_b_list: Vec<Arc<SpscQueue<Pkt>>> = (0..b_workers)
-        .map(|_| Arc::new(SpscQueue::new(cfg.general.rx_queue_capacity)))
-        .collect();
-    let q_merged = Arc::new(SpscQueue::new(cfg.general.merge_queue_capacity));
-
     // Parser (sequence only)
     let seq_cfg = SeqCfg {
         offset: cfg.sequence.offset,
         length: cfg.sequence.length,
         endian: cfg.sequence.endian.clone(),
+        unit: cfg.sequence.unit,
+        stream: cfg
+            .sequence
+            .stream_offset
+            .map(|off| (off, cfg.sequence.stream_length)),
+        moldudp64: cfg.sequence.moldudp64,
     };
+    if let Some(c) = &cfg.parser.capture_bad_payloads {
+        decode_stats::enable_capture(&c.path, c.max)?;
+    }
     let parser = build_parser(
         cfg.parser.kind.clone(),
         seq_cfg,
         cfg.parser.max_messages_per_packet,
+        cfg.parser.session_utc_offset_s,
     )?;
     let _ = parser.max_messages_per_packet;
 
-    // Sockets per worker
-    let mut socks_a = Vec::with_capacity(a_workers);
-    let mut socks_b = Vec::with_capacity(b_workers);
-    for _ in 0..a_workers {
-        socks_a.push(net::build_mcast_socket(&cfg.channels.a)?);
-    }
-    for _ in 0..b_workers {
-        socks_b.push(net::build_mcast_socket(&cfg.channels.b)?);
-    }
-
-    // RX A threads
+    // Per partition: RX workers for every line feeding one merge thread
     let mut rx_joins = Vec::new();
-    for (i, sa) in socks_a.into_iter().enumerate() {
-        let rx_shutdown = shutdown.clone();
-        let pool_i = pool.clone();
-        let q_i = q_rx_a_list[i].clone();
-        let parser_i = parser.clone();
-        let cfgc = cfg.clone();
-        let t = thread::Builder::new()
-            .name(format!("rx-A-{i}"))
-            .spawn(move || {
-                util::pin_to_core_with_offset(cfgc.cpu.a_rx_core, i);
-                set_realtime_priority_if(cfgc.cpu.rt_priority);
-                let _ = rx::rx_loop(
-                    "A",
-                    &sa,
-                    parser_i.seq_extractor(),
-                    q_i,
-                    pool_i,
-                    rx_shutdown,
-                    rx::RxConfig {
-                        spin_loops_per_yield: cfgc.general.spin_loops_per_yield,
-                        rx_batch: cfgc.general.rx_recvmmsg_batch.unwrap_or(0),
-                        ts_mode: cfgc.channels.a.timestamping.clone(),
-                    },
-                );
-            })?;
-        rx_joins.push(t);
-    }
-    // RX B threads
-    for (i, sb) in socks_b.into_iter().enumerate() {
-        let rx_shutdown = shutdown.clone();
-        let pool_i = pool.clone();
-        let q_i = q_rx_b_list[i].clone();
-        let parser_i = parser.clone();
-        let cfgc = cfg.clone();
-        let t = thread::Builder::new()
-            .name(format!("rx-B-{i}"))
+    let mut merge_joins = Vec::new();
+    let mut q_merged_list = Vec::new();
+    let parts = cfg.partitions();
+    let n_parts = parts.len();
+    let exchange_to_rx: Vec<_> = parts
+        .iter()
+        .map(|p| {
+            let lines: Vec<&str> = p.lines().iter().map(|l| l.name).collect();
+            metrics::ExchangeToRx::new(&p.name, &lines)
+        })
+        .collect();
+    let epoch = std::time::Instant::now();
+    for part in parts {
+        let q_merged = Arc::new(SpscQueue::new(cfg.general.merge_queue_capacity));
+        let mut sources = Vec::new();
+        for (line_idx, line) in part.lines().into_iter().enumerate() {
+            let ch = line.cfg;
+            let workers = ch.workers.unwrap_or(1).max(1);
+            let qs: Vec<Arc<SpscQueue<Pkt>>> = (0..workers)
+                .map(|_| Arc::new(SpscQueue::new(cfg.general.rx_queue_capacity)))
+                .collect();
+            for (i, q_i) in qs.iter().enumerate() {
+                // `chaos`: the worker fills its own queue, an impairment thread forwards
+                let q_i = match &ch.chaos {
+                    Some(c) => {
+                        let q_in = Arc::new(SpscQueue::new(cfg.general.rx_queue_capacity));
+                        rx_joins.push(chaos::spawn_chaos(
+                            c,
+                            i,
+                            epoch,
+                            &part.name,
+                            line.name,
+                            q_in.clone(),
+                            q_i.clone(),
+                            pool.clone(),
+                            shutdown.clone(),
+                        )?);
+                        q_in
+                    }
+                    None => q_i.clone(),
+                };
+                let sock = net::build_mcast_socket(ch)?;
+                let rx_shutdown = shutdown.clone();
+                let pool_i = pool.clone();
+                let seq = parser.seq_extractor();
+                let (core, rt) = (line.rx_core, cfg.cpu.rt_priority);
+                let rx_cfg = rx::RxConfig {
+                    spin_loops_per_yield: cfg.general.spin_loops_per_yield,
+                    rx_batch: cfg.general.rx_recvmmsg_batch.unwrap_or(0),
+                    ts_mode: ch.timestamping.clone(),
+                    backend: ch.rx_backend,
+                    sqpoll_idle_ms: ch.io_uring_sqpoll_idle_ms,
+                    partition: part.name.clone(),
+                    line: line_idx as u8,
+                };
+                let chan = line.name.to_string();
+                let t = thread::Builder::new()
+                    .name(format!("rx-{}-{chan}-{i}", part.name))
+                    .spawn(move || {
+                        util::pin_to_core_with_offset(core, i);
+                        set_realtime_priority_if(rt);
+                        let _ = rx::rx_loop(&chan, &sock, seq, q_i, pool_i, rx_shutdown, rx_cfg);
+                    })?;
+                rx_joins.push(t);
+            }
+            sources.push(merge::MergeSource {
+                name: line.name.to_string(),
+                queues: qs,
+                priority: line.priority,
+                switch_after: line.switch_after,
+                revert_after: line.revert_after,
+            });
+        }
+
+        let q_merged_for_merge = q_merged.clone();
+        let merge_shutdown = shutdown.clone();
+        let merge_cfg = merge::MergeConfig {
+            next_seq: part
+                .initial_expected_seq
+                .unwrap_or(cfg.merge.initial_expected_seq),
+            anchor_on_first: false,
+            reorder_window: cfg.merge.reorder_window,
+            max_pending: cfg.merge.max_pending_packets,
+            dwell_ns: cfg.merge.dwell_ns.unwrap_or(2_000_000),
+            adaptive: cfg.merge.adaptive,
+            reorder_window_max: cfg.merge.reorder_window_max.unwrap_or(
+                cfg.merge
+                    .reorder_window
+                    .saturating_mul(8)
+                    .max(cfg.merge.reorder_window + 1),
+            ),
+            gap_timeout_ns: cfg.merge.gap_timeout_ns.unwrap_or(0),
+            give_up_ns: cfg.merge.give_up_after_ns.unwrap_or(0),
+            on_give_up: cfg.merge.on_give_up,
+            reset_backjump: cfg.merge.reset_backjump.unwrap_or(0),
+            max_streams: cfg.merge.max_streams.unwrap_or(64),
+            verify: cfg
+                .merge
+                .verify
+                .as_ref()
+                .map(|v| dup_verify::DupVerifier::from_cfg(v, &part.name, n_parts))
+                .transpose()?,
+            race: cfg.merge.race.as_ref().map(|r| merge::RaceConfig {
+                history: r.history,
+                window: r.window,
+                report_ns: r.report_interval_ms.saturating_mul(1_000_000),
+            }),
+            partition: part.name.clone(),
+        };
+        let (core, rt) = (part.merge_core, cfg.cpu.rt_priority);
+        let t_merge = thread::Builder::new()
+            .name(format!("merge-{}", part.name))
             .spawn(move || {
-                util::pin_to_core_with_offset(cfgc.cpu.b_rx_core, i);
-                set_realtime_priority_if(cfgc.cpu.rt_priority);
-                let _ = rx::rx_loop(
-                    "B",
-                    &sb,
-                    parser_i.seq_extractor(),
-                    q_i,
-                    pool_i,
-                    rx_shutdown,
-                    rx::RxConfig {
-                        spin_loops_per_yield: cfgc.general.spin_loops_per_yield,
-                        rx_batch: cfgc.general.rx_recvmmsg_batch.unwrap_or(0),
-                        ts_mode: cfgc.channels.b.timestamping.clone(),
-                    },
+                pin_to_core_if_set(core);
+                set_realtime_priority_if(rt);
+                let _ = merge::merge_loop(
+                    sources,
+                    q_merged_for_merge,
+                    merge_cfg,
+                    merge_shutdown,
+                    None,
+                    None,
                 );
             })?;
-        rx_joins.push(t);
+        merge_joins.push(t_merge);
+        q_merged_list.push(q_merged);
     }
 
-    // Merge thread
-    let q_merged_for_merge = q_merged.clone();
-    let merge_shutdown = shutdown.clone();
-    let merge_cfg = merge::MergeConfig {
-        next_seq: cfg.merge.initial_expected_seq,
-        reorder_window: cfg.merge.reorder_window,
-        max_pending: cfg.merge.max_pending_packets,
-        dwell_ns: cfg.merge.dwell_ns.unwrap_or(2_000_000),
-        adaptive: cfg.merge.adaptive,
-        reorder_window_max: cfg.merge.reorder_window_max.unwrap_or(
-            cfg.merge
-                .reorder_window
-                .saturating_mul(8)
-                .max(cfg.merge.reorder_window + 1),
-        ),
-    };
-    let t_merge = thread::Builder::new().name("merge".into()).spawn(move || {
-        pin_to_core_if_set(cfg.cpu.merge_core);
-        set_realtime_priority_if(cfg.cpu.rt_priority);
-        let _ = merge::merge_loop(
-            q_rx_a_list,
-            q_rx_b_list,
-            q_merged_for_merge,
-            merge_cfg,
-            merge_shutdown,
-            None,
-            None,
-        );
-    })?;
-
     // Minimal decode/sink loop: update stage/e2e metrics and recycle packets
     let parser_for_decode = parser.clone();
     let t_decode = thread::Builder::new()
@@ -218,11 +291,9 @@ fn main() -> anyhow::Result<()> {
             pin_to_core_if_set(cfg.cpu.decode_core);
             set_realtime_priority_if(cfg.cpu.rt_priority);
             let mut idle = 0u32;
-            // Exercise parser decode path once with empty payload to avoid dead code
-            {
-                let mut tmp: Vec<parser::Event> = Vec::new();
-                parser_for_decode.decode_into(&[], &mut tmp);
-            }
+            let mut n_pkts = 0u64;
+            let mut events: Vec<parser::Event> =
+                Vec::with_capacity(parser_for_decode.max_messages_per_packet);
             // Exercise ITCH decoder once to avoid dead code in that module
             {
                 use parser::MessageDecoder;
@@ -231,7 +302,16 @@ fn main() -> anyhow::Result<()> {
                 dec.decode_messages(&[], &mut tmp);
             }
             while !shutdown.is_raised() {
-                if let Some(pkt) = q_merged.pop() {
+                let popped = q_merged_list
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, q)| q.pop().map(|p| (i, p)));
+                if let Some((part_idx, pkt)) = popped {
+                    // Merge control (given-up gaps): no book here to mark stale
+                    if pkt.chan == pool::CHAN_CONTROL {
+                        pkt.recycle(&pool);
+                        continue;
+                    }
                     metrics::inc_decode_pkts();
                     let now = now_nanos();
                     if pkt.merge_emit_ns > 0 && now > pkt.merge_emit_ns {
@@ -244,9 +324,20 @@ fn main() -> anyhow::Result<()> {
                         metrics::observe_latency_ns(now - pkt.ts_nanos);
                         metrics::observe_latency_by_kind_ns(pkt._ts_kind, now - pkt.ts_nanos);
                     }
+                    // Decode only to sample exchange -> RX latency
+                    n_pkts += 1;
+                    if exch_sample_every > 0 && n_pkts.is_multiple_of(exch_sample_every) {
+                        events.clear();
+                        parser_for_decode.decode_into(pkt.payload(), &mut events);
+                        if let Some(exch_ns) = events.iter().find_map(|e| e.exch_ts_ns()) {
+                            if pkt.ts_nanos > exch_ns {
+                                exchange_to_rx[part_idx]
+                                    .observe_ns(pkt.chan, pkt.ts_nanos - exch_ns);
+                            }
+                        }
+                    }
                     // Touch packet fields/methods to avoid dead code in pool.rs
                     let _ = pkt.len;
-                    let _ = pkt.payload();
                     pkt.recycle(&pool);
                     idle = 0;
                 } else {
@@ -259,7 +350,9 @@ fn main() -> anyhow::Result<()> {
     for j in rx_joins {
         let _ = j.join();
     }
-    let _ = t_merge.join();
+    for j in merge_joins {
+        let _ = j.join();
+    }
     let _ = t_decode.join();
     Ok(())
 }
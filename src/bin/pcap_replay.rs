@@ -6,7 +6,9 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 fn main() -> anyhow::Result<()> {
     let args: Vec<String> = std::env::args().collect();
     if args.len() < 6 {
-        eprintln!("usage: pcap_replay <pcap_file> <group> <port> <iface_ipv4> <pps> [report_ms]");
+        eprintln!(
+            "usage: pcap_replay <pcap_file> <group> <port> <iface_ipv4> <pps> [report_ms] [gap_at gap_packets]"
+        );
         std::process::exit(2);
     }
     let path = &args[1];
@@ -20,6 +22,11 @@ fn main() -> anyhow::Result<()> {
     } else {
         1000
     };
+    // Leave out `gap_packets` packets after the first `gap_at` (recovery tests)
+    let gap: Option<(u64, u64)> = match (args.get(7), args.get(8)) {
+        (Some(at), Some(n)) => Some((at.parse()?, n.parse()?)),
+        _ => None,
+    };
 
     // Open destination socket
     let sock = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
@@ -43,6 +50,7 @@ fn main() -> anyhow::Result<()> {
     let mut last_report = start;
     let mut sent_last = 0u64;
     let mut sent = 0u64;
+    let mut seen = 0u64;
     while off + 16 <= data.len() {
         let (incl_len, _) = if le {
             read_le_u32(&data, off + 8)
@@ -55,6 +63,10 @@ fn main() -> anyhow::Result<()> {
         }
         let pkt = &data[off..off + incl_len as usize];
         off += incl_len as usize;
+        seen += 1;
+        if gap.is_some_and(|(at, n)| seen > at && seen <= at + n) {
+            continue;
+        }
         // Best-effort: assume the captured payload is the UDP payload (not full frame)
         let _ = sock.send_to(pkt, &dest.into());
         sent += 1;
@@ -0,0 +1,469 @@
+// src/bin/replay_server.rs
+// Local replay service for recovery testing.
+//
+// Serves the TCP protocol the recovery injector speaks: `REPLAY <from> <to> [stream]\n`
+// answered by `[u32 BE len][u64 BE seq][bytes]` frames and a 12-byte zero terminator.
+// Packets come from a capture indexed by the configured sequence extractor, so a
+// partition's recovery can be pointed at it and exercised end to end. Faults can be
+// injected to exercise retries and failover: response delay, frame pacing, frames left
+// out, truncated responses and connections closed mid-response.
+//
+// The capture is a pcap (UDP payloads as written by `pcap_capture`, or Ethernet/IPv4/UDP
+// frames) or a journal of replay frames, e.g. a recorded response stream. Journal frames
+// are indexed by the extractor like pcap packets; their recorded sequence is ignored.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[allow(dead_code)] // sequence and parser sections only
+#[path = "../config.rs"]
+mod config;
+#[allow(dead_code)] // issue counters only
+#[path = "../decode_stats.rs"]
+mod decode_stats;
+#[allow(dead_code)]
+#[path = "../decoder_eobi.rs"]
+mod decoder_eobi;
+#[allow(dead_code)]
+#[path = "../decoder_fast.rs"]
+mod decoder_fast;
+#[allow(dead_code)]
+#[path = "../decoder_itch.rs"]
+mod decoder_itch;
+#[allow(dead_code)]
+#[path = "../decoder_mdp3.rs"]
+mod decoder_mdp3;
+#[allow(dead_code)]
+#[path = "../decoder_pitch.rs"]
+mod decoder_pitch;
+#[allow(dead_code)] // decoder counters only
+#[path = "../metrics.rs"]
+mod metrics;
+#[allow(dead_code)] // sequence extractor only
+#[path = "../parser.rs"]
+mod parser;
+#[allow(dead_code)] // metrics labels only
+#[path = "../pool.rs"]
+mod pool;
+#[allow(dead_code)] // metrics admin query only
+#[path = "../recovery_journal.rs"]
+mod recovery_journal;
+#[allow(dead_code)] // decoder timestamps only
+#[path = "../util.rs"]
+mod util;
+
+use config::AppConfig;
+use parser::{build_parser, SeqCfg, SeqExtractor};
+
+const USAGE: &str = "usage: replay_server <config.toml> <capture> [--bind addr] [--delay-ms n] \
+[--frame-delay-us n] [--drop-rate p] [--max-frames n] [--fail-rate p] [--fail-first n] [--seed n]";
+
+const LINKTYPE_ETHERNET: u32 = 1;
+
+/// Faults injected into responses
+#[derive(Clone, Default)]
+struct Faults {
+    /// Wait before the first frame of every response
+    delay: Duration,
+    /// Wait between frames
+    frame_delay: Duration,
+    /// Probability that a frame is left out; the injector re-requests what is missing
+    drop_rate: f64,
+    /// Frames per response at most; the rest is left out
+    max_frames: Option<usize>,
+    /// Probability that a request gets its connection closed halfway through
+    fail_rate: f64,
+    /// Close the connection on the first `n` requests (failover tests)
+    fail_first: u64,
+    seed: u64,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    }
+    let cfg = AppConfig::from_file(&PathBuf::from(&args[1]))?;
+    let path = &args[2];
+    let mut bind = "127.0.0.1:9000".to_string();
+    let mut faults = Faults {
+        seed: 1,
+        ..Default::default()
+    };
+    for kv in args[3..].chunks(2) {
+        let [k, v] = kv else {
+            anyhow::bail!("{} needs a value\n{USAGE}", kv[0]);
+        };
+        match k.as_str() {
+            "--bind" => bind = v.clone(),
+            "--delay-ms" => faults.delay = Duration::from_millis(v.parse()?),
+            "--frame-delay-us" => faults.frame_delay = Duration::from_micros(v.parse()?),
+            "--drop-rate" => faults.drop_rate = v.parse()?,
+            "--max-frames" => faults.max_frames = Some(v.parse()?),
+            "--fail-rate" => faults.fail_rate = v.parse()?,
+            "--fail-first" => faults.fail_first = v.parse()?,
+            "--seed" => faults.seed = v.parse()?,
+            _ => anyhow::bail!("unknown option {k}\n{USAGE}"),
+        }
+    }
+
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // Same sequence extractor as the receiver
+    let seq_cfg = SeqCfg {
+        offset: cfg.sequence.offset,
+        length: cfg.sequence.length,
+        endian: cfg.sequence.endian.clone(),
+        unit: cfg.sequence.unit,
+        stream: cfg
+            .sequence
+            .stream_offset
+            .map(|off| (off, cfg.sequence.stream_length)),
+        moldudp64: cfg.sequence.moldudp64,
+    };
+    let parser = build_parser(
+        cfg.parser.kind.clone(),
+        seq_cfg,
+        cfg.parser.max_messages_per_packet,
+        cfg.parser.session_utc_offset_s,
+    )?;
+
+    let index = Index::build(std::fs::read(path)?, &*parser.seq_extractor())?;
+    log::info!("indexed {} packets from {path}", index.pkts.len());
+    let listener = TcpListener::bind(&bind)?;
+    log::info!("serving REPLAY on {bind}");
+    serve(listener, Arc::new(index), faults);
+    Ok(())
+}
+
+/// Packets of a capture by `(stream, first sequence)`
+struct Index {
+    data: Vec<u8>,
+    /// `(offset, len, seq span)` into `data`
+    pkts: BTreeMap<(u32, u64), (usize, usize, u32)>,
+    max_span: u32,
+}
+
+impl Index {
+    fn build(data: Vec<u8>, seq: &dyn SeqExtractor) -> anyhow::Result<Self> {
+        let spans = if is_pcap(&data) {
+            pcap_packets(&data)?
+        } else {
+            journal_packets(&data)?
+        };
+        let mut pkts = BTreeMap::new();
+        let mut max_span = 1;
+        for (off, len) in spans {
+            let p = &data[off..off + len];
+            let Some((stream, s)) = seq.extract_stream_seq(p) else {
+                continue;
+            };
+            let span = seq.seq_span(p).max(1);
+            max_span = max_span.max(span);
+            // A/B captures hold every packet twice; keep the first copy
+            pkts.entry((stream, s)).or_insert((off, len, span));
+        }
+        Ok(Self {
+            data,
+            pkts,
+            max_span,
+        })
+    }
+
+    /// Packets of `stream` carrying any of `[from, to]`, in sequence order.
+    fn range(&self, stream: u32, from: u64, to: u64) -> impl Iterator<Item = (u64, &[u8])> {
+        let lo = from.saturating_sub(self.max_span as u64 - 1);
+        self.pkts
+            .range((stream, lo)..=(stream, to.max(lo)))
+            .filter(move |(&(_, s), &(_, _, span))| s + span as u64 > from)
+            .map(|(&(_, s), &(off, len, _))| (s, &self.data[off..off + len]))
+    }
+}
+
+fn is_pcap(data: &[u8]) -> bool {
+    let Some(m) = data.get(..4) else {
+        return false;
+    };
+    let magic = u32::from_le_bytes([m[0], m[1], m[2], m[3]]);
+    [0xA1B2C3D4, 0xA1B23C4D, 0xD4C3B2A1, 0x4D3CB2A1].contains(&magic)
+}
+
+/// `(offset, len)` of every packet's UDP payload.
+fn pcap_packets(data: &[u8]) -> anyhow::Result<Vec<(usize, usize)>> {
+    if data.len() < 24 {
+        anyhow::bail!("pcap too small");
+    }
+    let magic = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    let le = magic == 0xA1B2C3D4 || magic == 0xA1B23C4D;
+    let rd = |off: usize| {
+        let b = [data[off], data[off + 1], data[off + 2], data[off + 3]];
+        if le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        }
+    };
+    let linktype = rd(20);
+    let mut out = Vec::new();
+    let mut off = 24;
+    while off + 16 <= data.len() {
+        let incl_len = rd(off + 8) as usize;
+        off += 16;
+        if off + incl_len > data.len() {
+            break;
+        }
+        let frame = &data[off..off + incl_len];
+        if linktype == LINKTYPE_ETHERNET {
+            out.extend(udp_payload(frame).map(|(o, n)| (off + o, n)));
+        } else {
+            // Best-effort like `pcap_replay`: the captured payload is the UDP payload
+            out.push((off, incl_len));
+        }
+        off += incl_len;
+    }
+    Ok(out)
+}
+
+/// `(offset, len)` of the UDP payload of an Ethernet (optionally VLAN-tagged) IPv4 frame.
+fn udp_payload(f: &[u8]) -> Option<(usize, usize)> {
+    let be16 = |off: usize| Some(u16::from_be_bytes([*f.get(off)?, *f.get(off + 1)?]));
+    let mut off = 12;
+    let mut ethertype = be16(off)?;
+    while ethertype == 0x8100 || ethertype == 0x88A8 {
+        off += 4;
+        ethertype = be16(off)?;
+    }
+    let ip = off + 2;
+    if ethertype != 0x0800 || *f.get(ip + 9)? != 17 {
+        return None;
+    }
+    let udp = ip + (*f.get(ip)? & 0x0F) as usize * 4;
+    let len = (be16(udp + 4)? as usize).checked_sub(8)?;
+    (udp + 8 + len <= f.len()).then_some((udp + 8, len))
+}
+
+/// `(offset, len)` of every frame's bytes in a journal of replay frames.
+fn journal_packets(data: &[u8]) -> anyhow::Result<Vec<(usize, usize)>> {
+    let mut out = Vec::new();
+    let mut off = 0;
+    while off < data.len() {
+        if off + 12 > data.len() {
+            anyhow::bail!("journal truncated at byte {off}");
+        }
+        let len =
+            u32::from_be_bytes([data[off], data[off + 1], data[off + 2], data[off + 3]]) as usize;
+        off += 12;
+        if off + len > data.len() {
+            anyhow::bail!("journal truncated at byte {off}");
+        }
+        // Zero-length frames end a response; a recording may hold several
+        if len > 0 {
+            out.push((off, len));
+        }
+        off += len;
+    }
+    Ok(out)
+}
+
+/// Accept connections forever, one thread each; requests are numbered across them.
+fn serve(listener: TcpListener, index: Arc<Index>, faults: Faults) {
+    let requests = Arc::new(AtomicU64::new(0));
+    for (n, conn) in listener.incoming().enumerate() {
+        let conn = match conn {
+            Ok(c) => c,
+            Err(e) => {
+                log::warn!("accept: {e}");
+                continue;
+            }
+        };
+        let (index, faults, requests) = (index.clone(), faults.clone(), requests.clone());
+        std::thread::spawn(move || {
+            let rng = Rng((faults.seed ^ (n as u64).wrapping_mul(0x9E3779B97F4A7C15)) | 1);
+            if let Err(e) = serve_conn(conn, &index, &faults, &requests, rng) {
+                log::warn!("connection {n}: {e}");
+            }
+        });
+    }
+}
+
+fn serve_conn(
+    conn: TcpStream,
+    index: &Index,
+    faults: &Faults,
+    requests: &AtomicU64,
+    mut rng: Rng,
+) -> anyhow::Result<()> {
+    conn.set_nodelay(true).ok();
+    let peer = conn.peer_addr()?;
+    let mut w = BufWriter::new(conn.try_clone()?);
+    for line in BufReader::new(conn).lines() {
+        let line = line?;
+        let mut f = line.split_ascii_whitespace();
+        let verb = f.next();
+        let n: Vec<u64> = f.filter_map(|x| x.parse().ok()).collect();
+        let (from, to, stream) = match (verb, n.as_slice()) {
+            (Some("REPLAY"), [from, to]) => (*from, *to, 0),
+            (Some("REPLAY"), [from, to, stream]) => (*from, *to, *stream as u32),
+            _ => anyhow::bail!("bad request {line:?}"),
+        };
+        let started = Instant::now();
+        let nth = requests.fetch_add(1, Ordering::Relaxed);
+        let fail = nth < faults.fail_first || rng.chance(faults.fail_rate);
+        if !faults.delay.is_zero() {
+            std::thread::sleep(faults.delay);
+        }
+
+        let frames: Vec<_> = index.range(stream, from, to).collect();
+        let mut limit = faults.max_frames.unwrap_or(usize::MAX);
+        if fail {
+            limit = limit.min(frames.len() / 2);
+        }
+        let mut sent = 0;
+        for &(seq, p) in frames.iter().take(limit) {
+            if rng.chance(faults.drop_rate) {
+                continue;
+            }
+            if !faults.frame_delay.is_zero() {
+                w.flush()?;
+                std::thread::sleep(faults.frame_delay);
+            }
+            w.write_all(&(p.len() as u32).to_be_bytes())?;
+            w.write_all(&seq.to_be_bytes())?;
+            w.write_all(p)?;
+            sent += 1;
+        }
+        if fail {
+            w.flush()?;
+            log::info!(
+                "{peer}: REPLAY {from}..={to} (stream {stream}): closed after {sent} of {} frames (injected failure)",
+                frames.len()
+            );
+            return Ok(());
+        }
+        w.write_all(&[0u8; 12])?;
+        w.flush()?;
+        log::info!(
+            "{peer}: REPLAY {from}..={to} (stream {stream}): {sent} of {} frames in {:?}",
+            frames.len(),
+            started.elapsed()
+        );
+    }
+    Ok(())
+}
+
+/// xorshift64*
+struct Rng(u64);
+
+impl Rng {
+    fn chance(&mut self, p: f64) -> bool {
+        if p <= 0.0 {
+            return false;
+        }
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let x = self.0.wrapping_mul(0x2545F4914F6CDD1D);
+        ((x >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Endian, ParserKind};
+    use std::io::Read;
+
+    /// pcap as `pcap_capture` writes it; payloads start with a BE u64 sequence
+    fn capture(seqs: &[u64]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&0xA1B2C3D4u32.to_le_bytes());
+        out.extend_from_slice(&[0; 16]);
+        out.extend_from_slice(&101u32.to_le_bytes());
+        for s in seqs {
+            let mut p = s.to_be_bytes().to_vec();
+            p.push(*s as u8);
+            out.extend_from_slice(&[0; 8]);
+            out.extend_from_slice(&(p.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(p.len() as u32).to_le_bytes());
+            out.extend_from_slice(&p);
+        }
+        out
+    }
+
+    /// `(seq, payload)` frames of one response, and whether it ended with the terminator
+    fn read_response(s: &mut TcpStream) -> (Vec<(u64, Vec<u8>)>, bool) {
+        let mut frames = Vec::new();
+        let mut hdr = [0u8; 12];
+        loop {
+            if s.read_exact(&mut hdr).is_err() {
+                return (frames, false);
+            }
+            let len = u32::from_be_bytes(hdr[..4].try_into().unwrap()) as usize;
+            if len == 0 {
+                return (frames, true);
+            }
+            let mut p = vec![0; len];
+            s.read_exact(&mut p).unwrap();
+            frames.push((u64::from_be_bytes(hdr[4..].try_into().unwrap()), p));
+        }
+    }
+
+    #[test]
+    fn serves_indexed_ranges_and_injected_failures() {
+        let seq = SeqCfg {
+            offset: 0,
+            length: 8,
+            endian: Endian::Be,
+            unit: None,
+            stream: None,
+            moldudp64: false,
+        };
+        let parser = build_parser(ParserKind::FixedBinary, seq, 16, 0).unwrap();
+        // Both lines of an A/B capture
+        let index = Index::build(
+            capture(&[1, 2, 1, 3, 2, 4, 3, 5, 4, 6]),
+            &*parser.seq_extractor(),
+        )
+        .unwrap();
+        assert_eq!(index.pkts.len(), 6);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let faults = Faults {
+            fail_first: 1,
+            seed: 1,
+            ..Default::default()
+        };
+        std::thread::spawn(move || serve(listener, Arc::new(index), faults));
+
+        // The first request is cut off halfway through
+        let mut s = TcpStream::connect(addr).unwrap();
+        s.write_all(b"REPLAY 2 5\n").unwrap();
+        let (frames, done) = read_response(&mut s);
+        assert!(!done);
+        assert_eq!(frames.iter().map(|f| f.0).collect::<Vec<_>>(), vec![2, 3]);
+
+        let mut s = TcpStream::connect(addr).unwrap();
+        s.write_all(b"REPLAY 2 5\nREPLAY 9 12\n").unwrap();
+        let (frames, done) = read_response(&mut s);
+        assert!(done);
+        assert_eq!(
+            frames,
+            (2..=5u64)
+                .map(|q| {
+                    let mut p = q.to_be_bytes().to_vec();
+                    p.push(q as u8);
+                    (q, p)
+                })
+                .collect::<Vec<_>>()
+        );
+        // Nothing captured there: an empty response
+        assert_eq!(read_response(&mut s), (Vec::new(), true));
+    }
+}
@@ -0,0 +1,333 @@
+// src/chaos.rs
+// Fault injection between a line's RX workers and the merge (`chaos` on a channel).
+//
+// Each RX worker queue of a chaotic line gets an impairment thread that forwards to the
+// queue the merge reads, dropping (random loss, loss bursts, scheduled cuts), duplicating,
+// reordering and delaying packets on the way. This exercises line arbitration, gap
+// detection and recovery on one box without touching the network: `chaos_packets`
+// counts what was done to each line, the merge and recovery metrics show how the
+// pipeline coped. Cut windows are timed from the start of the partition's RX.
+
+use crate::config::{ChaosCfg, ChaosCut};
+use crate::metrics;
+use crate::pool::{PacketPool, Pkt, PktBuf};
+use crate::spsc::SpscQueue;
+use crate::util::BarrierFlag;
+use log::info;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A held-back packet goes out after this long even if nothing passes it
+const MAX_HOLD_NS: u64 = 1_000_000;
+
+/// Impairment state of one RX worker queue
+pub struct Impairment {
+    cfg: ChaosCfg,
+    rng: u64,
+    burst_left: u32,
+    cutting: bool,
+    /// Held-back packets: (later packets still to pass it, held since ns, packet)
+    held: Vec<(u32, u64, Pkt)>,
+    /// Delayed packets in arrival order: (due ns, packet)
+    delayed: VecDeque<(u64, Pkt)>,
+    partition: String,
+    chan: String,
+}
+
+impl Impairment {
+    pub fn new(cfg: ChaosCfg, seed: u64, partition: &str, chan: &str) -> Self {
+        Self {
+            cfg,
+            rng: seed | 1,
+            burst_left: 0,
+            cutting: false,
+            held: Vec::new(),
+            delayed: VecDeque::new(),
+            partition: partition.to_string(),
+            chan: chan.to_string(),
+        }
+    }
+
+    /// Impair `pkt`, received `now_ns` after the start of RX; what may go out right
+    /// away is appended to `out`.
+    pub fn push(&mut self, pkt: Pkt, now_ns: u64, pool: &PacketPool, out: &mut Vec<Pkt>) {
+        if let Some(action) = self.drop_reason(now_ns) {
+            metrics::inc_chaos(&self.partition, &self.chan, action);
+            pkt.recycle(pool);
+            return;
+        }
+        let dup = self.chance(self.cfg.duplicate).then(|| copy(&pkt, pool));
+        self.pass(pkt, now_ns, out);
+        if let Some(d) = dup {
+            metrics::inc_chaos(&self.partition, &self.chan, "duplicate");
+            self.pass(d, now_ns, out);
+        }
+    }
+
+    /// Append packets due at `now_ns`: held ones nothing passed in time, delayed ones.
+    pub fn poll(&mut self, now_ns: u64, out: &mut Vec<Pkt>) {
+        let mut i = 0;
+        while i < self.held.len() {
+            if now_ns.saturating_sub(self.held[i].1) >= MAX_HOLD_NS {
+                let (_, _, p) = self.held.remove(i);
+                self.send(p, now_ns, out);
+            } else {
+                i += 1;
+            }
+        }
+        while self.delayed.front().is_some_and(|(due, _)| *due <= now_ns) {
+            out.extend(self.delayed.pop_front().map(|(_, p)| p));
+        }
+    }
+
+    fn drop_reason(&mut self, now_ns: u64) -> Option<&'static str> {
+        let now_ms = now_ns / 1_000_000;
+        let cut = self.cfg.cuts.iter().any(|c| in_cut(c, now_ms));
+        if cut != self.cutting {
+            self.cutting = cut;
+            let what = if cut { "cut" } else { "restored" };
+            info!(
+                "[{}] chaos: line {} {what} at {now_ms} ms",
+                self.partition, self.chan
+            );
+        }
+        if cut {
+            return Some("cut");
+        }
+        if self.burst_left > 0 {
+            self.burst_left -= 1;
+            return Some("burst");
+        }
+        if self.chance(self.cfg.burst_start) {
+            self.burst_left = self.cfg.burst_len.saturating_sub(1);
+            return Some("burst");
+        }
+        self.chance(self.cfg.loss).then_some("loss")
+    }
+
+    /// Hold `pkt` back or send it; held packets it passes fall one further behind.
+    fn pass(&mut self, pkt: Pkt, now_ns: u64, out: &mut Vec<Pkt>) {
+        if self.chance(self.cfg.reorder) {
+            let pick = self.next() % self.cfg.reorder_depths.len() as u64;
+            let depth = self.cfg.reorder_depths[pick as usize];
+            metrics::inc_chaos(&self.partition, &self.chan, "reorder");
+            self.held.push((depth, now_ns, pkt));
+            return;
+        }
+        self.send(pkt, now_ns, out);
+        let mut i = 0;
+        while i < self.held.len() {
+            self.held[i].0 -= 1;
+            if self.held[i].0 == 0 {
+                let (_, _, p) = self.held.remove(i);
+                self.send(p, now_ns, out);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn send(&mut self, pkt: Pkt, now_ns: u64, out: &mut Vec<Pkt>) {
+        if self.cfg.delay_us == 0 {
+            out.push(pkt);
+        } else {
+            let due = now_ns + self.cfg.delay_us * 1_000;
+            self.delayed.push_back((due, pkt));
+        }
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        p > 0.0 && ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// xorshift64*
+    fn next(&mut self) -> u64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+}
+
+fn in_cut(c: &ChaosCut, now_ms: u64) -> bool {
+    let Some(since) = now_ms.checked_sub(c.at_ms) else {
+        return false;
+    };
+    match c.every_ms {
+        Some(every) => since % every < c.for_ms,
+        None => since < c.for_ms,
+    }
+}
+
+/// A second delivery of `pkt` in a pool buffer.
+fn copy(pkt: &Pkt, pool: &PacketPool) -> Pkt {
+    let payload = pkt.payload();
+    let mut buf = pool.get();
+    buf.extend_from_slice(payload);
+    Pkt {
+        buf: PktBuf::Bytes(buf),
+        len: payload.len(),
+        seq: pkt.seq,
+        stream: pkt.stream,
+        seq_span: pkt.seq_span,
+        seq_reset: pkt.seq_reset,
+        ts_nanos: pkt.ts_nanos,
+        chan: pkt.chan,
+        _ts_kind: pkt._ts_kind,
+        merge_emit_ns: 0,
+    }
+}
+
+/// Impairment thread for RX worker `worker` of a line: `q_in` is what the worker
+/// fills, `q_out` what the merge reads. `epoch` is the start of the partition's RX.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_chaos(
+    cfg: &ChaosCfg,
+    worker: usize,
+    epoch: Instant,
+    partition: &str,
+    chan: &str,
+    q_in: Arc<SpscQueue<Pkt>>,
+    q_out: Arc<SpscQueue<Pkt>>,
+    pool: Arc<PacketPool>,
+    shutdown: Arc<BarrierFlag>,
+) -> std::io::Result<std::thread::JoinHandle<()>> {
+    let seed = match cfg.seed {
+        0 => crate::util::now_nanos(),
+        s => s,
+    } ^ (worker as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15);
+    let mut imp = Impairment::new(cfg.clone(), seed, partition, chan);
+    let (partition, chan) = (partition.to_string(), chan.to_string());
+    std::thread::Builder::new()
+        .name(format!("chaos-{partition}-{chan}-{worker}"))
+        .spawn(move || {
+            let mut out = Vec::new();
+            while !shutdown.is_raised() {
+                let now = epoch.elapsed().as_nanos() as u64;
+                let pkt = q_in.pop();
+                let idle = pkt.is_none();
+                if let Some(p) = pkt {
+                    imp.push(p, now, &pool, &mut out);
+                }
+                imp.poll(now, &mut out);
+                for p in out.drain(..) {
+                    if let Err(p) = q_out.push(p) {
+                        metrics::inc_rx_drop(&partition, &chan);
+                        p.recycle(&pool);
+                    }
+                }
+                if idle {
+                    std::thread::yield_now();
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::TsKind;
+
+    fn pkt(pool: &PacketPool, seq: u64) -> Pkt {
+        let mut buf = pool.get();
+        buf.extend_from_slice(&seq.to_be_bytes());
+        Pkt {
+            buf: PktBuf::Bytes(buf),
+            len: 8,
+            seq,
+            stream: 0,
+            seq_span: 1,
+            seq_reset: false,
+            ts_nanos: 0,
+            chan: 0,
+            _ts_kind: TsKind::Sw,
+            merge_emit_ns: 0,
+        }
+    }
+
+    /// Push `seqs` 1 ms apart from t = 0, then poll well past any hold or delay
+    fn run(cfg: ChaosCfg, seqs: impl Iterator<Item = u64>) -> Vec<u64> {
+        let pool = PacketPool::new(64, 64).unwrap();
+        let mut imp = Impairment::new(cfg, 7, "test", "A");
+        let mut out = Vec::new();
+        let mut t = 0;
+        for s in seqs {
+            imp.push(pkt(&pool, s), t, &pool, &mut out);
+            t += 1_000_000;
+        }
+        imp.poll(t + 10_000_000, &mut out);
+        out.iter()
+            .map(|p| {
+                assert_eq!(p.payload(), p.seq.to_be_bytes());
+                p.seq
+            })
+            .collect()
+    }
+
+    #[test]
+    fn impairs_by_schedule_and_distribution() {
+        // Cut for 2 ms at 3 ms, every 10 ms
+        let cut = ChaosCfg {
+            cuts: vec![ChaosCut {
+                at_ms: 3,
+                for_ms: 2,
+                every_ms: Some(10),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            run(cut, 0..16),
+            vec![0, 1, 2, 5, 6, 7, 8, 9, 10, 11, 12, 15]
+        );
+
+        let dup = ChaosCfg {
+            duplicate: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(run(dup, 0..3), vec![0, 0, 1, 1, 2, 2]);
+
+        let burst = ChaosCfg {
+            burst_start: 1.0,
+            burst_len: 3,
+            ..Default::default()
+        };
+        assert!(run(burst, 0..9).is_empty());
+
+        // Every packet comes out once, held ones at most the deepest depth late
+        let reorder = ChaosCfg {
+            reorder: 0.3,
+            reorder_depths: vec![1, 1, 3],
+            ..Default::default()
+        };
+        let got = run(reorder, 0..200);
+        let mut sorted = got.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..200).collect::<Vec<_>>());
+        assert_ne!(got, sorted);
+        for (i, s) in got.iter().enumerate() {
+            assert!(i as u64 <= s + 3 * 2, "{s} at {i}");
+        }
+
+        // Delay keeps the order and holds packets back until due
+        let pool = PacketPool::new(4, 64).unwrap();
+        let mut imp = Impairment::new(
+            ChaosCfg {
+                delay_us: 100,
+                ..Default::default()
+            },
+            7,
+            "test",
+            "A",
+        );
+        let mut out = Vec::new();
+        imp.push(pkt(&pool, 1), 0, &pool, &mut out);
+        imp.push(pkt(&pool, 2), 50_000, &pool, &mut out);
+        imp.poll(99_999, &mut out);
+        assert!(out.is_empty());
+        imp.poll(100_000, &mut out);
+        assert_eq!(out.iter().map(|p| p.seq).collect::<Vec<_>>(), vec![1]);
+        imp.poll(150_000, &mut out);
+        assert_eq!(out.len(), 2);
+    }
+}
@@ -5,7 +5,9 @@
 use zerocopy::{AsBytes, FromBytes, FromZeroes, Unaligned};
 
 pub const MAGIC: [u8; 4] = *b"OBv1";
+#[allow(dead_code)] // v1 frames are no longer produced; kept for readers
 pub const VERSION_V1: u8 = 1;
+pub const VERSION_V2: u8 = 2; // FrameHeaderV2: adds exchange_time_ns
 
 // Codec identifiers
 pub mod codec {
@@ -24,6 +26,9 @@ pub mod msg_type {
     pub const GAP: u16 = 2;
     pub const SNAPSHOT_START: u16 = 3;
     pub const SNAPSHOT_END: u16 = 4;
+    pub const SEQ_RESET: u16 = 5; // SeqResetV1: venue sequence restarted; explicit resets clear books
+    pub const FEED_GAP: u16 = 6; // FeedGapV1: venue sequences given up on; instrument is stale
+    pub const BOOK_RECOVERED: u16 = 7; // BookRecoveredV1: book rebuilt; no longer stale
 
     // OBO events
     pub const OBO_ADD: u16 = 100;
@@ -31,8 +36,10 @@ pub mod msg_type {
     pub const OBO_CANCEL: u16 = 102;
     pub const OBO_EXECUTE: u16 = 103;
     pub const SNAPSHOT_HDR: u16 = 104; // FullBookSnapshotHdrV1
+    pub const OBO_LEVEL: u16 = 105; // OboLevelV1: venue price level (MBP) update
 }
 
+#[allow(dead_code)]
 #[repr(C, packed)]
 #[derive(Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct FrameHeaderV1 {
@@ -47,6 +54,23 @@ pub struct FrameHeaderV1 {
     pub payload_len: u32,
 }
 
+/// Header revision 2: `FrameHeaderV1` plus the venue timestamp of the event that
+/// produced the frame. `exchange_time_ns` is ns since Unix epoch, 0 when unknown.
+#[repr(C, packed)]
+#[derive(Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct FrameHeaderV2 {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub codec: u8,
+    pub message_type: u16,
+    pub channel_id: u32,
+    pub instrument_id: u64,
+    pub sequence: u64,
+    pub send_time_ns: u64,
+    pub exchange_time_ns: u64,
+    pub payload_len: u32,
+}
+
 // --------------------------- Control Payloads ----------------------------
 
 #[repr(C, packed)]
@@ -56,6 +80,55 @@ pub struct GapV1 {
     pub to_inclusive: u64,
 }
 
+/// Venue sequences the merge gave up on (`merge.on_give_up`). Sent per affected
+/// instrument; its book is stale until a snapshot restores it.
+#[repr(C, packed)]
+#[derive(Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct FeedGapV1 {
+    pub from_inclusive: u64,
+    pub to_inclusive: u64,
+    pub action: u8,     // gap_action::*
+    pub stream_id: u32, // sequence stream the gap belongs to (0 unless the feed multiplexes)
+}
+
+pub mod gap_action {
+    pub const SKIP: u8 = 1; // book kept, updates resume after the hole
+    pub const RESYNC: u8 = 2; // book cleared; a snapshot resync was requested
+}
+
+/// The venue restarted its sequence (new session, engine failover); merge re-anchored
+/// at `new_seq`. Per-instrument OBO sequences are unaffected; after an EXPLICIT reset
+/// the books of the restarted stream are cleared.
+#[repr(C, packed)]
+#[derive(Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct SeqResetV1 {
+    pub new_seq: u64,
+    pub prev_next_seq: u64, // sequence merge was waiting for before the reset
+    pub kind: u8,           // reset_kind::*
+    pub stream_id: u32,     // sequence stream that restarted
+}
+
+pub mod reset_kind {
+    pub const EXPLICIT: u8 = 1; // the sequence extractor flagged a reset packet
+    pub const HEURISTIC: u8 = 2; // lines jumped back by more than merge.reset_backjump
+}
+
+/// The instrument's book was rebuilt (`recovery_source::*`) and follows the feed again
+/// from venue sequence `from_seq`. Sent per instrument; clears a FEED_GAP's staleness.
+#[repr(C, packed)]
+#[derive(Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct BookRecoveredV1 {
+    pub from_seq: u64,
+    pub source: u8,     // recovery_source::*
+    pub stream_id: u32, // sequence stream `from_seq` belongs to
+}
+
+pub mod recovery_source {
+    pub const SNAPSHOT_FEED: u8 = 1; // rebuilt from the venue snapshot multicast
+    pub const VENUE_RESET: u8 = 2; // cleared by an explicit sequence reset; restarts empty
+    pub const GLIMPSE: u8 = 3; // rebuilt from a Glimpse snapshot
+}
+
 // --------------------------- OBO Payloads -------------------------------
 
 #[repr(C, packed)]
@@ -95,6 +168,29 @@ pub struct OboExecuteV1 {
     pub match_id: u64,
 }
 
+/// Price level update from a venue that sends MBP books (MDP 3.0 MDIncrementalRefreshBook).
+/// `level` is the 1-based position on the side, as the venue numbers it.
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
+pub struct OboLevelV1 {
+    pub price_e8: i64,
+    pub qty: u64,
+    pub orders: u32,
+    pub side: u8,   // 0 = Bid, 1 = Ask
+    pub level: u8,  // 1 = best
+    pub action: u8, // level_action::*
+    pub flags: u8,  // bit 0 = implied level
+}
+
+pub mod level_action {
+    pub const NEW: u8 = 0; // insert at `level`, shifting the levels behind it back
+    pub const CHANGE: u8 = 1; // replace `level`
+    pub const DELETE: u8 = 2; // remove `level`, shifting the levels behind it forward
+    pub const DELETE_THRU: u8 = 3; // remove levels 1 through `level`
+    pub const DELETE_FROM: u8 = 4; // remove `level` and every level behind it
+    pub const OVERLAY: u8 = 5; // replace `level` (same as CHANGE)
+}
+
 #[repr(C, packed)]
 #[derive(Clone, Copy, FromZeroes, FromBytes, AsBytes, Unaligned)]
 pub struct FullBookSnapshotHdrV1 {
@@ -1,18 +1,33 @@
 // src/config.rs
 use serde::Deserialize;
-use std::{fs, net::Ipv4Addr, path::Path};
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr},
+    path::Path,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AppConfig {
     pub general: General,
     pub sequence: Sequence,
     pub parser: Parser,
-    pub channels: Channels,
+    /// Single A/B pair; shorthand for one partition named "0"
+    #[serde(default)]
+    pub channels: Option<Channels>,
+    /// Independent A/B pairs, each with its own sequence space, merge and recovery
+    #[serde(default)]
+    pub partitions: Vec<PartitionCfg>,
     pub merge: Merge,
     pub book: Book,
     pub cpu: Cpu,
     pub metrics: Option<Metrics>,
     pub snapshot: Option<SnapshotCfg>,
+    /// Seed the book from a Glimpse snapshot over SoupBinTCP at start (ITCH only)
+    #[serde(default)]
+    pub glimpse: Option<GlimpseCfg>,
+    /// Late join and gap recovery from the venue's snapshot multicast (EOBI only)
+    #[serde(default)]
+    pub snapshot_feed: Option<SnapshotFeedCfg>,
     pub recovery: Option<RecoveryCfg>,
     pub afxdp: Option<AfxdpCfg>,
     #[serde(default)]
@@ -39,23 +54,73 @@ pub struct Sequence {
     pub offset: u16,    // bytes into packet payload
     pub length: u8,     // 4 or 8 for u32/u64
     pub endian: Endian, // "be" or "le"
+    #[serde(default)]
+    pub unit: Option<u8>, // PITCH: only accept this Sequenced Unit (all units if unset)
+    #[serde(default)]
+    pub stream_offset: Option<u16>, // stream id field for feeds multiplexing sequence spaces
+    #[serde(default = "default_stream_length")]
+    pub stream_length: u8, // 1, 2 or 4 bytes, same endianness as the sequence
+    #[serde(default)]
+    pub moldudp64: bool, // ITCH over MoldUDP64: sequence and message count from its header
+}
+
+fn default_stream_length() -> u8 {
+    1
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Parser {
     pub kind: ParserKind,
     pub max_messages_per_packet: usize,
+    /// Venue local time minus UTC, in seconds (e.g. -18000 for US Eastern standard time).
+    /// ITCH/PITCH timestamps count from the venue's midnight.
+    #[serde(default)]
+    pub session_utc_offset_s: i32,
+    /// Debug: append the first N payloads that trip a decoder issue to a file
+    #[serde(default)]
+    pub capture_bad_payloads: Option<CaptureCfg>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "snake_case")]
+pub struct CaptureCfg {
+    pub path: String,
+    #[serde(default = "default_capture_max")]
+    pub max: usize,
+}
+
+fn default_capture_max() -> usize {
+    100
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
 pub enum ParserKind {
-    #[serde(rename = "fixed_binary")]
     FixedBinary,
-    #[serde(rename = "fast_like")]
     FastLike,
-    #[serde(rename = "itch50")]
     Itch50,
+    Pitch,
+    Mdp3,
+    /// Decoder registered at runtime via `parser::register_decoder` ("custom:<name>")
+    Custom(String),
+}
+
+impl TryFrom<String> for ParserKind {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "fixed_binary" => Ok(ParserKind::FixedBinary),
+            "fast_like" => Ok(ParserKind::FastLike),
+            "itch50" => Ok(ParserKind::Itch50),
+            "pitch" => Ok(ParserKind::Pitch),
+            "mdp3" => Ok(ParserKind::Mdp3),
+            _ => match s.strip_prefix("custom:") {
+                Some(name) if !name.is_empty() => Ok(ParserKind::Custom(name.to_string())),
+                Some(_) => Err("parser.kind \"custom:\" requires a decoder name".to_string()),
+                None => Err(format!("unknown parser.kind \"{s}\"")),
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -69,13 +134,21 @@ pub enum Endian {
 pub struct Channels {
     pub a: ChannelCfg,
     pub b: ChannelCfg,
+    /// Further copies of the feed (wireless, WAN backup, ...) arbitrated alongside A/B
+    #[serde(default)]
+    pub extra: Vec<ChannelCfg>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChannelCfg {
-    pub group: Ipv4Addr,      // e.g., 239.10.10.1
-    pub port: u16,            // e.g., 5001
-    pub iface_addr: Ipv4Addr, // local interface IPv4 of the NIC to join on
+    pub group: IpAddr, // e.g., 239.10.10.1 or ff3e::8000:1
+    pub port: u16,     // e.g., 5001
+    #[serde(default)]
+    pub iface_addr: Option<Ipv4Addr>, // IPv4 groups: local interface address of the NIC to join on
+    #[serde(default)]
+    pub iface: Option<String>, // IPv6 groups: interface name, joined by index (default: routing table)
+    #[serde(default)]
+    pub sources: Vec<IpAddr>, // SSM: join (source, group) for each; empty = any-source join
     pub reuse_port: bool,
     pub recv_buffer_bytes: u32, // e.g., 64<<20
     #[allow(dead_code)]
@@ -85,6 +158,202 @@ pub struct ChannelCfg {
     pub timestamping: Option<TimestampingMode>, // default Off
     #[serde(default)]
     pub workers: Option<usize>, // per-channel UDP RX sockets/threads (requires reuse_port)
+    #[serde(default)]
+    pub rx_backend: RxBackend, // socket (recvmsg/recvmmsg) or io_uring multishot
+    #[serde(default)]
+    pub io_uring_sqpoll_idle_ms: Option<u32>, // enable SQPOLL with this idle timeout
+    #[serde(default)]
+    pub name: Option<String>, // line name for `extra` entries (A/B are always "A"/"B")
+    #[serde(default)]
+    pub priority: Option<u32>, // lower wins; default A=0, B=1, extra lines 2, 3, ...
+    #[serde(default)]
+    pub switch_after: Option<u32>, // consecutive wins before this line becomes preferred (default 2)
+    #[serde(default)]
+    pub revert_after: Option<u32>, // while preferred: forwards before retrying the top line (default 8)
+    #[serde(default)]
+    pub rx_core: Option<usize>, // overrides the partition/cpu RX core for this line
+    #[serde(default)]
+    pub chaos: Option<ChaosCfg>, // testing: impair this line's packets between RX and merge
+}
+
+/// `chaos` on a line: fault injection between its RX workers and the merge. Each
+/// worker queue gets its own impairment thread; probabilities are per packet.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ChaosCfg {
+    /// Independent random loss
+    pub loss: f64,
+    /// Chance that a loss burst starts at a packet
+    pub burst_start: f64,
+    /// Packets dropped per burst, the starting one included
+    pub burst_len: u32,
+    /// Chance that a packet is held back behind later ones
+    pub reorder: f64,
+    /// Packets a held one waits behind, sampled uniformly (repeat a depth to weight it)
+    pub reorder_depths: Vec<u32>,
+    /// Chance that a packet is delivered twice
+    pub duplicate: f64,
+    /// Added to every packet's delivery (FIFO, no reordering)
+    pub delay_us: u64,
+    /// Windows in which the line delivers nothing, from the start of RX
+    pub cuts: Vec<ChaosCut>,
+    /// Random seed (0: from the clock); workers derive their own from it
+    pub seed: u64,
+}
+
+/// `{ at_ms = 5000, for_ms = 200 }`: cut the line for `for_ms` at `at_ms`, again every
+/// `every_ms` if set
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosCut {
+    pub at_ms: u64,
+    pub for_ms: u64,
+    #[serde(default)]
+    pub every_ms: Option<u64>,
+}
+
+/// Arbitration defaults when a line leaves them unset
+pub const DEFAULT_SWITCH_AFTER: u32 = 2;
+pub const DEFAULT_REVERT_AFTER: u32 = 8;
+/// A/B plus extra lines must fit the `Pkt.chan` index (`pool::CHAN_RECOVERY` is reserved)
+pub const MAX_LINES: usize = 16;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PartitionCfg {
+    pub name: String, // metrics label and thread name component
+    pub a: ChannelCfg,
+    pub b: ChannelCfg,
+    #[serde(default)]
+    pub extra: Vec<ChannelCfg>,
+    #[serde(default)]
+    pub initial_expected_seq: Option<u64>, // default merge.initial_expected_seq
+    #[serde(default)]
+    pub a_rx_core: Option<usize>,
+    #[serde(default)]
+    pub b_rx_core: Option<usize>,
+    #[serde(default)]
+    pub merge_core: Option<usize>,
+    #[serde(default)]
+    pub recovery_endpoint: Option<String>, // overrides recovery.endpoint for this partition
+}
+
+/// One arbitrated copy of a partition's feed, with defaults resolved
+#[derive(Debug, Clone)]
+pub struct Line<'a> {
+    pub name: &'a str,
+    pub cfg: &'a ChannelCfg,
+    pub rx_core: Option<usize>,
+    pub priority: u32,
+    pub switch_after: u32,
+    pub revert_after: u32,
+}
+
+impl PartitionCfg {
+    /// A, B, then `extra` in config order; the index is the line's `Pkt.chan`.
+    pub fn lines(&self) -> Vec<Line<'_>> {
+        let fixed = [
+            ("A", &self.a, self.a_rx_core),
+            ("B", &self.b, self.b_rx_core),
+        ];
+        let extra = self
+            .extra
+            .iter()
+            .map(|ch| (ch.name.as_deref().unwrap_or(""), ch, None));
+        fixed
+            .into_iter()
+            .chain(extra)
+            .enumerate()
+            .map(|(i, (name, ch, core))| Line {
+                name,
+                cfg: ch,
+                rx_core: ch.rx_core.or(core),
+                priority: ch.priority.unwrap_or(i as u32),
+                switch_after: ch.switch_after.unwrap_or(DEFAULT_SWITCH_AFTER),
+                revert_after: ch.revert_after.unwrap_or(DEFAULT_REVERT_AFTER),
+            })
+            .collect()
+    }
+}
+
+impl ChannelCfg {
+    /// Membership and RX checks; `name` is the config path, e.g. `channels.a`.
+    fn validate(&self, name: &str) -> anyhow::Result<()> {
+        if !self.group.is_multicast() {
+            anyhow::bail!("{name}.group must be a multicast address");
+        }
+        if self.group.is_ipv4() {
+            if self.iface_addr.is_none() {
+                anyhow::bail!("{name}.iface_addr is required for IPv4 groups");
+            }
+            if self.iface.is_some() {
+                anyhow::bail!("{name}.iface applies to IPv6 groups; use iface_addr");
+            }
+        } else {
+            if self.iface_addr.is_some() {
+                anyhow::bail!("{name}.iface_addr applies to IPv4 groups; use iface");
+            }
+            if matches!(&self.iface, Some(i) if i.trim().is_empty()) {
+                anyhow::bail!("{name}.iface must be non-empty if set");
+            }
+        }
+        for src in &self.sources {
+            if src.is_ipv4() != self.group.is_ipv4() {
+                anyhow::bail!("{name}.sources: {src} is not the same family as the group");
+            }
+            if src.is_multicast() || src.is_unspecified() {
+                anyhow::bail!("{name}.sources: {src} is not a unicast source address");
+            }
+        }
+        if self.workers.unwrap_or(1) > 1 && !self.reuse_port {
+            anyhow::bail!("{name}.workers > 1 requires reuse_port = true");
+        }
+        if self.io_uring_sqpoll_idle_ms.is_some() && self.rx_backend != RxBackend::IoUring {
+            anyhow::bail!("{name}.io_uring_sqpoll_idle_ms requires rx_backend = \"io_uring\"");
+        }
+        if self.switch_after == Some(0) || self.revert_after == Some(0) {
+            anyhow::bail!("{name}.switch_after and {name}.revert_after must be > 0");
+        }
+        if let Some(c) = &self.chaos {
+            c.validate(&format!("{name}.chaos"))?;
+        }
+        Ok(())
+    }
+}
+
+impl ChaosCfg {
+    fn validate(&self, name: &str) -> anyhow::Result<()> {
+        for (k, p) in [
+            ("loss", self.loss),
+            ("burst_start", self.burst_start),
+            ("reorder", self.reorder),
+            ("duplicate", self.duplicate),
+        ] {
+            if !(0.0..=1.0).contains(&p) {
+                anyhow::bail!("{name}.{k} must be a probability in [0, 1]");
+            }
+        }
+        if self.burst_start > 0.0 && self.burst_len == 0 {
+            anyhow::bail!("{name}.burst_len must be > 0 with burst_start");
+        }
+        if self.reorder > 0.0
+            && (self.reorder_depths.is_empty() || self.reorder_depths.contains(&0))
+        {
+            anyhow::bail!("{name}.reorder needs reorder_depths, each > 0");
+        }
+        for c in &self.cuts {
+            if c.for_ms == 0 || c.every_ms.is_some_and(|e| e <= c.for_ms) {
+                anyhow::bail!("{name}.cuts: for_ms must be > 0 and below every_ms");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RxBackend {
+    #[default]
+    Socket,
+    IoUring,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -93,11 +362,79 @@ pub struct Merge {
     pub reorder_window: u64,        // window for out-of-order buffering
     pub max_pending_packets: usize, // hard cap for pending map
     #[serde(default)]
-    pub dwell_ns: Option<u64>, // preferred minimum dwell between A/B switches
+    pub dwell_ns: Option<u64>, // preferred minimum dwell between preferred-line switches
     #[serde(default)]
     pub adaptive: bool, // enable adaptive reorder window tuning
     #[serde(default)]
     pub reorder_window_max: Option<u64>, // cap for adaptive window
+    #[serde(default)]
+    pub gap_timeout_ns: Option<u64>, // declare an in-window hole after it has held packets this long
+    #[serde(default)]
+    pub give_up_after_ns: Option<u64>, // how long a declared gap may wait for recovery
+    #[serde(default)]
+    pub on_give_up: GiveUpPolicy,
+    #[serde(default)]
+    pub reset_backjump: Option<u64>, // backwards jump (on two lines) taken as a sequence reset
+    #[serde(default)]
+    pub verify: Option<VerifyCfg>, // compare late duplicates with the forwarded payload
+    #[serde(default)]
+    pub race: Option<RaceCfg>, // per-line win/loss analytics
+    #[serde(default)]
+    pub max_streams: Option<usize>, // sequence streams tracked per partition (default 64)
+}
+
+/// `[merge.race]`: line race analytics from the RX timestamps of every line copy
+#[derive(Debug, Clone, Deserialize)]
+pub struct RaceCfg {
+    /// Sequences tracked until they are scored (late copies must arrive within this)
+    #[serde(default = "default_race_history")]
+    pub history: usize,
+    /// Win/loss ratio gauges cover the last `window` scored sequences
+    #[serde(default = "default_race_window")]
+    pub window: u64,
+    /// Log a line-quality report this often (0 = off)
+    #[serde(default = "default_race_report_ms")]
+    pub report_interval_ms: u64,
+}
+
+fn default_race_history() -> usize {
+    4096
+}
+
+fn default_race_window() -> u64 {
+    100_000
+}
+
+fn default_race_report_ms() -> u64 {
+    10_000
+}
+
+/// `[merge.verify]`: A/B payload divergence checks
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerifyCfg {
+    /// Forwarded sequences remembered for comparison with late copies
+    #[serde(default = "default_verify_history")]
+    pub history: usize,
+    /// Append the first N divergent pairs (both payloads, hex) to a file
+    #[serde(default)]
+    pub capture: Option<CaptureCfg>,
+}
+
+fn default_verify_history() -> usize {
+    4096
+}
+
+/// What merge does once a declared gap outlives `merge.give_up_after_ns`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GiveUpPolicy {
+    /// Keep waiting for recovery (merge stays on the missing sequence)
+    #[default]
+    Wait,
+    /// Skip past the hole; affected instruments are marked stale
+    Skip,
+    /// Skip past the hole, clear affected books and request a snapshot
+    Resync,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -115,8 +452,85 @@ pub struct RecoveryCfg {
     /// TCP endpoint of replay service (e.g. "10.0.0.1:9000")
     pub endpoint: String,
     #[serde(default)]
-    /// Optional path to append-only backlog of gap requests
+    /// Optional path to the replay journal: requested, filled and abandoned ranges
     pub backlog_path: Option<String>,
+    /// At start, re-request ranges the journal left unresolved in the current venue
+    /// session (false: report and close them)
+    #[serde(default)]
+    pub resume_backlog: bool,
+    /// Further replay endpoints, tried in order when the current one fails
+    #[serde(default)]
+    pub fallback_endpoints: Vec<String>,
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Deadline for one replay response; missing sequences are then retried
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// Requests per range before it is abandoned
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// First retry delay, doubled per attempt up to retry_backoff_max_ms
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    #[serde(default = "default_retry_backoff_max_ms")]
+    pub retry_backoff_max_ms: u64,
+    /// TCP keepalive idle time on the persistent replay connection (0 = off)
+    #[serde(default = "default_keepalive_ms")]
+    pub keepalive_ms: u64,
+    /// Gap ranges tracked at once
+    #[serde(default = "default_max_outstanding")]
+    pub max_outstanding: usize,
+    /// Re-request gaps from a MoldUDP64 server at `endpoint` instead of the TCP replay protocol
+    #[serde(default)]
+    pub moldudp64: Option<MoldCfg>,
+}
+
+/// `[recovery.moldudp64]`: MoldUDP64 retransmission requests
+#[derive(Debug, Clone, Deserialize)]
+pub struct MoldCfg {
+    /// Session name as carried in the downstream header (up to 10 characters)
+    pub session: String,
+    /// Most messages the re-request server answers per request
+    #[serde(default = "default_mold_max_count")]
+    pub max_request_count: u16,
+    #[serde(default = "default_mold_rate")]
+    pub requests_per_sec: u32,
+}
+
+fn default_mold_max_count() -> u16 {
+    1000
+}
+
+fn default_mold_rate() -> u32 {
+    1000
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    100
+}
+
+fn default_request_timeout_ms() -> u64 {
+    200
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    10
+}
+
+fn default_retry_backoff_max_ms() -> u64 {
+    500
+}
+
+fn default_keepalive_ms() -> u64 {
+    5000
+}
+
+fn default_max_outstanding() -> usize {
+    4096
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -145,6 +559,60 @@ pub struct SnapshotCfg {
     pub enable_writer: bool,
 }
 
+/// `[glimpse]`: Glimpse snapshot server (SoupBinTCP)
+#[derive(Debug, Clone, Deserialize)]
+pub struct GlimpseCfg {
+    /// host:port of the snapshot server
+    pub endpoint: String,
+    pub username: String,
+    pub password: String,
+    /// Session to join; empty for the current one
+    #[serde(default)]
+    pub session: String,
+    /// Partition whose book the snapshot seeds (default: the first)
+    #[serde(default)]
+    pub partition: Option<String>,
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Deadline for the whole download, End of Snapshot included
+    #[serde(default = "default_glimpse_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Live packets held back while a snapshot loads; one ending before the oldest is fetched again
+    #[serde(default = "default_snapshot_buffer")]
+    pub max_buffered_packets: usize,
+}
+
+fn default_glimpse_timeout_ms() -> u64 {
+    60_000
+}
+
+/// `[snapshot_feed]`: cyclic snapshot multicast of an EOBI-style feed
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotFeedCfg {
+    /// Snapshot group; joined only while instruments await a snapshot
+    pub channel: ChannelCfg,
+    /// Partition whose books it rebuilds (default: the first)
+    #[serde(default)]
+    pub partition: Option<String>,
+    /// Build every instrument from one snapshot cycle at start
+    #[serde(default = "default_true")]
+    pub on_start: bool,
+    /// Rebuild the instruments of gaps the merge gives up on
+    #[serde(default = "default_true")]
+    pub on_gap: bool,
+    /// Incremental packets kept while joined; a snapshot needing evicted ones is skipped
+    #[serde(default = "default_snapshot_buffer")]
+    pub max_buffered_packets: usize,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_snapshot_buffer() -> usize {
+    65536
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct AfxdpCfg {
     #[serde(default)]
@@ -154,12 +622,57 @@ pub struct AfxdpCfg {
     #[serde(default)]
     /// Number of RX queues (RSS) to spawn when using AF_XDP/AF_PACKET ring
     pub queues: Option<usize>,
+    /// XDP attach mode for the bundled redirect program
+    #[serde(default)]
+    pub xdp_mode: XdpMode,
+    /// UMEM frames (2 KiB each) per queue; also the fill/RX ring size
+    #[serde(default = "default_umem_frames")]
+    pub umem_frames: u32,
+    /// AF_PACKET ring used as fallback (or directly with `xdp_mode = "off"`)
+    #[serde(default)]
+    pub packet_ring: PacketRing,
+    /// TPACKET_V3 block retire timeout: upper bound on added latency at low rates
+    #[serde(default = "default_block_timeout_ms")]
+    pub block_timeout_ms: u32,
+    /// Packet rings only: accept frames tagged with this VLAN id (in-frame or offloaded tag)
+    #[serde(default)]
+    pub vlan_id: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PacketRing {
+    #[default]
+    TpacketV2,
+    TpacketV3,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum XdpMode {
+    /// Native driver mode, falling back to generic
+    #[default]
+    Auto,
+    /// Native driver mode only (zero-copy where the driver supports it)
+    Drv,
+    /// Generic (SKB) mode; works on any device including veth, copy only
+    Skb,
+    /// Skip AF_XDP and use the packet ring directly
+    Off,
 }
 
 fn default_ifname() -> String {
     "eth0".to_string()
 }
 
+fn default_umem_frames() -> u32 {
+    4096
+}
+
+fn default_block_timeout_ms() -> u32 {
+    1
+}
+
 impl AppConfig {
     pub fn from_file(p: &Path) -> anyhow::Result<Self> {
         let s = fs::read_to_string(p)?;
@@ -168,26 +681,171 @@ impl AppConfig {
         Ok(cfg)
     }
 
+    /// Partitions to run: `[[partitions]]` as configured, or `[channels]` as partition "0"
+    /// pinned with the global `cpu` cores.
+    pub fn partitions(&self) -> Vec<PartitionCfg> {
+        match &self.channels {
+            Some(ch) if self.partitions.is_empty() => vec![PartitionCfg {
+                name: "0".into(),
+                a: ch.a.clone(),
+                b: ch.b.clone(),
+                extra: ch.extra.clone(),
+                initial_expected_seq: None,
+                a_rx_core: self.cpu.a_rx_core,
+                b_rx_core: self.cpu.b_rx_core,
+                merge_core: self.cpu.merge_core,
+                recovery_endpoint: None,
+            }],
+            _ => self.partitions.clone(),
+        }
+    }
+
     pub fn validate(&self) -> anyhow::Result<()> {
-        if !self.channels.a.group.is_multicast() || !self.channels.b.group.is_multicast() {
-            anyhow::bail!("channels.a.group and channels.b.group must be multicast IPv4 addresses");
+        match (&self.channels, self.partitions.is_empty()) {
+            (Some(_), false) => anyhow::bail!("use either [channels] or [[partitions]], not both"),
+            (None, true) => anyhow::bail!("no feed configured: add [channels] or [[partitions]]"),
+            _ => {}
+        }
+        if let Some(ch) = &self.channels {
+            ch.a.validate("channels.a")?;
+            ch.b.validate("channels.b")?;
+            validate_extra_lines(&ch.extra, "channels")?;
+        }
+        let mut names = std::collections::HashSet::new();
+        for p in &self.partitions {
+            if p.name.is_empty()
+                || !p
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                anyhow::bail!(
+                    "partitions.name {:?} must be non-empty [A-Za-z0-9_-]",
+                    p.name
+                );
+            }
+            if !names.insert(p.name.as_str()) {
+                anyhow::bail!("duplicate partition name {:?}", p.name);
+            }
+            p.a.validate(&format!("partitions.{}.a", p.name))?;
+            p.b.validate(&format!("partitions.{}.b", p.name))?;
+            validate_extra_lines(&p.extra, &format!("partitions.{}", p.name))?;
+            if matches!(&p.recovery_endpoint, Some(e) if !e.contains(':')) {
+                anyhow::bail!("partitions.{}.recovery_endpoint must be host:port", p.name);
+            }
+        }
+        // Busy-polling RX, merge and decode threads sharing a core starve each other
+        let mut pinned = std::collections::HashMap::new();
+        let mut pin = |core: Option<usize>, thread: String| match core {
+            Some(c) => match pinned.insert(c, thread.clone()) {
+                Some(other) => anyhow::bail!("core {c} is assigned to both {other} and {thread}"),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        };
+        for p in self.partitions() {
+            for line in p.lines() {
+                for w in 0..line.cfg.workers.unwrap_or(1) {
+                    let thread = format!("partition {} line {} RX worker {w}", p.name, line.name);
+                    pin(line.rx_core.map(|c| c + w), thread)?;
+                }
+            }
+            pin(p.merge_core, format!("partition {} merge", p.name))?;
         }
+        pin(self.cpu.decode_core, "decode".into())?;
         if self.sequence.length != 4 && self.sequence.length != 8 {
             anyhow::bail!("sequence.length must be 4 or 8");
         }
+        if ![1, 2, 4].contains(&self.sequence.stream_length) {
+            anyhow::bail!("sequence.stream_length must be 1, 2 or 4");
+        }
+        if self.sequence.moldudp64 && !matches!(self.parser.kind, ParserKind::Itch50) {
+            anyhow::bail!("sequence.moldudp64 requires parser.kind = \"itch50\"");
+        }
         if self.general.max_packet_size < 512 || self.general.max_packet_size > 65535 {
             anyhow::bail!("general.max_packet_size must be in [512, 65535]");
         }
+        // The registry lives in the library; the receiver binaries never fill theirs
+        if let ParserKind::Custom(name) = &self.parser.kind {
+            if !crate::parser::decoder_registered(name) {
+                anyhow::bail!(
+                    "parser.kind = \"custom:{name}\": no decoder registered under that name \
+                     (custom decoders are library-only: call orderbook::register_decoder before \
+                     loading the config)"
+                );
+            }
+        }
+        if self.parser.session_utc_offset_s.abs() > 14 * 3600 {
+            anyhow::bail!("parser.session_utc_offset_s must be within +/-14h");
+        }
+        if let Some(c) = &self.parser.capture_bad_payloads {
+            if c.path.is_empty() || c.max == 0 {
+                anyhow::bail!("parser.capture_bad_payloads needs a path and max > 0");
+            }
+        }
         // Touch optional logging flag to ensure it's validated across minimal binaries
         let _ = self.general.json_logs;
         if self.merge.reorder_window == 0 {
             anyhow::bail!("merge.reorder_window must be > 0");
         }
-        if self.channels.a.workers.unwrap_or(1) > 1 && !self.channels.a.reuse_port {
-            anyhow::bail!("channels.a.workers > 1 requires reuse_port = true");
+        if self.merge.gap_timeout_ns == Some(0) {
+            anyhow::bail!("merge.gap_timeout_ns must be > 0 if set");
+        }
+        match (self.merge.on_give_up, self.merge.give_up_after_ns) {
+            (GiveUpPolicy::Wait, Some(_)) => {
+                anyhow::bail!("merge.give_up_after_ns needs on_give_up = \"skip\" or \"resync\"")
+            }
+            (GiveUpPolicy::Skip | GiveUpPolicy::Resync, None | Some(0)) => {
+                anyhow::bail!("merge.on_give_up requires merge.give_up_after_ns > 0")
+            }
+            _ => {}
+        }
+        if self.merge.on_give_up == GiveUpPolicy::Resync {
+            // Resync clears books; only a snapshot source brings them back
+            let source = if self.glimpse.is_some() {
+                "glimpse"
+            } else if self.snapshot_feed.as_ref().is_some_and(|f| f.on_gap) {
+                "snapshot_feed"
+            } else {
+                anyhow::bail!(
+                    "merge.on_give_up = \"resync\" needs [glimpse] or [snapshot_feed] with on_gap = true"
+                );
+            };
+            if self.partitions().len() > 1 {
+                anyhow::bail!(
+                    "merge.on_give_up = \"resync\" clears books of every partition but {source} rebuilds one; use \"skip\""
+                );
+            }
+        }
+        if let Some(v) = &self.merge.verify {
+            if v.history == 0 {
+                anyhow::bail!("merge.verify.history must be > 0");
+            }
+            if let Some(c) = &v.capture {
+                if c.path.is_empty() || c.max == 0 {
+                    anyhow::bail!("merge.verify.capture needs a path and max > 0");
+                }
+            }
+        }
+        if let Some(r) = &self.merge.race {
+            if r.history == 0 || r.window == 0 {
+                anyhow::bail!("merge.race.history and merge.race.window must be > 0");
+            }
         }
-        if self.channels.b.workers.unwrap_or(1) > 1 && !self.channels.b.reuse_port {
-            anyhow::bail!("channels.b.workers > 1 requires reuse_port = true");
+        if self.merge.max_streams == Some(0) {
+            anyhow::bail!("merge.max_streams must be > 0 if set");
+        }
+        if let Some(jump) = self.merge.reset_backjump {
+            let window = self
+                .merge
+                .reorder_window_max
+                .unwrap_or(0)
+                .max(self.merge.reorder_window);
+            if jump <= window {
+                anyhow::bail!(
+                    "merge.reset_backjump ({jump}) must exceed the reorder window ({window})"
+                );
+            }
         }
         // Book constraints
         if self.book.max_depth == 0 {
@@ -239,6 +897,65 @@ impl AppConfig {
             let _ = s.load_on_start;
             let _ = s.enable_writer;
         }
+        // Glimpse cfg
+        if let Some(ref g) = self.glimpse {
+            if !matches!(self.parser.kind, ParserKind::Itch50) {
+                anyhow::bail!("glimpse requires parser.kind = \"itch50\"");
+            }
+            if !g.endpoint.contains(':') {
+                anyhow::bail!("glimpse.endpoint must be host:port");
+            }
+            for (field, v, max) in [
+                ("username", &g.username, 6),
+                ("password", &g.password, 10),
+                ("session", &g.session, 10),
+            ] {
+                if v.len() > max || !v.is_ascii() {
+                    anyhow::bail!("glimpse.{field} must be at most {max} ASCII characters");
+                }
+            }
+            if g.connect_timeout_ms == 0 || g.timeout_ms == 0 {
+                anyhow::bail!("glimpse.connect_timeout_ms and timeout_ms must be > 0");
+            }
+            if g.max_buffered_packets == 0 {
+                anyhow::bail!("glimpse.max_buffered_packets must be > 0");
+            }
+            if let Some(p) = &g.partition {
+                if !self.partitions().iter().any(|q| &q.name == p) {
+                    anyhow::bail!("glimpse.partition {p:?} is not a configured partition");
+                }
+            }
+            if self.snapshot.as_ref().is_some_and(|s| s.load_on_start) {
+                anyhow::bail!("glimpse and snapshot.load_on_start both seed the book; pick one");
+            }
+        }
+        // Snapshot feed cfg
+        if let Some(ref f) = self.snapshot_feed {
+            if !matches!(self.parser.kind, ParserKind::FixedBinary) {
+                anyhow::bail!("snapshot_feed requires parser.kind = \"fixed_binary\"");
+            }
+            f.channel.validate("snapshot_feed.channel")?;
+            if f.channel.chaos.is_some() {
+                anyhow::bail!("snapshot_feed.channel.chaos is not supported");
+            }
+            let _ = f.on_gap;
+            if f.max_buffered_packets == 0 {
+                anyhow::bail!("snapshot_feed.max_buffered_packets must be > 0");
+            }
+            if let Some(p) = &f.partition {
+                if !self.partitions().iter().any(|q| &q.name == p) {
+                    anyhow::bail!("snapshot_feed.partition {p:?} is not a configured partition");
+                }
+            }
+            if f.on_start
+                && (self.glimpse.is_some()
+                    || self.snapshot.as_ref().is_some_and(|s| s.load_on_start))
+            {
+                anyhow::bail!(
+                    "snapshot_feed.on_start and a loaded snapshot both seed the book; pick one"
+                );
+            }
+        }
         // Recovery cfg
         if let Some(ref r) = self.recovery {
             if r.enable_injector {
@@ -248,7 +965,33 @@ impl AppConfig {
                     );
                 }
             }
-            let _ = r.backlog_path; // read to avoid unused warning in minimal builds
+            let _ = (&r.backlog_path, r.resume_backlog); // read to avoid unused warning in minimal builds
+            if let Some(e) = r.fallback_endpoints.iter().find(|e| !e.contains(':')) {
+                anyhow::bail!("recovery.fallback_endpoints entry {e:?} must be host:port");
+            }
+            if r.connect_timeout_ms == 0 || r.request_timeout_ms == 0 {
+                anyhow::bail!("recovery.connect_timeout_ms and request_timeout_ms must be > 0");
+            }
+            if r.max_attempts == 0 || r.max_outstanding == 0 {
+                anyhow::bail!("recovery.max_attempts and max_outstanding must be > 0");
+            }
+            if r.retry_backoff_max_ms < r.retry_backoff_ms {
+                anyhow::bail!("recovery.retry_backoff_max_ms must be >= retry_backoff_ms");
+            }
+            if let Some(m) = &r.moldudp64 {
+                if !self.sequence.moldudp64 {
+                    anyhow::bail!("recovery.moldudp64 requires sequence.moldudp64 = true");
+                }
+                if m.session.is_empty() || m.session.len() > 10 || !m.session.is_ascii() {
+                    anyhow::bail!("recovery.moldudp64.session must be 1-10 ASCII characters");
+                }
+                if m.max_request_count == 0 || m.max_request_count == u16::MAX {
+                    anyhow::bail!("recovery.moldudp64.max_request_count must be in [1, 65534]");
+                }
+                if m.requests_per_sec == 0 {
+                    anyhow::bail!("recovery.moldudp64.requests_per_sec must be > 0");
+                }
+            }
         }
         // AF_XDP cfg (if present)
         if let Some(ref a) = self.afxdp {
@@ -256,12 +999,50 @@ impl AppConfig {
             if a.ifname.trim().is_empty() {
                 anyhow::bail!("afxdp.ifname must be non-empty if afxdp is configured");
             }
-            let _ = a.queues; // optional; just touch
+            let _ = (a.queues, a.xdp_mode, a.packet_ring, a.vlan_id); // optional; just touch
+            if !a.umem_frames.is_power_of_two() || a.umem_frames < 64 {
+                anyhow::bail!("afxdp.umem_frames must be a power of two >= 64");
+            }
+            if a.block_timeout_ms == 0 {
+                anyhow::bail!("afxdp.block_timeout_ms must be > 0");
+            }
+            // AF_XDP replaces channel A of the first partition
+            if a.enable
+                && !self
+                    .partitions()
+                    .first()
+                    .is_some_and(|p| p.a.group.is_ipv4())
+            {
+                anyhow::bail!("afxdp only parses IPv4/UDP; the first channel A group must be IPv4");
+            }
         }
         Ok(())
     }
 }
 
+/// Names are metric labels: unique, and distinct from A/B and recovery ("R").
+fn validate_extra_lines(extra: &[ChannelCfg], path: &str) -> anyhow::Result<()> {
+    if extra.len() + 2 > MAX_LINES {
+        anyhow::bail!("{path}: at most {} extra lines", MAX_LINES - 2);
+    }
+    let mut names = std::collections::HashSet::new();
+    for (i, ch) in extra.iter().enumerate() {
+        let name = ch.name.as_deref().unwrap_or("");
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            anyhow::bail!("{path}.extra[{i}].name must be non-empty [A-Za-z0-9_-]");
+        }
+        if matches!(name, "A" | "B" | "R") || !names.insert(name) {
+            anyhow::bail!("{path}.extra: line name {name:?} is reserved or duplicated");
+        }
+        ch.validate(&format!("{path}.extra.{name}"))?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TimestampingMode {
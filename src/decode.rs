@@ -1,20 +1,27 @@
 // src/decode.rs Numan Thabit:
 use crate::codec_raw::channel_id;
 use crate::codec_raw::msg_type;
+use crate::codec_raw::{
+    gap_action, recovery_source, reset_kind, BookRecoveredV1, FeedGapV1, SeqResetV1,
+};
+use crate::glimpse::Glimpse;
+use crate::late_join::LateJoin;
 use crate::metrics;
 use crate::obo::{map_event_to_obo_parts, OboEventV1};
 use crate::orderbook::OrderBook;
+use crate::parser::Event;
 use crate::parser::Parser;
-use crate::pool::{PacketPool, Pkt};
+use crate::pool::{PacketPool, Pkt, CHAN_CONTROL};
 use crate::pubsub::Publisher as OboPublisher;
 use crate::spsc::SpscQueue;
 use crate::util::{now_nanos, BarrierFlag};
 use crossbeam_channel::Receiver;
 use crossbeam_channel::Sender;
+use hashbrown::{HashMap, HashSet};
 use log::{info, warn};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use zerocopy::AsBytes;
+use zerocopy::{AsBytes, FromBytes};
 
 pub struct DecodeConfig {
     pub max_depth: usize,
@@ -24,11 +31,18 @@ pub struct DecodeConfig {
     pub initial_book: Option<OrderBook>,
     pub snapshot_trigger_rx: Option<Receiver<()>>,
     pub obo_publisher: Option<OboPublisher>,
+    /// Snapshot multicast recovery for one partition
+    pub late_join: Option<LateJoin>,
+    /// Glimpse snapshot seeding and resync for one partition
+    pub glimpse: Option<Glimpse>,
+    /// Exchange -> RX latency handles, one per partition in `q_in` order
+    pub exchange_to_rx: Vec<metrics::ExchangeToRx>,
 }
 
 // TODO: Group arguments into a DecodeConfig struct to reduce parameter count.
+/// `q_in` holds one merged queue per partition; they are served round-robin.
 pub fn decode_loop(
-    q_in: Arc<SpscQueue<Pkt>>,
+    q_in: Vec<Arc<SpscQueue<Pkt>>>,
     pool: Arc<PacketPool>,
     parser: Parser,
     shutdown: Arc<BarrierFlag>,
@@ -48,8 +62,148 @@ pub fn decode_loop(
     let mut processed_msgs: u64 = 0;
 
     let mut idle_iters: u32 = 0;
+    let n_in = q_in.len().max(1);
+    let mut next_q: usize = 0;
+    // Instruments that missed updates the merge gave up on
+    let mut stale: HashSet<u32> = HashSet::new();
+    // Instruments the late join rebuilt in the last poll, with the sequence they resume at
+    let mut rebuilt: Vec<(u32, u64)> = Vec::new();
+    // With several partitions, which instruments each one carries (a single partition owns the book)
+    let mut part_instrs: Vec<HashSet<u32>> = if n_in > 1 {
+        vec![HashSet::new(); n_in]
+    } else {
+        Vec::new()
+    };
+    // Instruments of each multiplexed sequence stream, per partition (explicit resets are per stream)
+    let mut stream_instrs: HashMap<(usize, u32), HashSet<u32>> = HashMap::new();
+    let mut late_join = cfg.late_join;
+    let mut glimpse = cfg.glimpse;
     while !shutdown.is_raised() {
-        if let Some(pkt) = q_in.pop() {
+        if let Some(lj) = late_join.as_mut() {
+            if lj.poll(&mut book, &mut rebuilt) > 0 {
+                idle_iters = 0;
+            }
+            if !rebuilt.is_empty() {
+                on_book_recovered(
+                    &mut stale,
+                    &rebuilt,
+                    recovery_source::SNAPSHOT_FEED,
+                    0,
+                    cfg.obo_publisher.as_ref(),
+                );
+                rebuilt.clear();
+            }
+        }
+        if let Some(g) = glimpse.as_mut() {
+            if let Some(snap) = g.poll() {
+                let i = g.partition;
+                let affected: Vec<u32> = match part_instrs.get(i) {
+                    Some(set) => set.iter().copied().collect(),
+                    None => book.instruments().collect(),
+                };
+                for &instr in &affected {
+                    book.clear_instrument(instr);
+                }
+                let mut instrs = HashSet::new();
+                snap.apply(&parser, &mut book, &mut instrs);
+                if let Some(set) = part_instrs.get_mut(i) {
+                    set.extend(instrs.iter().copied());
+                }
+                // Cleared instruments the snapshot has no orders for are rebuilt as empty
+                instrs.extend(affected);
+                let recovered: Vec<(u32, u64)> = instrs
+                    .into_iter()
+                    .map(|instr| (instr, snap.next_seq))
+                    .collect();
+                on_book_recovered(
+                    &mut stale,
+                    &recovered,
+                    recovery_source::GLIMPSE,
+                    0,
+                    cfg.obo_publisher.as_ref(),
+                );
+            }
+        }
+        // Packets held back for a snapshot go first, then the partitions round-robin
+        let released = glimpse
+            .as_mut()
+            .and_then(|g| g.release().map(|p| (g.partition, p)));
+        let from_held = released.is_some();
+        let popped = released.or_else(|| {
+            (0..q_in.len()).find_map(|k| {
+                let i = (next_q + k) % n_in;
+                q_in[i].pop().map(|p| (i, p))
+            })
+        });
+        if let Some((i, pkt)) = popped {
+            next_q = i + 1;
+            let pkt = match glimpse.as_mut().filter(|g| g.partition == i) {
+                Some(g) if !from_held && pkt.chan != CHAN_CONTROL => match g.hold(pkt) {
+                    Some(p) => p,
+                    None => continue,
+                },
+                _ => pkt,
+            };
+            if pkt.chan == CHAN_CONTROL {
+                // Merge control: [u16 LE message type][payload]
+                let (msg, body) = pkt.payload().split_at(2.min(pkt.len));
+                let msg = match msg {
+                    [lo, hi] => u16::from_le_bytes([*lo, *hi]),
+                    _ => 0,
+                };
+                match msg {
+                    msg_type::FEED_GAP => {
+                        if let Some(gap) = FeedGapV1::read_from(body) {
+                            let affected = stream_affected(
+                                &book,
+                                &part_instrs,
+                                &stream_instrs,
+                                i,
+                                gap.stream_id,
+                            );
+                            on_feed_gap(
+                                &mut book,
+                                &mut stale,
+                                &affected,
+                                &gap,
+                                cfg.obo_publisher.as_ref(),
+                            );
+                            if let Some(lj) = late_join.as_mut().filter(|lj| lj.partition == i) {
+                                lj.on_gap(&affected);
+                            }
+                            if let Some(g) = glimpse.as_mut().filter(|g| g.partition == i) {
+                                g.on_gap(gap.to_inclusive, gap.action == gap_action::RESYNC);
+                            }
+                        }
+                    }
+                    msg_type::SEQ_RESET => {
+                        if let Some(reset) = SeqResetV1::read_from(body) {
+                            // The venue sends an explicitly reset book again from empty
+                            let stream = reset.stream_id;
+                            let affected: Vec<u32> = if reset.kind != reset_kind::EXPLICIT {
+                                Vec::new()
+                            } else {
+                                stream_affected(&book, &part_instrs, &stream_instrs, i, stream)
+                            };
+                            on_seq_reset(&mut book, &affected, &reset, cfg.obo_publisher.as_ref());
+                            let restarted: Vec<(u32, u64)> = affected
+                                .iter()
+                                .map(|&instr| (instr, reset.new_seq))
+                                .collect();
+                            on_book_recovered(
+                                &mut stale,
+                                &restarted,
+                                recovery_source::VENUE_RESET,
+                                stream,
+                                cfg.obo_publisher.as_ref(),
+                            );
+                        }
+                    }
+                    other => warn!("unknown merge control message type {other}"),
+                }
+                pkt.recycle(&pool);
+                continue;
+            }
             processed_pkts += 1;
             metrics::inc_decode_pkts();
 
@@ -57,6 +211,7 @@ pub fn decode_loop(
             let ts_nanos = pkt.ts_nanos;
             let _ts_kind = pkt._ts_kind;
             let merge_emit_ns = pkt.merge_emit_ns;
+            let (seq, seq_span, stream) = (pkt.seq, pkt.seq_span, pkt.stream);
             let payload = pkt.payload();
             let cap_before = events.capacity();
             parser.decode_into(payload, &mut events);
@@ -82,8 +237,26 @@ pub fn decode_loop(
                 }
             }
 
+            let late_join_here = late_join.as_mut().filter(|lj| lj.partition == i);
             for ev in &events {
+                if late_join_here
+                    .as_ref()
+                    .is_some_and(|lj| lj.skip(seq, ev, &book))
+                {
+                    continue;
+                }
                 book.apply(ev);
+                if let Event::Add { instr, .. }
+                | Event::Level { instr, .. }
+                | Event::TradingStatus { instr, .. } = *ev
+                {
+                    if let Some(set) = part_instrs.get_mut(i) {
+                        set.insert(instr);
+                    }
+                    if stream != 0 {
+                        stream_instrs.entry((i, stream)).or_default().insert(instr);
+                    }
+                }
                 if let Some(pubh) = &cfg.obo_publisher {
                     let (maybe_instr, maybe_obo) = map_event_to_obo_parts(ev);
                     if let Some(obo_ev) = maybe_obo {
@@ -95,10 +268,11 @@ pub fn decode_loop(
                                 crate::parser::Event::Mod { order_id, .. } => {
                                     book.instrument_for_order(order_id)
                                 }
-                                crate::parser::Event::Del { order_id } => {
+                                crate::parser::Event::Del { order_id, .. } => {
                                     book.instrument_for_order(order_id)
                                 }
                                 crate::parser::Event::Trade { instr, .. } => Some(instr),
+                                crate::parser::Event::Level { instr, .. } => Some(instr),
                                 _ => None,
                             }
                         };
@@ -110,13 +284,33 @@ pub fn decode_loop(
                             OboEventV1::Execute(p) => {
                                 (msg_type::OBO_EXECUTE, p.as_bytes().to_vec())
                             }
+                            OboEventV1::Level(p) => (msg_type::OBO_LEVEL, p.as_bytes().to_vec()),
                         };
                         let seq = pubh.next_seq_for_instrument(instr);
-                        pubh.publish_raw(msg_ty, channel_id::OBO_L3, instr, seq, &payload_bytes);
+                        pubh.publish_raw(
+                            msg_ty,
+                            channel_id::OBO_L3,
+                            instr,
+                            seq,
+                            ev.exch_ts_ns().unwrap_or(0),
+                            &payload_bytes,
+                        );
                     }
                 }
             }
 
+            if let Some(lj) = late_join_here {
+                lj.on_packet(seq, seq_span, &events);
+            }
+
+            // Exchange -> RX latency. Only meaningful when RX carries a wall-clock
+            // (kernel/NIC) timestamp; monotonic fallback stamps fail the ordering check.
+            if let Some(exch_ns) = events.iter().find_map(|e| e.exch_ts_ns()) {
+                if let Some(h) = cfg.exchange_to_rx.get(i).filter(|_| ts_nanos > exch_ns) {
+                    h.observe_ns(pkt.chan, ts_nanos - exch_ns);
+                }
+            }
+
             let now_ns = now_nanos();
             if ts_nanos != 0 && now_ns > ts_nanos {
                 let d = now_ns - ts_nanos;
@@ -158,3 +352,190 @@ pub fn decode_loop(
     }
     Ok(())
 }
+
+/// Instruments carried by `stream` of partition `i`: the stream's own set when the feed
+/// multiplexes streams, else every instrument of the partition.
+fn stream_affected(
+    book: &OrderBook,
+    part_instrs: &[HashSet<u32>],
+    stream_instrs: &HashMap<(usize, u32), HashSet<u32>>,
+    i: usize,
+    stream: u32,
+) -> Vec<u32> {
+    if stream != 0 {
+        return stream_instrs
+            .get(&(i, stream))
+            .map(|set| set.iter().copied().collect())
+            .unwrap_or_default();
+    }
+    match part_instrs.get(i) {
+        Some(set) => set.iter().copied().collect(),
+        None => book.instruments().collect(),
+    }
+}
+
+/// The venue sequence restarted. An explicit reset (new session, channel reset, unit
+/// clear) restarts the venue's book, so `affected` are cleared; after a heuristic one
+/// (engine failover) books stay as they are. Subscribers get one SEQ_RESET on instrument 0.
+fn on_seq_reset(
+    book: &mut OrderBook,
+    affected: &[u32],
+    reset: &SeqResetV1,
+    pubh: Option<&OboPublisher>,
+) {
+    let (new_seq, prev, kind, stream) = (
+        reset.new_seq,
+        reset.prev_next_seq,
+        reset.kind,
+        reset.stream_id,
+    );
+    for &instr in affected {
+        book.clear_instrument(instr);
+    }
+    warn!(
+        "feed sequence reset (kind={kind}, stream={stream}): {prev} -> {new_seq}; {} books cleared",
+        affected.len()
+    );
+    if let Some(pubh) = pubh {
+        let seq = pubh.next_seq_for_instrument(0);
+        pubh.publish_raw(
+            msg_type::SEQ_RESET,
+            channel_id::OBO_L3,
+            0,
+            seq,
+            0,
+            reset.as_bytes(),
+        );
+    }
+}
+
+/// `recovered` books are whole again from the paired venue sequence: they are no longer
+/// stale, and subscribers get one BOOK_RECOVERED frame per instrument.
+fn on_book_recovered(
+    stale: &mut HashSet<u32>,
+    recovered: &[(u32, u64)],
+    source: u8,
+    stream_id: u32,
+    pubh: Option<&OboPublisher>,
+) {
+    let before = stale.len();
+    for (instr, _) in recovered {
+        stale.remove(instr);
+    }
+    if stale.len() != before {
+        metrics::set_stale_instruments(stale.len());
+        info!(
+            "{} stale instruments recovered (source={source}); {} still stale",
+            before - stale.len(),
+            stale.len()
+        );
+    }
+    let Some(pubh) = pubh else {
+        return;
+    };
+    for &(instr, from_seq) in recovered {
+        let msg = BookRecoveredV1 {
+            from_seq,
+            source,
+            stream_id,
+        };
+        let instr = instr as u64;
+        let seq = pubh.next_seq_for_instrument(instr);
+        pubh.publish_raw(
+            msg_type::BOOK_RECOVERED,
+            channel_id::OBO_L3,
+            instr,
+            seq,
+            0,
+            msg.as_bytes(),
+        );
+    }
+}
+
+/// Merge abandoned `gap`: mark `affected` stale (clearing their books on resync) and tell
+/// subscribers with one FEED_GAP frame per instrument, or instrument 0 if none is known.
+fn on_feed_gap(
+    book: &mut OrderBook,
+    stale: &mut HashSet<u32>,
+    affected: &[u32],
+    gap: &FeedGapV1,
+    pubh: Option<&OboPublisher>,
+) {
+    let (from, to, action, stream) = (
+        gap.from_inclusive,
+        gap.to_inclusive,
+        gap.action,
+        gap.stream_id,
+    );
+    for &instr in affected {
+        if action == gap_action::RESYNC {
+            book.clear_instrument(instr);
+        }
+        stale.insert(instr);
+    }
+    metrics::set_stale_instruments(stale.len());
+    warn!(
+        "feed gap {from}..={to} (stream {stream}) given up (action={action}); {} instruments stale",
+        affected.len()
+    );
+    if let Some(pubh) = pubh {
+        let targets: &[u32] = if affected.is_empty() { &[0] } else { affected };
+        for &instr in targets {
+            let instr = instr as u64;
+            let seq = pubh.next_seq_for_instrument(instr);
+            pubh.publish_raw(
+                msg_type::FEED_GAP,
+                channel_id::OBO_L3,
+                instr,
+                seq,
+                0,
+                gap.as_bytes(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Side;
+
+    #[test]
+    fn feed_gap_on_one_stream_leaves_the_other_streams_books_alone() {
+        let mut book = OrderBook::new(10);
+        for (order_id, instr) in [(1, 7), (2, 8)] {
+            book.apply(&Event::Add {
+                order_id,
+                instr,
+                px: 100,
+                qty: 1,
+                side: Side::Bid,
+                exch_ts_ns: None,
+            });
+        }
+        // One partition multiplexing streams 1 (instrument 7) and 2 (instrument 8)
+        let part_instrs: Vec<HashSet<u32>> = Vec::new();
+        let mut stream_instrs: HashMap<(usize, u32), HashSet<u32>> = HashMap::new();
+        stream_instrs.entry((0, 1)).or_default().insert(7);
+        stream_instrs.entry((0, 2)).or_default().insert(8);
+
+        let gap = FeedGapV1 {
+            from_inclusive: 10,
+            to_inclusive: 12,
+            action: gap_action::RESYNC,
+            stream_id: 1,
+        };
+        let affected = stream_affected(&book, &part_instrs, &stream_instrs, 0, gap.stream_id);
+        assert_eq!(affected, vec![7]);
+        let mut stale = HashSet::new();
+        on_feed_gap(&mut book, &mut stale, &affected, &gap, None);
+        assert!(stale.contains(&7) && !stale.contains(&8));
+        assert_eq!(book.instrument_for_order(2), Some(8));
+        assert_eq!(book.instrument_for_order(1), None);
+
+        // Stream 0 is the whole partition
+        let mut all = stream_affected(&book, &part_instrs, &stream_instrs, 0, 0);
+        all.sort_unstable();
+        assert_eq!(all, vec![8]);
+    }
+}
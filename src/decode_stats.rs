@@ -0,0 +1,197 @@
+// src/decode_stats.rs
+// Per-decoder coverage and error accounting.
+//
+// Each decoder owns a `DecoderStats` that counts messages by type/template and
+// malformed input by kind. Only types the decoder knows get their own `type` label;
+// everything else shares `type="unknown"`, so garbage input cannot grow the label set.
+// Counter handles are resolved once and cached, so the hot path is an atomic increment. Optionally, the first N payloads that trip an
+// issue are appended to a capture file (one hex line each) for offline inspection.
+
+use crate::metrics;
+use once_cell::sync::OnceCell;
+use prometheus::IntCounter;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Kind of malformed or unsupported input seen by a decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeIssue {
+    /// Message type/template not defined by the venue spec the decoder follows
+    UnknownType,
+    /// Message header claims more bytes than the packet holds
+    Truncated,
+    /// Message body shorter than the fixed layout for its type
+    ShortBody,
+    /// Leftover bytes after the last complete message
+    TrailingBytes,
+}
+
+impl DecodeIssue {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DecodeIssue::UnknownType => "unknown_type",
+            DecodeIssue::Truncated => "truncated",
+            DecodeIssue::ShortBody => "short_body",
+            DecodeIssue::TrailingBytes => "trailing_bytes",
+        }
+    }
+}
+
+/// How message type ids are rendered in the `type` label.
+#[derive(Debug, Clone, Copy)]
+pub enum TypeLabel {
+    /// ASCII message type (ITCH 'A')
+    Char,
+    /// Hex byte (PITCH 0x21)
+    Hex,
+    /// Numeric template id (SBE/FAST)
+    Decimal,
+}
+
+const TYPE_SLOTS: usize = 256;
+
+pub struct DecoderStats {
+    decoder: &'static str,
+    label: TypeLabel,
+    /// Counter cache, open addressing from the low byte; the stored id tells slots apart
+    by_type: Box<[OnceCell<(u16, IntCounter)>]>,
+    /// Messages of types outside the spec (`type="unknown"`)
+    unknown: IntCounter,
+    issues: [IntCounter; 4],
+}
+
+impl DecoderStats {
+    pub fn new(decoder: &'static str, label: TypeLabel) -> Self {
+        let issue = |k: DecodeIssue| metrics::decoder_issue_counter(decoder, k.as_str());
+        Self {
+            decoder,
+            label,
+            by_type: (0..TYPE_SLOTS).map(|_| OnceCell::new()).collect(),
+            unknown: metrics::decoder_message_counter(decoder, "unknown"),
+            issues: [
+                issue(DecodeIssue::UnknownType),
+                issue(DecodeIssue::Truncated),
+                issue(DecodeIssue::ShortBody),
+                issue(DecodeIssue::TrailingBytes),
+            ],
+        }
+    }
+
+    fn type_counter(&self, ty: u16) -> IntCounter {
+        let label = match self.label {
+            TypeLabel::Char if ty < 0x80 && (ty as u8).is_ascii_graphic() => {
+                (ty as u8 as char).to_string()
+            }
+            TypeLabel::Char | TypeLabel::Hex => format!("0x{ty:02X}"),
+            TypeLabel::Decimal => ty.to_string(),
+        };
+        metrics::decoder_message_counter(self.decoder, &label)
+    }
+
+    /// Count one message of a type the decoder knows. Types outside the spec go through
+    /// `issue(DecodeIssue::UnknownType, ..)` instead.
+    #[inline]
+    pub fn msg(&self, ty: u16) {
+        for i in 0..TYPE_SLOTS {
+            let slot = &self.by_type[(ty as usize + i) % TYPE_SLOTS];
+            let (id, c) = slot.get_or_init(|| (ty, self.type_counter(ty)));
+            if *id == ty {
+                c.inc();
+                return;
+            }
+        }
+        // More known types than slots
+        self.unknown.inc();
+    }
+
+    /// Count an issue and capture `payload` if capture is enabled. `off` is the byte
+    /// offset in `payload` where the problem was detected; an unknown type also counts
+    /// the message under `type="unknown"`.
+    #[inline]
+    pub fn issue(&self, kind: DecodeIssue, payload: &[u8], off: usize) {
+        self.issues[kind as usize].inc();
+        if kind == DecodeIssue::UnknownType {
+            self.unknown.inc();
+        }
+        if CAPTURE_REMAINING.load(Ordering::Relaxed) > 0 {
+            capture(self.decoder, kind, payload, off);
+        }
+    }
+}
+
+// -------------------- Bad payload capture --------------------
+
+static CAPTURE_REMAINING: AtomicUsize = AtomicUsize::new(0);
+static CAPTURE_FILE: OnceCell<Mutex<File>> = OnceCell::new();
+
+/// Append the first `max` offending payloads to `path`. Call once before decoding starts.
+pub fn enable_capture(path: &str, max: usize) -> anyhow::Result<()> {
+    let f = OpenOptions::new().create(true).append(true).open(path)?;
+    if CAPTURE_FILE.set(Mutex::new(f)).is_err() {
+        anyhow::bail!("bad payload capture already enabled");
+    }
+    CAPTURE_REMAINING.store(max, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cold]
+fn capture(decoder: &str, kind: DecodeIssue, payload: &[u8], off: usize) {
+    // Claim a slot; racing decoders may both see > 0 but only `max` writes succeed
+    if CAPTURE_REMAINING
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        .is_err()
+    {
+        return;
+    }
+    let Some(file) = CAPTURE_FILE.get() else {
+        return;
+    };
+    let mut hex = String::with_capacity(payload.len() * 2);
+    for b in payload {
+        hex.push_str(&format!("{b:02x}"));
+    }
+    let mut f = file.lock().unwrap();
+    let _ = writeln!(
+        f,
+        "decoder={} kind={} offset={} len={} payload={}",
+        decoder,
+        kind.as_str(),
+        off,
+        payload.len(),
+        hex
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_cache_handles_slot_collisions() {
+        let s = DecoderStats::new("test_collide", TypeLabel::Decimal);
+        s.msg(1001);
+        s.msg(1001 + TYPE_SLOTS as u16);
+        s.msg(1001);
+        assert_eq!(
+            metrics::decoder_message_counter("test_collide", "1001").get(),
+            2
+        );
+        assert_eq!(
+            metrics::decoder_message_counter("test_collide", "1257").get(),
+            1
+        );
+        s.issue(DecodeIssue::ShortBody, &[1, 2], 0);
+        assert_eq!(
+            metrics::decoder_issue_counter("test_collide", "short_body").get(),
+            1
+        );
+        s.issue(DecodeIssue::UnknownType, &[1, 2], 0);
+        s.issue(DecodeIssue::UnknownType, &[3, 4], 0);
+        assert_eq!(
+            metrics::decoder_message_counter("test_collide", "unknown").get(),
+            2
+        );
+    }
+}
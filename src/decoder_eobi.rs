@@ -2,16 +2,46 @@
 //  maps venue messages
 // to the engine's Event model. This is not a full Eurex spec, but follows
 // SBE framing and common order-flow templates. Hot-path does zero heap allocs.
+// Each template may append TransactTime (u64 ns since epoch) right after its fixed
+// fields; when block_len covers it, it is reported as the exchange timestamp.
 
 //
+use crate::decode_stats::{DecodeIssue, DecoderStats, TypeLabel};
 use crate::parser::{Event, MessageDecoder, Side};
 
-#[derive(Default, Clone)]
-pub struct EobiSbeDecoder;
+pub struct EobiSbeDecoder {
+    stats: DecoderStats,
+}
 
 impl EobiSbeDecoder {
     pub fn new() -> Self {
-        Self
+        Self {
+            stats: DecoderStats::new("eobi", TypeLabel::Decimal),
+        }
+    }
+}
+
+impl Default for EobiSbeDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for EobiSbeDecoder {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+/// Fixed body length per template (excluding the optional trailing TransactTime)
+#[inline]
+fn min_body_len(template_id: u16) -> Option<usize> {
+    match template_id {
+        1001 => Some(29),
+        1002 => Some(16),
+        1003 => Some(8),
+        1004 => Some(29),
+        _ => None,
     }
 }
 
@@ -20,6 +50,7 @@ impl MessageDecoder for EobiSbeDecoder {
     fn decode_messages(&self, payload: &[u8], out: &mut Vec<Event>) {
         let mut off = 0usize;
         while off + 8 <= payload.len() {
+            let msg_off = off;
             let block_len = le_u16(&payload[off..off + 2]) as usize;
             off += 2;
             let template_id = le_u16(&payload[off..off + 2]);
@@ -30,11 +61,24 @@ impl MessageDecoder for EobiSbeDecoder {
             off += 2;
 
             if off + block_len > payload.len() {
-                break;
+                self.stats.issue(DecodeIssue::Truncated, payload, msg_off);
+                return;
             }
             let body = &payload[off..off + block_len];
             off += block_len;
 
+            match min_body_len(template_id) {
+                None => {
+                    self.stats.issue(DecodeIssue::UnknownType, payload, msg_off);
+                    continue;
+                }
+                Some(min) if body.len() < min => {
+                    self.stats.msg(template_id);
+                    self.stats.issue(DecodeIssue::ShortBody, payload, msg_off);
+                    continue;
+                }
+                Some(_) => self.stats.msg(template_id),
+            }
             match template_id {
                 1001 => decode_add(body, out),
                 1002 => decode_mod(body, out),
@@ -43,6 +87,9 @@ impl MessageDecoder for EobiSbeDecoder {
                 _ => { /* skip unknown template */ }
             }
         }
+        if off < payload.len() {
+            self.stats.issue(DecodeIssue::TrailingBytes, payload, off);
+        }
     }
 }
 
@@ -119,12 +166,14 @@ fn decode_add(body: &[u8], out: &mut Vec<Event>) {
         Some(v) => v,
         None => return,
     };
+    let ts = read_le_u64_checked(body, LEN);
     out.push(Event::Add {
         order_id,
         instr,
         px,
         qty,
         side,
+        exch_ts_ns: ts,
     });
 }
 
@@ -143,7 +192,12 @@ fn decode_mod(body: &[u8], out: &mut Vec<Event>) {
         Some(v) => v,
         None => return,
     };
-    out.push(Event::Mod { order_id, qty });
+    let ts = read_le_u64_checked(body, LEN);
+    out.push(Event::Mod {
+        order_id,
+        qty,
+        exch_ts_ns: ts,
+    });
 }
 
 #[inline]
@@ -152,8 +206,12 @@ fn decode_del(body: &[u8], out: &mut Vec<Event>) {
     if body.len() < 8 {
         return;
     }
+    let ts = read_le_u64_checked(body, 8);
     if let Some(order_id) = read_le_u64_checked(body, 0) {
-        out.push(Event::Del { order_id });
+        out.push(Event::Del {
+            order_id,
+            exch_ts_ns: ts,
+        });
     }
 }
 
@@ -186,15 +244,66 @@ fn decode_trade(body: &[u8], out: &mut Vec<Event>) {
         1 => Some(Side::Ask),
         _ => None,
     };
+    let ts = read_le_u64_checked(body, LEN);
     out.push(Event::Trade {
         instr,
         px,
         qty,
         maker_order_id: Some(maker_order_id),
         taker_side,
+        exch_ts_ns: ts,
     });
 }
 
+/// Snapshot channel message: per instrument and cycle, a summary followed by its orders.
+#[derive(Debug, Clone)]
+pub enum SnapshotMsg {
+    /// Template 1010: [instr u32][last_seq u64][orders u32]; `last_seq` is the last
+    /// incremental packet sequence the snapshot includes
+    Instrument {
+        instr: u32,
+        last_seq: u64,
+        orders: u32,
+    },
+    /// Template 1011: a resting order, laid out like template 1001
+    Order(Event),
+}
+
+/// Decode snapshot channel messages (templates 1010/1011); other templates are skipped.
+pub fn decode_snapshot(payload: &[u8], out: &mut Vec<SnapshotMsg>) {
+    let mut off = 0usize;
+    let mut ev = Vec::with_capacity(1);
+    while off + 8 <= payload.len() {
+        let block_len = le_u16(&payload[off..off + 2]) as usize;
+        let template_id = le_u16(&payload[off + 2..off + 4]);
+        off += 8;
+        let Some(body) = payload.get(off..off + block_len) else {
+            return;
+        };
+        off += block_len;
+        match template_id {
+            1010 => {
+                if let (Some(instr), Some(last_seq), Some(orders)) = (
+                    read_le_u32_checked(body, 0),
+                    read_le_u64_checked(body, 4),
+                    read_le_u32_checked(body, 12),
+                ) {
+                    out.push(SnapshotMsg::Instrument {
+                        instr,
+                        last_seq,
+                        orders,
+                    });
+                }
+            }
+            1011 => {
+                decode_add(body, &mut ev);
+                out.extend(ev.drain(..).map(SnapshotMsg::Order));
+            }
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -233,6 +342,7 @@ mod tests {
                 px,
                 qty,
                 side,
+                exch_ts_ns: None,
             }] => {
                 assert_eq!(*order_id, 123);
                 assert_eq!(*instr, 42);
@@ -275,10 +385,11 @@ mod tests {
             out[0],
             Event::Mod {
                 order_id: 123,
-                qty: 5
+                qty: 5,
+                ..
             }
         ));
-        assert!(matches!(out[1], Event::Del { order_id: 123 }));
+        assert!(matches!(out[1], Event::Del { order_id: 123, .. }));
         match out[2] {
             Event::Trade {
                 instr,
@@ -286,6 +397,7 @@ mod tests {
                 qty,
                 maker_order_id,
                 taker_side,
+                ..
             } => {
                 assert_eq!(instr, 7);
                 assert_eq!(px, 111);
@@ -297,6 +409,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decode_trailing_transact_time() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&123u64.to_le_bytes());
+        body.extend_from_slice(&(5i64).to_le_bytes());
+        body.extend_from_slice(&1_700_000_000_123_456_789u64.to_le_bytes());
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&hdr(body.len() as u16, 1002, 1, 1));
+        buf.extend_from_slice(&body);
+
+        let dec = EobiSbeDecoder::new();
+        let mut out = Vec::new();
+        dec.decode_messages(&buf, &mut out);
+        assert!(matches!(
+            out.as_slice(),
+            [Event::Mod {
+                order_id: 123,
+                qty: 5,
+                exch_ts_ns: Some(1_700_000_000_123_456_789),
+            }]
+        ));
+    }
+
+    #[test]
+    fn unknown_template_and_trailing_bytes_are_counted() {
+        let issues = |k: &str| crate::metrics::decoder_issue_counter("eobi", k).get();
+        let (unknown0, trailing0) = (issues("unknown_type"), issues("trailing_bytes"));
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&hdr(4, 9999, 1, 1));
+        buf.extend_from_slice(&[0u8; 4]);
+        buf.extend_from_slice(&[0xAA, 0xBB, 0xCC]);
+
+        let dec = EobiSbeDecoder::new();
+        let mut out = Vec::new();
+        dec.decode_messages(&buf, &mut out);
+        assert!(out.is_empty());
+        // Counters are process-wide; other tests may bump them concurrently
+        assert!(issues("unknown_type") > unknown0);
+        assert!(issues("trailing_bytes") > trailing0);
+    }
+
     proptest! {
         #[test]
         fn decode_random_input_does_not_panic(payload in proptest::collection::vec(any::<u8>(), 0..4096)) {
@@ -12,15 +12,32 @@
 //   2: Mod { order_id(u64 sbi), qty(i64 zigzag) }
 //   3: Del { order_id(u64 sbi) }
 //   4: Trade { instr(u32 sbi), price(i64 zigzag), qty(i64 zigzag), maker_order_id(u64 sbi, optional via pmap bit0), taker_side(u8 raw, optional pmap bit1) }
+// Any template may end with transact_time(u64 sbi, ns since epoch), present when pmap bit2 is set.
 
+use crate::decode_stats::{DecodeIssue, DecoderStats, TypeLabel};
 use crate::parser::{Event, MessageDecoder, Side};
 
-#[derive(Default, Clone)]
-pub struct FastEmdiDecoder;
+pub struct FastEmdiDecoder {
+    stats: DecoderStats,
+}
 
 impl FastEmdiDecoder {
     pub fn new() -> Self {
-        Self
+        Self {
+            stats: DecoderStats::new("fast", TypeLabel::Decimal),
+        }
+    }
+}
+
+impl Default for FastEmdiDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for FastEmdiDecoder {
+    fn clone(&self) -> Self {
+        Self::new()
     }
 }
 
@@ -29,33 +46,37 @@ impl MessageDecoder for FastEmdiDecoder {
     fn decode_messages(&self, payload: &[u8], out: &mut Vec<Event>) {
         let mut off = 0usize;
         while off < payload.len() {
+            let msg_off = off;
             let (pmap, n) = read_pmap(payload, off);
-            if n == 0 {
-                break;
-            }
             off += n;
             let (tmpl, n2) = read_sbi_u64(payload, off);
-            if n2 == 0 {
-                break;
-            }
             off += n2;
             let (body_len, n3) = read_sbi_u64(payload, off);
-            if n3 == 0 {
-                break;
-            }
             off += n3;
-            if off + (body_len as usize) > payload.len() {
-                break;
+            if n2 == 0 || n3 == 0 || off + (body_len as usize) > payload.len() {
+                // Header or body runs past the packet
+                self.stats.issue(DecodeIssue::Truncated, payload, msg_off);
+                return;
             }
             let body = &payload[off..off + (body_len as usize)];
             off += body_len as usize;
 
-            match tmpl {
-                1 => on_add(body, out),
-                2 => on_mod(body, out),
-                3 => on_del(body, out),
-                4 => on_trade(body, out, pmap),
-                _ => { /* skip unknown */ }
+            let on_msg: fn(&[u8], &mut Vec<Event>, u64) = match tmpl {
+                1 => on_add,
+                2 => on_mod,
+                3 => on_del,
+                4 => on_trade,
+                _ => {
+                    self.stats.issue(DecodeIssue::UnknownType, payload, msg_off);
+                    continue;
+                }
+            };
+            self.stats.msg(tmpl as u16);
+            let before = out.len();
+            on_msg(body, out, pmap);
+            if out.len() == before {
+                // Handler bailed out on a missing required field
+                self.stats.issue(DecodeIssue::ShortBody, payload, msg_off);
             }
         }
     }
@@ -105,9 +126,21 @@ fn read_sbi_u64(b: &[u8], mut off: usize) -> (u64, usize) {
     (v, consumed)
 }
 
+/// Optional trailing transact_time (pmap bit2).
+#[inline]
+fn read_transact_time(body: &[u8], off: usize, pmap: u64) -> Option<u64> {
+    if pmap & 0x4 == 0 {
+        return None;
+    }
+    match read_sbi_u64(body, off) {
+        (_, 0) => None,
+        (v, _) => Some(v),
+    }
+}
+
 #[inline]
 #[allow(dead_code)] // Called from decode_messages
-fn on_add(body: &[u8], out: &mut Vec<Event>) {
+fn on_add(body: &[u8], out: &mut Vec<Event>, pmap: u64) {
     let mut o = 0usize;
     let (order_id, n1) = read_sbi_u64(body, o);
     o += n1;
@@ -132,22 +165,25 @@ fn on_add(body: &[u8], out: &mut Vec<Event>) {
     }
     let px = ((uv_px >> 1) as i64) ^ (-((uv_px & 1) as i64));
     let (uv_qty, n4) = read_sbi_u64(body, o);
+    o += n4;
     if n4 == 0 {
         return;
     }
     let qty = ((uv_qty >> 1) as i64) ^ (-((uv_qty & 1) as i64));
+    let ts = read_transact_time(body, o, pmap);
     out.push(Event::Add {
         order_id,
         instr: instr as u32,
         px,
         qty,
         side,
+        exch_ts_ns: ts,
     });
 }
 
 #[inline]
 #[allow(dead_code)] // Called from decode_messages
-fn on_mod(body: &[u8], out: &mut Vec<Event>) {
+fn on_mod(body: &[u8], out: &mut Vec<Event>, pmap: u64) {
     let mut o = 0usize;
     let (order_id, n1) = read_sbi_u64(body, o);
     o += n1;
@@ -155,16 +191,32 @@ fn on_mod(body: &[u8], out: &mut Vec<Event>) {
         return;
     }
     // Inline zigzag decode
-    let (uv_qty, _n2) = read_sbi_u64(body, o);
+    let (uv_qty, n2) = read_sbi_u64(body, o);
+    o += n2;
+    if n2 == 0 {
+        return;
+    }
     let qty = ((uv_qty >> 1) as i64) ^ (-((uv_qty & 1) as i64));
-    out.push(Event::Mod { order_id, qty });
+    let ts = read_transact_time(body, o, pmap);
+    out.push(Event::Mod {
+        order_id,
+        qty,
+        exch_ts_ns: ts,
+    });
 }
 
 #[inline]
 #[allow(dead_code)] // Called from decode_messages
-fn on_del(body: &[u8], out: &mut Vec<Event>) {
-    let (order_id, _n1) = read_sbi_u64(body, 0);
-    out.push(Event::Del { order_id });
+fn on_del(body: &[u8], out: &mut Vec<Event>, pmap: u64) {
+    let (order_id, n1) = read_sbi_u64(body, 0);
+    if n1 == 0 {
+        return;
+    }
+    let ts = read_transact_time(body, n1, pmap);
+    out.push(Event::Del {
+        order_id,
+        exch_ts_ns: ts,
+    });
 }
 
 #[inline]
@@ -201,13 +253,16 @@ fn on_trade(body: &[u8], out: &mut Vec<Event>, pmap: u64) {
     let mut taker_side = None;
     if pmap & 0x2 != 0 && o < body.len() {
         taker_side = Some(if body[o] == 0 { Side::Bid } else { Side::Ask });
+        o += 1;
     }
+    let ts = read_transact_time(body, o, pmap);
     out.push(Event::Trade {
         instr: instr as u32,
         px,
         qty,
         maker_order_id,
         taker_side,
+        exch_ts_ns: ts,
     });
 }
 
@@ -14,15 +14,21 @@
 //  - 'U' Order Replace (delete old, add new with new id/price/qty)
 //  - 'P' Trade (non-cross) — treated as execution against a displayed order
 //  - 'R' Stock Directory (optional; we simply accept it to avoid warnings)
-// Unknown types are safely skipped.
-
-use crate::parser::{Event, MessageDecoder, Side};
+// Every message carries a 48-bit ns-since-midnight timestamp; it is anchored to the
+// session's midnight (see `with_session_offset`, `util::SessionClock`) and reported as
+// Unix epoch ns.
+// Unknown types are safely skipped and counted (see `decode_stats`).
+
+use crate::decode_stats::{DecodeIssue, DecoderStats, TypeLabel};
+use crate::parser::{Event, MessageDecoder, SeqExtractor, Side};
+use crate::util::SessionClock;
 use hashbrown::HashMap;
 use std::cell::UnsafeCell;
 
 pub struct Itch50Decoder {
     // Decoder is used by a single decode thread; we avoid mutex overhead.
     inner: UnsafeCell<Inner>,
+    stats: DecoderStats,
 }
 
 // Safety: We only use this decoder from a single thread (decode thread). We still
@@ -37,6 +43,10 @@ struct Inner {
     orders: HashMap<u64, OrderState>,
     /// optional: stock locate -> (last seen 8-byte symbol). Not required for book logic.
     last_symbol_by_locate: HashMap<u16, [u8; 8]>,
+    /// Anchors ns-since-midnight timestamps at the session's midnight
+    clock: SessionClock,
+    /// Exchange timestamp of the message being decoded
+    msg_ts: Option<u64>,
 }
 
 #[derive(Clone, Copy)]
@@ -49,8 +59,18 @@ struct OrderState {
 
 impl Itch50Decoder {
     pub fn new() -> Self {
+        Self::with_session_offset(0)
+    }
+
+    /// Anchor message timestamps at midnight of a venue whose local time is
+    /// UTC + `utc_offset_s` (e.g. -18000 for Nasdaq in winter).
+    pub fn with_session_offset(utc_offset_s: i32) -> Self {
         Self {
-            inner: UnsafeCell::new(Inner::default()),
+            inner: UnsafeCell::new(Inner {
+                clock: SessionClock::new(utc_offset_s),
+                ..Inner::default()
+            }),
+            stats: DecoderStats::new("itch50", TypeLabel::Char),
         }
     }
 }
@@ -63,7 +83,36 @@ impl Default for Itch50Decoder {
 
 impl Clone for Itch50Decoder {
     fn clone(&self) -> Self {
-        Self::new()
+        let clock = unsafe { (*self.inner.get()).clock };
+        Self {
+            inner: UnsafeCell::new(Inner {
+                clock,
+                ..Inner::default()
+            }),
+            stats: DecoderStats::new("itch50", TypeLabel::Char),
+        }
+    }
+}
+
+/// Minimum body length (after the type byte) for each ITCH 5.0 message type we
+/// decode; 0 for spec types we deliberately skip; None for types outside the spec.
+#[inline]
+fn min_body_len(typ: u8) -> Option<usize> {
+    match typ {
+        b'A' => Some(35),
+        b'F' => Some(39),
+        b'E' => Some(30),
+        b'C' => Some(35),
+        b'X' => Some(22),
+        b'D' => Some(18),
+        b'U' => Some(34),
+        b'P' => Some(43),
+        b'R' => Some(18),
+        // System event, trading action, Reg SHO, MPP, MWCB, IPO, LULD, ops halt,
+        // cross trade, broken trade, NOII, RPII
+        b'S' | b'H' | b'Y' | b'L' | b'V' | b'W' | b'K' | b'J' | b'h' | b'Q' | b'B' | b'I'
+        | b'N' | b'O' => Some(0),
+        _ => None,
     }
 }
 
@@ -72,6 +121,7 @@ impl MessageDecoder for Itch50Decoder {
     fn decode_messages(&self, payload: &[u8], out: &mut Vec<Event>) {
         let mut off = 0usize;
         let st: &mut Inner = unsafe { &mut *self.inner.get() };
+        st.clock.tick();
 
         while off + 3 <= payload.len() {
             let msg_len = be_u16(&payload[off..off + 2]) as usize;
@@ -79,11 +129,13 @@ impl MessageDecoder for Itch50Decoder {
                 // length must at least contain message type
                 break;
             }
-            off += 2;
-            if off + msg_len > payload.len() {
+            if off + 2 + msg_len > payload.len() {
                 // Truncated packet (drop tail gracefully)
-                break;
+                self.stats.issue(DecodeIssue::Truncated, payload, off);
+                return;
             }
+            let msg_off = off;
+            off += 2;
 
             let typ = payload[off] as char;
             off += 1;
@@ -91,6 +143,25 @@ impl MessageDecoder for Itch50Decoder {
             let body = &payload[off..off + (msg_len - 1)];
             off += msg_len - 1;
 
+            match min_body_len(typ as u8) {
+                None => {
+                    self.stats.issue(DecodeIssue::UnknownType, payload, msg_off);
+                    continue;
+                }
+                Some(min) if body.len() < min => {
+                    self.stats.msg(typ as u16);
+                    self.stats.issue(DecodeIssue::ShortBody, payload, msg_off);
+                    continue;
+                }
+                _ => self.stats.msg(typ as u16),
+            }
+            // locate(2) track(2) ts(6) prefix is common to all message types
+            st.msg_ts = if body.len() >= 10 {
+                Some(st.clock.at(be_u48(&body[4..10])))
+            } else {
+                None
+            };
+
             match typ {
                 'A' => on_add(body, st, out, /*with_mpid*/ false),
                 'F' => on_add(body, st, out, /*with_mpid*/ true),
@@ -105,20 +176,70 @@ impl MessageDecoder for Itch50Decoder {
                 _ => { /* ignore other admin/metadata messages */ }
             }
         }
+        if off < payload.len() {
+            self.stats.issue(DecodeIssue::TrailingBytes, payload, off);
+        }
+    }
+}
+
+// -------------------- MoldUDP64 framing --------------------
+// ITCH is multicast in MoldUDP64 packets: `[session 10][sequence u64][count u16]`
+// (big-endian) followed by `count` length-prefixed messages. Sequences number messages.
+
+/// Downstream header: session, sequence of the first message, message count
+pub const MOLD_HEADER_LEN: usize = 20;
+/// Message count of a heartbeat; no messages follow
+pub const MOLD_HEARTBEAT: u16 = 0;
+/// Message count announcing the end of the session
+pub const MOLD_END_OF_SESSION: u16 = 0xFFFF;
+
+/// `(session, sequence, count)` of a MoldUDP64 downstream packet.
+#[inline]
+pub fn mold_header(pkt: &[u8]) -> Option<(&[u8], u64, u16)> {
+    let h = pkt.get(..MOLD_HEADER_LEN)?;
+    Some((&h[..10], be_u64(&h[10..18]), be_u16(&h[18..20])))
+}
+
+/// Sequence of ITCH over MoldUDP64: a packet covers `sequence..sequence + count`.
+/// Heartbeats and end of session carry no messages and are not sequenced. Every session
+/// numbers its messages from 1, so the packet starting at 1 marks a session change
+/// (comparing session names would need state shared by all lines and partitions).
+pub struct MoldSeq;
+
+impl SeqExtractor for MoldSeq {
+    #[inline]
+    fn extract_seq(&self, pkt: &[u8]) -> Option<u64> {
+        match mold_header(pkt)? {
+            (_, _, MOLD_HEARTBEAT | MOLD_END_OF_SESSION) => None,
+            (_, seq, _) => Some(seq),
+        }
+    }
+
+    #[inline]
+    fn seq_span(&self, pkt: &[u8]) -> u32 {
+        mold_header(pkt).map_or(1, |(_, _, count)| count.max(1) as u32)
+    }
+
+    #[inline]
+    fn is_seq_reset(&self, pkt: &[u8]) -> bool {
+        self.extract_seq(pkt) == Some(1)
     }
 }
 
 #[inline]
-#[allow(dead_code)] // Used in decode_messages
 fn be_u16(b: &[u8]) -> u16 {
     u16::from_be_bytes([b[0], b[1]])
 }
+#[inline]
+fn be_u48(b: &[u8]) -> u64 {
+    u64::from_be_bytes([0, 0, b[0], b[1], b[2], b[3], b[4], b[5]])
+}
+
 #[allow(dead_code)]
 #[inline]
 fn be_u32(b: &[u8]) -> u32 {
     u32::from_be_bytes([b[0], b[1], b[2], b[3]])
 }
-#[allow(dead_code)]
 #[inline]
 fn be_u64(b: &[u8]) -> u64 {
     u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
@@ -205,6 +326,7 @@ fn on_add(body: &[u8], st: &mut Inner, out: &mut Vec<Event>, with_mpid: bool) {
         px: price,
         qty: shares,
         side,
+        exch_ts_ns: st.msg_ts,
     });
 
     // Track state for subsequent exec/cancel/replace
@@ -242,6 +364,7 @@ fn on_exec(body: &[u8], st: &mut Inner, out: &mut Vec<Event>, _with_price: bool)
             out.push(Event::Mod {
                 order_id: order_ref,
                 qty: new_qty,
+                exch_ts_ns: st.msg_ts,
             });
             // update state
             if let Some(ent) = st.orders.get_mut(&order_ref) {
@@ -250,6 +373,7 @@ fn on_exec(body: &[u8], st: &mut Inner, out: &mut Vec<Event>, _with_price: bool)
         } else {
             out.push(Event::Del {
                 order_id: order_ref,
+                exch_ts_ns: st.msg_ts,
             });
             st.orders.remove(&order_ref);
         }
@@ -261,6 +385,7 @@ fn on_exec(body: &[u8], st: &mut Inner, out: &mut Vec<Event>, _with_price: bool)
             qty: executed,
             maker_order_id: Some(order_ref),
             taker_side: Some(opposite(s.side)),
+            exch_ts_ns: st.msg_ts,
         });
     } else {
         // If we don't have the order (late join), ignore or route to recovery.
@@ -286,10 +411,12 @@ fn on_cancel(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
             out.push(Event::Mod {
                 order_id: order_ref,
                 qty: ent.qty,
+                exch_ts_ns: st.msg_ts,
             });
         } else {
             out.push(Event::Del {
                 order_id: order_ref,
+                exch_ts_ns: st.msg_ts,
             });
             st.orders.remove(&order_ref);
         }
@@ -311,6 +438,7 @@ fn on_delete(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
     if st.orders.remove(&order_ref).is_some() {
         out.push(Event::Del {
             order_id: order_ref,
+            exch_ts_ns: st.msg_ts,
         });
     }
 }
@@ -339,7 +467,10 @@ fn on_replace(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
         .unwrap_or(Side::Bid);
     // Delete original
     if st.orders.remove(&orig_ref).is_some() {
-        out.push(Event::Del { order_id: orig_ref });
+        out.push(Event::Del {
+            order_id: orig_ref,
+            exch_ts_ns: st.msg_ts,
+        });
     }
 
     // Add new with new id/qty/price, keep side
@@ -349,6 +480,7 @@ fn on_replace(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
         px: price,
         qty: shares,
         side,
+        exch_ts_ns: st.msg_ts,
     });
     st.orders.insert(
         new_ref,
@@ -386,6 +518,7 @@ fn on_trade(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
             out.push(Event::Mod {
                 order_id: order_ref,
                 qty: new_qty,
+                exch_ts_ns: st.msg_ts,
             });
             if let Some(ent) = st.orders.get_mut(&order_ref) {
                 ent.qty = new_qty;
@@ -394,6 +527,7 @@ fn on_trade(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
         } else {
             out.push(Event::Del {
                 order_id: order_ref,
+                exch_ts_ns: st.msg_ts,
             });
             st.orders.remove(&order_ref);
         }
@@ -403,6 +537,7 @@ fn on_trade(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
             qty: shares,
             maker_order_id: Some(order_ref),
             taker_side: Some(opposite(s.side)),
+            exch_ts_ns: st.msg_ts,
         });
     } else {
         // If we don't know the maker order (e.g., late join), still emit trade analytics
@@ -416,6 +551,7 @@ fn on_trade(body: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
             } else {
                 Side::Ask
             }),
+            exch_ts_ns: st.msg_ts,
         });
     }
 }
@@ -434,6 +570,46 @@ mod tests {
     use super::*;
     use proptest::prelude::*;
 
+    #[test]
+    fn timestamp_is_anchored_at_session_midnight() {
+        let dec = Itch50Decoder::with_session_offset(-18_000);
+        let midnight = unsafe { (*dec.inner.get()).clock.midnight_ns() };
+        let mut body = Vec::new();
+        body.extend_from_slice(&1u16.to_be_bytes()); // locate
+        body.extend_from_slice(&0u16.to_be_bytes()); // tracking
+        body.extend_from_slice(&34_200_000_000_000u64.to_be_bytes()[2..]); // 09:30
+        body.extend_from_slice(&99u64.to_be_bytes());
+        body.push(b'B');
+        body.extend_from_slice(&100u32.to_be_bytes());
+        body.extend_from_slice(b"AAPL    ");
+        body.extend_from_slice(&1_500_000u32.to_be_bytes());
+        let mut p = ((body.len() + 1) as u16).to_be_bytes().to_vec();
+        p.push(b'A');
+        p.extend_from_slice(&body);
+
+        let mut out = Vec::new();
+        dec.decode_messages(&p, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].exch_ts_ns(), Some(midnight + 34_200_000_000_000));
+        // Midnight in UTC-5 falls at 05:00 UTC
+        assert_eq!((midnight / 1_000_000_000) % 86_400, 5 * 3600);
+    }
+
+    #[test]
+    fn session_clock_rolls_over_at_venue_midnight() {
+        const DAY: u64 = 86_400 * 1_000_000_000;
+        let mut c = SessionClock::new(-18_000);
+        let m = c.midnight_ns();
+        c.tick_at(m + DAY - 1_000_000);
+        assert_eq!(c.at(DAY - 2_000_000), m + DAY - 2_000_000);
+
+        c.tick_at(m + DAY + 1_000_000);
+        assert_eq!(c.midnight_ns(), m + DAY);
+        assert_eq!(c.at(34_200_000_000_000), m + DAY + 34_200_000_000_000);
+        // Stamped before midnight, decoded after it: still the previous day
+        assert_eq!(c.at(DAY - 2_000_000), m + DAY - 2_000_000);
+    }
+
     proptest! {
         #[test]
         fn decode_random_input_does_not_panic(payload in proptest::collection::vec(any::<u8>(), 0..4096)) {
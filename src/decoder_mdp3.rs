@@ -0,0 +1,720 @@
+// src/decoder_mdp3.rs
+// CME Globex MDP 3.0 decoder; little-endian; Binary Packet Header followed by SBE messages;
+// stateful (keeps an order map so MBO changes can be expressed as absolute updates).
+//
+// Packet layout:
+//   Binary Packet Header (12 bytes): MsgSeqNum(u32) SendingTime(u64, ns since epoch, unused)
+//   then repeated: MsgSize(u16, includes itself)
+//                  SBE header: BlockLength(u16) TemplateID(u16) SchemaID(u16) Version(u16)
+//                  root block (BlockLength bytes), then repeating groups
+//   Repeating groups use groupSize [blockLength u16][numInGroup u8] or, for order id
+//   groups, groupSize8Byte [blockLength u16][pad 5][numInGroup u8].
+//
+// Supported templates:
+//  - 46 MDIncrementalRefreshBook: MBP levels (outright and implied) -> Event::Level,
+//       plus its optional OrderID entries -> order events
+//  - 47 MDIncrementalRefreshOrderBook: MBO -> Event::Add / Mod / Del
+//  - 48 MDIncrementalRefreshTradeSummary -> Event::Trade
+//  - 30 SecurityStatus -> Event::TradingStatus
+// Prices are PRICE9 mantissas (1e-9); they are normalized to 1/10000 so books built from
+// MDP 3.0, ITCH and PITCH share the same price scale. Ticks finer than 1e-4 (some FX
+// futures) are truncated.
+// Every supported template starts with TransactTime (ns since epoch), which is reported
+// as the exchange timestamp of the events it produces.
+// Root and group block lengths are taken from the wire so newer schema versions that
+// append fields still decode. Unknown templates are safely skipped.
+
+use crate::decode_stats::{DecodeIssue, DecoderStats, TypeLabel};
+use crate::parser::{Event, LevelAction, MessageDecoder, SeqExtractor, Side};
+use hashbrown::HashMap;
+use std::cell::UnsafeCell;
+
+/// Size of the Binary Packet Header that prefixes every MDP 3.0 packet.
+pub const PACKET_HDR_LEN: usize = 12;
+const SBE_HDR_LEN: usize = 8;
+
+/// ChannelReset4: receivers drop every book of the channel; sequencing carries on
+const TEMPLATE_CHANNEL_RESET: u16 = 4;
+const TEMPLATE_SECURITY_STATUS: u16 = 30;
+const TEMPLATE_INCR_BOOK: u16 = 46;
+const TEMPLATE_INCR_ORDER_BOOK: u16 = 47;
+const TEMPLATE_INCR_TRADE_SUMMARY: u16 = 48;
+/// Root block sizes of the supported templates
+const INCR_ROOT_LEN: usize = 11;
+const SECURITY_STATUS_ROOT_LEN: usize = 30;
+
+/// Templates defined by the MDP 3.0 schema that we deliberately skip (channel reset,
+/// admin, instrument definitions, volume, statistics, banding, snapshots, quote requests).
+#[inline]
+fn is_skipped_template(t: u16) -> bool {
+    matches!(
+        t,
+        TEMPLATE_CHANNEL_RESET
+            | 12
+            | 15
+            | 16
+            | 27
+            | 29
+            | 37
+            | 39
+            | 41
+            | 49
+            | 50
+            | 51
+            | 52
+            | 53
+            | 54
+            | 55
+            | 56
+            | 57
+            | 58
+            | 61
+            | 62
+            | 63
+    )
+}
+
+const INT32_NULL: i32 = i32::MAX;
+const PRICE_NULL: i64 = i64::MAX;
+
+pub struct CmeMdp3Decoder {
+    // Decoder is used by a single decode thread; we avoid mutex overhead.
+    inner: UnsafeCell<Inner>,
+    stats: DecoderStats,
+}
+
+// Safety: see `Itch50Decoder`; the decoder is only driven from the decode thread.
+unsafe impl Send for CmeMdp3Decoder {}
+unsafe impl Sync for CmeMdp3Decoder {}
+
+#[derive(Default)]
+struct Inner {
+    /// order_id -> state
+    orders: HashMap<u64, OrderState>,
+}
+
+#[derive(Clone, Copy)]
+struct OrderState {
+    instr: u32,
+    qty: i64,
+    px: i64, // price in 1/10000
+    side: Side,
+}
+
+impl CmeMdp3Decoder {
+    pub fn new() -> Self {
+        Self {
+            inner: UnsafeCell::new(Inner::default()),
+            stats: DecoderStats::new("mdp3", TypeLabel::Decimal),
+        }
+    }
+}
+
+impl Default for CmeMdp3Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for CmeMdp3Decoder {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl MessageDecoder for CmeMdp3Decoder {
+    #[inline]
+    fn decode_messages(&self, payload: &[u8], out: &mut Vec<Event>) {
+        if payload.len() < PACKET_HDR_LEN {
+            return;
+        }
+        let st: &mut Inner = unsafe { &mut *self.inner.get() };
+        let mut off = PACKET_HDR_LEN;
+        while off + 2 + SBE_HDR_LEN <= payload.len() {
+            let msg_size = le_u16(payload, off) as usize;
+            if msg_size < 2 + SBE_HDR_LEN || off + msg_size > payload.len() {
+                // Truncated packet (drop tail gracefully)
+                self.stats.issue(DecodeIssue::Truncated, payload, off);
+                return;
+            }
+            let msg_off = off;
+            let msg = &payload[off + 2..off + msg_size];
+            off += msg_size;
+
+            let block_len = le_u16(msg, 0) as usize;
+            let template_id = le_u16(msg, 2);
+            let body = &msg[SBE_HDR_LEN..];
+            let known = matches!(
+                template_id,
+                TEMPLATE_INCR_BOOK
+                    | TEMPLATE_INCR_ORDER_BOOK
+                    | TEMPLATE_INCR_TRADE_SUMMARY
+                    | TEMPLATE_SECURITY_STATUS
+            ) || is_skipped_template(template_id);
+            if !known {
+                self.stats.issue(DecodeIssue::UnknownType, payload, msg_off);
+                continue;
+            }
+            self.stats.msg(template_id);
+            if block_len > body.len() {
+                self.stats.issue(DecodeIssue::ShortBody, payload, msg_off);
+                continue;
+            }
+
+            let ts = if block_len >= 8 {
+                read_le_u64(body, 0)
+            } else {
+                None
+            };
+            let ok = match template_id {
+                TEMPLATE_INCR_BOOK => {
+                    block_len >= INCR_ROOT_LEN && on_incr_book(body, block_len, ts, st, out)
+                }
+                TEMPLATE_INCR_ORDER_BOOK => {
+                    block_len >= INCR_ROOT_LEN && on_incr_order_book(body, block_len, ts, st, out)
+                }
+                TEMPLATE_INCR_TRADE_SUMMARY => {
+                    block_len >= INCR_ROOT_LEN && on_trade_summary(body, block_len, ts, out)
+                }
+                TEMPLATE_SECURITY_STATUS => {
+                    block_len >= SECURITY_STATUS_ROOT_LEN && {
+                        on_security_status(body, ts, out);
+                        true
+                    }
+                }
+                // Skipped template
+                _ => true,
+            };
+            if !ok {
+                self.stats.issue(DecodeIssue::ShortBody, payload, msg_off);
+            }
+        }
+        if off < payload.len() {
+            self.stats.issue(DecodeIssue::TrailingBytes, payload, off);
+        }
+    }
+}
+
+// -------------------- Packet header / sequencing --------------------
+
+/// Fields of the MDP 3.0 Binary Packet Header.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketHeader {
+    pub msg_seq_num: u32,
+}
+
+#[inline]
+pub fn packet_header(pkt: &[u8]) -> Option<PacketHeader> {
+    if pkt.len() < PACKET_HDR_LEN {
+        return None;
+    }
+    Some(PacketHeader {
+        msg_seq_num: u32::from_le_bytes([pkt[0], pkt[1], pkt[2], pkt[3]]),
+    })
+}
+
+/// Sequence extractor for the Binary Packet Header. MDP 3.0 numbers packets, not
+/// messages, so every packet consumes exactly one sequence number.
+pub struct Mdp3PacketSeq;
+
+impl SeqExtractor for Mdp3PacketSeq {
+    #[inline]
+    fn extract_seq(&self, pkt: &[u8]) -> Option<u64> {
+        packet_header(pkt).map(|h| h.msg_seq_num as u64)
+    }
+
+    /// A packet carrying a ChannelReset restarts every book of the channel
+    #[inline]
+    fn is_seq_reset(&self, pkt: &[u8]) -> bool {
+        let mut off = PACKET_HDR_LEN;
+        while off + 2 + SBE_HDR_LEN <= pkt.len() {
+            let msg_size = le_u16(pkt, off) as usize;
+            if msg_size < 2 + SBE_HDR_LEN {
+                return false;
+            }
+            if le_u16(pkt, off + 4) == TEMPLATE_CHANNEL_RESET {
+                return true;
+            }
+            off += msg_size;
+        }
+        false
+    }
+}
+
+// -------------------- Template handlers --------------------
+
+#[inline]
+fn le_u16(b: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([b[off], b[off + 1]])
+}
+
+#[inline]
+fn read_le_i32(b: &[u8], off: usize) -> Option<i32> {
+    b.get(off..off + 4)
+        .map(|s| i32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+#[inline]
+fn read_le_u64(b: &[u8], off: usize) -> Option<u64> {
+    b.get(off..off + 8)
+        .map(|s| u64::from_le_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]))
+}
+
+#[inline]
+fn read_le_i64(b: &[u8], off: usize) -> Option<i64> {
+    read_le_u64(b, off).map(|v| v as i64)
+}
+
+/// PRICE9 mantissa -> 1/10000; null prices (deletes) map to 0.
+#[inline]
+fn px_e4(raw: i64) -> i64 {
+    if raw == PRICE_NULL {
+        0
+    } else {
+        raw / 100_000
+    }
+}
+
+#[inline]
+fn qty_of(raw: i32) -> i64 {
+    if raw == INT32_NULL {
+        0
+    } else {
+        raw as i64
+    }
+}
+
+/// A repeating group located at `off`: (entry block length, entry count, first entry offset).
+#[inline]
+fn read_group(b: &[u8], off: usize, eight_byte: bool) -> Option<(usize, usize, usize)> {
+    let hdr_len = if eight_byte { 8 } else { 3 };
+    if off + hdr_len > b.len() {
+        return None;
+    }
+    let block_len = le_u16(b, off) as usize;
+    let count = b[off + hdr_len - 1] as usize;
+    let start = off + hdr_len;
+    if block_len == 0 || start + block_len * count > b.len() {
+        return None;
+    }
+    Some((block_len, count, start))
+}
+
+/// Book side and implied flag for an MDEntryType.
+#[inline]
+fn book_entry_type(t: u8) -> Option<(Side, bool)> {
+    match t {
+        b'0' => Some((Side::Bid, false)),
+        b'1' => Some((Side::Ask, false)),
+        b'E' => Some((Side::Bid, true)),
+        b'F' => Some((Side::Ask, true)),
+        _ => None,
+    }
+}
+
+#[inline]
+fn level_action(a: u8) -> Option<LevelAction> {
+    match a {
+        0 => Some(LevelAction::New),
+        1 => Some(LevelAction::Change),
+        2 => Some(LevelAction::Delete),
+        3 => Some(LevelAction::DeleteThru),
+        4 => Some(LevelAction::DeleteFrom),
+        5 => Some(LevelAction::Overlay),
+        _ => None,
+    }
+}
+
+fn on_incr_book(
+    body: &[u8],
+    block_len: usize,
+    ts: Option<u64>,
+    st: &mut Inner,
+    out: &mut Vec<Event>,
+) -> bool {
+    // Root: TransactTime(0,8) MatchEventIndicator(8,1) pad(2)
+    // NoMDEntries (groupSize), entry:
+    //   MDEntryPx(0,8) MDEntrySize(8,4) SecurityID(12,4) RptSeq(16,4) NumberOfOrders(20,4)
+    //   MDPriceLevel(24,1) MDUpdateAction(25,1) MDEntryType(26,1)
+    // NoOrderIDEntries (groupSize8Byte), entry:
+    //   OrderID(0,8) MDOrderPriority(8,8) MDDisplayQty(16,4) ReferenceID(20,1)
+    //   OrderUpdateAction(21,1)
+    const ENTRY_MIN: usize = 27;
+    const ORDER_ENTRY_MIN: usize = 22;
+    let Some((blk, n, start)) = read_group(body, block_len, false) else {
+        return false;
+    };
+    if blk < ENTRY_MIN {
+        return false;
+    }
+    for i in 0..n {
+        let e = &body[start + i * blk..start + (i + 1) * blk];
+        let Some((side, implied)) = book_entry_type(e[26]) else {
+            continue;
+        };
+        let Some(action) = level_action(e[25]) else {
+            continue;
+        };
+        out.push(Event::Level {
+            instr: read_le_i32(e, 12).unwrap() as u32,
+            side,
+            level: e[24],
+            px: px_e4(read_le_i64(e, 0).unwrap()),
+            qty: qty_of(read_le_i32(e, 8).unwrap()),
+            orders: read_le_i32(e, 20).unwrap(),
+            action,
+            implied,
+            exch_ts_ns: ts,
+        });
+    }
+
+    // Optional order-level detail for the outright entries above
+    let md_entries_end = start + n * blk;
+    let Some((oblk, on, ostart)) = read_group(body, md_entries_end, true) else {
+        // Older schema versions end after the MD entries
+        return true;
+    };
+    if oblk < ORDER_ENTRY_MIN {
+        return false;
+    }
+    for i in 0..on {
+        let o = &body[ostart + i * oblk..ostart + (i + 1) * oblk];
+        // ReferenceID is the 1-based index of the MD entry this order belongs to
+        let r = o[20] as usize;
+        if r == 0 || r > n {
+            continue;
+        }
+        let e = &body[start + (r - 1) * blk..start + r * blk];
+        let Some((side, false)) = book_entry_type(e[26]) else {
+            continue;
+        };
+        apply_order(
+            st,
+            out,
+            ts,
+            read_le_u64(o, 0).unwrap(),
+            read_le_i32(e, 12).unwrap() as u32,
+            side,
+            px_e4(read_le_i64(e, 0).unwrap()),
+            qty_of(read_le_i32(o, 16).unwrap()),
+            o[21],
+        );
+    }
+    true
+}
+
+fn on_incr_order_book(
+    body: &[u8],
+    block_len: usize,
+    ts: Option<u64>,
+    st: &mut Inner,
+    out: &mut Vec<Event>,
+) -> bool {
+    // Root: TransactTime(0,8) MatchEventIndicator(8,1) pad(2)
+    // NoMDEntries (groupSize8Byte), entry:
+    //   OrderID(0,8) MDOrderPriority(8,8) MDEntryPx(16,8) MDDisplayQty(24,4)
+    //   SecurityID(28,4) MDUpdateAction(32,1) MDEntryType(33,1)
+    const ENTRY_MIN: usize = 34;
+    let Some((blk, n, start)) = read_group(body, block_len, true) else {
+        return false;
+    };
+    if blk < ENTRY_MIN {
+        return false;
+    }
+    for i in 0..n {
+        let e = &body[start + i * blk..start + (i + 1) * blk];
+        let Some((side, false)) = book_entry_type(e[33]) else {
+            continue;
+        };
+        apply_order(
+            st,
+            out,
+            ts,
+            read_le_u64(e, 0).unwrap(),
+            read_le_i32(e, 28).unwrap() as u32,
+            side,
+            px_e4(read_le_i64(e, 16).unwrap()),
+            qty_of(read_le_i32(e, 24).unwrap()),
+            e[32],
+        );
+    }
+    true
+}
+
+/// Map an MBO update (0 = New, 1 = Change, 2 = Delete) onto book events.
+#[allow(clippy::too_many_arguments)]
+fn apply_order(
+    st: &mut Inner,
+    out: &mut Vec<Event>,
+    ts: Option<u64>,
+    order_id: u64,
+    instr: u32,
+    side: Side,
+    px: i64,
+    qty: i64,
+    action: u8,
+) {
+    match action {
+        0 => {
+            out.push(Event::Add {
+                order_id,
+                instr,
+                px,
+                qty,
+                side,
+                exch_ts_ns: ts,
+            });
+            st.orders.insert(
+                order_id,
+                OrderState {
+                    instr,
+                    qty,
+                    px,
+                    side,
+                },
+            );
+        }
+        1 => {
+            let Some(ent) = st.orders.get_mut(&order_id) else {
+                // Unknown order (late join): treat as new so the book converges
+                return apply_order(st, out, ts, order_id, instr, side, px, qty, 0);
+            };
+            if ent.instr == instr && ent.px == px && ent.side == side {
+                ent.qty = qty;
+                out.push(Event::Mod {
+                    order_id,
+                    qty,
+                    exch_ts_ns: ts,
+                });
+            } else {
+                out.push(Event::Del {
+                    order_id,
+                    exch_ts_ns: ts,
+                });
+                st.orders.remove(&order_id);
+                apply_order(st, out, ts, order_id, instr, side, px, qty, 0);
+            }
+        }
+        2 if st.orders.remove(&order_id).is_some() => {
+            out.push(Event::Del {
+                order_id,
+                exch_ts_ns: ts,
+            });
+        }
+        _ => {}
+    }
+}
+
+fn on_trade_summary(body: &[u8], block_len: usize, ts: Option<u64>, out: &mut Vec<Event>) -> bool {
+    // Root: TransactTime(0,8) MatchEventIndicator(8,1) pad(2)
+    // NoMDEntries (groupSize), entry:
+    //   MDEntryPx(0,8) MDEntrySize(8,4) SecurityID(12,4) RptSeq(16,4) NumberOfOrders(20,4)
+    //   AggressorSide(24,1: 0 none, 1 buy, 2 sell) MDUpdateAction(25,1) MDTradeEntryID(26,4)
+    // The trailing NoOrderIDEntries group lists per-order fills; resting orders are
+    // updated through the MBO stream, so only the aggregate trade is emitted here.
+    const ENTRY_MIN: usize = 25;
+    let Some((blk, n, start)) = read_group(body, block_len, false) else {
+        return false;
+    };
+    if blk < ENTRY_MIN {
+        return false;
+    }
+    for i in 0..n {
+        let e = &body[start + i * blk..start + (i + 1) * blk];
+        let taker_side = match e[24] {
+            1 => Some(Side::Bid),
+            2 => Some(Side::Ask),
+            _ => None,
+        };
+        out.push(Event::Trade {
+            instr: read_le_i32(e, 12).unwrap() as u32,
+            px: px_e4(read_le_i64(e, 0).unwrap()),
+            qty: qty_of(read_le_i32(e, 8).unwrap()),
+            maker_order_id: None,
+            taker_side,
+            exch_ts_ns: ts,
+        });
+    }
+    true
+}
+
+fn on_security_status(body: &[u8], ts: Option<u64>, out: &mut Vec<Event>) {
+    // TransactTime(0,8) SecurityGroup(8,6) Asset(14,6) SecurityID(20,4) TradeDate(24,2)
+    // MatchEventIndicator(26,1) SecurityTradingStatus(27,1) HaltReason(28,1) ...
+    if body.len() < 28 {
+        return;
+    }
+    let sec_id = read_le_i32(body, 20).unwrap();
+    if sec_id == INT32_NULL {
+        // Group/asset-wide status; no single instrument to attribute it to
+        return;
+    }
+    out.push(Event::TradingStatus {
+        instr: sec_id as u32,
+        status: body[27],
+        exch_ts_ns: ts,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn packet(seq: u32, msgs: &[Vec<u8>]) -> Vec<u8> {
+        let mut p = Vec::new();
+        p.extend_from_slice(&seq.to_le_bytes());
+        p.extend_from_slice(&1_700_000_000_000_000_000u64.to_le_bytes());
+        for m in msgs {
+            p.extend_from_slice(&((m.len() + 2) as u16).to_le_bytes());
+            p.extend_from_slice(m);
+        }
+        p
+    }
+
+    fn sbe(template: u16, root: &[u8], groups: &[u8]) -> Vec<u8> {
+        let mut m = Vec::new();
+        m.extend_from_slice(&(root.len() as u16).to_le_bytes());
+        m.extend_from_slice(&template.to_le_bytes());
+        m.extend_from_slice(&1u16.to_le_bytes());
+        m.extend_from_slice(&9u16.to_le_bytes());
+        m.extend_from_slice(root);
+        m.extend_from_slice(groups);
+        m
+    }
+
+    fn root() -> Vec<u8> {
+        let mut r = 5u64.to_le_bytes().to_vec();
+        r.extend_from_slice(&[0x80, 0, 0]);
+        r
+    }
+
+    fn mbo_entry(order_id: u64, px: i64, qty: i32, sec: i32, action: u8, ty: u8) -> Vec<u8> {
+        let mut e = Vec::new();
+        e.extend_from_slice(&order_id.to_le_bytes());
+        e.extend_from_slice(&1u64.to_le_bytes());
+        e.extend_from_slice(&px.to_le_bytes());
+        e.extend_from_slice(&qty.to_le_bytes());
+        e.extend_from_slice(&sec.to_le_bytes());
+        e.push(action);
+        e.push(ty);
+        e.extend_from_slice(&[0u8; 6]);
+        e
+    }
+
+    #[test]
+    fn packet_header_sequence() {
+        let p = packet(77, &[]);
+        assert_eq!(Mdp3PacketSeq.extract_seq(&p), Some(77));
+        assert_eq!(Mdp3PacketSeq.seq_span(&p), 1);
+        assert_eq!(packet_header(&p).map(|h| h.msg_seq_num), Some(77));
+        assert!(!Mdp3PacketSeq.is_seq_reset(&p));
+        let status = sbe(TEMPLATE_SECURITY_STATUS, &[0; 30], &[]);
+        let reset = sbe(TEMPLATE_CHANNEL_RESET, &root()[..9], &[0, 0, 0]);
+        assert!(Mdp3PacketSeq.is_seq_reset(&packet(78, &[status, reset])));
+    }
+
+    #[test]
+    fn mbo_new_change_delete() {
+        let entries = [
+            mbo_entry(9, 4_500_250_000_000, 3, 101, 0, b'0'),
+            mbo_entry(9, 4_500_250_000_000, 1, 101, 1, b'0'),
+            mbo_entry(9, 4_500_500_000_000, 1, 101, 1, b'0'),
+            mbo_entry(9, 0, 0, 101, 2, b'0'),
+        ];
+        let mut g = 40u16.to_le_bytes().to_vec();
+        g.extend_from_slice(&[0u8; 5]);
+        g.push(entries.len() as u8);
+        for e in &entries {
+            g.extend_from_slice(e);
+        }
+        let p = packet(1, &[sbe(TEMPLATE_INCR_ORDER_BOOK, &root(), &g)]);
+
+        let dec = CmeMdp3Decoder::new();
+        let mut out = Vec::new();
+        dec.decode_messages(&p, &mut out);
+        assert!(matches!(
+            out.as_slice(),
+            [
+                Event::Add {
+                    order_id: 9,
+                    instr: 101,
+                    px: 45_002_500,
+                    qty: 3,
+                    side: Side::Bid,
+                    exch_ts_ns: Some(5),
+                },
+                Event::Mod {
+                    order_id: 9,
+                    qty: 1,
+                    ..
+                },
+                Event::Del { order_id: 9, .. },
+                Event::Add {
+                    order_id: 9,
+                    px: 45_005_000,
+                    ..
+                },
+                Event::Del { order_id: 9, .. },
+            ]
+        ));
+    }
+
+    #[test]
+    fn mbp_levels_flag_implied() {
+        let mut g = 32u16.to_le_bytes().to_vec();
+        g.push(2);
+        for (ty, lvl) in [(b'1', 1u8), (b'E', 2u8)] {
+            let mut e = Vec::new();
+            e.extend_from_slice(&4_501_000_000_000i64.to_le_bytes());
+            e.extend_from_slice(&25i32.to_le_bytes());
+            e.extend_from_slice(&101i32.to_le_bytes());
+            e.extend_from_slice(&1u32.to_le_bytes());
+            e.extend_from_slice(&4i32.to_le_bytes());
+            e.push(lvl);
+            e.push(0);
+            e.push(ty);
+            e.extend_from_slice(&[0u8; 5]);
+            g.extend_from_slice(&e);
+        }
+        // empty NoOrderIDEntries
+        g.extend_from_slice(&24u16.to_le_bytes());
+        g.extend_from_slice(&[0u8; 6]);
+        let p = packet(1, &[sbe(TEMPLATE_INCR_BOOK, &root(), &g)]);
+
+        let dec = CmeMdp3Decoder::new();
+        let mut out = Vec::new();
+        dec.decode_messages(&p, &mut out);
+        assert!(matches!(
+            out.as_slice(),
+            [
+                Event::Level {
+                    instr: 101,
+                    side: Side::Ask,
+                    level: 1,
+                    qty: 25,
+                    orders: 4,
+                    action: LevelAction::New,
+                    implied: false,
+                    ..
+                },
+                Event::Level {
+                    side: Side::Bid,
+                    level: 2,
+                    implied: true,
+                    ..
+                },
+            ]
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn decode_random_input_does_not_panic(payload in proptest::collection::vec(any::<u8>(), 0..4096)) {
+            let dec = CmeMdp3Decoder::new();
+            let mut out = Vec::new();
+            dec.decode_messages(&payload, &mut out);
+            prop_assert!(out.len() <= payload.len());
+        }
+    }
+}
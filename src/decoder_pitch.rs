@@ -0,0 +1,828 @@
+// src/decoder_pitch.rs
+// Cboe (BATS) Multicast PITCH decoder; little-endian; Sequenced Unit Header followed by
+// [u8 length][u8 type][body] messages; stateful (keeps an order map and a symbol table).
+//
+// Packet layout:
+//   Sequenced Unit Header (8 bytes):
+//     hdr_length(2) hdr_count(1) hdr_unit(1) hdr_sequence(4)
+//   then `hdr_count` messages, each starting with length(1) and type(1).
+//   `hdr_sequence` is the sequence of the first message; sequences are per unit.
+//
+// Supported messages:
+//  - 0x21 Add Order Long, 0x22 Add Order Short, 0x2F Add Order Expanded
+//  - 0x23 Order Executed, 0x24 Order Executed at Price/Size
+//  - 0x25 Reduce Size Long, 0x26 Reduce Size Short
+//  - 0x27 Modify Order Long, 0x28 Modify Order Short (delete + add, priority is lost)
+//  - 0x29 Delete Order
+//  - 0x2A Trade Long, 0x2B Trade Short, 0x30 Trade Expanded (non-displayed executions)
+//  - 0x31 Trading Status
+//  - 0x20 Time (seconds since midnight; later messages carry a ns offset from it)
+//  - 0x97 Unit Clear (drops the unit's orders; decode restarts its books on the reset)
+// Long prices carry 4 implied decimals, short prices 2; both are normalized to 1/10000
+// so books built from PITCH and ITCH share the same price scale.
+// Exchange timestamps are only reported once a Time message has been seen.
+// Unknown types are safely skipped.
+
+use crate::decode_stats::{DecodeIssue, DecoderStats, TypeLabel};
+use crate::parser::{Event, MessageDecoder, SeqExtractor, Side};
+use crate::util::SessionClock;
+use hashbrown::HashMap;
+use std::cell::UnsafeCell;
+
+/// Size of the Sequenced Unit Header that prefixes every PITCH packet.
+pub const UNIT_HDR_LEN: usize = 8;
+/// Unit Clear: drop every order of the unit; the unit's book is sent again from empty
+const UNIT_CLEAR: u8 = 0x97;
+
+pub struct CboePitchDecoder {
+    // Decoder is used by a single decode thread; we avoid mutex overhead.
+    inner: UnsafeCell<Inner>,
+    stats: DecoderStats,
+}
+
+// Safety: see `Itch50Decoder`; the decoder is only driven from the decode thread.
+unsafe impl Send for CboePitchDecoder {}
+unsafe impl Sync for CboePitchDecoder {}
+
+#[derive(Default)]
+struct Inner {
+    /// order_id -> state
+    orders: HashMap<u64, OrderState>,
+    /// space-padded symbol -> instrument id (assigned on first sight)
+    symbols: HashMap<[u8; 8], u32>,
+    /// Anchors ns-since-midnight timestamps at the session's midnight
+    clock: SessionClock,
+    /// Seconds since midnight from the last Time message
+    time_s: Option<u32>,
+    /// Exchange timestamp of the message being decoded
+    msg_ts: Option<u64>,
+    /// Unit of the packet being decoded
+    unit: u8,
+}
+
+#[derive(Clone, Copy)]
+struct OrderState {
+    /// Unit the order was added on (Unit Clear scope)
+    unit: u8,
+    instr: u32,
+    qty: i64,
+    px: i64, // price in 1/10000
+    side: Side,
+}
+
+impl CboePitchDecoder {
+    pub fn new() -> Self {
+        Self::with_session_offset(0)
+    }
+
+    /// Anchor Time messages at midnight of a venue whose local time is UTC + `utc_offset_s`.
+    pub fn with_session_offset(utc_offset_s: i32) -> Self {
+        Self {
+            inner: UnsafeCell::new(Inner {
+                clock: SessionClock::new(utc_offset_s),
+                ..Inner::default()
+            }),
+            stats: DecoderStats::new("pitch", TypeLabel::Hex),
+        }
+    }
+}
+
+impl Default for CboePitchDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for CboePitchDecoder {
+    fn clone(&self) -> Self {
+        let clock = unsafe { (*self.inner.get()).clock };
+        Self {
+            inner: UnsafeCell::new(Inner {
+                clock,
+                ..Inner::default()
+            }),
+            stats: DecoderStats::new("pitch", TypeLabel::Hex),
+        }
+    }
+}
+
+/// Minimum message length (including length and type bytes) for each type we decode;
+/// 0 for spec types we deliberately skip; None for types outside the spec.
+#[inline]
+fn min_msg_len(typ: u8) -> Option<usize> {
+    match typ {
+        0x20 => Some(6),
+        0x21 => Some(34),
+        0x22 => Some(26),
+        0x2F => Some(36),
+        0x23 => Some(26),
+        0x24 => Some(38),
+        0x25 => Some(18),
+        0x26 => Some(16),
+        0x27 => Some(27),
+        0x28 => Some(19),
+        0x29 => Some(14),
+        0x2A => Some(41),
+        0x2B => Some(33),
+        0x30 => Some(43),
+        0x31 => Some(15),
+        // Trade Break, End of Session, Auction Update/Summary, Unit Clear, RPI
+        0x2C | 0x2D | 0x95 | 0x96 | UNIT_CLEAR | 0x98 => Some(0),
+        _ => None,
+    }
+}
+
+impl MessageDecoder for CboePitchDecoder {
+    #[inline]
+    fn decode_messages(&self, payload: &[u8], out: &mut Vec<Event>) {
+        if payload.len() < UNIT_HDR_LEN {
+            return;
+        }
+        let st: &mut Inner = unsafe { &mut *self.inner.get() };
+        st.clock.tick();
+        let hdr_len = le_u16(&payload[0..2]) as usize;
+        let count = payload[2] as usize;
+        st.unit = payload[3];
+        // Trust the header length when it is sane, otherwise fall back to the datagram size
+        let end = if hdr_len >= UNIT_HDR_LEN && hdr_len <= payload.len() {
+            hdr_len
+        } else {
+            payload.len()
+        };
+
+        let mut off = UNIT_HDR_LEN;
+        for _ in 0..count {
+            let msg_len = payload.get(off).map_or(0, |&b| b as usize);
+            if off + 2 > end || msg_len < 2 || off + msg_len > end {
+                // Truncated packet (drop tail gracefully)
+                self.stats.issue(DecodeIssue::Truncated, payload, off);
+                return;
+            }
+            let msg = &payload[off..off + msg_len];
+            off += msg_len;
+
+            match min_msg_len(msg[1]) {
+                None => {
+                    self.stats
+                        .issue(DecodeIssue::UnknownType, payload, off - msg_len);
+                    continue;
+                }
+                Some(min) if msg_len < min => {
+                    self.stats.msg(msg[1] as u16);
+                    self.stats
+                        .issue(DecodeIssue::ShortBody, payload, off - msg_len);
+                    continue;
+                }
+                _ => self.stats.msg(msg[1] as u16),
+            }
+            // Every message after the Time message carries ts(2,4) in ns past that second
+            st.msg_ts = match (st.time_s, read_le_u32(msg, 2)) {
+                (Some(secs), Some(ns)) => {
+                    Some(st.clock.at(secs as u64 * 1_000_000_000 + ns as u64))
+                }
+                _ => None,
+            };
+
+            match msg[1] {
+                0x20 => st.time_s = read_le_u32(msg, 2),
+                0x21 => on_add(msg, st, out, AddLayout::Long),
+                0x22 => on_add(msg, st, out, AddLayout::Short),
+                0x2F => on_add(msg, st, out, AddLayout::Expanded),
+                0x23 => on_exec(msg, st, out),
+                0x24 => on_exec_at_price(msg, st, out),
+                0x25 => on_reduce(msg, st, out, /*long*/ true),
+                0x26 => on_reduce(msg, st, out, /*long*/ false),
+                0x27 => on_modify(msg, st, out, /*long*/ true),
+                0x28 => on_modify(msg, st, out, /*long*/ false),
+                0x29 => on_delete(msg, st, out),
+                0x2A => on_trade(msg, st, out, TradeLayout::Long),
+                0x2B => on_trade(msg, st, out, TradeLayout::Short),
+                0x30 => on_trade(msg, st, out, TradeLayout::Expanded),
+                0x31 => on_trading_status(msg, st, out),
+                UNIT_CLEAR => {
+                    let unit = st.unit;
+                    st.orders.retain(|_, o| o.unit != unit);
+                }
+                // Auction, Retail Price Improvement, End of Session, ...
+                _ => { /* ignore other admin/metadata messages */ }
+            }
+        }
+        if off < end {
+            self.stats.issue(DecodeIssue::TrailingBytes, payload, off);
+        }
+    }
+}
+
+// -------------------- Sequencing --------------------
+
+/// Sequence extractor for PITCH Sequenced Unit Headers.
+///
+/// Each unit carries its own sequence space, so when `unit` is set only packets
+/// for that unit yield a sequence and all others are dropped at RX. Unsequenced
+/// packets (unit 0) and sequenced heartbeats (count 0) carry no messages and are
+/// dropped as well. A packet consumes `hdr_count` sequence numbers. A packet carrying
+/// a Unit Clear restarts the unit's book and is reported as a sequence reset.
+pub struct PitchUnitSeq {
+    unit: Option<u8>,
+}
+
+impl PitchUnitSeq {
+    pub fn new(unit: Option<u8>) -> Self {
+        Self { unit }
+    }
+
+    #[inline]
+    fn header(&self, pkt: &[u8]) -> Option<(u8, u8, u64)> {
+        if pkt.len() < UNIT_HDR_LEN {
+            return None;
+        }
+        let count = pkt[2];
+        let unit = pkt[3];
+        let seq = u32::from_le_bytes([pkt[4], pkt[5], pkt[6], pkt[7]]) as u64;
+        if unit == 0 || count == 0 || seq == 0 {
+            return None;
+        }
+        if let Some(want) = self.unit {
+            if want != unit {
+                return None;
+            }
+        }
+        Some((count, unit, seq))
+    }
+}
+
+impl SeqExtractor for PitchUnitSeq {
+    #[inline]
+    fn extract_seq(&self, pkt: &[u8]) -> Option<u64> {
+        self.header(pkt).map(|(_, _, seq)| seq)
+    }
+
+    /// Each Sequenced Unit numbers its own packets
+    #[inline]
+    fn extract_stream_seq(&self, pkt: &[u8]) -> Option<(u32, u64)> {
+        self.header(pkt).map(|(_, unit, seq)| (unit as u32, seq))
+    }
+
+    #[inline]
+    fn seq_span(&self, pkt: &[u8]) -> u32 {
+        self.header(pkt)
+            .map(|(count, _, _)| count as u32)
+            .unwrap_or(1)
+    }
+
+    #[inline]
+    fn is_seq_reset(&self, pkt: &[u8]) -> bool {
+        let Some((count, _, _)) = self.header(pkt) else {
+            return false;
+        };
+        let mut off = UNIT_HDR_LEN;
+        for _ in 0..count {
+            match pkt.get(off..off + 2) {
+                Some(&[_, UNIT_CLEAR]) => return true,
+                Some(&[len, _]) if len >= 2 => off += len as usize,
+                _ => return false,
+            }
+        }
+        false
+    }
+}
+
+// -------------------- Message handlers --------------------
+// Offsets below are relative to the start of the message (including length/type),
+// matching the layout tables in the Cboe PITCH specification.
+
+#[derive(Clone, Copy)]
+enum AddLayout {
+    Long,
+    Short,
+    Expanded,
+}
+
+#[derive(Clone, Copy)]
+enum TradeLayout {
+    Long,
+    Short,
+    Expanded,
+}
+
+#[inline]
+fn le_u16(b: &[u8]) -> u16 {
+    u16::from_le_bytes([b[0], b[1]])
+}
+
+#[inline]
+fn read_le_u16(b: &[u8], off: usize) -> Option<u16> {
+    b.get(off..off + 2)
+        .map(|s| u16::from_le_bytes([s[0], s[1]]))
+}
+
+#[inline]
+fn read_le_u32(b: &[u8], off: usize) -> Option<u32> {
+    b.get(off..off + 4)
+        .map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+#[inline]
+fn read_le_u64(b: &[u8], off: usize) -> Option<u64> {
+    b.get(off..off + 8)
+        .map(|s| u64::from_le_bytes([s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7]]))
+}
+
+/// Short prices have 2 implied decimals; scale to the 4-decimal long format.
+#[inline]
+fn short_px(raw: u16) -> i64 {
+    (raw as i64) * 100
+}
+
+#[inline]
+fn side_of(b: u8) -> Side {
+    if b == b'B' {
+        Side::Bid
+    } else {
+        Side::Ask
+    }
+}
+
+#[inline]
+fn opposite(s: Side) -> Side {
+    match s {
+        Side::Bid => Side::Ask,
+        Side::Ask => Side::Bid,
+    }
+}
+
+/// Resolve a 6- or 8-byte space-padded symbol to a stable instrument id.
+fn instr_for_symbol(st: &mut Inner, sym: &[u8]) -> u32 {
+    let mut key = [b' '; 8];
+    let n = sym.len().min(8);
+    key[..n].copy_from_slice(&sym[..n]);
+    let next = st.symbols.len() as u32 + 1;
+    *st.symbols.entry(key).or_insert(next)
+}
+
+fn on_add(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>, layout: AddLayout) {
+    // Long:     ts(2,4) order_id(6,8) side(14,1) qty(15,4) symbol(19,6) price(25,8) flags(33,1)
+    // Short:    ts(2,4) order_id(6,8) side(14,1) qty(15,2) symbol(17,6) price(23,2) flags(25,1)
+    // Expanded: ts(2,4) order_id(6,8) side(14,1) qty(15,4) symbol(19,8) price(27,8) flags(35,1) ...
+    let (min_len, qty, sym, px) = match layout {
+        AddLayout::Long => (
+            34,
+            read_le_u32(msg, 15).map(|v| v as i64),
+            msg.get(19..25),
+            read_le_u64(msg, 25).map(|v| v as i64),
+        ),
+        AddLayout::Short => (
+            26,
+            read_le_u16(msg, 15).map(|v| v as i64),
+            msg.get(17..23),
+            read_le_u16(msg, 23).map(short_px),
+        ),
+        AddLayout::Expanded => (
+            36,
+            read_le_u32(msg, 15).map(|v| v as i64),
+            msg.get(19..27),
+            read_le_u64(msg, 27).map(|v| v as i64),
+        ),
+    };
+    if msg.len() < min_len {
+        return;
+    }
+    let (order_id, qty, sym, px) = match (read_le_u64(msg, 6), qty, sym, px) {
+        (Some(o), Some(q), Some(s), Some(p)) => (o, q, s, p),
+        _ => return,
+    };
+    let side = side_of(msg[14]);
+    let instr = instr_for_symbol(st, sym);
+
+    out.push(Event::Add {
+        order_id,
+        instr,
+        px,
+        qty,
+        side,
+        exch_ts_ns: st.msg_ts,
+    });
+    st.orders.insert(
+        order_id,
+        OrderState {
+            unit: st.unit,
+            instr,
+            qty,
+            px,
+            side,
+        },
+    );
+}
+
+/// Reduce a tracked order by `by` shares, emitting absolute Mod or Del.
+/// A zero reduction emits nothing. Returns the order state as it was before.
+fn reduce_order(
+    st: &mut Inner,
+    out: &mut Vec<Event>,
+    order_id: u64,
+    by: i64,
+) -> Option<OrderState> {
+    let ent = st.orders.get_mut(&order_id)?;
+    let before = *ent;
+    if by <= 0 {
+        return Some(before);
+    }
+    ent.qty = (ent.qty - by).max(0);
+    if ent.qty > 0 {
+        out.push(Event::Mod {
+            order_id,
+            qty: ent.qty,
+            exch_ts_ns: st.msg_ts,
+        });
+    } else {
+        out.push(Event::Del {
+            order_id,
+            exch_ts_ns: st.msg_ts,
+        });
+        st.orders.remove(&order_id);
+    }
+    Some(before)
+}
+
+fn on_exec(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
+    // 0x23 Order Executed: ts(2,4) order_id(6,8) executed_qty(14,4) execution_id(18,8)
+    if msg.len() < 26 {
+        return;
+    }
+    let order_id = read_le_u64(msg, 6).unwrap();
+    let executed = read_le_u32(msg, 14).unwrap() as i64;
+    if let Some(s) = reduce_order(st, out, order_id, executed) {
+        out.push(Event::Trade {
+            instr: s.instr,
+            px: s.px,
+            qty: executed,
+            maker_order_id: Some(order_id),
+            taker_side: Some(opposite(s.side)),
+            exch_ts_ns: st.msg_ts,
+        });
+    }
+}
+
+fn on_exec_at_price(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
+    // 0x24 Order Executed at Price/Size:
+    // ts(2,4) order_id(6,8) executed_qty(14,4) remaining_qty(18,4) execution_id(22,8) price(30,8)
+    if msg.len() < 38 {
+        return;
+    }
+    let order_id = read_le_u64(msg, 6).unwrap();
+    let executed = read_le_u32(msg, 14).unwrap() as i64;
+    let remaining = read_le_u32(msg, 18).unwrap() as i64;
+    let px = read_le_u64(msg, 30).unwrap() as i64;
+    if let Some(s) = st.orders.get(&order_id).copied() {
+        // Remaining size is authoritative; it may differ from qty - executed
+        let by = (s.qty - remaining).max(0);
+        reduce_order(st, out, order_id, by);
+        out.push(Event::Trade {
+            instr: s.instr,
+            px,
+            qty: executed,
+            maker_order_id: Some(order_id),
+            taker_side: Some(opposite(s.side)),
+            exch_ts_ns: st.msg_ts,
+        });
+    }
+}
+
+fn on_reduce(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>, long: bool) {
+    // 0x25 Reduce Size Long:  ts(2,4) order_id(6,8) canceled_qty(14,4)
+    // 0x26 Reduce Size Short: ts(2,4) order_id(6,8) canceled_qty(14,2)
+    let min_len = if long { 18 } else { 16 };
+    if msg.len() < min_len {
+        return;
+    }
+    let order_id = read_le_u64(msg, 6).unwrap();
+    let canceled = if long {
+        read_le_u32(msg, 14).unwrap() as i64
+    } else {
+        read_le_u16(msg, 14).unwrap() as i64
+    };
+    reduce_order(st, out, order_id, canceled);
+}
+
+fn on_modify(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>, long: bool) {
+    // 0x27 Modify Order Long:  ts(2,4) order_id(6,8) qty(14,4) price(18,8) flags(26,1)
+    // 0x28 Modify Order Short: ts(2,4) order_id(6,8) qty(14,2) price(16,2) flags(18,1)
+    let min_len = if long { 27 } else { 19 };
+    if msg.len() < min_len {
+        return;
+    }
+    let order_id = read_le_u64(msg, 6).unwrap();
+    let (qty, px) = if long {
+        (
+            read_le_u32(msg, 14).unwrap() as i64,
+            read_le_u64(msg, 18).unwrap() as i64,
+        )
+    } else {
+        (
+            read_le_u16(msg, 14).unwrap() as i64,
+            short_px(read_le_u16(msg, 16).unwrap()),
+        )
+    };
+    let Some(s) = st.orders.get(&order_id).copied() else {
+        // Unknown order (late join); nothing to modify
+        return;
+    };
+    if px == s.px && qty <= s.qty {
+        // Size-down at the same price keeps priority
+        reduce_order(st, out, order_id, s.qty - qty);
+        return;
+    }
+    // Price change or size-up loses priority: delete and re-add under the same id
+    out.push(Event::Del {
+        order_id,
+        exch_ts_ns: st.msg_ts,
+    });
+    out.push(Event::Add {
+        order_id,
+        instr: s.instr,
+        px,
+        qty,
+        side: s.side,
+        exch_ts_ns: st.msg_ts,
+    });
+    st.orders.insert(order_id, OrderState { qty, px, ..s });
+}
+
+fn on_delete(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
+    // 0x29 Delete Order: ts(2,4) order_id(6,8)
+    if msg.len() < 14 {
+        return;
+    }
+    let order_id = read_le_u64(msg, 6).unwrap();
+    if st.orders.remove(&order_id).is_some() {
+        out.push(Event::Del {
+            order_id,
+            exch_ts_ns: st.msg_ts,
+        });
+    }
+}
+
+fn on_trade(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>, layout: TradeLayout) {
+    // Executions against non-displayed orders; the book is not affected.
+    // Long:     ts(2,4) order_id(6,8) side(14,1) qty(15,4) symbol(19,6) price(25,8) exec_id(33,8)
+    // Short:    ts(2,4) order_id(6,8) side(14,1) qty(15,2) symbol(17,6) price(23,2) exec_id(25,8)
+    // Expanded: ts(2,4) order_id(6,8) side(14,1) qty(15,4) symbol(19,8) price(27,8) exec_id(35,8)
+    let (min_len, qty, sym, px) = match layout {
+        TradeLayout::Long => (
+            41,
+            read_le_u32(msg, 15).map(|v| v as i64),
+            msg.get(19..25),
+            read_le_u64(msg, 25).map(|v| v as i64),
+        ),
+        TradeLayout::Short => (
+            33,
+            read_le_u16(msg, 15).map(|v| v as i64),
+            msg.get(17..23),
+            read_le_u16(msg, 23).map(short_px),
+        ),
+        TradeLayout::Expanded => (
+            43,
+            read_le_u32(msg, 15).map(|v| v as i64),
+            msg.get(19..27),
+            read_le_u64(msg, 27).map(|v| v as i64),
+        ),
+    };
+    if msg.len() < min_len {
+        return;
+    }
+    let (qty, sym, px) = match (qty, sym, px) {
+        (Some(q), Some(s), Some(p)) => (q, s, p),
+        _ => return,
+    };
+    let instr = instr_for_symbol(st, sym);
+    // Side is that of the non-displayed resting order; the aggressor is the other side
+    out.push(Event::Trade {
+        instr,
+        px,
+        qty,
+        maker_order_id: None,
+        taker_side: Some(opposite(side_of(msg[14]))),
+        exch_ts_ns: st.msg_ts,
+    });
+}
+
+fn on_trading_status(msg: &[u8], st: &mut Inner, out: &mut Vec<Event>) {
+    // 0x31 Trading Status: ts(2,4) symbol(6,8) halt_status(14,1) reg_sho(15,1) ...
+    // halt_status: 'H' halted, 'Q' quote-only, 'S' suspended, 'T' trading
+    if msg.len() < 15 {
+        return;
+    }
+    let instr = instr_for_symbol(st, &msg[6..14]);
+    out.push(Event::TradingStatus {
+        instr,
+        status: msg[14],
+        exch_ts_ns: st.msg_ts,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn packet(unit: u8, seq: u32, msgs: &[Vec<u8>]) -> Vec<u8> {
+        let body_len: usize = msgs.iter().map(|m| m.len()).sum();
+        let mut p = Vec::with_capacity(UNIT_HDR_LEN + body_len);
+        p.extend_from_slice(&((UNIT_HDR_LEN + body_len) as u16).to_le_bytes());
+        p.push(msgs.len() as u8);
+        p.push(unit);
+        p.extend_from_slice(&seq.to_le_bytes());
+        for m in msgs {
+            p.extend_from_slice(m);
+        }
+        p
+    }
+
+    fn add_long(order_id: u64, side: u8, qty: u32, sym: &[u8; 6], px: u64) -> Vec<u8> {
+        let mut m = vec![34u8, 0x21];
+        m.extend_from_slice(&0u32.to_le_bytes());
+        m.extend_from_slice(&order_id.to_le_bytes());
+        m.push(side);
+        m.extend_from_slice(&qty.to_le_bytes());
+        m.extend_from_slice(sym);
+        m.extend_from_slice(&px.to_le_bytes());
+        m.push(0);
+        m
+    }
+
+    fn exec(order_id: u64, qty: u32) -> Vec<u8> {
+        let mut m = vec![26u8, 0x23];
+        m.extend_from_slice(&0u32.to_le_bytes());
+        m.extend_from_slice(&order_id.to_le_bytes());
+        m.extend_from_slice(&qty.to_le_bytes());
+        m.extend_from_slice(&7u64.to_le_bytes());
+        m
+    }
+
+    fn modify_short(order_id: u64, qty: u16, px: u16) -> Vec<u8> {
+        let mut m = vec![19u8, 0x28];
+        m.extend_from_slice(&0u32.to_le_bytes());
+        m.extend_from_slice(&order_id.to_le_bytes());
+        m.extend_from_slice(&qty.to_le_bytes());
+        m.extend_from_slice(&px.to_le_bytes());
+        m.push(0);
+        m
+    }
+
+    #[test]
+    fn unit_header_sequence_and_span() {
+        let p = packet(3, 1000, &[exec(1, 1), exec(2, 1)]);
+        let any = PitchUnitSeq::new(None);
+        assert_eq!(any.extract_seq(&p), Some(1000));
+        assert_eq!(any.seq_span(&p), 2);
+        // Units are separate sequence streams
+        assert_eq!(any.extract_stream_seq(&p), Some((3, 1000)));
+        assert_eq!(PitchUnitSeq::new(Some(3)).extract_seq(&p), Some(1000));
+        assert_eq!(PitchUnitSeq::new(Some(4)).extract_seq(&p), None);
+        // Sequenced heartbeat (count 0) carries nothing to merge
+        assert_eq!(any.extract_seq(&packet(3, 1002, &[])), None);
+        assert!(!any.is_seq_reset(&p));
+        let clear = vec![6u8, UNIT_CLEAR, 0, 0, 0, 0];
+        assert!(any.is_seq_reset(&packet(3, 1, &[exec(1, 1), clear])));
+    }
+
+    #[test]
+    fn add_exec_modify_flow() {
+        let dec = CboePitchDecoder::new();
+        let mut out = Vec::new();
+        let p = packet(
+            1,
+            1,
+            &[
+                add_long(42, b'B', 300, b"AAPL  ", 1_500_000),
+                exec(42, 100),
+                modify_short(42, 200, 15100),
+            ],
+        );
+        dec.decode_messages(&p, &mut out);
+        match out.as_slice() {
+            [Event::Add {
+                order_id: 42,
+                instr,
+                px: 1_500_000,
+                qty: 300,
+                side: Side::Bid,
+                ..
+            }, Event::Mod {
+                order_id: 42,
+                qty: 200,
+                ..
+            }, Event::Trade {
+                qty: 100,
+                maker_order_id: Some(42),
+                taker_side: Some(Side::Ask),
+                ..
+            }, Event::Del { order_id: 42, .. }, Event::Add {
+                order_id: 42,
+                instr: instr2,
+                px: 1_510_000,
+                qty: 200,
+                side: Side::Bid,
+                ..
+            }] => assert_eq!(instr, instr2),
+            _ => panic!("unexpected events: {:?}", out),
+        }
+    }
+
+    fn exec_at_price(order_id: u64, qty: u32, remaining: u32, px: u64) -> Vec<u8> {
+        let mut m = vec![38u8, 0x24];
+        m.extend_from_slice(&0u32.to_le_bytes());
+        m.extend_from_slice(&order_id.to_le_bytes());
+        m.extend_from_slice(&qty.to_le_bytes());
+        m.extend_from_slice(&remaining.to_le_bytes());
+        m.extend_from_slice(&7u64.to_le_bytes());
+        m.extend_from_slice(&px.to_le_bytes());
+        m
+    }
+
+    #[test]
+    fn exec_at_price_with_unchanged_remaining_emits_only_the_trade() {
+        let dec = CboePitchDecoder::new();
+        let mut out = Vec::new();
+        let p = packet(
+            1,
+            1,
+            &[
+                add_long(5, b'S', 100, b"AAPL  ", 1_500_000),
+                exec_at_price(5, 40, 100, 1_499_900),
+            ],
+        );
+        dec.decode_messages(&p, &mut out);
+        assert!(matches!(
+            out.as_slice(),
+            [
+                Event::Add { order_id: 5, .. },
+                Event::Trade {
+                    px: 1_499_900,
+                    qty: 40,
+                    maker_order_id: Some(5),
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn unit_clear_drops_only_that_units_orders() {
+        let dec = CboePitchDecoder::new();
+        let mut out = Vec::new();
+        dec.decode_messages(
+            &packet(1, 1, &[add_long(1, b'B', 10, b"AAPL  ", 1_500_000)]),
+            &mut out,
+        );
+        dec.decode_messages(
+            &packet(2, 1, &[add_long(2, b'B', 10, b"MSFT  ", 4_000_000)]),
+            &mut out,
+        );
+        let clear = vec![6u8, UNIT_CLEAR, 0, 0, 0, 0];
+        dec.decode_messages(&packet(1, 2, &[clear]), &mut out);
+
+        out.clear();
+        dec.decode_messages(&packet(1, 3, &[exec(1, 5)]), &mut out);
+        dec.decode_messages(&packet(2, 2, &[exec(2, 5)]), &mut out);
+        assert!(matches!(
+            out.as_slice(),
+            [
+                Event::Mod {
+                    order_id: 2,
+                    qty: 5,
+                    ..
+                },
+                Event::Trade {
+                    maker_order_id: Some(2),
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn time_message_anchors_exchange_timestamps() {
+        let dec = CboePitchDecoder::with_session_offset(0);
+        let midnight = unsafe { (*dec.inner.get()).clock.midnight_ns() };
+        let mut time = vec![6u8, 0x20];
+        time.extend_from_slice(&34_200u32.to_le_bytes());
+        let mut add = add_long(7, b'S', 10, b"MSFT  ", 4_000_000);
+        add[2..6].copy_from_slice(&250u32.to_le_bytes());
+
+        let mut out = Vec::new();
+        dec.decode_messages(&packet(1, 1, &[add.clone(), time, add]), &mut out);
+        let ts: Vec<_> = out.iter().map(|e| e.exch_ts_ns()).collect();
+        assert_eq!(ts, vec![None, Some(midnight + 34_200_000_000_000 + 250)]);
+    }
+
+    proptest! {
+        #[test]
+        fn decode_random_input_does_not_panic(payload in proptest::collection::vec(any::<u8>(), 0..4096)) {
+            let dec = CboePitchDecoder::new();
+            let mut out = Vec::new();
+            dec.decode_messages(&payload, &mut out);
+            prop_assert!(out.len() <= payload.len());
+        }
+    }
+}
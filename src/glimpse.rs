@@ -0,0 +1,362 @@
+// src/glimpse.rs
+// Glimpse snapshot recovery for one ITCH partition, alongside the live feed.
+//
+// A fetch thread downloads the snapshot over SoupBinTCP (`soupbin::glimpse_download`)
+// whenever the decode stage asks: at start, and when the merge gives up on a gap with
+// `on_give_up = "resync"`. RX and merge keep running meanwhile; the partition's merge
+// starts at the first packet it sees and decode holds the partition's packets back
+// until the snapshot is in. The partition's books are then rebuilt from the snapshot,
+// held packets it already covers are dropped (a MoldUDP64 packet straddling its
+// sequence is cut down to the messages past it) and the rest is released in order.
+//
+// A snapshot ending before the oldest held packet (the hold buffer overflowed, or a
+// gap was given up on while loading) cannot be joined with the feed and is fetched
+// again.
+
+use crate::config::GlimpseCfg;
+use crate::decoder_itch::{mold_header, MOLD_HEADER_LEN};
+use crate::metrics;
+use crate::pool::{PacketPool, Pkt, PktBuf};
+use crate::soupbin::{glimpse_download, GlimpseSnapshot};
+use crate::util::BarrierFlag;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use log::{error, info, warn};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Pause after a failed download before the next attempt
+const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+pub struct Glimpse {
+    /// Index of the seeded partition among the decode stage's input queues
+    pub partition: usize,
+    req: Sender<()>,
+    rx: Receiver<GlimpseSnapshot>,
+    pool: Arc<PacketPool>,
+    /// A snapshot was asked for and has not been applied yet
+    loading: bool,
+    max_held: usize,
+    /// The partition's packets since loading started, in merge order
+    held: VecDeque<Pkt>,
+    /// A snapshot must reach this sequence: packets below it were dropped
+    need_from: u64,
+}
+
+impl Glimpse {
+    /// Asks for the first snapshot right away.
+    pub fn new(
+        cfg: &GlimpseCfg,
+        partition: usize,
+        req: Sender<()>,
+        rx: Receiver<GlimpseSnapshot>,
+        pool: Arc<PacketPool>,
+    ) -> Self {
+        let mut g = Self {
+            partition,
+            req,
+            rx,
+            pool,
+            loading: false,
+            max_held: cfg.max_buffered_packets,
+            held: VecDeque::new(),
+            need_from: 0,
+        };
+        g.request();
+        g
+    }
+
+    fn request(&mut self) {
+        if !self.loading {
+            self.loading = true;
+            let _ = self.req.try_send(());
+        }
+    }
+
+    /// The merge gave up on sequences up to `to`; `resync` asks for a new snapshot.
+    pub fn on_gap(&mut self, to: u64, resync: bool) {
+        if self.loading {
+            // Held packets before the hole cannot be joined with a snapshot any more
+            self.need_from = self.need_from.max(to.wrapping_add(1));
+            while self.held.front().is_some_and(|p| p.seq <= to) {
+                self.drop_front();
+            }
+            metrics::set_glimpse_held(self.held.len());
+        } else if resync {
+            self.need_from = to.wrapping_add(1);
+            self.request();
+        }
+    }
+
+    /// Hold `pkt` back while a snapshot loads; otherwise hand it back for decoding.
+    pub fn hold(&mut self, pkt: Pkt) -> Option<Pkt> {
+        if !self.loading {
+            return Some(pkt);
+        }
+        let pkt = match pkt.buf {
+            PktBuf::Bytes(_) => pkt,
+            // Keep AF_XDP frames out of the hold buffer; they belong to the fill ring
+            PktBuf::Umem { .. } => {
+                let copy = copy(&pkt, pkt.payload(), pkt.seq, pkt.seq_span, &self.pool);
+                pkt.recycle(&self.pool);
+                copy
+            }
+        };
+        self.held.push_back(pkt);
+        if self.held.len() > self.max_held {
+            self.drop_front();
+            metrics::inc_glimpse_evicted();
+        }
+        metrics::set_glimpse_held(self.held.len());
+        None
+    }
+
+    fn drop_front(&mut self) {
+        if let Some(p) = self.held.pop_front() {
+            let end = p.seq.wrapping_add(p.seq_span.max(1) as u64);
+            self.need_from = self.need_from.max(end);
+            p.recycle(&self.pool);
+        }
+    }
+
+    /// A held packet to decode, once the snapshot has been applied.
+    #[inline]
+    pub fn release(&mut self) -> Option<Pkt> {
+        if self.loading || self.held.is_empty() {
+            return None;
+        }
+        let p = self.held.pop_front();
+        metrics::set_glimpse_held(self.held.len());
+        p
+    }
+
+    /// The downloaded snapshot, when one arrived that joins the held packets; the caller
+    /// applies it before decoding what `release` hands out.
+    pub fn poll(&mut self) -> Option<GlimpseSnapshot> {
+        if !self.loading {
+            return None;
+        }
+        let snap = self.rx.try_recv().ok()?;
+        let oldest = self.held.front().map_or(self.need_from, |p| p.seq);
+        if snap.next_seq < self.need_from.max(oldest) {
+            metrics::inc_glimpse_snapshot("too_old");
+            warn!(
+                "Glimpse snapshot ends at {} but the held feed starts at {}; fetching again",
+                snap.next_seq,
+                self.need_from.max(oldest)
+            );
+            let _ = self.req.try_send(());
+            return None;
+        }
+        // Drop what the snapshot covers; cut a packet straddling its end
+        while let Some(p) = self.held.pop_front() {
+            let end = p.seq.wrapping_add(p.seq_span.max(1) as u64);
+            if end <= snap.next_seq {
+                p.recycle(&self.pool);
+                continue;
+            }
+            if p.seq < snap.next_seq {
+                if let Some(rest) = trim_mold(&p, snap.next_seq - p.seq, &self.pool) {
+                    self.held.push_front(rest);
+                }
+                p.recycle(&self.pool);
+            } else {
+                self.held.push_front(p);
+            }
+            break;
+        }
+        self.loading = false;
+        self.need_from = 0;
+        metrics::set_glimpse_held(self.held.len());
+        metrics::inc_glimpse_snapshot("applied");
+        info!(
+            "Glimpse snapshot of {} messages applied; live feed resumes at {} ({} packets held)",
+            snap.messages,
+            snap.next_seq,
+            self.held.len()
+        );
+        Some(snap)
+    }
+}
+
+/// `pkt` with its first `skip` MoldUDP64 messages removed.
+fn trim_mold(pkt: &Pkt, skip: u64, pool: &PacketPool) -> Option<Pkt> {
+    let p = pkt.payload();
+    let (session, seq, count) = mold_header(p)?;
+    let left = (count as u64).checked_sub(skip).filter(|&n| n > 0)?;
+    let mut off = MOLD_HEADER_LEN;
+    for _ in 0..skip {
+        let len = u16::from_be_bytes([*p.get(off)?, *p.get(off + 1)?]) as usize;
+        off += 2 + len;
+    }
+    let mut msg = Vec::with_capacity(MOLD_HEADER_LEN + p.len().saturating_sub(off));
+    msg.extend_from_slice(session);
+    msg.extend_from_slice(&(seq + skip).to_be_bytes());
+    msg.extend_from_slice(&(left as u16).to_be_bytes());
+    msg.extend_from_slice(p.get(off..)?);
+    Some(copy(pkt, &msg, seq + skip, left as u32, pool))
+}
+
+/// `payload` in a pool buffer, with `pkt`'s metadata
+fn copy(pkt: &Pkt, payload: &[u8], seq: u64, seq_span: u32, pool: &PacketPool) -> Pkt {
+    let mut buf = pool.get();
+    buf.extend_from_slice(payload);
+    Pkt {
+        buf: PktBuf::Bytes(buf),
+        len: payload.len(),
+        seq,
+        stream: pkt.stream,
+        seq_span,
+        seq_reset: pkt.seq_reset,
+        ts_nanos: pkt.ts_nanos,
+        chan: pkt.chan,
+        _ts_kind: pkt._ts_kind,
+        merge_emit_ns: pkt.merge_emit_ns,
+    }
+}
+
+/// Download a snapshot into `tx` for every request on `req`; failed downloads are
+/// retried until one succeeds.
+pub fn spawn_glimpse_fetch(
+    cfg: GlimpseCfg,
+    req: Receiver<()>,
+    tx: Sender<GlimpseSnapshot>,
+    shutdown: Arc<BarrierFlag>,
+) -> std::io::Result<std::thread::JoinHandle<()>> {
+    std::thread::Builder::new()
+        .name("glimpse".into())
+        .spawn(move || {
+            while !shutdown.is_raised() {
+                match req.recv_timeout(Duration::from_millis(100)) {
+                    Ok(()) => {}
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                while req.try_recv().is_ok() {}
+                while !shutdown.is_raised() {
+                    match glimpse_download(&cfg) {
+                        Ok(snap) => {
+                            let _ = tx.send(snap);
+                            break;
+                        }
+                        Err(e) => {
+                            metrics::inc_glimpse_snapshot("failed");
+                            error!("Glimpse download from {} failed: {e:#}", cfg.endpoint);
+                            std::thread::sleep(RETRY_AFTER);
+                        }
+                    }
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool::TsKind;
+
+    fn cfg() -> GlimpseCfg {
+        GlimpseCfg {
+            endpoint: "127.0.0.1:1".into(),
+            username: String::new(),
+            password: String::new(),
+            session: String::new(),
+            partition: None,
+            connect_timeout_ms: 1000,
+            timeout_ms: 1000,
+            max_buffered_packets: 4,
+        }
+    }
+
+    /// MoldUDP64 packet of `count` one-byte messages numbered by their sequence
+    fn mold(pool: &PacketPool, seq: u64, count: u16) -> Pkt {
+        let mut buf = pool.get();
+        buf.extend_from_slice(b"SESSION001");
+        buf.extend_from_slice(&seq.to_be_bytes());
+        buf.extend_from_slice(&count.to_be_bytes());
+        for s in seq..seq + count as u64 {
+            buf.extend_from_slice(&[0, 1, s as u8]);
+        }
+        Pkt {
+            len: buf.len(),
+            buf: PktBuf::Bytes(buf),
+            seq,
+            stream: 0,
+            seq_span: count as u32,
+            seq_reset: false,
+            ts_nanos: 0,
+            chan: 0,
+            _ts_kind: TsKind::Sw,
+            merge_emit_ns: 0,
+        }
+    }
+
+    fn snapshot(next_seq: u64) -> GlimpseSnapshot {
+        GlimpseSnapshot {
+            data: Vec::new(),
+            next_seq,
+            messages: 0,
+        }
+    }
+
+    #[test]
+    fn holds_the_feed_and_resumes_at_the_snapshot_sequence() {
+        let pool = Arc::new(PacketPool::new(16, 256).unwrap());
+        let (req_tx, req_rx) = crossbeam_channel::bounded(4);
+        let (snap_tx, snap_rx) = crossbeam_channel::bounded(4);
+        let mut g = Glimpse::new(&cfg(), 0, req_tx, snap_rx, pool.clone());
+        assert!(req_rx.try_recv().is_ok());
+
+        for (seq, count) in [(10, 2), (12, 3), (15, 1)] {
+            assert!(g.hold(mold(&pool, seq, count)).is_none());
+        }
+        assert!(g.release().is_none(), "nothing is released while loading");
+
+        // The snapshot covers up to 12: packet 12..=14 is cut down to 13..=14
+        snap_tx.send(snapshot(13)).unwrap();
+        assert_eq!(g.poll().unwrap().next_seq, 13);
+        let p = g.release().unwrap();
+        assert_eq!((p.seq, p.seq_span), (13, 2));
+        let (_, seq, count) = mold_header(p.payload()).unwrap();
+        assert_eq!((seq, count), (13, 2));
+        assert_eq!(&p.payload()[MOLD_HEADER_LEN..], &[0, 1, 13, 0, 1, 14]);
+        assert_eq!(g.release().unwrap().seq, 15);
+        assert!(g.release().is_none());
+
+        // Live packets pass straight through once the book is seeded
+        let p = g.hold(mold(&pool, 16, 1)).unwrap();
+        assert_eq!(p.seq, 16);
+    }
+
+    #[test]
+    fn fetches_again_when_the_snapshot_ends_before_the_held_feed() {
+        let pool = Arc::new(PacketPool::new(16, 256).unwrap());
+        let (req_tx, req_rx) = crossbeam_channel::bounded(4);
+        let (snap_tx, snap_rx) = crossbeam_channel::bounded(4);
+        let mut g = Glimpse::new(&cfg(), 0, req_tx, snap_rx, pool.clone());
+        let _ = req_rx.try_recv();
+
+        // Six packets through a hold buffer of four: 1 and 2 are evicted
+        for seq in 1..=6 {
+            assert!(g.hold(mold(&pool, seq, 1)).is_none());
+        }
+        snap_tx.send(snapshot(2)).unwrap();
+        assert!(g.poll().is_none());
+        assert!(req_rx.try_recv().is_ok(), "a new snapshot was asked for");
+
+        // A gap given up on while loading: the snapshot must reach past it
+        g.on_gap(4, false);
+        snap_tx.send(snapshot(4)).unwrap();
+        assert!(g.poll().is_none());
+        snap_tx.send(snapshot(6)).unwrap();
+        assert!(g.poll().is_some());
+        assert_eq!(g.release().unwrap().seq, 6);
+        assert!(g.release().is_none());
+
+        // After that, a resync asks for another snapshot and holds the feed again
+        while req_rx.try_recv().is_ok() {}
+        g.on_gap(8, true);
+        assert!(req_rx.try_recv().is_ok());
+        assert!(g.hold(mold(&pool, 9, 1)).is_none());
+    }
+}
@@ -10,7 +10,7 @@ use h3_quinn::Connection;
 use quinn::{Endpoint, ServerConfig, TransportConfig};
 use rustls::{Certificate, PrivateKey};
 
-use crate::codec_raw::{self, FrameHeaderV1};
+use crate::codec_raw::{self, FrameHeaderV2};
 use crate::codec_raw::{channel_id, msg_type};
 use crate::pubsub::{Bus, RecvError, Subscription};
 use url::Url;
@@ -200,18 +200,19 @@ fn parse_query_params(qs: &str) -> (Option<u64>, bool) {
 }
 
 fn build_frame(msg_ty: u16, payload: &[u8], instrument_id: u64, sequence: u64) -> Vec<u8> {
-    let hdr = FrameHeaderV1 {
+    let hdr = FrameHeaderV2 {
         magic: codec_raw::MAGIC,
-        version: codec_raw::VERSION_V1,
+        version: codec_raw::VERSION_V2,
         codec: codec_raw::codec::RAW_V1,
         message_type: msg_ty,
         channel_id: channel_id::OBO_L3,
         instrument_id,
         sequence,
         send_time_ns: crate::util::now_nanos(),
+        exchange_time_ns: 0,
         payload_len: payload.len() as u32,
     };
-    let mut v = Vec::with_capacity(std::mem::size_of::<FrameHeaderV1>() + payload.len());
+    let mut v = Vec::with_capacity(std::mem::size_of::<FrameHeaderV2>() + payload.len());
     v.extend_from_slice(hdr.as_bytes());
     v.extend_from_slice(payload);
     v
@@ -0,0 +1,580 @@
+// src/late_join.rs
+// Late join and gap recovery from a venue snapshot multicast (EOBI style).
+//
+// The venue cycles through every instrument's book on its own group; each entry is a
+// summary carrying the last incremental packet sequence it includes, then the resting
+// orders (`decoder_eobi::SnapshotMsg`). On start, or when the merge gives up on a gap,
+// the decode stage joins that group and keeps a copy of the partition's decoded
+// incrementals. As the entry of each wanted instrument completes, its book is rebuilt
+// from the snapshot and the buffered incrementals past the snapshot's sequence are
+// replayed on top; live packets the snapshot already covers are then skipped for that
+// instrument. Once nothing is wanted the group is left and the buffer dropped.
+//
+// A snapshot older than what is needed (it predates the gap, or incrementals it would
+// need were evicted from a full buffer) is passed over for the next cycle. On start
+// every instrument is wanted until one whole cycle has been applied.
+
+use crate::config::{ChannelCfg, SnapshotFeedCfg};
+use crate::decoder_eobi::{decode_snapshot, SnapshotMsg};
+use crate::metrics;
+use crate::orderbook::OrderBook;
+use crate::parser::Event;
+use crate::pool::{PacketPool, Pkt, PktBuf, TsKind};
+use crate::spsc::SpscQueue;
+use crate::util::BarrierFlag;
+use bytes::BufMut;
+use hashbrown::{HashMap, HashSet};
+use log::{error, info, warn};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Snapshot packets handled per decode loop iteration
+const POLL_BATCH: usize = 64;
+
+pub struct LateJoin {
+    /// Index of the recovered partition among the decode stage's input queues
+    pub partition: usize,
+    q_snapshot: Arc<SpscQueue<Pkt>>,
+    pool: Arc<PacketPool>,
+    /// Raised while the snapshot group should be joined
+    join: Arc<AtomicBool>,
+    on_gap: bool,
+    max_buffered: usize,
+    /// Every instrument is wanted until a cycle applies cleanly
+    all: bool,
+    /// Lowest usable `last_seq + 1` in `all` mode
+    all_from: u64,
+    cycle: HashSet<u32>,
+    cycle_clean: bool,
+    /// Instrument -> lowest usable `last_seq + 1`
+    wanted: HashMap<u32, u64>,
+    entry: Option<Entry>,
+    /// Incremental packets since joining: (packet sequence, events in `buffered_events`)
+    buffer: VecDeque<(u64, usize)>,
+    /// Decoded events of the packets in `buffer`, in order; reused across packets
+    buffered_events: VecDeque<Event>,
+    /// Snapshots must include everything below this (evicted from `buffer`)
+    evicted_to: u64,
+    /// Sequence after the last incremental packet seen
+    next_seen: u64,
+    /// Rebuilt instruments whose snapshot is ahead of the feed: skip packets up to this
+    covered: HashMap<u32, u64>,
+    msgs: Vec<SnapshotMsg>,
+}
+
+/// One instrument's snapshot being collected
+struct Entry {
+    instr: u32,
+    last_seq: u64,
+    left: u32,
+    orders: Vec<Event>,
+}
+
+impl LateJoin {
+    pub fn new(
+        cfg: &SnapshotFeedCfg,
+        partition: usize,
+        q_snapshot: Arc<SpscQueue<Pkt>>,
+        pool: Arc<PacketPool>,
+        join: Arc<AtomicBool>,
+    ) -> Self {
+        let mut lj = Self {
+            partition,
+            q_snapshot,
+            pool,
+            join,
+            on_gap: cfg.on_gap,
+            max_buffered: cfg.max_buffered_packets,
+            all: false,
+            all_from: 0,
+            cycle: HashSet::new(),
+            cycle_clean: true,
+            wanted: HashMap::new(),
+            entry: None,
+            buffer: VecDeque::new(),
+            buffered_events: VecDeque::new(),
+            evicted_to: 0,
+            next_seen: 0,
+            covered: HashMap::new(),
+            msgs: Vec::new(),
+        };
+        if cfg.on_start {
+            lj.want_all();
+        }
+        lj
+    }
+
+    #[inline]
+    pub fn active(&self) -> bool {
+        self.all || !self.wanted.is_empty()
+    }
+
+    /// The merge gave up on a gap affecting `affected` (empty: instruments unknown).
+    pub fn on_gap(&mut self, affected: &[u32]) {
+        if !self.on_gap {
+            return;
+        }
+        if affected.is_empty() {
+            self.want_all();
+            return;
+        }
+        for &instr in affected {
+            self.covered.remove(&instr);
+            let from = self.wanted.entry(instr).or_insert(0);
+            *from = (*from).max(self.next_seen);
+        }
+        self.set_joined();
+    }
+
+    /// Whether live event `ev` of packet `seq` is already in a rebuilt instrument's book.
+    #[inline]
+    pub fn skip(&self, seq: u64, ev: &Event, book: &OrderBook) -> bool {
+        !self.covered.is_empty()
+            && event_instr(ev, book)
+                .and_then(|i| self.covered.get(&i))
+                .is_some_and(|&last| seq <= last)
+    }
+
+    /// Account for incremental packet `seq` (spanning `span`) decoded into `events`.
+    pub fn on_packet(&mut self, seq: u64, span: u32, events: &[Event]) {
+        self.next_seen = seq.wrapping_add(span.max(1) as u64);
+        if !self.covered.is_empty() {
+            self.covered.retain(|_, last| *last > seq);
+        }
+        if !self.active() {
+            return;
+        }
+        self.buffer.push_back((seq, events.len()));
+        self.buffered_events.extend(events.iter().cloned());
+        if self.buffer.len() > self.max_buffered {
+            if let Some((old, n)) = self.buffer.pop_front() {
+                self.buffered_events.drain(..n);
+                self.evicted_to = old + 1;
+            }
+            metrics::inc_snapshot_feed_evicted();
+        }
+        metrics::set_snapshot_feed_buffered(self.buffer.len());
+    }
+
+    /// Apply waiting snapshot packets; returns how many were taken off the queue.
+    /// Rebuilt instruments are appended to `rebuilt` with the sequence they resume at.
+    pub fn poll(&mut self, book: &mut OrderBook, rebuilt: &mut Vec<(u32, u64)>) -> usize {
+        let mut n = 0;
+        while n < POLL_BATCH {
+            let Some(pkt) = self.q_snapshot.pop() else {
+                break;
+            };
+            n += 1;
+            // Packets still queued after the group was left are dropped
+            if self.active() {
+                let mut msgs = std::mem::take(&mut self.msgs);
+                msgs.clear();
+                decode_snapshot(pkt.payload(), &mut msgs);
+                for m in msgs.drain(..) {
+                    self.on_msg(m, book, rebuilt);
+                }
+                self.msgs = msgs;
+            }
+            pkt.recycle(&self.pool);
+        }
+        n
+    }
+
+    fn on_msg(&mut self, msg: SnapshotMsg, book: &mut OrderBook, rebuilt: &mut Vec<(u32, u64)>) {
+        match msg {
+            SnapshotMsg::Instrument {
+                instr,
+                last_seq,
+                orders,
+            } => {
+                if let Some(e) = self.entry.take() {
+                    // Order packets went missing; try again next cycle
+                    metrics::inc_snapshot_feed_instrument("incomplete");
+                    warn!("snapshot of instrument {} incomplete", e.instr);
+                    self.cycle_clean = false;
+                }
+                if self.all {
+                    if self.cycle.contains(&instr) {
+                        if self.cycle_clean {
+                            info!(
+                                "snapshot feed: full cycle of {} instruments applied",
+                                self.cycle.len()
+                            );
+                            self.all = false;
+                        }
+                        self.cycle.clear();
+                        self.cycle_clean = true;
+                    }
+                    self.cycle.insert(instr);
+                }
+                let from = match (self.all, self.wanted.get(&instr)) {
+                    (true, w) => self.all_from.max(w.copied().unwrap_or(0)),
+                    (false, Some(&w)) => w,
+                    (false, None) => return self.finish_if_done(),
+                };
+                if last_seq.wrapping_add(1) < from.max(self.evicted_to) {
+                    metrics::inc_snapshot_feed_instrument("too_old");
+                    self.cycle_clean = false;
+                    return;
+                }
+                self.entry = Some(Entry {
+                    instr,
+                    last_seq,
+                    left: orders,
+                    orders: Vec::with_capacity(orders as usize),
+                });
+                if orders == 0 {
+                    self.commit(book, rebuilt);
+                }
+            }
+            SnapshotMsg::Order(ev) => {
+                if let Some(e) = self.entry.as_mut() {
+                    e.orders.push(ev);
+                    e.left = e.left.saturating_sub(1);
+                    if e.left == 0 {
+                        self.commit(book, rebuilt);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Rebuild the collected instrument and replay what the feed sent after the snapshot.
+    fn commit(&mut self, book: &mut OrderBook, rebuilt: &mut Vec<(u32, u64)>) {
+        let Some(e) = self.entry.take() else {
+            return;
+        };
+        book.clear_instrument(e.instr);
+        for ev in &e.orders {
+            book.apply(ev);
+        }
+        let mut replayed = 0usize;
+        let mut at = 0;
+        for &(seq, n) in &self.buffer {
+            at += n;
+            if seq <= e.last_seq {
+                continue;
+            }
+            for ev in self.buffered_events.range(at - n..at) {
+                if event_instr(ev, book) == Some(e.instr) {
+                    book.apply(ev);
+                    replayed += 1;
+                }
+            }
+        }
+        if e.last_seq >= self.next_seen {
+            self.covered.insert(e.instr, e.last_seq);
+        }
+        self.wanted.remove(&e.instr);
+        rebuilt.push((e.instr, e.last_seq.wrapping_add(1)));
+        metrics::inc_snapshot_feed_instrument("rebuilt");
+        log::debug!(
+            "instrument {} rebuilt from snapshot at {} ({} orders, {replayed} events replayed)",
+            e.instr,
+            e.last_seq,
+            e.orders.len()
+        );
+        self.finish_if_done();
+    }
+
+    fn finish_if_done(&mut self) {
+        if self.active() || !self.join.load(Ordering::Relaxed) {
+            return;
+        }
+        info!(
+            "snapshot feed: recovery complete, leaving group ({} packets buffered)",
+            self.buffer.len()
+        );
+        self.buffer.clear();
+        self.buffered_events.clear();
+        self.evicted_to = 0;
+        metrics::set_snapshot_feed_buffered(0);
+        self.set_joined();
+    }
+
+    fn want_all(&mut self) {
+        self.all = true;
+        self.all_from = self.all_from.max(self.next_seen);
+        self.cycle.clear();
+        self.cycle_clean = true;
+        self.covered.clear();
+        self.set_joined();
+    }
+
+    fn set_joined(&self) {
+        self.join.store(self.active(), Ordering::Relaxed);
+        metrics::set_snapshot_feed_joined(self.active());
+    }
+}
+
+/// Instrument an event applies to; order-level events resolve through the book.
+fn event_instr(ev: &Event, book: &OrderBook) -> Option<u32> {
+    match *ev {
+        Event::Add { instr, .. }
+        | Event::Trade { instr, .. }
+        | Event::TradingStatus { instr, .. }
+        | Event::Level { instr, .. } => Some(instr),
+        Event::Mod { order_id, .. } | Event::Del { order_id, .. } => {
+            book.instrument_for_order(order_id)
+        }
+        Event::Heartbeat => None,
+    }
+}
+
+/// Receive the snapshot group into `q` while `join` is raised; the socket (and with
+/// it the membership) is dropped when it falls.
+pub fn spawn_snapshot_rx(
+    ch: ChannelCfg,
+    join: Arc<AtomicBool>,
+    q: Arc<SpscQueue<Pkt>>,
+    pool: Arc<PacketPool>,
+    shutdown: Arc<BarrierFlag>,
+) -> std::io::Result<std::thread::JoinHandle<()>> {
+    std::thread::Builder::new()
+        .name("snapshot-rx".into())
+        .spawn(move || {
+            let mut sock: Option<std::net::UdpSocket> = None;
+            while !shutdown.is_raised() {
+                let want = join.load(Ordering::Relaxed);
+                if want && sock.is_none() {
+                    match crate::net::build_mcast_socket(&ch).and_then(|s| {
+                        s.set_nonblocking(false)?;
+                        s.set_read_timeout(Some(Duration::from_millis(10)))?;
+                        Ok(s)
+                    }) {
+                        Ok(s) => {
+                            info!("snapshot feed: joined {}:{}", ch.group, ch.port);
+                            sock = Some(s);
+                        }
+                        Err(e) => {
+                            error!("snapshot feed: join {} failed: {e:?}", ch.group);
+                            std::thread::sleep(Duration::from_secs(1));
+                            continue;
+                        }
+                    }
+                } else if !want && sock.take().is_some() {
+                    info!("snapshot feed: left {}:{}", ch.group, ch.port);
+                }
+                let Some(s) = sock.as_ref() else {
+                    std::thread::sleep(Duration::from_millis(10));
+                    continue;
+                };
+                let mut bufm = pool.get();
+                // Safety: buffer is at least pool's max packet size
+                let dst = unsafe {
+                    let c = bufm.chunk_mut();
+                    std::slice::from_raw_parts_mut(c.as_mut_ptr(), c.len())
+                };
+                let n = match s.recv(dst) {
+                    Ok(n) => n,
+                    Err(e) => {
+                        pool.put(bufm);
+                        if !matches!(
+                            e.kind(),
+                            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                        ) {
+                            warn!("snapshot feed: recv: {e}");
+                        }
+                        continue;
+                    }
+                };
+                unsafe {
+                    bufm.advance_mut(n);
+                }
+                let pkt = Pkt {
+                    buf: PktBuf::Bytes(bufm),
+                    len: n,
+                    seq: 0,
+                    stream: 0,
+                    seq_span: 1,
+                    seq_reset: false,
+                    ts_nanos: crate::util::now_nanos(),
+                    chan: crate::pool::CHAN_RECOVERY,
+                    _ts_kind: TsKind::Sw,
+                    merge_emit_ns: 0,
+                };
+                // A lost snapshot packet leaves its instrument for the next cycle
+                if let Err(pkt) = q.push(pkt) {
+                    metrics::inc_snapshot_feed_instrument("dropped_packet");
+                    pkt.recycle(&pool);
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Side;
+
+    fn cfg(on_start: bool) -> SnapshotFeedCfg {
+        toml::from_str(&format!(
+            r#"
+            on_start = {on_start}
+            max_buffered_packets = 16
+            [channel]
+            group = "239.1.1.9"
+            port = 5009
+            reuse_port = true
+            recv_buffer_bytes = 0
+            nonblocking = false
+            "#
+        ))
+        .unwrap()
+    }
+
+    fn msg(template: u16, body: &[u8]) -> Vec<u8> {
+        let mut m = Vec::new();
+        m.extend_from_slice(&(body.len() as u16).to_le_bytes());
+        m.extend_from_slice(&template.to_le_bytes());
+        m.extend_from_slice(&[1, 0, 1, 0]);
+        m.extend_from_slice(body);
+        m
+    }
+
+    fn summary(instr: u32, last_seq: u64, orders: u32) -> Vec<u8> {
+        let mut b = instr.to_le_bytes().to_vec();
+        b.extend_from_slice(&last_seq.to_le_bytes());
+        b.extend_from_slice(&orders.to_le_bytes());
+        msg(1010, &b)
+    }
+
+    fn order(order_id: u64, instr: u32, px: i64, qty: i64) -> Vec<u8> {
+        let mut b = order_id.to_le_bytes().to_vec();
+        b.extend_from_slice(&instr.to_le_bytes());
+        b.push(0);
+        b.extend_from_slice(&px.to_le_bytes());
+        b.extend_from_slice(&qty.to_le_bytes());
+        msg(1011, &b)
+    }
+
+    fn add(order_id: u64, instr: u32, px: i64, qty: i64) -> Event {
+        Event::Add {
+            order_id,
+            instr,
+            px,
+            qty,
+            side: Side::Bid,
+            exch_ts_ns: None,
+        }
+    }
+
+    fn del(order_id: u64) -> Event {
+        Event::Del {
+            order_id,
+            exch_ts_ns: None,
+        }
+    }
+
+    /// Decode-stage stand-in: skip covered events, apply, then account for the packet.
+    fn live(lj: &mut LateJoin, book: &mut OrderBook, seq: u64, events: &[Event]) {
+        for ev in events {
+            if !lj.skip(seq, ev, book) {
+                book.apply(ev);
+            }
+        }
+        lj.on_packet(seq, 1, events);
+    }
+
+    fn snapshot(q: &SpscQueue<Pkt>, pool: &PacketPool, payload: &[u8]) {
+        let mut b = pool.get();
+        b.put_slice(payload);
+        q.push(Pkt {
+            buf: PktBuf::Bytes(b),
+            len: payload.len(),
+            seq: 0,
+            stream: 0,
+            seq_span: 1,
+            seq_reset: false,
+            ts_nanos: 0,
+            chan: crate::pool::CHAN_RECOVERY,
+            _ts_kind: TsKind::Sw,
+            merge_emit_ns: 0,
+        })
+        .ok()
+        .unwrap();
+    }
+
+    #[test]
+    fn rebuilds_gapped_instrument_and_replays_incrementals_past_the_snapshot() {
+        let pool = Arc::new(PacketPool::new(8, 512).unwrap());
+        let q = Arc::new(SpscQueue::new(8));
+        let join = Arc::new(AtomicBool::new(false));
+        let mut lj = LateJoin::new(&cfg(false), 0, q.clone(), pool.clone(), join.clone());
+        let mut book = OrderBook::new(10);
+        let mut rebuilt = Vec::new();
+        assert!(!join.load(Ordering::Relaxed));
+
+        live(
+            &mut lj,
+            &mut book,
+            10,
+            &[add(1, 7, 100, 5), add(2, 8, 200, 5)],
+        );
+        // Packets 11..=12 were given up: instrument 7 missed order 3
+        lj.on_gap(&[7]);
+        assert!(join.load(Ordering::Relaxed));
+        live(
+            &mut lj,
+            &mut book,
+            13,
+            &[add(4, 7, 101, 1), add(5, 8, 201, 1)],
+        );
+        live(&mut lj, &mut book, 14, &[del(1)]);
+
+        // Stale cycle entry (predates the gap) is passed over
+        snapshot(&q, &pool, &[summary(7, 9, 1), order(1, 7, 100, 5)].concat());
+        lj.poll(&mut book, &mut rebuilt);
+        assert!(lj.active());
+
+        // Snapshot taken after packet 13: orders 1, 3 and 4 rest
+        let mut p = summary(7, 13, 3);
+        p.extend(order(1, 7, 100, 5));
+        p.extend(order(3, 7, 99, 2));
+        p.extend(order(4, 7, 101, 1));
+        snapshot(&q, &pool, &p);
+        lj.poll(&mut book, &mut rebuilt);
+
+        // Packet 14 (delete of 1) was replayed on top; instrument 8 never touched
+        assert!(!lj.active());
+        assert!(!join.load(Ordering::Relaxed));
+        assert_eq!(rebuilt, vec![(7, 14)]);
+        assert_eq!(book.instrument_for_order(1), None);
+        assert_eq!(book.instrument_for_order(3), Some(7));
+        assert_eq!(book.instrument_for_order(4), Some(7));
+        assert_eq!(book.order_count(), 4); // 3, 4 on 7; 2, 5 on 8
+    }
+
+    #[test]
+    fn late_join_skips_live_packets_the_snapshot_already_has() {
+        let pool = Arc::new(PacketPool::new(8, 512).unwrap());
+        let q = Arc::new(SpscQueue::new(8));
+        let join = Arc::new(AtomicBool::new(false));
+        let mut lj = LateJoin::new(&cfg(true), 0, q.clone(), pool.clone(), join.clone());
+        let mut book = OrderBook::new(10);
+        let mut rebuilt = Vec::new();
+        assert!(join.load(Ordering::Relaxed));
+
+        live(&mut lj, &mut book, 50, &[add(1, 7, 100, 5)]);
+        // One cycle: instrument 7 up to packet 51 (not seen yet), 8 empty up to 50
+        let mut p = summary(7, 51, 2);
+        p.extend(order(1, 7, 100, 5));
+        p.extend(order(2, 7, 100, 3));
+        p.extend(summary(8, 50, 0));
+        snapshot(&q, &pool, &p);
+        lj.poll(&mut book, &mut rebuilt);
+        assert!(lj.active(), "needs to see the cycle wrap");
+        snapshot(&q, &pool, &summary(7, 60, 0));
+        lj.poll(&mut book, &mut rebuilt);
+        assert!(!lj.active());
+        assert_eq!(rebuilt, vec![(7, 52), (8, 51)]);
+
+        // Packet 51 (add of 2) is already in the book; 52 is new
+        live(&mut lj, &mut book, 51, &[add(2, 7, 100, 3)]);
+        live(&mut lj, &mut book, 52, &[del(2)]);
+        assert_eq!(book.order_count(), 1);
+        assert_eq!(book.instrument_for_order(1), Some(7));
+    }
+}
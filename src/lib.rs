@@ -0,0 +1,25 @@
+// src/lib.rs: parser API for embedding code (custom decoders, offline decoding).
+// The receiver itself is the `orderbook` binary (src/main.rs).
+pub mod config;
+#[allow(dead_code)] // decode counters only
+mod decode_stats;
+#[allow(dead_code)] // snapshot channel templates only
+mod decoder_eobi;
+mod decoder_fast;
+mod decoder_itch;
+mod decoder_mdp3;
+mod decoder_pitch;
+#[allow(dead_code)] // decoder metrics only
+mod metrics;
+pub mod parser;
+#[allow(dead_code)] // packet timestamp kinds for metrics only
+mod pool;
+#[allow(dead_code)] // journal report for metrics only
+mod recovery_journal;
+#[allow(dead_code)] // venue time helpers only
+mod util;
+
+pub use parser::{
+    build_parser, register_decoder, CustomDecoder, Event, MessageDecoder, Parser, SeqCfg,
+    SeqExtractor,
+};
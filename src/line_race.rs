@@ -0,0 +1,297 @@
+// src/line_race.rs
+// Line race analytics for the merge.
+//
+// Every line carries a copy of each sequence; which copy arrives first, and by how
+// much, is what the extra lines are paid for. The merge reports the RX timestamp of
+// every line copy here. Per sequence we keep the earliest copy and the set of lines
+// that delivered one. Each (loser - winner) delta goes to a histogram as it is seen;
+// once a sequence leaves the history ring it is scored: a win for the earliest line
+// and a miss for every line that never delivered it. Scores feed Prometheus counters,
+// win/loss ratios over a sliding window of sequences and a periodic line-quality log.
+
+use crate::metrics;
+use log::info;
+use prometheus::{Histogram, IntCounter};
+use std::collections::VecDeque;
+
+/// The sliding window is kept as this many buckets of `window / BUCKETS` sequences
+const BUCKETS: usize = 10;
+
+#[derive(Clone, Copy)]
+struct Entry {
+    stream: u32,
+    seq: u64,
+    first_ts: u64,
+    winner: u8,
+    /// Bit per line that delivered a copy
+    seen: u32,
+}
+
+#[derive(Clone)]
+struct Tally {
+    seqs: u64,
+    wins: Vec<u64>,
+    missed: Vec<u64>,
+    /// Sum and count of the winner's lead over later copies, per winning line
+    lead_ns: Vec<u64>,
+    leads: Vec<u64>,
+}
+
+impl Tally {
+    fn new(lines: usize) -> Self {
+        Self {
+            seqs: 0,
+            wins: vec![0; lines],
+            missed: vec![0; lines],
+            lead_ns: vec![0; lines],
+            leads: vec![0; lines],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.seqs = 0;
+        for v in [
+            &mut self.wins,
+            &mut self.missed,
+            &mut self.lead_ns,
+            &mut self.leads,
+        ] {
+            v.fill(0);
+        }
+    }
+}
+
+pub struct LineRace {
+    part: String,
+    names: Vec<String>,
+    history: Vec<Option<Entry>>,
+    wins: Vec<IntCounter>,
+    missed: Vec<IntCounter>,
+    /// Indexed `winner * lines + loser`
+    delta: Vec<Histogram>,
+    window: VecDeque<Tally>,
+    current: Tally,
+    bucket_len: u64,
+    report: Tally,
+    report_ns: u64,
+    last_report_ns: u64,
+    arrivals: u64,
+}
+
+impl LineRace {
+    /// `history` sequences are tracked until scored; ratios cover the last `window`
+    /// scored sequences; a report is logged every `report_ns` (0 = never).
+    pub fn new(part: &str, names: &[&str], history: usize, window: u64, report_ns: u64) -> Self {
+        let n = names.len();
+        let mut delta = Vec::with_capacity(n * n);
+        for w in names {
+            for l in names {
+                delta.push(metrics::merge_line_race_delta_histogram(part, w, l));
+            }
+        }
+        Self {
+            part: part.to_string(),
+            names: names.iter().map(|s| s.to_string()).collect(),
+            history: vec![None; history.max(1)],
+            wins: names
+                .iter()
+                .map(|l| metrics::merge_line_win_counter(part, l))
+                .collect(),
+            missed: names
+                .iter()
+                .map(|l| metrics::merge_line_missed_counter(part, l))
+                .collect(),
+            delta,
+            window: VecDeque::with_capacity(BUCKETS),
+            current: Tally::new(n),
+            bucket_len: (window / BUCKETS as u64).max(1),
+            report: Tally::new(n),
+            report_ns,
+            last_report_ns: crate::util::now_nanos(),
+            arrivals: 0,
+        }
+    }
+
+    /// A copy of `seq` in `stream` from line `src` was received at `ts` (RX clock; 0 = unknown).
+    #[inline]
+    pub fn arrival(&mut self, src: usize, stream: u32, seq: u64, ts: u64) {
+        if ts == 0 {
+            return;
+        }
+        let idx = (seq % self.history.len() as u64) as usize;
+        let bit = 1u32 << src;
+        match self.history[idx] {
+            Some(ref mut e) if e.seq == seq && e.stream == stream => {
+                if e.seen & bit != 0 {
+                    return;
+                }
+                e.seen |= bit;
+                let (winner, loser, lead) = if ts < e.first_ts {
+                    let prev = e.winner as usize;
+                    e.winner = src as u8;
+                    let lead = e.first_ts - ts;
+                    e.first_ts = ts;
+                    (src, prev, lead)
+                } else {
+                    (e.winner as usize, src, ts - e.first_ts)
+                };
+                let n = self.names.len();
+                self.delta[winner * n + loser].observe(lead as f64 / 1_000_000_000.0);
+                self.report.lead_ns[winner] += lead;
+                self.report.leads[winner] += 1;
+            }
+            // Late copy of a sequence already scored
+            Some(e) if e.seq > seq && e.stream == stream => {}
+            old => {
+                if let Some(e) = old {
+                    self.score(e);
+                }
+                self.history[idx] = Some(Entry {
+                    stream,
+                    seq,
+                    first_ts: ts,
+                    winner: src as u8,
+                    seen: bit,
+                });
+            }
+        }
+        self.arrivals = self.arrivals.wrapping_add(1);
+        if self.report_ns > 0 && self.arrivals & 0xfff == 0 {
+            let now = crate::util::now_nanos();
+            if now.saturating_sub(self.last_report_ns) >= self.report_ns {
+                self.last_report_ns = now;
+                self.log_report();
+            }
+        }
+    }
+
+    /// The venue sequence of `stream` restarted: score what is tracked for it and start over.
+    pub fn reset(&mut self, stream: u32) {
+        for i in 0..self.history.len() {
+            if let Some(e) = self.history[i].take_if(|e| e.stream == stream) {
+                self.score(e);
+            }
+        }
+    }
+
+    fn score(&mut self, e: Entry) {
+        let w = e.winner as usize;
+        self.wins[w].inc();
+        for t in [&mut self.current, &mut self.report] {
+            t.seqs += 1;
+            t.wins[w] += 1;
+        }
+        for line in 0..self.names.len() {
+            if e.seen & (1 << line) == 0 {
+                self.missed[line].inc();
+                self.current.missed[line] += 1;
+                self.report.missed[line] += 1;
+            }
+        }
+        if self.current.seqs >= self.bucket_len {
+            if self.window.len() == BUCKETS {
+                self.window.pop_front();
+            }
+            self.window.push_back(self.current.clone());
+            self.current.clear();
+            self.publish_window();
+        }
+    }
+
+    fn publish_window(&self) {
+        let seqs: u64 = self.window.iter().map(|t| t.seqs).sum();
+        if seqs == 0 {
+            return;
+        }
+        for (line, name) in self.names.iter().enumerate() {
+            let wins: u64 = self.window.iter().map(|t| t.wins[line]).sum();
+            let missed: u64 = self.window.iter().map(|t| t.missed[line]).sum();
+            metrics::set_merge_line_ratios(
+                &self.part,
+                name,
+                wins as f64 / seqs as f64,
+                missed as f64 / seqs as f64,
+            );
+        }
+    }
+
+    fn log_report(&mut self) {
+        let t = &self.report;
+        if t.seqs == 0 {
+            return;
+        }
+        let lines: Vec<String> = self
+            .names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let lead_us = if t.leads[i] > 0 {
+                    t.lead_ns[i] as f64 / t.leads[i] as f64 / 1_000.0
+                } else {
+                    0.0
+                };
+                format!(
+                    "{name}: won {:.1}% missed {:.3}% lead {:.1}us",
+                    100.0 * t.wins[i] as f64 / t.seqs as f64,
+                    100.0 * t.missed[i] as f64 / t.seqs as f64,
+                    lead_us
+                )
+            })
+            .collect();
+        info!(
+            "[{}] line quality over {} seqs: {}",
+            self.part,
+            t.seqs,
+            lines.join(", ")
+        );
+        self.report.clear();
+    }
+
+    #[cfg(test)]
+    fn window_totals(&self) -> (u64, Vec<u64>, Vec<u64>) {
+        let seqs = self.window.iter().map(|t| t.seqs).sum();
+        let per = |f: fn(&Tally) -> &Vec<u64>| {
+            (0..self.names.len())
+                .map(|i| self.window.iter().map(|t| f(t)[i]).sum())
+                .collect()
+        };
+        (seqs, per(|t| &t.wins), per(|t| &t.missed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earliest_copy_wins_and_missing_copies_count_as_misses() {
+        let mut r = LineRace::new("race_test", &["A", "B"], 4, 10, 0);
+        // 1: A first by 50ns; 2: B's copy is earlier although it arrives second
+        r.arrival(0, 0, 1, 1_000);
+        r.arrival(1, 0, 1, 1_050);
+        r.arrival(0, 0, 2, 2_000);
+        r.arrival(1, 0, 2, 1_900);
+        // 3: only A; 4: only B
+        r.arrival(0, 0, 3, 3_000);
+        r.arrival(1, 0, 4, 4_000);
+        // Duplicate on the same line is ignored
+        r.arrival(1, 0, 4, 4_100);
+        // 5..=8 push 1..=4 out of the history and score them
+        for s in 5..=8 {
+            r.arrival(0, 0, s, s * 1_000);
+            r.arrival(1, 0, s, s * 1_000 + 10);
+        }
+        // A stray copy of a scored sequence is not a new sequence
+        r.arrival(1, 0, 3, 9_000);
+
+        assert_eq!(r.wins[0].get(), 2);
+        assert_eq!(r.wins[1].get(), 2);
+        assert_eq!(r.missed[0].get(), 1);
+        assert_eq!(r.missed[1].get(), 1);
+        let (seqs, wins, missed) = r.window_totals();
+        assert_eq!((seqs, wins, missed), (4, vec![2, 2], vec![1, 1]));
+        // Leads: 50ns for A on 1 and 10ns on each of 5..=8; 100ns for B on 2
+        assert_eq!(r.report.lead_ns, vec![90, 100]);
+        assert_eq!(r.report.leads, vec![5, 1]);
+    }
+}
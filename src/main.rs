@@ -1,13 +1,21 @@
 // src/main.rs (updated: integrate metrics, snapshot, recovery)
 mod alloc;
+mod chaos;
 mod codec_raw;
 mod config;
 mod decode;
+mod decode_stats;
 mod decoder_eobi;
 mod decoder_fast;
 mod decoder_itch;
+mod decoder_mdp3;
+mod decoder_pitch;
+mod dup_verify;
+mod glimpse;
 #[cfg(feature = "h3")]
 mod h3_server;
+mod late_join;
+mod line_race;
 mod merge;
 mod metrics;
 mod net;
@@ -17,19 +25,24 @@ mod parser;
 mod pool;
 mod pubsub;
 mod recovery;
+mod recovery_journal;
+mod recovery_mold;
 mod rx;
 mod rx_afxdp;
+mod rx_uring;
 mod snapshot;
+mod soupbin;
 mod spsc;
 mod util;
 mod ws_server;
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, Line, PartitionCfg};
 use crate::decode::decode_loop;
 use crate::merge::merge_loop;
 use crate::parser::{build_parser, SeqCfg};
-use crate::pool::PacketPool;
+use crate::pool::{PacketPool, Pkt};
 use crate::rx::rx_loop;
+use crate::spsc::SpscQueue;
 use crate::util::{lock_all_memory_if, pin_to_core_if_set, set_realtime_priority_if, BarrierFlag};
 use crossbeam_channel::{bounded, Receiver, Sender};
 use log::{error, info};
@@ -122,49 +135,28 @@ fn main() -> anyhow::Result<()> {
         cfg.general.max_packet_size as usize,
     )?);
 
-    // Queues
-    let a_workers = cfg.channels.a.workers.unwrap_or(1).max(1);
-    let b_workers = cfg.channels.b.workers.unwrap_or(1).max(1);
-    let mut q_rx_a_list: Vec<Arc<crate::spsc::SpscQueue<crate::pool::Pkt>>> =
-        Vec::with_capacity(a_workers);
-    let mut q_rx_b_list: Vec<Arc<crate::spsc::SpscQueue<crate::pool::Pkt>>> =
-        Vec::with_capacity(b_workers);
-    for _ in 0..a_workers {
-        q_rx_a_list.push(Arc::new(crate::spsc::SpscQueue::new(
-            cfg.general.rx_queue_capacity,
-        )));
-    }
-    for _ in 0..b_workers {
-        q_rx_b_list.push(Arc::new(crate::spsc::SpscQueue::new(
-            cfg.general.rx_queue_capacity,
-        )));
-    }
-    let q_merged = Arc::new(crate::spsc::SpscQueue::new(
-        cfg.general.merge_queue_capacity,
-    ));
-
     // Parser & Sequence
     let seq_cfg = SeqCfg {
         offset: cfg.sequence.offset,
         length: cfg.sequence.length,
         endian: cfg.sequence.endian.clone(),
+        unit: cfg.sequence.unit,
+        stream: cfg
+            .sequence
+            .stream_offset
+            .map(|off| (off, cfg.sequence.stream_length)),
+        moldudp64: cfg.sequence.moldudp64,
     };
+    if let Some(c) = &cfg.parser.capture_bad_payloads {
+        decode_stats::enable_capture(&c.path, c.max)?;
+    }
     let parser = build_parser(
         cfg.parser.kind.clone(),
         seq_cfg,
         cfg.parser.max_messages_per_packet,
+        cfg.parser.session_utc_offset_s,
     )?;
 
-    // Sockets (support multi-worker via SO_REUSEPORT)
-    let mut socks_a = Vec::with_capacity(a_workers);
-    let mut socks_b = Vec::with_capacity(b_workers);
-    for _ in 0..a_workers {
-        socks_a.push(net::build_mcast_socket(&cfg.channels.a)?);
-    }
-    for _ in 0..b_workers {
-        socks_b.push(net::build_mcast_socket(&cfg.channels.b)?);
-    }
-
     // Snapshot manager
     let (snapshot_tx, snapshot_handle) = if let Some(snap) = &cfg.snapshot {
         if snap.enable_writer {
@@ -197,184 +189,66 @@ fn main() -> anyhow::Result<()> {
         None
     };
 
-    // Recovery manager: TCP injector if enabled, else logger-only
-    let (recovery_client, recovery_handle, q_recovery_opt): (
-        recovery::RecoveryClient,
-        recovery::RecoveryHandle,
-        Option<Arc<crate::spsc::SpscQueue<crate::pool::Pkt>>>,
-    ) = if let Some(rcfg) = &cfg.recovery {
-        if rcfg.enable_injector {
-            let q_recovery = Arc::new(crate::spsc::SpscQueue::new(
-                cfg.general.merge_queue_capacity,
-            ));
-            let (cli, handle) = recovery::spawn_tcp_injector(
-                rcfg.endpoint.clone(),
-                q_recovery.clone(),
-                pool.clone(),
-                rcfg.backlog_path.clone(),
-            );
-            (cli, handle, Some(q_recovery))
-        } else {
-            let (cli, handle) = recovery::spawn_logger();
-            (cli, handle, None)
-        }
-    } else {
-        let (cli, handle) = recovery::spawn_logger();
-        (cli, handle, None)
-    };
+    // Or download it from Glimpse while the feed runs: that partition's merge starts at
+    // the first live packet and decode holds the feed back until the snapshot is in
+    let partitions = cfg.partitions();
+    let glimpse_idx = cfg.glimpse.as_ref().map(|g| {
+        g.partition
+            .as_ref()
+            .and_then(|p| partitions.iter().position(|q| &q.name == p))
+            .unwrap_or(0)
+    });
 
-    // RX threads
+    // One RX/merge/recovery set per partition, all feeding the shared decode stage
+    let mut part_threads = Vec::with_capacity(partitions.len());
+    for (idx, part) in partitions.iter().enumerate() {
+        part_threads.push(spawn_partition(
+            &cfg,
+            part,
+            idx,
+            partitions.len(),
+            glimpse_idx == Some(idx),
+            &parser,
+            &pool,
+            &shutdown,
+        )?);
+    }
+    let q_merged: Vec<_> = part_threads.iter().map(|p| p.q_merged.clone()).collect();
 
-    let t_rx_a = if cfg.afxdp.as_ref().map(|c| c.enable).unwrap_or(false) {
-        // Spawn one AF_PACKET/AF_XDP-like worker per requested queue
-        let ifname = cfg.afxdp.as_ref().unwrap().ifname.clone();
-        let queues = cfg.afxdp.as_ref().unwrap().queues.unwrap_or(1).max(1);
-        let mut joins = Vec::with_capacity(queues);
-        for (i, q_ai) in q_rx_a_list.iter().take(queues).enumerate() {
-            let rx_a_shutdown_i = shutdown.clone();
-            let pool_ai = pool.clone();
-            let q_ai = q_ai.clone();
-            let parser_ai = parser.clone();
-            let cfg = cfg.clone();
-            let ifn = ifname.clone();
-            let qid = i as u32; // queue id hint
-            let name = format!("afxdp-A-{i}");
-            let t = thread::Builder::new().name(name).spawn(move || {
-                crate::util::pin_to_core_with_offset(cfg.cpu.a_rx_core, i);
-                set_realtime_priority_if(cfg.cpu.rt_priority);
-                if let Err(e) = rx_afxdp::afxdp_loop(
-                    &ifn,
-                    qid,
-                    parser_ai.seq_extractor(),
-                    "A",
-                    q_ai,
-                    pool_ai,
-                    rx_a_shutdown_i,
-                ) {
-                    error!("afxdp failed: {e:?}");
-                }
-            })?;
-            joins.push(t);
+    let (glimpse, glimpse_fetch) = match (&cfg.glimpse, glimpse_idx) {
+        (Some(g), Some(idx)) => {
+            let (req_tx, req_rx) = bounded(4);
+            let (snap_tx, snap_rx) = bounded(1);
+            let t = glimpse::spawn_glimpse_fetch(g.clone(), req_rx, snap_tx, shutdown.clone())?;
+            let g = glimpse::Glimpse::new(g, idx, req_tx, snap_rx, pool.clone());
+            (Some(g), Some(t))
         }
-        thread::Builder::new()
-            .name("rx-A-join".into())
-            .spawn(move || {
-                for j in joins {
-                    let _ = j.join();
-                }
-            })?
-    } else {
-        // Spawn N workers for A
-        let mut joins = Vec::with_capacity(a_workers);
-        for (i, sa) in socks_a.into_iter().enumerate() {
-            let rx_a_shutdown_i = shutdown.clone();
-            let pool_ai = pool.clone();
-            let q_ai = q_rx_a_list[i].clone();
-            let parser_ai = parser.clone();
-            let cfg = cfg.clone();
-            let name = format!("rx-A-{i}");
-            let t = thread::Builder::new().name(name).spawn(move || {
-                crate::util::pin_to_core_with_offset(cfg.cpu.a_rx_core, i);
-                set_realtime_priority_if(cfg.cpu.rt_priority);
-                if let Err(e) = rx_loop(
-                    "A",
-                    &sa,
-                    parser_ai.seq_extractor(),
-                    q_ai,
-                    pool_ai,
-                    rx_a_shutdown_i,
-                    crate::rx::RxConfig {
-                        spin_loops_per_yield: cfg.general.spin_loops_per_yield,
-                        rx_batch: cfg.general.rx_recvmmsg_batch.unwrap_or(0),
-                        ts_mode: cfg.channels.a.timestamping.clone(),
-                    },
-                ) {
-                    error!("rx-A failed: {e:?}");
-                }
-            })?;
-            joins.push(t);
-        }
-        // Join all A workers using a proxy handle
-        thread::Builder::new()
-            .name("rx-A-join".into())
-            .spawn(move || {
-                for j in joins {
-                    let _ = j.join();
-                }
-            })?
+        _ => (None, None),
     };
 
-    let t_rx_b = {
-        let mut joins = Vec::with_capacity(b_workers);
-        for (i, sb) in socks_b.into_iter().enumerate() {
-            let rx_b_shutdown_i = shutdown.clone();
-            let pool_bi = pool.clone();
-            let q_bi = q_rx_b_list[i].clone();
-            let parser_bi = parser.clone();
-            let cfg = cfg.clone();
-            let name = format!("rx-B-{i}");
-            let t = thread::Builder::new().name(name).spawn(move || {
-                crate::util::pin_to_core_with_offset(cfg.cpu.b_rx_core, i);
-                set_realtime_priority_if(cfg.cpu.rt_priority);
-                if let Err(e) = rx_loop(
-                    "B",
-                    &sb,
-                    parser_bi.seq_extractor(),
-                    q_bi,
-                    pool_bi,
-                    rx_b_shutdown_i,
-                    crate::rx::RxConfig {
-                        spin_loops_per_yield: cfg.general.spin_loops_per_yield,
-                        rx_batch: cfg.general.rx_recvmmsg_batch.unwrap_or(0),
-                        ts_mode: cfg.channels.b.timestamping.clone(),
-                    },
-                ) {
-                    error!("rx-B failed: {e:?}");
-                }
-            })?;
-            joins.push(t);
+    // Snapshot multicast: joined by its RX thread whenever decode wants a snapshot
+    let (late_join, snapshot_rx) = match &cfg.snapshot_feed {
+        Some(f) => {
+            let idx = f
+                .partition
+                .as_ref()
+                .and_then(|p| partitions.iter().position(|q| &q.name == p))
+                .unwrap_or(0);
+            let q = Arc::new(SpscQueue::new(cfg.general.merge_queue_capacity));
+            let join = Arc::new(std::sync::atomic::AtomicBool::new(false));
+            let t = late_join::spawn_snapshot_rx(
+                f.channel.clone(),
+                join.clone(),
+                q.clone(),
+                pool.clone(),
+                shutdown.clone(),
+            )?;
+            let lj = late_join::LateJoin::new(f, idx, q, pool.clone(), join);
+            (Some(lj), Some(t))
         }
-        thread::Builder::new()
-            .name("rx-B-join".into())
-            .spawn(move || {
-                for j in joins {
-                    let _ = j.join();
-                }
-            })?
+        None => (None, None),
     };
 
-    // Merge thread
-    let merge_shutdown = shutdown.clone();
-    let recovery_cli = recovery_client.clone();
-    let q_merged_for_merge = q_merged.clone();
-    let t_merge = thread::Builder::new().name("merge".into()).spawn(move || {
-        pin_to_core_if_set(cfg.cpu.merge_core);
-        set_realtime_priority_if(cfg.cpu.rt_priority);
-        if let Err(e) = merge_loop(
-            q_rx_a_list,
-            q_rx_b_list,
-            q_merged_for_merge,
-            crate::merge::MergeConfig {
-                next_seq: cfg.merge.initial_expected_seq,
-                reorder_window: cfg.merge.reorder_window,
-                max_pending: cfg.merge.max_pending_packets,
-                dwell_ns: cfg.merge.dwell_ns.unwrap_or(2_000_000),
-                adaptive: cfg.merge.adaptive,
-                reorder_window_max: cfg.merge.reorder_window_max.unwrap_or(
-                    cfg.merge
-                        .reorder_window
-                        .saturating_mul(8)
-                        .max(cfg.merge.reorder_window + 1),
-                ),
-            },
-            merge_shutdown,
-            Some(recovery_cli),
-            q_recovery_opt,
-        ) {
-            error!("merge failed: {e:?}");
-        }
-    })?;
-
     // Decode thread
     let decode_shutdown = shutdown.clone();
     // Feeds / Publishers setup (WS A/B; H3 pending)
@@ -462,6 +336,13 @@ fn main() -> anyhow::Result<()> {
     let h3_handles: Vec<(std::thread::JoinHandle<()>, std::thread::JoinHandle<()>)> = Vec::new();
 
     let obo_pub_for_decode = obo_bus.as_ref().map(|b| b.publisher());
+    let exchange_to_rx: Vec<_> = partitions
+        .iter()
+        .map(|p| {
+            let lines: Vec<&str> = p.lines().iter().map(|l| l.name).collect();
+            metrics::ExchangeToRx::new(&p.name, &lines)
+        })
+        .collect();
 
     let t_decode = thread::Builder::new()
         .name("decode".into())
@@ -481,6 +362,9 @@ fn main() -> anyhow::Result<()> {
                     initial_book,
                     snapshot_trigger_rx: Some(snaptr_rx),
                     obo_publisher: obo_pub_for_decode,
+                    late_join,
+                    glimpse,
+                    exchange_to_rx,
                 },
             ) {
                 error!("decode failed: {e:?}");
@@ -488,18 +372,22 @@ fn main() -> anyhow::Result<()> {
         })?;
 
     // Join (log panics explicitly to aid diagnosis in production)
-    if t_rx_a.join().is_err() {
-        error!("rx-A thread panicked");
-    }
-    if t_rx_b.join().is_err() {
-        error!("rx-B thread panicked");
-    }
-    if t_merge.join().is_err() {
-        error!("merge thread panicked");
+    for p in &mut part_threads {
+        for (name, t) in p.threads.drain(..) {
+            if t.join().is_err() {
+                error!("{name} thread panicked");
+            }
+        }
     }
     if t_decode.join().is_err() {
         error!("decode thread panicked");
     }
+    if let Some(t) = snapshot_rx {
+        let _ = t.join();
+    }
+    if let Some(t) = glimpse_fetch {
+        let _ = t.join();
+    }
     // WS handles
     for (a, b) in ws_handles {
         let _ = a.join();
@@ -512,7 +400,9 @@ fn main() -> anyhow::Result<()> {
     if let Some(h) = snapshot_handle {
         h.join();
     }
-    recovery_handle.join();
+    for p in part_threads {
+        p.recovery.join();
+    }
     // Gracefully stop metrics HTTP (poke /shutdown and join)
     if let Some(m) = &cfg.metrics {
         request_http_shutdown(&m.bind);
@@ -532,3 +422,256 @@ fn request_http_shutdown(addr: &str) {
         let _ = s.flush();
     }
 }
+
+/// Threads and queues owned by one feed partition.
+struct PartitionThreads {
+    threads: Vec<(String, thread::JoinHandle<()>)>,
+    recovery: recovery::RecoveryHandle,
+    q_merged: Arc<SpscQueue<Pkt>>,
+}
+
+/// Spawn RX workers for every line, a recovery client and a merge thread for `part`.
+/// AF_XDP, when enabled, replaces channel A of the first partition. With `anchor_on_first`
+/// the merge starts at the first sequence it sees (the book comes from Glimpse).
+#[allow(clippy::too_many_arguments)]
+fn spawn_partition(
+    cfg: &AppConfig,
+    part: &PartitionCfg,
+    idx: usize,
+    n_partitions: usize,
+    anchor_on_first: bool,
+    parser: &parser::Parser,
+    pool: &Arc<PacketPool>,
+    shutdown: &Arc<BarrierFlag>,
+) -> anyhow::Result<PartitionThreads> {
+    let name = part.name.as_str();
+    let mut threads = Vec::new();
+
+    // Queues: one per RX worker of each line
+    let lines = part.lines();
+    let q_rx: Vec<Vec<Arc<SpscQueue<Pkt>>>> = lines
+        .iter()
+        .map(|l| {
+            (0..l.cfg.workers.unwrap_or(1).max(1))
+                .map(|_| Arc::new(SpscQueue::new(cfg.general.rx_queue_capacity)))
+                .collect()
+        })
+        .collect();
+    let q_merged = Arc::new(SpscQueue::new(cfg.general.merge_queue_capacity));
+
+    // Lines with `chaos`: RX workers fill their own queues, impairment threads forward
+    let epoch = std::time::Instant::now();
+    let mut q_rx_in = Vec::new();
+    for (line, queues) in lines.iter().zip(&q_rx) {
+        let Some(c) = &line.cfg.chaos else {
+            q_rx_in.push(queues.clone());
+            continue;
+        };
+        let mut ins = Vec::new();
+        for (w, q_out) in queues.iter().enumerate() {
+            let q_in = Arc::new(SpscQueue::new(cfg.general.rx_queue_capacity));
+            let t = chaos::spawn_chaos(
+                c,
+                w,
+                epoch,
+                name,
+                line.name,
+                q_in.clone(),
+                q_out.clone(),
+                pool.clone(),
+                shutdown.clone(),
+            )?;
+            threads.push((format!("chaos-{name}-{}-{w}", line.name), t));
+            ins.push(q_in);
+        }
+        q_rx_in.push(ins);
+    }
+
+    // Recovery manager: TCP or MoldUDP64 injector if enabled, else logger-only
+    let (recovery_client, recovery_handle, q_recovery_opt) = match &cfg.recovery {
+        Some(rcfg) if rcfg.enable_injector => {
+            let q_recovery = Arc::new(SpscQueue::new(cfg.general.merge_queue_capacity));
+            let icfg = recovery::InjectorCfg::from_cfg(
+                rcfg,
+                part.recovery_endpoint.as_deref(),
+                name,
+                n_partitions,
+                cfg.parser.session_utc_offset_s,
+            );
+            let (cli, handle) = match &rcfg.moldudp64 {
+                Some(m) => recovery_mold::spawn_mold_requester(
+                    icfg,
+                    recovery_mold::MoldParams::from_cfg(m),
+                    parser.seq_extractor(),
+                    q_recovery.clone(),
+                    pool.clone(),
+                ),
+                None => recovery::spawn_tcp_injector(icfg, q_recovery.clone(), pool.clone()),
+            };
+            (cli, handle, Some(q_recovery))
+        }
+        _ => {
+            let (cli, handle) = recovery::spawn_logger();
+            (cli, handle, None)
+        }
+    };
+
+    // RX threads
+    let afxdp = cfg.afxdp.as_ref().filter(|a| a.enable && idx == 0);
+    if let Some(ax) = afxdp {
+        // Spawn one AF_PACKET/AF_XDP-like worker per requested queue
+        let queues = ax.queues.unwrap_or(1).max(1);
+        for (i, q_ai) in q_rx_in[0].iter().take(queues).enumerate() {
+            let shutdown = shutdown.clone();
+            let pool = pool.clone();
+            let q_ai = q_ai.clone();
+            let seq = parser.seq_extractor();
+            let ax = ax.clone();
+            let (core, rt, port) = (lines[0].rx_core, cfg.cpu.rt_priority, part.a.port);
+            let label = name.to_string();
+            let qid = i as u32; // queue id hint
+            let tname = format!("afxdp-{name}-A-{i}");
+            let t = thread::Builder::new().name(tname.clone()).spawn(move || {
+                crate::util::pin_to_core_with_offset(core, i);
+                set_realtime_priority_if(rt);
+                if let Err(e) =
+                    rx_afxdp::afxdp_loop(&ax, port, qid, seq, "A", &label, q_ai, pool, shutdown)
+                {
+                    error!("afxdp failed: {e:?}");
+                }
+            })?;
+            threads.push((tname, t));
+        }
+    }
+    for (i, (line, queues)) in lines.iter().zip(&q_rx_in).enumerate() {
+        if i == 0 && afxdp.is_some() {
+            continue;
+        }
+        spawn_rx_workers(
+            cfg,
+            name,
+            line,
+            i as u8,
+            queues,
+            parser,
+            pool,
+            shutdown,
+            &mut threads,
+        )?;
+    }
+
+    // Merge thread
+    let sources: Vec<crate::merge::MergeSource> = lines
+        .iter()
+        .zip(q_rx)
+        .map(|(l, queues)| crate::merge::MergeSource {
+            name: l.name.to_string(),
+            queues,
+            priority: l.priority,
+            switch_after: l.switch_after,
+            revert_after: l.revert_after,
+        })
+        .collect();
+    let merge_shutdown = shutdown.clone();
+    let q_merged_for_merge = q_merged.clone();
+    let merge_cfg = crate::merge::MergeConfig {
+        next_seq: part
+            .initial_expected_seq
+            .unwrap_or(cfg.merge.initial_expected_seq),
+        anchor_on_first,
+        reorder_window: cfg.merge.reorder_window,
+        max_pending: cfg.merge.max_pending_packets,
+        dwell_ns: cfg.merge.dwell_ns.unwrap_or(2_000_000),
+        adaptive: cfg.merge.adaptive,
+        reorder_window_max: cfg.merge.reorder_window_max.unwrap_or(
+            cfg.merge
+                .reorder_window
+                .saturating_mul(8)
+                .max(cfg.merge.reorder_window + 1),
+        ),
+        gap_timeout_ns: cfg.merge.gap_timeout_ns.unwrap_or(0),
+        give_up_ns: cfg.merge.give_up_after_ns.unwrap_or(0),
+        on_give_up: cfg.merge.on_give_up,
+        reset_backjump: cfg.merge.reset_backjump.unwrap_or(0),
+        max_streams: cfg.merge.max_streams.unwrap_or(64),
+        verify: cfg
+            .merge
+            .verify
+            .as_ref()
+            .map(|v| crate::dup_verify::DupVerifier::from_cfg(v, name, n_partitions))
+            .transpose()?,
+        race: cfg.merge.race.as_ref().map(|r| crate::merge::RaceConfig {
+            history: r.history,
+            window: r.window,
+            report_ns: r.report_interval_ms.saturating_mul(1_000_000),
+        }),
+        partition: name.to_string(),
+    };
+    let (core, rt) = (part.merge_core, cfg.cpu.rt_priority);
+    let tname = format!("merge-{name}");
+    let t_merge = thread::Builder::new().name(tname.clone()).spawn(move || {
+        pin_to_core_if_set(core);
+        set_realtime_priority_if(rt);
+        if let Err(e) = merge_loop(
+            sources,
+            q_merged_for_merge,
+            merge_cfg,
+            merge_shutdown,
+            Some(recovery_client),
+            q_recovery_opt,
+        ) {
+            error!("merge failed: {e:?}");
+        }
+    })?;
+    threads.push((tname, t_merge));
+
+    Ok(PartitionThreads {
+        threads,
+        recovery: recovery_handle,
+        q_merged,
+    })
+}
+
+/// Socket RX workers for one line (multiple via SO_REUSEPORT), one per queue.
+#[allow(clippy::too_many_arguments)]
+fn spawn_rx_workers(
+    cfg: &AppConfig,
+    partition: &str,
+    line: &Line<'_>,
+    line_idx: u8,
+    queues: &[Arc<SpscQueue<Pkt>>],
+    parser: &parser::Parser,
+    pool: &Arc<PacketPool>,
+    shutdown: &Arc<BarrierFlag>,
+    threads: &mut Vec<(String, thread::JoinHandle<()>)>,
+) -> anyhow::Result<()> {
+    let (ch, core) = (line.cfg, line.rx_core);
+    for (i, q) in queues.iter().enumerate() {
+        let sock = net::build_mcast_socket(ch)?;
+        let shutdown = shutdown.clone();
+        let pool = pool.clone();
+        let q = q.clone();
+        let seq = parser.seq_extractor();
+        let rt = cfg.cpu.rt_priority;
+        let rx_cfg = crate::rx::RxConfig {
+            spin_loops_per_yield: cfg.general.spin_loops_per_yield,
+            rx_batch: cfg.general.rx_recvmmsg_batch.unwrap_or(0),
+            ts_mode: ch.timestamping.clone(),
+            backend: ch.rx_backend,
+            sqpoll_idle_ms: ch.io_uring_sqpoll_idle_ms,
+            partition: partition.to_string(),
+            line: line_idx,
+        };
+        let chan = line.name.to_string();
+        let tname = format!("rx-{partition}-{chan}-{i}");
+        let t = thread::Builder::new().name(tname.clone()).spawn(move || {
+            crate::util::pin_to_core_with_offset(core, i);
+            set_realtime_priority_if(rt);
+            if let Err(e) = rx_loop(&chan, &sock, seq, q, pool, shutdown, rx_cfg) {
+                error!("rx-{chan} failed: {e:?}");
+            }
+        })?;
+        threads.push((tname, t));
+    }
+    Ok(())
+}
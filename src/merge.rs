@@ -1,26 +1,68 @@
 // src/merge.rs (updated: metrics + recovery)
+use crate::codec_raw::{gap_action, msg_type, reset_kind, FeedGapV1, SeqResetV1};
+use crate::config::GiveUpPolicy;
+use crate::dup_verify::DupVerifier;
+use crate::line_race::LineRace;
 use crate::metrics;
-use crate::pool::Pkt;
+use crate::pool::{Pkt, PktBuf, CHAN_CONTROL};
 use crate::recovery::RecoveryClient;
 use crate::spsc::SpscQueue;
 use crate::util::BarrierFlag;
+use hashbrown::HashMap;
 use log::warn;
 // Reorder buffer is implemented as a fixed-size ring to minimize allocations and compares
 use std::sync::Arc;
 
 pub struct MergeConfig {
     pub next_seq: u64,
+    /// Start each stream at the first sequence it sees instead of `next_seq` (the book
+    /// comes from a snapshot that decode applies from its own sequence)
+    pub anchor_on_first: bool,
     pub reorder_window: u64,
     pub max_pending: usize,
     pub dwell_ns: u64,
     pub adaptive: bool,
     pub reorder_window_max: u64,
+    /// Declare the hole at the head of the buffer once packets behind it have been held
+    /// this long (0 = only when a packet lands beyond the window)
+    pub gap_timeout_ns: u64,
+    /// How long a declared gap may stall the merge before `on_give_up` applies
+    pub give_up_ns: u64,
+    pub on_give_up: GiveUpPolicy,
+    /// Backwards jump (seen on two lines) taken as a sequence reset; 0 = explicit resets only
+    pub reset_backjump: u64,
+    /// Compare late duplicates with the forwarded payload (`[merge.verify]`)
+    pub verify: Option<DupVerifier>,
+    /// Line race analytics (`[merge.race]`)
+    pub race: Option<RaceConfig>,
+    /// Sequence streams (`Pkt.stream`) tracked; packets of further streams are dropped
+    pub max_streams: usize,
+    /// Partition label for metrics and logs
+    pub partition: String,
+}
+
+pub struct RaceConfig {
+    pub history: usize,
+    pub window: u64,
+    pub report_ns: u64,
+}
+
+/// One copy of the feed arbitrated by the merge; its index in the list is `Pkt.chan`.
+pub struct MergeSource {
+    pub name: String,
+    /// One queue per RX worker, polled round-robin
+    pub queues: Vec<Arc<SpscQueue<Pkt>>>,
+    /// Lower wins; the lowest is the primary line
+    pub priority: u32,
+    /// Consecutive in-order wins before this line becomes preferred
+    pub switch_after: u32,
+    /// While preferred (and not primary): forwards before handing back to the primary
+    pub revert_after: u32,
 }
 
 // TODO: Group arguments into a MergeConfig struct to reduce parameter count.
 pub fn merge_loop(
-    q_a_list: Vec<Arc<SpscQueue<Pkt>>>,
-    q_b_list: Vec<Arc<SpscQueue<Pkt>>>,
+    sources: Vec<MergeSource>,
     q_out: Arc<SpscQueue<Pkt>>,
     cfg: MergeConfig,
     shutdown: Arc<BarrierFlag>,
@@ -28,27 +70,50 @@ pub fn merge_loop(
     q_recovery_in: Option<Arc<SpscQueue<Pkt>>>, // optional recovery->merge SPSC queue
 ) -> anyhow::Result<()> {
     let MergeConfig {
-        mut next_seq,
+        next_seq,
+        anchor_on_first,
         mut reorder_window,
         max_pending,
         dwell_ns,
         adaptive,
         reorder_window_max,
+        gap_timeout_ns,
+        give_up_ns,
+        on_give_up,
+        reset_backjump,
+        max_streams,
+        mut verify,
+        race,
+        partition,
     } = cfg;
+    if sources.is_empty() || sources.iter().any(|s| s.queues.is_empty()) {
+        anyhow::bail!("merge needs at least one source, each with a queue");
+    }
+    let part = partition.as_str();
+    let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+    let mut race = race.map(|r| LineRace::new(part, &names, r.history, r.window, r.report_ns));
     let cap: usize = (reorder_window as usize).saturating_add(1);
-    let mut ring: Vec<Option<(u64, Pkt)>> = (0..cap).map(|_| None).collect();
-    let mut pending_count: usize = 0;
-
-    // Prefer-A with hysteresis: start preferring A; consider switching based on consecutive
-    // non-preferred forwards and a minimum dwell time since the last switch.
-    let mut prefer_a: bool = true;
-    let mut streak_preferred: u32 = 0;
-    let mut streak_nonpreferred: u32 = 0;
-    const SWITCH_TO_B_AFTER: u32 = 2; // consecutive non-preferred forwards
-    const SWITCH_TO_A_AFTER: u32 = 8; // consecutive preferred forwards to flip back
-    let mut last_switch_ns: u64 = crate::util::now_nanos();
-    let mut min_dwell_ns: u64 = if dwell_ns == 0 { 2_000_000 } else { dwell_ns };
-    metrics::set_merge_preferred_is_a(true);
+    // Every stream gets its own ring, next_seq and gap tracking, starting at `next_seq`
+    let mut streams = Streams {
+        list: Vec::new(),
+        index: HashMap::new(),
+        last: 0,
+        max: max_streams.max(1),
+        next_seq,
+        anchor_on_first,
+        cap,
+        lines: sources.len(),
+        reset_backjump,
+        window_max: reorder_window_max,
+        part,
+    };
+
+    // Poll the preferred line first, then the rest by priority
+    let mut by_priority: Vec<usize> = (0..sources.len()).collect();
+    by_priority.sort_by_key(|&i| (sources[i].priority, i));
+    let mut arbiter = Arbiter::new(&sources, by_priority[0], dwell_ns);
+    publish_preferred(part, &names, arbiter.preferred);
+    let mut rr: Vec<usize> = vec![0; sources.len()];
 
     // Adaptive window counters
     let mut forwarded_since_check: u64 = 0;
@@ -56,11 +121,6 @@ pub fn merge_loop(
     let mut recent_ooo: u64 = 0;
     let mut switches_in_window: u32 = 0;
 
-    let mut idx_a: usize = 0;
-    let mut idx_b: usize = 0;
-    let na = q_a_list.len().max(1);
-    let nb = q_b_list.len().max(1);
-
     while !shutdown.is_raised() {
         let mut moved = false;
 
@@ -70,65 +130,52 @@ pub fn merge_loop(
             for _ in 0..32 {
                 if let Some(pkt) = qrec.pop() {
                     let s = pkt.seq;
-                    if s < next_seq {
-                        metrics::inc_merge_dup();
-                    } else if s == next_seq {
+                    let Some(st) = streams.get(pkt.stream) else {
+                        continue;
+                    };
+                    if s < st.next_seq {
+                        metrics::inc_merge_dup(part);
+                    } else if s == st.next_seq {
+                        let span = pkt.seq_span.max(1) as u64;
+                        if let Some(v) = verify.as_mut() {
+                            v.record(&pkt);
+                        }
                         forward(&q_out, pkt);
-                        metrics::inc_merge_forward_chan("R");
-                        next_seq = next_seq.wrapping_add(1);
+                        metrics::inc_merge_forward_chan(part, "R");
+                        st.next_seq = st.next_seq.wrapping_add(span);
                         moved = true;
-                        // Drain contiguous buffered packets
-                        loop {
-                            let idx = (next_seq % (cap as u64)) as usize;
-                            if let Some((stored_seq, node)) = ring[idx].take() {
-                                if stored_seq != next_seq {
-                                    ring[idx] = Some((stored_seq, node));
-                                    break;
-                                }
-                                pending_count = pending_count.saturating_sub(1);
-                                metrics::inc_merge_ooo();
-                                let c = if node.chan == b'A' {
-                                    "A"
-                                } else if node.chan == b'B' {
-                                    "B"
-                                } else {
-                                    "R"
-                                };
-                                forward(&q_out, node);
-                                metrics::inc_merge_forward_chan(c);
-                                next_seq = next_seq.wrapping_add(1);
-                                forwarded_since_check = forwarded_since_check.saturating_add(1);
-                            } else {
-                                break;
-                            }
-                        }
+                        let drained = st.drain_ready(&q_out, part, &names, &mut verify);
+                        forwarded_since_check = forwarded_since_check.saturating_add(drained);
                     } else {
-                        let distance = s.wrapping_sub(next_seq);
-                        if distance <= reorder_window && pending_count < max_pending {
+                        let distance = s.wrapping_sub(st.next_seq);
+                        if distance <= reorder_window && st.pending_count < max_pending {
                             let idx = (s % (cap as u64)) as usize;
-                            match &ring[idx] {
+                            match &st.ring[idx] {
                                 Some((seq_in_slot, _)) => {
                                     if *seq_in_slot == s {
-                                        metrics::inc_merge_dup();
-                                    } else if *seq_in_slot < next_seq {
-                                        ring[idx] = Some((s, pkt));
-                                        pending_count += 1;
+                                        metrics::inc_merge_dup(part);
+                                    } else if *seq_in_slot < st.next_seq {
+                                        st.ring[idx] = Some((s, pkt));
+                                        st.held_since[idx] = crate::util::now_nanos();
+                                        st.pending_count += 1;
                                     } else {
-                                        metrics::inc_merge_dup();
+                                        metrics::inc_merge_dup(part);
                                     }
                                 }
                                 None => {
-                                    ring[idx] = Some((s, pkt));
-                                    pending_count += 1;
+                                    st.ring[idx] = Some((s, pkt));
+                                    st.held_since[idx] = crate::util::now_nanos();
+                                    st.pending_count += 1;
                                 }
                             }
                         } else {
-                            metrics::inc_merge_gap();
+                            metrics::inc_merge_gap(part);
+                            stall_on(&mut st.stall, st.next_seq);
                             recent_gaps = recent_gaps.saturating_add(1);
-                            metrics::inc_merge_gap_chan("R");
+                            metrics::inc_merge_gap_chan(part, "R");
                             if let Some(ref cli) = recovery {
-                                if s > next_seq {
-                                    cli.notify_gap(next_seq, s - 1);
+                                if s > st.next_seq {
+                                    cli.notify_stream_gap(st.id, st.next_seq, s - 1);
                                 }
                             }
                         }
@@ -139,120 +186,122 @@ pub fn merge_loop(
             }
         }
 
-        // Try preferred first, then the other (round-robin across workers)
-        for src in 0..2 {
-            let take_a_first = (src == 0) == prefer_a;
-            let pkt = if take_a_first {
-                let q = &q_a_list[idx_a % na];
-                idx_a = idx_a.wrapping_add(1);
-                q.pop()
-            } else {
-                let q = &q_b_list[idx_b % nb];
-                idx_b = idx_b.wrapping_add(1);
+        // Try preferred first, then the others (round-robin across workers)
+        let first = arbiter.preferred;
+        let order =
+            std::iter::once(first).chain(by_priority.iter().copied().filter(|&i| i != first));
+        for src in order {
+            let pkt = {
+                let qs = &sources[src].queues;
+                let q = &qs[rr[src] % qs.len()];
+                rr[src] = rr[src].wrapping_add(1);
                 q.pop()
             };
             if let Some(pkt) = pkt {
                 let s = pkt.seq;
-                if s < next_seq {
-                    metrics::inc_merge_dup();
+                let chan = names[src];
+                let Some(st) = streams.get(pkt.stream) else {
                     continue;
+                };
+                let tag = Tag(part, st.id);
+                if !st.anchored {
+                    log::info!("[{tag}] merge starts at the first sequence seen, {s}");
+                    st.next_seq = s;
+                    st.anchored = true;
                 }
-                if s == next_seq {
-                    let chan = if pkt.chan == b'A' { "A" } else { "B" };
-                    forward(&q_out, pkt);
-                    metrics::inc_merge_forward_chan(chan);
-                    next_seq = next_seq.wrapping_add(1);
-                    moved = true;
-                    // Drain contiguous buffered packets
-                    loop {
-                        let idx = (next_seq % (cap as u64)) as usize;
-                        if let Some((stored_seq, node)) = ring[idx].take() {
-                            if stored_seq != next_seq {
-                                // stale/aliased entry; drop it and stop draining
-                                ring[idx] = Some((stored_seq, node));
-                                break;
-                            }
-                            pending_count = pending_count.saturating_sub(1);
-                            metrics::inc_merge_ooo();
-                            recent_ooo = recent_ooo.saturating_add(1);
-                            let c = if node.chan == b'A' { "A" } else { "B" };
-                            forward(&q_out, node);
-                            metrics::inc_merge_forward_chan(c);
-                            next_seq = next_seq.wrapping_add(1);
-                            forwarded_since_check = forwarded_since_check.saturating_add(1);
-                        } else {
-                            break;
+                match st.resets.classify(src, s, pkt.seq_reset, st.next_seq) {
+                    Verdict::Normal => {}
+                    Verdict::Stale => {
+                        metrics::inc_merge_dup(part);
+                        continue;
+                    }
+                    Verdict::Reset(anchor, kind) => {
+                        // Everything buffered belongs to the old session
+                        st.ring.iter_mut().for_each(|slot| *slot = None);
+                        st.pending_count = 0;
+                        st.hole = None;
+                        st.stall = None;
+                        st.skip_armed = false;
+                        seq_reset(&q_out, tag, kind, st.next_seq, anchor, chan);
+                        st.next_seq = anchor;
+                        if let Some(r) = race.as_mut() {
+                            r.reset(st.id);
                         }
                     }
+                }
+                if let Some(r) = race.as_mut() {
+                    r.arrival(src, st.id, s, pkt.ts_nanos);
+                }
+                if st.skip_armed && s > st.next_seq {
+                    give_up(&q_out, tag, on_give_up, st.next_seq, s - 1, &recovery);
+                    st.next_seq = s;
+                    st.skip_armed = false;
+                }
+                if s < st.next_seq {
+                    metrics::inc_merge_dup(part);
+                    if let Some(v) = verify.as_mut() {
+                        let _ = v.check_forwarded(&pkt, part, &names);
+                    }
+                    continue;
+                }
+                if s == st.next_seq {
+                    let span = pkt.seq_span.max(1) as u64;
+                    if let Some(v) = verify.as_mut() {
+                        v.record(&pkt);
+                    }
+                    forward(&q_out, pkt);
+                    metrics::inc_merge_forward_chan(part, chan);
+                    st.next_seq = st.next_seq.wrapping_add(span);
+                    moved = true;
+                    let drained = st.drain_ready(&q_out, part, &names, &mut verify);
+                    recent_ooo = recent_ooo.saturating_add(drained);
+                    forwarded_since_check = forwarded_since_check.saturating_add(drained);
 
-                    // Hysteresis update: observe source vs preference
-                    let was_a = chan == "A";
-                    let is_preferred_src = (prefer_a && was_a) || (!prefer_a && !was_a);
-                    if is_preferred_src {
-                        streak_preferred = streak_preferred.saturating_add(1);
-                        streak_nonpreferred = 0;
-                        if !prefer_a
-                            && streak_preferred >= SWITCH_TO_A_AFTER
-                            && crate::util::now_nanos().saturating_sub(last_switch_ns)
-                                >= min_dwell_ns
-                        {
-                            prefer_a = true;
-                            streak_preferred = 0;
-                            metrics::inc_merge_failover();
-                            metrics::set_merge_preferred_is_a(true);
-                            last_switch_ns = crate::util::now_nanos();
-                            switches_in_window = switches_in_window.saturating_add(1);
-                        }
-                    } else {
-                        streak_nonpreferred = streak_nonpreferred.saturating_add(1);
-                        streak_preferred = 0;
-                        if prefer_a
-                            && streak_nonpreferred >= SWITCH_TO_B_AFTER
-                            && crate::util::now_nanos().saturating_sub(last_switch_ns)
-                                >= min_dwell_ns
-                        {
-                            prefer_a = false;
-                            streak_nonpreferred = 0;
-                            metrics::inc_merge_failover();
-                            metrics::set_merge_preferred_is_a(false);
-                            last_switch_ns = crate::util::now_nanos();
-                            switches_in_window = switches_in_window.saturating_add(1);
-                        }
+                    // Hysteresis update: observe which line won this sequence
+                    if arbiter.observe(src, crate::util::now_nanos()) {
+                        metrics::inc_merge_failover(part);
+                        publish_preferred(part, &names, arbiter.preferred);
+                        switches_in_window = switches_in_window.saturating_add(1);
                     }
                 } else {
-                    let distance = s.wrapping_sub(next_seq);
-                    if distance <= reorder_window && pending_count < max_pending {
+                    let distance = s.wrapping_sub(st.next_seq);
+                    if distance <= reorder_window && st.pending_count < max_pending {
                         let idx = (s % (cap as u64)) as usize;
-                        match &ring[idx] {
-                            Some((seq_in_slot, _)) => {
+                        match &st.ring[idx] {
+                            Some((seq_in_slot, held)) => {
                                 if *seq_in_slot == s {
-                                    metrics::inc_merge_dup();
-                                } else if *seq_in_slot < next_seq {
+                                    metrics::inc_merge_dup(part);
+                                    if let Some(v) = verify.as_mut() {
+                                        let _ = v.check_buffered(held, &pkt, part, &names);
+                                    }
+                                } else if *seq_in_slot < st.next_seq {
                                     // stale slot from an old window; replace
-                                    ring[idx] = Some((s, pkt));
-                                    pending_count += 1;
+                                    st.ring[idx] = Some((s, pkt));
+                                    st.held_since[idx] = crate::util::now_nanos();
+                                    st.pending_count += 1;
                                 } else {
                                     // different seq still in-window shouldn't alias due to cap, but guard anyway
-                                    metrics::inc_merge_dup();
+                                    metrics::inc_merge_dup(part);
                                 }
                             }
                             None => {
-                                ring[idx] = Some((s, pkt));
-                                pending_count += 1;
+                                st.ring[idx] = Some((s, pkt));
+                                st.held_since[idx] = crate::util::now_nanos();
+                                st.pending_count += 1;
                             }
                         }
                     } else {
-                        metrics::inc_merge_gap();
+                        metrics::inc_merge_gap(part);
+                        stall_on(&mut st.stall, st.next_seq);
                         recent_gaps = recent_gaps.saturating_add(1);
-                        let chan = if pkt.chan == b'A' { "A" } else { "B" };
-                        metrics::inc_merge_gap_chan(chan);
+                        metrics::inc_merge_gap_chan(part, chan);
                         warn!(
-                            "gap/overflow: got seq={}, expected={}, pending={}, window={}, from={}",
-                            s, next_seq, pending_count, reorder_window, chan
+                            "[{}] gap/overflow: got seq={}, expected={}, pending={}, window={}, from={}",
+                            tag, s, st.next_seq, st.pending_count, reorder_window, chan
                         );
                         if let Some(ref cli) = recovery {
-                            if s > next_seq {
-                                cli.notify_gap(next_seq, s - 1);
+                            if s > st.next_seq {
+                                cli.notify_stream_gap(st.id, st.next_seq, s - 1);
                             }
                         }
                     }
@@ -260,6 +309,74 @@ pub fn merge_loop(
             }
         }
 
+        for st in streams.list.iter_mut() {
+            let tag = Tag(part, st.id);
+            // Hole at the head of the buffer: time how long it holds packets back, and
+            // declare it once that exceeds gap_timeout_ns rather than waiting for traffic
+            // beyond the window (which may never come at the end of a burst)
+            if matches!(st.hole, Some(h) if h.start != st.next_seq || st.pending_count == 0) {
+                if let Some(h) = st.hole.take() {
+                    let held = crate::util::now_nanos().saturating_sub(h.held_since_ns);
+                    metrics::observe_merge_hole_hold_ns(part, held);
+                }
+            }
+            if st.hole.is_none() && st.pending_count > 0 {
+                st.hole =
+                    scan_pending(&st.ring, &st.held_since, st.next_seq).map(|(_, since)| Hole {
+                        start: st.next_seq,
+                        held_since_ns: since,
+                        declared: false,
+                    });
+            }
+            if let Some(h) = st
+                .hole
+                .as_mut()
+                .filter(|h| gap_timeout_ns > 0 && !h.declared)
+            {
+                let now = crate::util::now_nanos();
+                if now.saturating_sub(h.held_since_ns) >= gap_timeout_ns {
+                    h.declared = true;
+                    let end = scan_pending(&st.ring, &st.held_since, st.next_seq)
+                        .map_or(st.next_seq, |(lowest, _)| lowest.wrapping_sub(1));
+                    metrics::inc_merge_gap(part);
+                    stall_on(&mut st.stall, st.next_seq);
+                    metrics::inc_merge_gap_timeout(part);
+                    recent_gaps = recent_gaps.saturating_add(1);
+                    warn!(
+                        "[{}] gap timeout: seq {}..={} missing, held {}us, pending={}",
+                        tag,
+                        st.next_seq,
+                        end,
+                        now.saturating_sub(h.held_since_ns) / 1_000,
+                        st.pending_count
+                    );
+                    if let Some(ref cli) = recovery {
+                        cli.notify_stream_gap(st.id, st.next_seq, end);
+                    }
+                }
+            }
+
+            // Give up on a gap recovery did not fill in time
+            if let Some((seq, since)) = st.stall {
+                if seq != st.next_seq {
+                    st.stall = None;
+                } else if on_give_up != GiveUpPolicy::Wait
+                    && crate::util::now_nanos().saturating_sub(since) >= give_up_ns
+                {
+                    st.stall = None;
+                    match scan_pending(&st.ring, &st.held_since, st.next_seq) {
+                        Some((lowest, _)) => {
+                            give_up(&q_out, tag, on_give_up, st.next_seq, lowest - 1, &recovery);
+                            st.next_seq = lowest;
+                            let drained = st.drain_ready(&q_out, part, &names, &mut verify);
+                            forwarded_since_check = forwarded_since_check.saturating_add(drained);
+                        }
+                        None => st.skip_armed = true,
+                    }
+                }
+            }
+        }
+
         // Adaptive window adjustment checkpoint
         if adaptive && forwarded_since_check >= 4096 {
             if recent_gaps > 0 && reorder_window < reorder_window_max {
@@ -271,11 +388,14 @@ pub fn merge_loop(
             }
             // Adapt dwell if we ping-pong too often
             if switches_in_window >= 4 {
-                min_dwell_ns = (min_dwell_ns.saturating_mul(2)).min(50_000_000);
+                arbiter.min_dwell_ns = (arbiter.min_dwell_ns.saturating_mul(2)).min(50_000_000);
             // cap at 50ms
-            } else if switches_in_window == 0 && min_dwell_ns > dwell_ns {
+            } else if switches_in_window == 0 && arbiter.min_dwell_ns > dwell_ns {
                 // decay
-                min_dwell_ns = (min_dwell_ns.saturating_sub(min_dwell_ns / 4)).max(dwell_ns);
+                arbiter.min_dwell_ns = (arbiter
+                    .min_dwell_ns
+                    .saturating_sub(arbiter.min_dwell_ns / 4))
+                .max(dwell_ns);
             }
             forwarded_since_check = 0;
             recent_gaps = 0;
@@ -291,6 +411,399 @@ pub fn merge_loop(
     Ok(())
 }
 
+/// Reorder state of one sequence stream (`Pkt.stream`)
+struct Stream {
+    id: u32,
+    next_seq: u64,
+    ring: Vec<Option<(u64, Pkt)>>,
+    /// When each slot was filled; the oldest pending packet dates the current hole
+    held_since: Vec<u64>,
+    pending_count: usize,
+    hole: Option<Hole>,
+    /// Sequence the stream has been stuck on since a gap was declared for it
+    stall: Option<(u64, u64)>,
+    /// Given up with nothing buffered: resume at the next line packet past the hole
+    skip_armed: bool,
+    resets: Resets,
+    /// `next_seq` is known (false until the first packet under `anchor_on_first`)
+    anchored: bool,
+}
+
+impl Stream {
+    /// Forward buffered packets contiguous with `next_seq`; returns how many. A slot
+    /// holding another sequence (stale or aliased) is left in place and ends the drain.
+    fn drain_ready(
+        &mut self,
+        q_out: &Arc<SpscQueue<Pkt>>,
+        part: &str,
+        names: &[&str],
+        verify: &mut Option<DupVerifier>,
+    ) -> u64 {
+        let cap = self.ring.len() as u64;
+        let mut drained = 0;
+        loop {
+            let idx = (self.next_seq % cap) as usize;
+            match self.ring[idx].take() {
+                Some((stored_seq, node)) if stored_seq == self.next_seq => {
+                    self.pending_count = self.pending_count.saturating_sub(1);
+                    metrics::inc_merge_ooo(part);
+                    drained += 1;
+                    let c = line_label(names, node.chan);
+                    let span = node.seq_span.max(1) as u64;
+                    if let Some(v) = verify.as_mut() {
+                        v.record(&node);
+                    }
+                    forward(q_out, node);
+                    metrics::inc_merge_forward_chan(part, c);
+                    self.next_seq = self.next_seq.wrapping_add(span);
+                }
+                other => {
+                    self.ring[idx] = other;
+                    break;
+                }
+            }
+        }
+        drained
+    }
+}
+
+/// Streams seen so far, created on first sight up to `max`
+struct Streams<'a> {
+    list: Vec<Stream>,
+    index: HashMap<u32, usize>,
+    /// Most recently used stream; single-stream feeds never reach the map
+    last: usize,
+    max: usize,
+    next_seq: u64,
+    anchor_on_first: bool,
+    cap: usize,
+    lines: usize,
+    reset_backjump: u64,
+    window_max: u64,
+    part: &'a str,
+}
+
+impl Streams<'_> {
+    #[inline]
+    fn get(&mut self, id: u32) -> Option<&mut Stream> {
+        if self.list.get(self.last).is_some_and(|s| s.id == id) {
+            return Some(&mut self.list[self.last]);
+        }
+        let i = match self.index.get(&id) {
+            Some(&i) => i,
+            None => self.add(id)?,
+        };
+        self.last = i;
+        Some(&mut self.list[i])
+    }
+
+    #[cold]
+    fn add(&mut self, id: u32) -> Option<usize> {
+        if self.list.len() >= self.max {
+            metrics::inc_merge_stream_drop(self.part);
+            return None;
+        }
+        if self.list.len() + 1 == self.max {
+            warn!(
+                "[{}] stream {id} reaches merge.max_streams ({}); packets of further streams are dropped",
+                self.part, self.max
+            );
+        } else if !self.list.is_empty() {
+            log::info!("[{}] new sequence stream {id}", self.part);
+        }
+        self.list.push(Stream {
+            id,
+            next_seq: self.next_seq,
+            ring: (0..self.cap).map(|_| None).collect(),
+            held_since: vec![0; self.cap],
+            pending_count: 0,
+            hole: None,
+            stall: None,
+            skip_armed: false,
+            resets: Resets::new(self.lines, self.reset_backjump, self.window_max),
+            anchored: !self.anchor_on_first,
+        });
+        self.index.insert(id, self.list.len() - 1);
+        metrics::set_merge_streams(self.part, self.list.len());
+        Some(self.list.len() - 1)
+    }
+}
+
+/// Log prefix: the partition, plus the stream when the feed has more than stream 0
+#[derive(Clone, Copy)]
+struct Tag<'a>(&'a str, u32);
+
+impl std::fmt::Display for Tag<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.1 {
+            0 => f.write_str(self.0),
+            stream => write!(f, "{}/{}", self.0, stream),
+        }
+    }
+}
+
+/// Start the give-up clock for `next_seq` unless it is already running.
+#[inline]
+fn stall_on(stall: &mut Option<(u64, u64)>, next_seq: u64) {
+    if stall.map(|(s, _)| s) != Some(next_seq) {
+        *stall = Some((next_seq, crate::util::now_nanos()));
+    }
+}
+
+/// Abandon `[from, to]`: tell decode in-band (it marks instruments stale and notifies
+/// subscribers) and, for resync, ask recovery for a snapshot.
+fn give_up(
+    q_out: &Arc<SpscQueue<Pkt>>,
+    tag: Tag,
+    policy: GiveUpPolicy,
+    from: u64,
+    to: u64,
+    recovery: &Option<RecoveryClient>,
+) {
+    let (action, label) = match policy {
+        GiveUpPolicy::Resync => (gap_action::RESYNC, "resync"),
+        _ => (gap_action::SKIP, "skip"),
+    };
+    warn!("[{tag}] giving up on seq {from}..={to}: {label}");
+    metrics::inc_merge_give_up(tag.0, label);
+    if policy == GiveUpPolicy::Resync {
+        if let Some(cli) = recovery {
+            cli.request_snapshot();
+        }
+    }
+    let gap = FeedGapV1 {
+        from_inclusive: from,
+        to_inclusive: to,
+        action,
+        stream_id: tag.1,
+    };
+    control(
+        q_out,
+        msg_type::FEED_GAP,
+        zerocopy::AsBytes::as_bytes(&gap),
+        tag.1,
+        from,
+    );
+}
+
+/// Re-anchor on a new venue session: tell decode in-band so subscribers see SEQ_RESET.
+fn seq_reset(q_out: &Arc<SpscQueue<Pkt>>, tag: Tag, kind: u8, prev: u64, anchor: u64, chan: &str) {
+    let label = if kind == reset_kind::EXPLICIT {
+        "explicit"
+    } else {
+        "heuristic"
+    };
+    warn!(
+        "[{tag}] sequence reset ({label}, seen on {chan}): expected {prev}, resuming at {anchor}"
+    );
+    metrics::inc_merge_seq_reset(tag.0, label);
+    let reset = SeqResetV1 {
+        new_seq: anchor,
+        prev_next_seq: prev,
+        kind,
+        stream_id: tag.1,
+    };
+    control(
+        q_out,
+        msg_type::SEQ_RESET,
+        zerocopy::AsBytes::as_bytes(&reset),
+        tag.1,
+        anchor,
+    );
+}
+
+/// Emit an in-band control packet: `[u16 LE message type][payload]` on `CHAN_CONTROL`.
+fn control(q_out: &Arc<SpscQueue<Pkt>>, msg: u16, body: &[u8], stream: u32, seq: u64) {
+    let mut buf = bytes::BytesMut::with_capacity(2 + body.len());
+    buf.extend_from_slice(&msg.to_le_bytes());
+    buf.extend_from_slice(body);
+    let ctl = Pkt {
+        len: buf.len(),
+        buf: PktBuf::Bytes(buf),
+        seq,
+        stream,
+        seq_span: 0,
+        seq_reset: false,
+        ts_nanos: 0,
+        chan: CHAN_CONTROL,
+        _ts_kind: crate::pool::TsKind::None,
+        merge_emit_ns: 0,
+    };
+    forward(q_out, ctl);
+}
+
+enum Verdict {
+    Normal,
+    /// Old-session packet around a reset; dropped
+    Stale,
+    /// Re-anchor at this sequence (`reset_kind::*`)
+    Reset(u64, u8),
+}
+
+/// Sequence reset detection. A packet flagged by the extractor resets at once; otherwise
+/// a line jumping back more than `backjump` below `next_seq` is held off until enough
+/// lines (two, or all if fewer) have done the same, then the merge re-anchors at the
+/// lowest sequence they reported. Lines still on the old session are ignored until they
+/// come back within reach of the new one.
+struct Resets {
+    backjump: u64,
+    /// How far ahead of `next_seq` a line that has not followed a reset yet is still trusted
+    lag_limit: u64,
+    needed: usize,
+    /// Per line: lowest sequence since it jumped back, until the reset is confirmed
+    jumped: Vec<Option<u64>>,
+    /// Per line: delivered on the current session
+    joined: Vec<bool>,
+    anchor: Option<u64>,
+}
+
+impl Resets {
+    fn new(lines: usize, backjump: u64, window_max: u64) -> Self {
+        Self {
+            backjump,
+            lag_limit: if backjump > 0 { backjump } else { window_max },
+            needed: lines.min(2),
+            jumped: vec![None; lines],
+            joined: vec![true; lines],
+            anchor: None,
+        }
+    }
+
+    fn classify(&mut self, src: usize, s: u64, marker: bool, next_seq: u64) -> Verdict {
+        if marker {
+            self.joined[src] = true;
+            // Another line's copy of the reset we already applied
+            if self.anchor == Some(s) && s <= next_seq && next_seq - s <= self.lag_limit {
+                return Verdict::Normal;
+            }
+            return self.reset(src, s, reset_kind::EXPLICIT);
+        }
+        if !self.joined[src] {
+            if s > next_seq.saturating_add(self.lag_limit) {
+                return Verdict::Stale;
+            }
+            self.joined[src] = true;
+        }
+        if self.backjump == 0 || next_seq.saturating_sub(s) <= self.backjump {
+            self.jumped[src] = None;
+            return Verdict::Normal;
+        }
+        self.jumped[src] = Some(self.jumped[src].map_or(s, |j| j.min(s)));
+        if self.jumped.iter().flatten().count() < self.needed {
+            return Verdict::Stale;
+        }
+        let anchor = self.jumped.iter().flatten().copied().min().unwrap_or(s);
+        self.reset(src, anchor, reset_kind::HEURISTIC)
+    }
+
+    fn reset(&mut self, src: usize, anchor: u64, kind: u8) -> Verdict {
+        for (i, joined) in self.joined.iter_mut().enumerate() {
+            *joined = i == src || self.jumped[i].is_some();
+        }
+        self.jumped.fill(None);
+        self.anchor = Some(anchor);
+        Verdict::Reset(anchor, kind)
+    }
+}
+
+/// The hole at `next_seq` while later packets wait in the ring
+#[derive(Clone, Copy)]
+struct Hole {
+    start: u64,
+    held_since_ns: u64,
+    declared: bool,
+}
+
+/// Lowest buffered sequence above `next_seq`, and when the oldest pending packet was buffered.
+fn scan_pending(
+    ring: &[Option<(u64, Pkt)>],
+    held_since: &[u64],
+    next_seq: u64,
+) -> Option<(u64, u64)> {
+    let mut lowest: Option<u64> = None;
+    let mut oldest = u64::MAX;
+    for (slot, since) in ring.iter().zip(held_since) {
+        if let Some((s, _)) = slot {
+            if *s > next_seq {
+                lowest = Some(lowest.map_or(*s, |l| l.min(*s)));
+                oldest = oldest.min(*since);
+            }
+        }
+    }
+    lowest.map(|l| (l, oldest))
+}
+
+/// Preferred-line hysteresis. Starts on the primary; another line takes over after
+/// `switch_after` consecutive in-order wins, and a non-primary preferred line hands back
+/// to the primary after `revert_after` forwards of its own. Switches also need a minimum
+/// dwell since the previous one.
+struct Arbiter {
+    primary: usize,
+    preferred: usize,
+    switch_after: Vec<u32>,
+    revert_after: Vec<u32>,
+    streak_src: usize,
+    streak: u32,
+    last_switch_ns: u64,
+    min_dwell_ns: u64,
+}
+
+impl Arbiter {
+    fn new(sources: &[MergeSource], primary: usize, dwell_ns: u64) -> Self {
+        Self {
+            primary,
+            preferred: primary,
+            switch_after: sources.iter().map(|s| s.switch_after.max(1)).collect(),
+            revert_after: sources.iter().map(|s| s.revert_after.max(1)).collect(),
+            streak_src: primary,
+            streak: 0,
+            last_switch_ns: crate::util::now_nanos(),
+            min_dwell_ns: if dwell_ns == 0 { 2_000_000 } else { dwell_ns },
+        }
+    }
+
+    /// Record that `src` forwarded the next sequence; true if the preference changed.
+    #[inline]
+    fn observe(&mut self, src: usize, now_ns: u64) -> bool {
+        if src == self.streak_src {
+            self.streak = self.streak.saturating_add(1);
+        } else {
+            self.streak_src = src;
+            self.streak = 1;
+        }
+        let target = if src != self.preferred && self.streak >= self.switch_after[src] {
+            src
+        } else if src == self.preferred
+            && src != self.primary
+            && self.streak >= self.revert_after[src]
+        {
+            self.primary
+        } else {
+            return false;
+        };
+        if now_ns.saturating_sub(self.last_switch_ns) < self.min_dwell_ns {
+            return false;
+        }
+        self.preferred = target;
+        self.streak = 0;
+        self.last_switch_ns = now_ns;
+        true
+    }
+}
+
+fn publish_preferred(part: &str, names: &[&str], preferred: usize) {
+    for (i, name) in names.iter().enumerate() {
+        metrics::set_merge_preferred_source(part, name, i == preferred);
+    }
+    metrics::set_merge_preferred_is_a(part, preferred == 0);
+}
+
+/// Metric label of the line a buffered packet came in on
+#[inline]
+fn line_label<'a>(names: &[&'a str], chan: u8) -> &'a str {
+    names.get(chan as usize).copied().unwrap_or("R")
+}
+
 #[inline]
 fn forward(q_out: &Arc<SpscQueue<Pkt>>, mut pkt: Pkt) {
     // Stage timing and mark merge emit time
@@ -319,15 +832,54 @@ fn forward(q_out: &Arc<SpscQueue<Pkt>>, mut pkt: Pkt) {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use bytes::BytesMut;
+    use zerocopy::FromBytes;
+
+    /// Merge from seq 1 with an 8-packet window and no timers; tests override the rest
+    pub(crate) fn cfg() -> MergeConfig {
+        MergeConfig {
+            next_seq: 1,
+            anchor_on_first: false,
+            reorder_window: 8,
+            max_pending: 64,
+            dwell_ns: 0,
+            adaptive: false,
+            reorder_window_max: 8,
+            gap_timeout_ns: 0,
+            give_up_ns: 0,
+            on_give_up: GiveUpPolicy::Wait,
+            reset_backjump: 0,
+            verify: None,
+            race: None,
+            max_streams: 64,
+            partition: "0".into(),
+        }
+    }
+
+    pub(crate) fn lines(queues: Vec<Arc<SpscQueue<Pkt>>>) -> Vec<MergeSource> {
+        queues
+            .into_iter()
+            .enumerate()
+            .map(|(i, q)| MergeSource {
+                name: ["A", "B", "wireless"][i].into(),
+                queues: vec![q],
+                priority: i as u32,
+                switch_after: 2,
+                revert_after: 8,
+            })
+            .collect()
+    }
 
     fn pkt(seq: u64, chan: u8) -> Pkt {
         Pkt {
             buf: crate::pool::PktBuf::Bytes(BytesMut::new()),
             len: 0,
             seq,
+            stream: 0,
+            seq_span: 1,
+            seq_reset: false,
             ts_nanos: 0,
             chan,
             _ts_kind: crate::pool::TsKind::Sw,
@@ -348,22 +900,18 @@ mod tests {
         let sd = shutdown.clone();
         let t = std::thread::spawn(move || {
             let cfg = MergeConfig {
-                next_seq: 1,
                 reorder_window: 4,
-                max_pending: 64,
-                dwell_ns: 0,
-                adaptive: false,
-                reorder_window_max: 8,
+                ..cfg()
             };
-            let _ = merge_loop(vec![qa], vec![qb], qo, cfg, sd, None, None);
+            let _ = merge_loop(lines(vec![qa, qb]), qo, cfg, sd, None, None);
         });
 
         // Feed out-of-order within window and duplicates across channels
-        let _ = q_a.push(pkt(1, b'A'));
-        let _ = q_b.push(pkt(3, b'B'));
-        let _ = q_a.push(pkt(2, b'A'));
-        let _ = q_b.push(pkt(2, b'B')); // duplicate
-        let _ = q_a.push(pkt(4, b'A'));
+        let _ = q_a.push(pkt(1, 0));
+        let _ = q_b.push(pkt(3, 1));
+        let _ = q_a.push(pkt(2, 0));
+        let _ = q_b.push(pkt(2, 1)); // duplicate
+        let _ = q_a.push(pkt(4, 0));
 
         // wait until we see at least 4 outputs or timeout
         let deadline = std::time::Instant::now() + std::time::Duration::from_millis(200);
@@ -379,4 +927,578 @@ mod tests {
         }
         assert_eq!(seqs, vec![1, 2, 3, 4]);
     }
+
+    #[test]
+    fn merge_advances_by_seq_span() {
+        let q_a: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_b: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+
+        let qa = q_a.clone();
+        let qb = q_b.clone();
+        let qo = q_out.clone();
+        let sd = shutdown.clone();
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                reorder_window: 16,
+                reorder_window_max: 32,
+                ..cfg()
+            };
+            let _ = merge_loop(lines(vec![qa, qb]), qo, cfg, sd, None, None);
+        });
+
+        // Message-numbered feed: packets cover [1..3], [4..5], [6..9]
+        let mut p = pkt(4, 0);
+        p.seq_span = 2;
+        let _ = q_a.push(p);
+        let mut p = pkt(1, 1);
+        p.seq_span = 3;
+        let _ = q_b.push(p);
+        let mut p = pkt(6, 0);
+        p.seq_span = 4;
+        let _ = q_a.push(p);
+        let _ = q_b.push(pkt(4, 1)); // duplicate of the A copy
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(200);
+        while q_out.len() < 3 && std::time::Instant::now() < deadline {
+            crate::util::spin_wait(1000);
+        }
+        shutdown.raise();
+        let _ = t.join();
+
+        let mut seqs = Vec::new();
+        while let Some(p) = q_out.pop() {
+            seqs.push(p.seq);
+        }
+        assert_eq!(seqs, vec![1, 4, 6]);
+    }
+
+    #[test]
+    fn merge_takes_first_copy_from_any_line() {
+        let qs: Vec<Arc<SpscQueue<Pkt>>> = (0..3).map(|_| Arc::new(SpscQueue::new(64))).collect();
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+
+        let srcs = lines(qs.clone());
+        let qo = q_out.clone();
+        let sd = shutdown.clone();
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                reorder_window_max: 16,
+                ..cfg()
+            };
+            let _ = merge_loop(srcs, qo, cfg, sd, None, None);
+        });
+
+        // A and B both miss 2; the wireless line fills it
+        let _ = qs[0].push(pkt(1, 0));
+        let _ = qs[1].push(pkt(1, 1));
+        let _ = qs[1].push(pkt(3, 1));
+        let _ = qs[2].push(pkt(2, 2));
+        let _ = qs[2].push(pkt(3, 2)); // duplicate
+        let _ = qs[0].push(pkt(4, 0));
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(200);
+        while q_out.len() < 4 && std::time::Instant::now() < deadline {
+            crate::util::spin_wait(1000);
+        }
+        shutdown.raise();
+        let _ = t.join();
+
+        let mut seqs = Vec::new();
+        while let Some(p) = q_out.pop() {
+            seqs.push(p.seq);
+        }
+        assert_eq!(seqs, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn arbiter_switches_per_line_and_reverts_to_primary() {
+        let mut srcs = lines(
+            (0..3)
+                .map(|_| Arc::new(SpscQueue::new(4)))
+                .collect::<Vec<_>>(),
+        );
+        srcs[2].switch_after = 3;
+        srcs[2].revert_after = 4;
+        let mut arb = Arbiter::new(&srcs, 0, 1);
+        let t0 = arb.last_switch_ns + 10;
+
+        // Interleaved wins never build a streak
+        assert!(!arb.observe(1, t0));
+        assert!(!arb.observe(2, t0));
+        assert!(!arb.observe(1, t0));
+        // Wireless needs three in a row
+        assert!(!arb.observe(2, t0));
+        assert!(!arb.observe(2, t0));
+        assert!(arb.observe(2, t0 + 10));
+        assert_eq!(arb.preferred, 2);
+        // Dwell holds the preference even once the revert streak is reached
+        arb.min_dwell_ns = 1_000;
+        for _ in 0..4 {
+            assert!(!arb.observe(2, t0 + 20));
+        }
+        assert!(arb.observe(2, t0 + 2_000));
+        assert_eq!(arb.preferred, 0);
+    }
+
+    /// Gap requests as `(from, to)`, and the stream of each in `.1`
+    #[derive(Default)]
+    struct Capture(
+        std::sync::Mutex<Vec<(u64, u64)>>,
+        std::sync::Mutex<Vec<u32>>,
+    );
+
+    impl crate::recovery::Replayer for Capture {
+        fn notify_gap(&self, from: u64, to: u64) {
+            self.0.lock().unwrap().push((from, to));
+        }
+        fn notify_stream_gap(&self, stream: u32, from: u64, to: u64) {
+            self.1.lock().unwrap().push(stream);
+            self.notify_gap(from, to);
+        }
+    }
+
+    #[test]
+    fn merge_declares_in_window_hole_after_timeout() {
+        let q_a: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_b: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_rec: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+        let gaps = Arc::new(Capture::default());
+
+        let srcs = lines(vec![q_a.clone(), q_b.clone()]);
+        let (qo, qr, sd) = (q_out.clone(), q_rec.clone(), shutdown.clone());
+        let cli: RecoveryClient = gaps.clone();
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                reorder_window: 64,
+                reorder_window_max: 64,
+                gap_timeout_ns: 5_000_000,
+                ..cfg()
+            };
+            let _ = merge_loop(srcs, qo, cfg, sd, Some(cli), Some(qr));
+        });
+
+        // End of a burst: 3 and 4 lost on both lines, nothing follows beyond the window
+        for s in [1, 2, 5, 6] {
+            let _ = q_a.push(pkt(s, 0));
+        }
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(500);
+        while gaps.0.lock().unwrap().is_empty() && std::time::Instant::now() < deadline {
+            crate::util::spin_wait(1000);
+        }
+        assert_eq!(*gaps.0.lock().unwrap(), vec![(3, 4)]);
+        assert_eq!(q_out.len(), 2);
+
+        // Recovery fills the hole and releases the held packets
+        let _ = q_rec.push(pkt(3, crate::pool::CHAN_RECOVERY));
+        let _ = q_rec.push(pkt(4, crate::pool::CHAN_RECOVERY));
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(200);
+        while q_out.len() < 6 && std::time::Instant::now() < deadline {
+            crate::util::spin_wait(1000);
+        }
+        shutdown.raise();
+        let _ = t.join();
+
+        let mut seqs = Vec::new();
+        while let Some(p) = q_out.pop() {
+            seqs.push(p.seq);
+        }
+        assert_eq!(seqs, vec![1, 2, 3, 4, 5, 6]);
+        // Declared once per hole
+        assert_eq!(gaps.0.lock().unwrap().len(), 1);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Out {
+        Seq(u64),
+        Gap(u8, u64, u64),
+        Reset(u8, u64, u64),
+    }
+
+    fn collect(q_out: &SpscQueue<Pkt>) -> Vec<Out> {
+        let mut out = Vec::new();
+        while let Some(p) = q_out.pop() {
+            if p.chan != CHAN_CONTROL {
+                out.push(Out::Seq(p.seq));
+                continue;
+            }
+            let (msg, body) = p.payload().split_at(2);
+            match u16::from_le_bytes([msg[0], msg[1]]) {
+                msg_type::FEED_GAP => {
+                    let g = FeedGapV1::read_from(body).unwrap();
+                    out.push(Out::Gap(g.action, g.from_inclusive, g.to_inclusive));
+                }
+                msg_type::SEQ_RESET => {
+                    let r = SeqResetV1::read_from(body).unwrap();
+                    out.push(Out::Reset(r.kind, r.prev_next_seq, r.new_seq));
+                }
+                other => panic!("unexpected control type {other}"),
+            }
+        }
+        out
+    }
+
+    /// Push `(seq, delay_ms)` on line A against a merge giving up after 5ms.
+    fn run_give_up(pushes: &[(u64, u64)], policy: GiveUpPolicy) -> (Vec<Out>, Vec<(u64, u64)>) {
+        let q_a: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_b: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+        let gaps = Arc::new(Capture::default());
+
+        let srcs = lines(vec![q_a.clone(), q_b.clone()]);
+        let (qo, sd) = (q_out.clone(), shutdown.clone());
+        let cli: RecoveryClient = gaps.clone();
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                reorder_window: 4,
+                reorder_window_max: 4,
+                gap_timeout_ns: 1_000_000,
+                give_up_ns: 5_000_000,
+                on_give_up: policy,
+                ..cfg()
+            };
+            let _ = merge_loop(srcs, qo, cfg, sd, Some(cli), None);
+        });
+        for &(seq, wait_ms) in pushes {
+            std::thread::sleep(std::time::Duration::from_millis(wait_ms));
+            let _ = q_a.push(pkt(seq, 0));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        shutdown.raise();
+        let _ = t.join();
+
+        let requested = gaps.0.lock().unwrap().clone();
+        (collect(&q_out), requested)
+    }
+
+    #[test]
+    fn merge_skips_unrecovered_hole_to_buffered_packets() {
+        let (out, requested) = run_give_up(&[(1, 0), (3, 0), (4, 0)], GiveUpPolicy::Skip);
+        assert_eq!(requested, vec![(2, 2)]);
+        let skip = gap_action::SKIP;
+        assert_eq!(
+            out,
+            vec![Out::Seq(1), Out::Gap(skip, 2, 2), Out::Seq(3), Out::Seq(4)]
+        );
+    }
+
+    #[test]
+    fn merge_resyncs_at_next_packet_after_window_overflow() {
+        // 9 lands beyond the window and is dropped; nothing is buffered to resume at,
+        // so the merge picks up at the next packet once it has given up
+        let (out, requested) = run_give_up(&[(1, 0), (9, 0), (10, 20)], GiveUpPolicy::Resync);
+        assert_eq!(requested, vec![(2, 8)]);
+        let resync = gap_action::RESYNC;
+        assert_eq!(out, vec![Out::Seq(1), Out::Gap(resync, 2, 9), Out::Seq(10)]);
+    }
+
+    #[test]
+    fn merge_keeps_separate_order_per_stream() {
+        let q_a: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_b: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+        let gaps = Arc::new(Capture::default());
+        // Stream 1 arrives reordered; stream 2 is missing 11
+        for (stream, seq) in [
+            (1, 10),
+            (2, 10),
+            (1, 12),
+            (2, 12),
+            (1, 11),
+            (2, 13),
+            (1, 13),
+        ] {
+            let mut p = pkt(seq, 0);
+            p.stream = stream;
+            let _ = q_a.push(p);
+        }
+
+        let srcs = lines(vec![q_a, q_b]);
+        let (qo, sd) = (q_out.clone(), shutdown.clone());
+        let cli: RecoveryClient = gaps.clone();
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                next_seq: 10,
+                gap_timeout_ns: 2_000_000,
+                ..cfg()
+            };
+            let _ = merge_loop(srcs, qo, cfg, sd, Some(cli), None);
+        });
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        shutdown.raise();
+        let _ = t.join();
+
+        let mut out = Vec::new();
+        while let Some(p) = q_out.pop() {
+            out.push((p.stream, p.seq));
+        }
+        // The hole in stream 2 holds back only stream 2
+        assert_eq!(out, vec![(1, 10), (2, 10), (1, 11), (1, 12), (1, 13)]);
+        assert_eq!(*gaps.0.lock().unwrap(), vec![(11, 11)]);
+        assert_eq!(*gaps.1.lock().unwrap(), vec![2]);
+    }
+
+    /// Queue `(seq, reset marker)` per line up front, then run a merge starting at `start`.
+    fn run_resets(
+        per_line: &[&[(u64, bool)]],
+        start: u64,
+        backjump: u64,
+    ) -> (Vec<Out>, Vec<(u64, u64)>) {
+        let qs: Vec<Arc<SpscQueue<Pkt>>> = per_line
+            .iter()
+            .enumerate()
+            .map(|(line, pkts)| {
+                let q = Arc::new(SpscQueue::new(64));
+                for &(seq, marker) in pkts.iter() {
+                    let mut p = pkt(seq, line as u8);
+                    p.seq_reset = marker;
+                    let _ = q.push(p);
+                }
+                q
+            })
+            .collect();
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+        let gaps = Arc::new(Capture::default());
+
+        let srcs = lines(qs);
+        let (qo, sd) = (q_out.clone(), shutdown.clone());
+        let cli: RecoveryClient = gaps.clone();
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                next_seq: start,
+                reset_backjump: backjump,
+                ..cfg()
+            };
+            let _ = merge_loop(srcs, qo, cfg, sd, Some(cli), None);
+        });
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        shutdown.raise();
+        let _ = t.join();
+        let requested = gaps.0.lock().unwrap().clone();
+        (collect(&q_out), requested)
+    }
+
+    #[test]
+    fn merge_reanchors_on_explicit_reset_marker() {
+        let day = [(500, false), (501, false), (1, true), (2, false)];
+        let (out, requested) = run_resets(&[&day, &day], 500, 0);
+        assert!(requested.is_empty());
+        // B's copy of the marker is a plain duplicate
+        let explicit = reset_kind::EXPLICIT;
+        assert_eq!(
+            out,
+            vec![
+                Out::Seq(500),
+                Out::Seq(501),
+                Out::Reset(explicit, 502, 1),
+                Out::Seq(1),
+                Out::Seq(2),
+            ]
+        );
+    }
+
+    /// Feed the same raw packets on lines A and B, sequenced by `x`, from `start`.
+    fn run_feed(x: &dyn crate::parser::SeqExtractor, pkts: &[Vec<u8>], start: u64) -> Vec<Out> {
+        let qs: Vec<Arc<SpscQueue<Pkt>>> = (0..2u8)
+            .map(|line| {
+                let q = Arc::new(SpscQueue::new(64));
+                for raw in pkts {
+                    let (stream, seq) = x.extract_stream_seq(raw).unwrap();
+                    let mut p = pkt(seq, line);
+                    p.buf = crate::pool::PktBuf::Bytes(BytesMut::from(&raw[..]));
+                    p.len = raw.len();
+                    p.stream = stream;
+                    p.seq_span = x.seq_span(raw);
+                    p.seq_reset = x.is_seq_reset(raw);
+                    let _ = q.push(p);
+                }
+                q
+            })
+            .collect();
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+        let srcs = lines(qs);
+        let (qo, sd) = (q_out.clone(), shutdown.clone());
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                next_seq: start,
+                ..cfg()
+            };
+            let _ = merge_loop(srcs, qo, cfg, sd, None, None);
+        });
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        shutdown.raise();
+        let _ = t.join();
+        collect(&q_out)
+    }
+
+    #[test]
+    fn merge_reanchors_on_venue_reset_packets() {
+        let explicit = reset_kind::EXPLICIT;
+
+        // MoldUDP64: a new session numbers its messages from 1 again
+        let mold = |seq: u64, count: u16| {
+            let mut p = b"SESSION001".to_vec();
+            p.extend_from_slice(&seq.to_be_bytes());
+            p.extend_from_slice(&count.to_be_bytes());
+            for _ in 0..count {
+                p.extend_from_slice(&[0, 1, b'Z']);
+            }
+            p
+        };
+        let out = run_feed(
+            &crate::decoder_itch::MoldSeq,
+            &[mold(500, 2), mold(502, 1), mold(1, 2), mold(3, 1)],
+            500,
+        );
+        assert_eq!(
+            out,
+            vec![
+                Out::Seq(500),
+                Out::Seq(502),
+                Out::Reset(explicit, 503, 1),
+                Out::Seq(1),
+                Out::Seq(3),
+            ]
+        );
+
+        // PITCH: a Unit Clear restarts the unit's sequence
+        let unit = |seq: u32, ty: u8| {
+            let mut p = 14u16.to_le_bytes().to_vec();
+            p.extend_from_slice(&[1, 1]);
+            p.extend_from_slice(&seq.to_le_bytes());
+            p.extend_from_slice(&[6, ty, 0, 0, 0, 0]);
+            p
+        };
+        let out = run_feed(
+            &crate::decoder_pitch::PitchUnitSeq::new(None),
+            &[
+                unit(1000, 0x20),
+                unit(1001, 0x20),
+                unit(1, 0x97),
+                unit(2, 0x20),
+            ],
+            1000,
+        );
+        assert_eq!(
+            out,
+            vec![
+                Out::Seq(1000),
+                Out::Seq(1001),
+                Out::Reset(explicit, 1002, 1),
+                Out::Seq(1),
+                Out::Seq(2),
+            ]
+        );
+
+        // MDP 3.0: a ChannelReset keeps the packet sequence but restarts the books
+        let mdp3 = |seq: u32, template: u16| {
+            let mut p = seq.to_le_bytes().to_vec();
+            p.extend_from_slice(&0u64.to_le_bytes());
+            p.extend_from_slice(&12u16.to_le_bytes());
+            p.extend_from_slice(&2u16.to_le_bytes());
+            p.extend_from_slice(&template.to_le_bytes());
+            p.extend_from_slice(&[1, 0, 9, 0, 0, 0]);
+            p
+        };
+        let out = run_feed(
+            &crate::decoder_mdp3::Mdp3PacketSeq,
+            &[mdp3(77, 30), mdp3(78, 4), mdp3(79, 30)],
+            77,
+        );
+        assert_eq!(
+            out,
+            vec![
+                Out::Seq(77),
+                Out::Reset(explicit, 78, 78),
+                Out::Seq(78),
+                Out::Seq(79),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_anchors_at_first_sequence_seen() {
+        let q_a: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_b: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(256));
+        let shutdown = Arc::new(crate::util::BarrierFlag::default());
+        let gaps = Arc::new(Capture::default());
+        for seq in [5000, 5002, 5001] {
+            let _ = q_a.push(pkt(seq, 0));
+        }
+
+        let srcs = lines(vec![q_a, q_b]);
+        let (qo, sd) = (q_out.clone(), shutdown.clone());
+        let cli: RecoveryClient = gaps.clone();
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                anchor_on_first: true,
+                gap_timeout_ns: 1_000_000,
+                ..cfg()
+            };
+            let _ = merge_loop(srcs, qo, cfg, sd, Some(cli), None);
+        });
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        shutdown.raise();
+        let _ = t.join();
+
+        // Nothing below the first packet is missing
+        let out = collect(&q_out);
+        assert_eq!(out, vec![Out::Seq(5000), Out::Seq(5001), Out::Seq(5002)]);
+        assert!(gaps.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_detects_backjump_on_two_lines_and_ignores_lagging_line() {
+        let a = [
+            (1000, false),
+            (1001, false),
+            (1, false),
+            (2, false),
+            (3, false),
+        ];
+        let b = [
+            (1000, false),
+            (1001, false),
+            (1, false),
+            (2, false),
+            (3, false),
+        ];
+        // The third line is still on the old session when the reset is confirmed
+        let w = [(1000, false), (1001, false), (1002, false), (4, false)];
+        let (out, requested) = run_resets(&[&a, &b, &w], 1000, 100);
+        assert!(requested.is_empty());
+        let heuristic = reset_kind::HEURISTIC;
+        assert_eq!(
+            out,
+            vec![
+                Out::Seq(1000),
+                Out::Seq(1001),
+                Out::Reset(heuristic, 1002, 1),
+                Out::Seq(1),
+                Out::Seq(2),
+                Out::Seq(3),
+                Out::Seq(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_line_backjump_is_not_a_reset() {
+        let mut r = Resets::new(2, 100, 8);
+        assert!(matches!(r.classify(0, 5, false, 1000), Verdict::Stale));
+        // B keeps going on the current session; A alone never confirms
+        assert!(matches!(r.classify(1, 1000, false, 1000), Verdict::Normal));
+        assert!(matches!(r.classify(0, 6, false, 1001), Verdict::Stale));
+        // A short step back is an ordinary duplicate
+        assert!(matches!(r.classify(1, 950, false, 1001), Verdict::Normal));
+    }
 }
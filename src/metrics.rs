@@ -3,8 +3,8 @@ use crossbeam_channel::Sender;
 use hashbrown::HashMap;
 use once_cell::sync::Lazy;
 use prometheus::{
-    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
-    Registry, TextEncoder,
+    Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
 };
 use std::net::ToSocketAddrs;
 use std::sync::Mutex;
@@ -15,7 +15,7 @@ static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
 static RX_PACKETS: Lazy<IntCounterVec> = Lazy::new(|| {
     let c = IntCounterVec::new(
         Opts::new("rx_packets", "Packets received per channel"),
-        &["chan"],
+        &["partition", "chan"],
     )
     .expect("rx_packets");
     REGISTRY.register(Box::new(c.clone())).ok();
@@ -25,7 +25,7 @@ static RX_PACKETS: Lazy<IntCounterVec> = Lazy::new(|| {
 static RX_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
     let c = IntCounterVec::new(
         Opts::new("rx_bytes", "Bytes received per channel"),
-        &["chan"],
+        &["partition", "chan"],
     )
     .expect("rx_bytes");
     REGISTRY.register(Box::new(c.clone())).ok();
@@ -35,34 +35,69 @@ static RX_BYTES: Lazy<IntCounterVec> = Lazy::new(|| {
 static RX_DROPS: Lazy<IntCounterVec> = Lazy::new(|| {
     let c = IntCounterVec::new(
         Opts::new("rx_drops", "Dropped packets due to backpressure"),
-        &["chan"],
+        &["partition", "chan"],
     )
     .expect("rx_drops");
     REGISTRY.register(Box::new(c.clone())).ok();
     c
 });
 
-static MERGE_DUPS: Lazy<IntCounter> = Lazy::new(|| {
-    let c = IntCounter::new("merge_duplicates", "Duplicate packets filtered by merge")
-        .expect("merge_duplicates");
+static CHAOS_PACKETS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "chaos_packets",
+            "Packets impaired by line fault injection (loss, burst, cut, duplicate, reorder)",
+        ),
+        &["partition", "chan", "action"],
+    )
+    .expect("chaos_packets");
     REGISTRY.register(Box::new(c.clone())).ok();
     c
 });
 
-static MERGE_GAPS: Lazy<IntCounter> = Lazy::new(|| {
-    let c = IntCounter::new(
-        "merge_gaps",
-        "Gaps detected by merge (out-of-band recovery advisable)",
+static RX_FLOWS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new(
+            "rx_flows",
+            "Distinct RX flow hashes seen per packet ring worker (capped at 64)",
+        ),
+        &["chan", "queue"],
+    )
+    .expect("rx_flows");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static MERGE_DUPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new("merge_duplicates", "Duplicate packets filtered by merge"),
+        &["partition"],
+    )
+    .expect("merge_duplicates");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_GAPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_gaps",
+            "Gaps detected by merge (out-of-band recovery advisable)",
+        ),
+        &["partition"],
     )
     .expect("merge_gaps");
     REGISTRY.register(Box::new(c.clone())).ok();
     c
 });
 
-static MERGE_OOO: Lazy<IntCounter> = Lazy::new(|| {
-    let c = IntCounter::new(
-        "merge_out_of_order",
-        "Out-of-order packets buffered within reorder window",
+static MERGE_OOO: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_out_of_order",
+            "Out-of-order packets buffered within reorder window",
+        ),
+        &["partition"],
     )
     .expect("merge_out_of_order");
     REGISTRY.register(Box::new(c.clone())).ok();
@@ -76,7 +111,7 @@ static MERGE_FORWARD_BY_CHAN: Lazy<IntCounterVec> = Lazy::new(|| {
             "merge_forward_packets",
             "Packets forwarded by merge per channel",
         ),
-        &["chan"],
+        &["partition", "chan"],
     )
     .expect("merge_forward_packets");
     REGISTRY.register(Box::new(c.clone())).ok();
@@ -86,33 +121,285 @@ static MERGE_FORWARD_BY_CHAN: Lazy<IntCounterVec> = Lazy::new(|| {
 static MERGE_GAPS_BY_CHAN: Lazy<IntCounterVec> = Lazy::new(|| {
     let c = IntCounterVec::new(
         Opts::new("merge_gaps_by_chan", "Gaps signaled by merge per channel"),
-        &["chan"],
+        &["partition", "chan"],
     )
     .expect("merge_gaps_by_chan");
     REGISTRY.register(Box::new(c.clone())).ok();
     c
 });
 
-static MERGE_FAILOVERS: Lazy<IntCounter> = Lazy::new(|| {
-    let c = IntCounter::new(
-        "merge_failovers",
-        "Number of preferred-channel switches due to hysteresis",
+static MERGE_GAP_TIMEOUTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_gap_timeouts",
+            "In-window holes declared as gaps after merge.gap_timeout_ns",
+        ),
+        &["partition"],
+    )
+    .expect("merge_gap_timeouts");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_GIVE_UPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_give_ups",
+            "Gaps abandoned after merge.give_up_after_ns, by action",
+        ),
+        &["partition", "action"],
+    )
+    .expect("merge_give_ups");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_DUP_VERIFIED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_dup_verified",
+            "Duplicates compared against the forwarded copy ([merge.verify])",
+        ),
+        &["partition"],
+    )
+    .expect("merge_dup_verified");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_DUP_MISMATCHES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_dup_mismatches",
+            "Duplicates whose payload differs from the forwarded copy, by late line",
+        ),
+        &["partition", "line"],
+    )
+    .expect("merge_dup_mismatches");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_LINE_WINS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_line_wins",
+            "Sequences whose earliest copy (by RX timestamp) came from this line",
+        ),
+        &["partition", "line"],
+    )
+    .expect("merge_line_wins");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_LINE_MISSED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_line_missed",
+            "Sequences another line delivered but this line never did",
+        ),
+        &["partition", "line"],
+    )
+    .expect("merge_line_missed");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_LINE_RACE_DELTA: Lazy<HistogramVec> = Lazy::new(|| {
+    // Buckets in seconds: 100ns .. 10ms
+    let buckets = vec![
+        1e-7, 2.5e-7, 5e-7, 1e-6, 2.5e-6, 5e-6, 1e-5, 2.5e-5, 5e-5, 1e-4, 2.5e-4, 5e-4, 1e-3, 1e-2,
+    ];
+    let h = HistogramVec::new(
+        HistogramOpts::new(
+            "merge_line_race_delta_seconds",
+            "RX time of a later copy minus the earliest copy of the same sequence",
+        )
+        .buckets(buckets),
+        &["partition", "winner", "loser"],
+    )
+    .expect("merge_line_race_delta");
+    REGISTRY.register(Box::new(h.clone())).ok();
+    h
+});
+
+static MERGE_LINE_WIN_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    let g = GaugeVec::new(
+        Opts::new(
+            "merge_line_win_ratio",
+            "Share of sequences won by this line over the [merge.race] window",
+        ),
+        &["partition", "line"],
+    )
+    .expect("merge_line_win_ratio");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static MERGE_LINE_LOSS_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    let g = GaugeVec::new(
+        Opts::new(
+            "merge_line_loss_ratio",
+            "Share of sequences missed by this line over the [merge.race] window",
+        ),
+        &["partition", "line"],
+    )
+    .expect("merge_line_loss_ratio");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static MERGE_SEQ_RESETS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_seq_resets",
+            "Venue sequence resets the merge re-anchored on, by kind",
+        ),
+        &["partition", "kind"],
+    )
+    .expect("merge_seq_resets");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static RECOVERY_GAP_FILL: Lazy<HistogramVec> = Lazy::new(|| {
+    // Buckets in seconds around the 100ms replay SLO
+    let buckets = vec![
+        1e-3, 5e-3, 1e-2, 2.5e-2, 5e-2, 7.5e-2, 1e-1, 2.5e-1, 5e-1, 1.0, 5.0,
+    ];
+    let h = HistogramVec::new(
+        HistogramOpts::new(
+            "recovery_gap_fill_seconds",
+            "Time from a gap request until replay filled the whole range",
+        )
+        .buckets(buckets),
+        &["partition"],
+    )
+    .expect("recovery_gap_fill_seconds");
+    REGISTRY.register(Box::new(h.clone())).ok();
+    h
+});
+
+static RECOVERY_OUTSTANDING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new("recovery_outstanding_gaps", "Gap ranges waiting for replay"),
+        &["partition"],
+    )
+    .expect("recovery_outstanding_gaps");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static RECOVERY_EVENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "recovery_events",
+            "Replay retries, partial fills, abandoned ranges and endpoint failovers",
+        ),
+        &["partition", "event"],
+    )
+    .expect("recovery_events");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static RECOVERY_SPILLED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "recovery_requests_spilled",
+            "Recovery requests that found the channel full, by outcome",
+        ),
+        &["outcome"],
+    )
+    .expect("recovery_requests_spilled");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_STREAMS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new(
+            "merge_streams",
+            "Sequence streams the merge keeps reorder state for",
+        ),
+        &["partition"],
+    )
+    .expect("merge_streams");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static MERGE_STREAM_DROPS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_stream_drops",
+            "Packets dropped because their stream exceeded merge.max_streams",
+        ),
+        &["partition"],
+    )
+    .expect("merge_stream_drops");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static MERGE_HOLE_HOLD: Lazy<HistogramVec> = Lazy::new(|| {
+    // Buckets in seconds: 1us .. 1s
+    let buckets = vec![
+        1e-6, 5e-6, 1e-5, 5e-5, 1e-4, 5e-4, 1e-3, 5e-3, 1e-2, 5e-2, 1e-1, 1.0,
+    ];
+    let h = HistogramVec::new(
+        HistogramOpts::new(
+            "merge_hole_hold_seconds",
+            "Time packets behind a sequence hole were held before it closed",
+        )
+        .buckets(buckets),
+        &["partition"],
+    )
+    .expect("merge_hole_hold");
+    REGISTRY.register(Box::new(h.clone())).ok();
+    h
+});
+
+static MERGE_FAILOVERS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "merge_failovers",
+            "Number of preferred-channel switches due to hysteresis",
+        ),
+        &["partition"],
     )
     .expect("merge_failovers");
     REGISTRY.register(Box::new(c.clone())).ok();
     c
 });
 
-static MERGE_PREFERRED_IS_A: Lazy<IntGauge> = Lazy::new(|| {
-    let g = IntGauge::new(
-        "merge_preferred_is_a",
-        "1 if channel A is currently preferred, else 0",
+static MERGE_PREFERRED_IS_A: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new(
+            "merge_preferred_is_a",
+            "1 if channel A is currently preferred, else 0",
+        ),
+        &["partition"],
     )
     .expect("merge_preferred_is_a");
     REGISTRY.register(Box::new(g.clone())).ok();
     g
 });
 
+static MERGE_PREFERRED_SOURCE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    let g = IntGaugeVec::new(
+        Opts::new(
+            "merge_preferred_source",
+            "1 for the line merge currently polls first, else 0",
+        ),
+        &["partition", "source"],
+    )
+    .expect("merge_preferred_source");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
 static DECODE_PKTS: Lazy<IntCounter> = Lazy::new(|| {
     let c =
         IntCounter::new("decode_packets", "Packets processed by decoder").expect("decode_packets");
@@ -137,6 +424,92 @@ static BOOK_LIVE_ORDERS: Lazy<IntGauge> = Lazy::new(|| {
     g
 });
 
+static BOOK_STALE_INSTRUMENTS: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new(
+        "book_stale_instruments",
+        "Instruments whose book missed updates the merge gave up on",
+    )
+    .expect("book_stale_instruments");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static SNAPSHOT_FEED_JOINED: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new(
+        "snapshot_feed_joined",
+        "1 while the snapshot multicast is joined for late join/gap recovery",
+    )
+    .expect("snapshot_feed_joined");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static SNAPSHOT_FEED_BUFFERED: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new(
+        "snapshot_feed_buffered_packets",
+        "Incremental packets held for replay on top of snapshots",
+    )
+    .expect("snapshot_feed_buffered_packets");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static SNAPSHOT_FEED_EVICTED: Lazy<IntCounter> = Lazy::new(|| {
+    let c = IntCounter::new(
+        "snapshot_feed_evicted_packets",
+        "Buffered incrementals dropped at snapshot_feed.max_buffered_packets",
+    )
+    .expect("snapshot_feed_evicted_packets");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static SNAPSHOT_FEED_INSTRUMENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "snapshot_feed_instruments",
+            "Snapshot cycle entries by outcome (rebuilt, too_old, incomplete, dropped_packet)",
+        ),
+        &["outcome"],
+    )
+    .expect("snapshot_feed_instruments");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static GLIMPSE_HELD: Lazy<IntGauge> = Lazy::new(|| {
+    let g = IntGauge::new(
+        "glimpse_held_packets",
+        "Live packets held back while a Glimpse snapshot loads",
+    )
+    .expect("glimpse_held_packets");
+    REGISTRY.register(Box::new(g.clone())).ok();
+    g
+});
+
+static GLIMPSE_EVICTED: Lazy<IntCounter> = Lazy::new(|| {
+    let c = IntCounter::new(
+        "glimpse_evicted_packets",
+        "Held packets dropped at glimpse.max_buffered_packets",
+    )
+    .expect("glimpse_evicted_packets");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static GLIMPSE_SNAPSHOTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "glimpse_snapshots",
+            "Glimpse downloads by outcome (applied, too_old, failed)",
+        ),
+        &["outcome"],
+    )
+    .expect("glimpse_snapshots");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
 static E2E_LATENCY: Lazy<Histogram> = Lazy::new(|| {
     // Buckets in seconds: 100ns .. 10ms
     let buckets = vec![1e-7, 2e-7, 5e-7, 1e-6, 2e-6, 5e-6, 1e-5, 2e-5, 5e-5, 1e-4];
@@ -221,6 +594,50 @@ static STAGE_MERGE_TO_DECODE: Lazy<Histogram> = Lazy::new(|| {
     h
 });
 
+static EXCHANGE_TO_RX: Lazy<HistogramVec> = Lazy::new(|| {
+    // Buckets in seconds: 1us .. 1s (venue clock to our RX timestamp)
+    let buckets = vec![
+        1e-6, 5e-6, 1e-5, 2.5e-5, 5e-5, 1e-4, 2.5e-4, 5e-4, 1e-3, 1e-2, 1e-1, 1.0,
+    ];
+    let h = HistogramVec::new(
+        HistogramOpts::new(
+            "exchange_to_rx_latency_seconds",
+            "Venue timestamp to RX timestamp latency per partition and line",
+        )
+        .buckets(buckets),
+        &["partition", "line"],
+    )
+    .expect("exchange_to_rx_latency");
+    REGISTRY.register(Box::new(h.clone())).ok();
+    h
+});
+
+static DECODER_MESSAGES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "decoder_messages",
+            "Messages seen by decoder per type/template",
+        ),
+        &["decoder", "type"],
+    )
+    .expect("decoder_messages");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
+static DECODER_ISSUES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let c = IntCounterVec::new(
+        Opts::new(
+            "decoder_issues",
+            "Malformed or unsupported input per decoder (unknown_type, truncated, short_body, trailing_bytes)",
+        ),
+        &["decoder", "kind"],
+    )
+    .expect("decoder_issues");
+    REGISTRY.register(Box::new(c.clone())).ok();
+    c
+});
+
 static QUEUE_LEN: Lazy<IntGaugeVec> = Lazy::new(|| {
     let g = IntGaugeVec::new(
         Opts::new("queue_len", "Current length of internal queues"),
@@ -241,39 +658,129 @@ static QUEUE_HWM: Lazy<IntGaugeVec> = Lazy::new(|| {
     g
 });
 
-static HWM_TRACK: Lazy<Mutex<HashMap<&'static str, i64>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+static HWM_TRACK: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn inc_rx(partition: &str, chan: &str, bytes: usize) {
+    RX_PACKETS.with_label_values(&[partition, chan]).inc();
+    RX_BYTES
+        .with_label_values(&[partition, chan])
+        .inc_by(bytes as u64);
+}
 
-pub fn inc_rx(chan: &str, bytes: usize) {
-    RX_PACKETS.with_label_values(&[chan]).inc();
-    RX_BYTES.with_label_values(&[chan]).inc_by(bytes as u64);
+pub fn inc_rx_drop(partition: &str, chan: &str) {
+    RX_DROPS.with_label_values(&[partition, chan]).inc();
+}
+pub fn inc_chaos(partition: &str, chan: &str, action: &str) {
+    CHAOS_PACKETS
+        .with_label_values(&[partition, chan, action])
+        .inc();
 }
 
-pub fn inc_rx_drop(chan: &str) {
-    RX_DROPS.with_label_values(&[chan]).inc();
+#[allow(dead_code)] // TPACKET_V3 ring only
+pub fn rx_flows_gauge(chan: &str, queue: u32) -> IntGauge {
+    RX_FLOWS.with_label_values(&[chan, &queue.to_string()])
 }
 
-pub fn inc_merge_dup() {
-    MERGE_DUPS.inc();
+pub fn inc_merge_dup(partition: &str) {
+    MERGE_DUPS.with_label_values(&[partition]).inc();
 }
-pub fn inc_merge_gap() {
-    MERGE_GAPS.inc();
+pub fn inc_merge_gap(partition: &str) {
+    MERGE_GAPS.with_label_values(&[partition]).inc();
 }
-pub fn inc_merge_ooo() {
-    MERGE_OOO.inc();
+pub fn inc_merge_ooo(partition: &str) {
+    MERGE_OOO.with_label_values(&[partition]).inc();
 }
 
-pub fn inc_merge_forward_chan(chan: &str) {
-    MERGE_FORWARD_BY_CHAN.with_label_values(&[chan]).inc();
+pub fn inc_merge_forward_chan(partition: &str, chan: &str) {
+    MERGE_FORWARD_BY_CHAN
+        .with_label_values(&[partition, chan])
+        .inc();
+}
+pub fn inc_merge_gap_chan(partition: &str, chan: &str) {
+    MERGE_GAPS_BY_CHAN
+        .with_label_values(&[partition, chan])
+        .inc();
+}
+pub fn inc_merge_gap_timeout(partition: &str) {
+    MERGE_GAP_TIMEOUTS.with_label_values(&[partition]).inc();
+}
+pub fn inc_merge_give_up(partition: &str, action: &str) {
+    MERGE_GIVE_UPS.with_label_values(&[partition, action]).inc();
+}
+pub fn inc_merge_dup_verified(partition: &str) {
+    MERGE_DUP_VERIFIED.with_label_values(&[partition]).inc();
+}
+pub fn inc_merge_dup_mismatch(partition: &str, line: &str) {
+    MERGE_DUP_MISMATCHES
+        .with_label_values(&[partition, line])
+        .inc();
+}
+pub fn inc_merge_seq_reset(partition: &str, kind: &str) {
+    MERGE_SEQ_RESETS.with_label_values(&[partition, kind]).inc();
+}
+pub fn observe_recovery_gap_fill_ns(partition: &str, ns: u64) {
+    let secs = (ns as f64) / 1_000_000_000.0;
+    RECOVERY_GAP_FILL
+        .with_label_values(&[partition])
+        .observe(secs);
+}
+pub fn set_recovery_outstanding(partition: &str, n: usize) {
+    RECOVERY_OUTSTANDING
+        .with_label_values(&[partition])
+        .set(n as i64);
+}
+pub fn inc_recovery_retry(partition: &str) {
+    RECOVERY_EVENTS
+        .with_label_values(&[partition, "retry"])
+        .inc();
+}
+pub fn inc_recovery_partial_fill(partition: &str) {
+    RECOVERY_EVENTS
+        .with_label_values(&[partition, "partial_fill"])
+        .inc();
+}
+pub fn inc_recovery_abandoned(partition: &str) {
+    RECOVERY_EVENTS
+        .with_label_values(&[partition, "abandoned"])
+        .inc();
 }
-pub fn inc_merge_gap_chan(chan: &str) {
-    MERGE_GAPS_BY_CHAN.with_label_values(&[chan]).inc();
+pub fn inc_recovery_failover(partition: &str) {
+    RECOVERY_EVENTS
+        .with_label_values(&[partition, "failover"])
+        .inc();
 }
-pub fn inc_merge_failover() {
-    MERGE_FAILOVERS.inc();
+/// A request was spilled instead of queued (picked up on the next tick)
+pub fn inc_recovery_spilled() {
+    RECOVERY_SPILLED.with_label_values(&["spilled"]).inc();
+}
+/// The spill was full as well and the request was dropped
+pub fn inc_recovery_dropped() {
+    RECOVERY_SPILLED.with_label_values(&["dropped"]).inc();
+}
+pub fn set_merge_streams(partition: &str, n: usize) {
+    MERGE_STREAMS.with_label_values(&[partition]).set(n as i64);
+}
+pub fn inc_merge_stream_drop(partition: &str) {
+    MERGE_STREAM_DROPS.with_label_values(&[partition]).inc();
+}
+pub fn observe_merge_hole_hold_ns(partition: &str, ns: u64) {
+    let secs = (ns as f64) / 1_000_000_000.0;
+    MERGE_HOLE_HOLD
+        .with_label_values(&[partition])
+        .observe(secs);
 }
-pub fn set_merge_preferred_is_a(is_a: bool) {
-    MERGE_PREFERRED_IS_A.set(if is_a { 1 } else { 0 });
+pub fn inc_merge_failover(partition: &str) {
+    MERGE_FAILOVERS.with_label_values(&[partition]).inc();
+}
+pub fn set_merge_preferred_is_a(partition: &str, is_a: bool) {
+    MERGE_PREFERRED_IS_A
+        .with_label_values(&[partition])
+        .set(if is_a { 1 } else { 0 });
+}
+pub fn set_merge_preferred_source(partition: &str, source: &str, preferred: bool) {
+    MERGE_PREFERRED_SOURCE
+        .with_label_values(&[partition, source])
+        .set(if preferred { 1 } else { 0 });
 }
 
 pub fn inc_decode_pkts() {
@@ -286,6 +793,32 @@ pub fn inc_decode_msgs(n: u64) {
 pub fn set_live_orders(n: usize) {
     BOOK_LIVE_ORDERS.set(n as i64);
 }
+pub fn set_stale_instruments(n: usize) {
+    BOOK_STALE_INSTRUMENTS.set(n as i64);
+}
+pub fn set_snapshot_feed_joined(joined: bool) {
+    SNAPSHOT_FEED_JOINED.set(joined as i64);
+}
+pub fn set_snapshot_feed_buffered(n: usize) {
+    SNAPSHOT_FEED_BUFFERED.set(n as i64);
+}
+pub fn inc_snapshot_feed_evicted() {
+    SNAPSHOT_FEED_EVICTED.inc();
+}
+pub fn inc_snapshot_feed_instrument(outcome: &str) {
+    SNAPSHOT_FEED_INSTRUMENTS
+        .with_label_values(&[outcome])
+        .inc();
+}
+pub fn set_glimpse_held(n: usize) {
+    GLIMPSE_HELD.set(n as i64);
+}
+pub fn inc_glimpse_evicted() {
+    GLIMPSE_EVICTED.inc();
+}
+pub fn inc_glimpse_snapshot(outcome: &str) {
+    GLIMPSE_SNAPSHOTS.with_label_values(&[outcome]).inc();
+}
 
 pub fn observe_latency_ns(ns: u64) {
     let secs = (ns as f64) / 1_000_000_000.0;
@@ -313,12 +846,65 @@ pub fn observe_stage_merge_to_decode_ns(ns: u64) {
     STAGE_MERGE_TO_DECODE.observe(secs);
 }
 
+/// Exchange -> RX latency handles for one partition, resolved once per line so the
+/// decode thread indexes by `Pkt::chan` instead of resolving labels per packet.
+pub struct ExchangeToRx {
+    lines: Vec<Histogram>,
+    recovery: Histogram,
+}
+
+impl ExchangeToRx {
+    /// `lines` are the partition's line names in channel order (`PartitionCfg::lines`)
+    pub fn new(partition: &str, lines: &[&str]) -> Self {
+        Self {
+            lines: lines
+                .iter()
+                .map(|l| EXCHANGE_TO_RX.with_label_values(&[partition, l]))
+                .collect(),
+            recovery: EXCHANGE_TO_RX.with_label_values(&[partition, "recovery"]),
+        }
+    }
+
+    #[inline]
+    pub fn observe_ns(&self, chan: u8, ns: u64) {
+        let h = self.lines.get(chan as usize).unwrap_or(&self.recovery);
+        h.observe((ns as f64) / 1_000_000_000.0);
+    }
+}
+
+// Decoders cache these handles (see decode_stats.rs) instead of resolving labels per message
+pub fn decoder_message_counter(decoder: &str, ty: &str) -> IntCounter {
+    DECODER_MESSAGES.with_label_values(&[decoder, ty])
+}
+pub fn decoder_issue_counter(decoder: &str, kind: &str) -> IntCounter {
+    DECODER_ISSUES.with_label_values(&[decoder, kind])
+}
+
+// Line race handles are resolved once per merge thread (see line_race.rs)
+pub fn merge_line_win_counter(partition: &str, line: &str) -> IntCounter {
+    MERGE_LINE_WINS.with_label_values(&[partition, line])
+}
+pub fn merge_line_missed_counter(partition: &str, line: &str) -> IntCounter {
+    MERGE_LINE_MISSED.with_label_values(&[partition, line])
+}
+pub fn merge_line_race_delta_histogram(partition: &str, winner: &str, loser: &str) -> Histogram {
+    MERGE_LINE_RACE_DELTA.with_label_values(&[partition, winner, loser])
+}
+pub fn set_merge_line_ratios(partition: &str, line: &str, win: f64, loss: f64) {
+    MERGE_LINE_WIN_RATIO
+        .with_label_values(&[partition, line])
+        .set(win);
+    MERGE_LINE_LOSS_RATIO
+        .with_label_values(&[partition, line])
+        .set(loss);
+}
+
 // removed
 
-pub fn set_queue_len(queue: &'static str, len: usize) {
+pub fn set_queue_len(queue: &str, len: usize) {
     QUEUE_LEN.with_label_values(&[queue]).set(len as i64);
     let mut hwm = HWM_TRACK.lock().unwrap();
-    let e = hwm.entry(queue).or_insert(0);
+    let e = hwm.entry_ref(queue).or_insert(0);
     if *e < len as i64 {
         *e = len as i64;
         QUEUE_HWM.with_label_values(&[queue]).set(*e);
@@ -406,6 +992,14 @@ pub fn spawn_http<A: ToSocketAddrs + Send + 'static>(
                         .is_some();
                     let status = if ok { 202 } else { 503 };
                     let _ = req.respond(tiny_http::Response::empty(status));
+                } else if url == "/recovery/gaps" || url.starts_with("/recovery/gaps?") {
+                    // Optional `?status=outstanding|filled|partial|abandoned`
+                    let status = url
+                        .split_once("?status=")
+                        .map(|(_, s)| s.split('&').next().unwrap_or(s));
+                    let body = crate::recovery_journal::report(status);
+                    let _ =
+                        req.respond(tiny_http::Response::from_string(body).with_status_code(200));
                 } else if url == "/live" || url == "/healthz" {
                     let _ =
                         req.respond(tiny_http::Response::from_string("OK").with_status_code(200));
@@ -2,10 +2,18 @@
 use crate::config::ChannelCfg;
 use anyhow::Context;
 use socket2::{Domain, Protocol, Socket, Type};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
 
 pub fn build_mcast_socket(cfg: &ChannelCfg) -> anyhow::Result<UdpSocket> {
-    let sock = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).context("socket")?;
+    let domain = if cfg.group.is_ipv4() {
+        Domain::IPV4
+    } else {
+        Domain::IPV6
+    };
+    let sock = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP)).context("socket")?;
+    if !cfg.group.is_ipv4() {
+        sock.set_only_v6(true).ok();
+    }
 
     sock.set_reuse_address(true).ok();
     if cfg.reuse_port {
@@ -13,8 +21,16 @@ pub fn build_mcast_socket(cfg: &ChannelCfg) -> anyhow::Result<UdpSocket> {
         sock.set_reuse_port(true).ok();
     }
 
-    // Bind to wildcard:port for multicast RX
-    let bind_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), cfg.port);
+    // Bind to group:port so channels and partitions sharing a port neither see each
+    // other's groups nor share one SO_REUSEPORT balancing set (Unix only; wildcard elsewhere)
+    let bind_ip = if cfg!(unix) {
+        cfg.group
+    } else if cfg.group.is_ipv4() {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    };
+    let bind_addr = SocketAddr::new(bind_ip, cfg.port);
     sock.bind(&bind_addr.into()).context("bind")?;
 
     // Increase receive buffer to tolerate bursts
@@ -22,11 +38,7 @@ pub fn build_mcast_socket(cfg: &ChannelCfg) -> anyhow::Result<UdpSocket> {
         let _ = sock.set_recv_buffer_size(cfg.recv_buffer_bytes as usize);
     }
 
-    // Join multicast group on specified interface
-    let group = cfg.group;
-    let iface = cfg.iface_addr;
-    sock.join_multicast_v4(&group, &iface)
-        .context("join_multicast_v4")?;
+    join_group(&sock, cfg)?;
 
     // Optional busy-poll hint (Linux only)
     #[cfg(target_os = "linux")]
@@ -102,3 +114,107 @@ pub fn build_mcast_socket(cfg: &ChannelCfg) -> anyhow::Result<UdpSocket> {
     }
     Ok(s)
 }
+
+/// Join the channel's group on its interface: any-source, or one (source, group)
+/// membership per configured SSM source.
+fn join_group(sock: &Socket, cfg: &ChannelCfg) -> anyhow::Result<()> {
+    match cfg.group {
+        IpAddr::V4(group) => {
+            let iface = cfg.iface_addr.unwrap_or(Ipv4Addr::UNSPECIFIED);
+            if cfg.sources.is_empty() {
+                sock.join_multicast_v4(&group, &iface)
+                    .context("join_multicast_v4")?;
+            }
+            for src in &cfg.sources {
+                let IpAddr::V4(src) = src else {
+                    anyhow::bail!("SSM source {src} does not match IPv4 group {group}");
+                };
+                sock.join_ssm_v4(src, &group, &iface)
+                    .with_context(|| format!("join_ssm_v4 ({src}, {group})"))?;
+            }
+        }
+        IpAddr::V6(group) => {
+            let ifindex = match &cfg.iface {
+                Some(name) => nix::net::if_::if_nametoindex(name.as_str())
+                    .with_context(|| format!("interface {name}"))?,
+                None => 0,
+            };
+            if cfg.sources.is_empty() {
+                sock.join_multicast_v6(&group, ifindex)
+                    .context("join_multicast_v6")?;
+            }
+            for src in &cfg.sources {
+                let IpAddr::V6(src) = src else {
+                    anyhow::bail!("SSM source {src} does not match IPv6 group {group}");
+                };
+                join_ssm_v6(sock, src, &group, ifindex)
+                    .with_context(|| format!("MCAST_JOIN_SOURCE_GROUP ({src}, {group})"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// IPv6 source-specific join via the protocol-independent MCAST_JOIN_SOURCE_GROUP
+/// (socket2 only wraps the IPv4 variant).
+#[cfg(target_os = "linux")]
+fn join_ssm_v6(
+    sock: &Socket,
+    source: &Ipv6Addr,
+    group: &Ipv6Addr,
+    ifindex: u32,
+) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // struct group_source_req from <netinet/in.h>; not exported by libc
+    #[repr(C)]
+    struct GroupSourceReq {
+        gsr_interface: u32,
+        gsr_group: libc::sockaddr_storage,
+        gsr_source: libc::sockaddr_storage,
+    }
+    fn storage(ip: &Ipv6Addr) -> libc::sockaddr_storage {
+        let addr = socket2::SockAddr::from(SocketAddr::new(IpAddr::V6(*ip), 0));
+        let mut ss: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                addr.as_ptr() as *const u8,
+                &mut ss as *mut _ as *mut u8,
+                addr.len() as usize,
+            );
+        }
+        ss
+    }
+    let req = GroupSourceReq {
+        gsr_interface: ifindex,
+        gsr_group: storage(group),
+        gsr_source: storage(source),
+    };
+    let rc = unsafe {
+        libc::setsockopt(
+            sock.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::MCAST_JOIN_SOURCE_GROUP,
+            &req as *const _ as *const libc::c_void,
+            std::mem::size_of::<GroupSourceReq>() as libc::socklen_t,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn join_ssm_v6(
+    _sock: &Socket,
+    _source: &Ipv6Addr,
+    _group: &Ipv6Addr,
+    _ifindex: u32,
+) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "IPv6 SSM joins are only implemented on Linux",
+    ))
+}
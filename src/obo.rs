@@ -1,7 +1,9 @@
 // Normalized L3 (Order-by-Order) events and mapping from internal parser events
 
-use crate::codec_raw::{OboAddV1, OboCancelV1, OboExecuteV1, OboModifyV1};
-use crate::parser::{Event, Side};
+use crate::codec_raw::{
+    level_action, OboAddV1, OboCancelV1, OboExecuteV1, OboLevelV1, OboModifyV1,
+};
+use crate::parser::{Event, LevelAction, Side};
 
 #[derive(Debug, Clone, Copy)]
 pub enum OboEventV1 {
@@ -9,6 +11,7 @@ pub enum OboEventV1 {
     Modify(OboModifyV1),
     Cancel(OboCancelV1),
     Execute(OboExecuteV1),
+    Level(OboLevelV1),
 }
 
 #[inline]
@@ -19,6 +22,18 @@ fn side_to_u8(side: Side) -> u8 {
     }
 }
 
+#[inline]
+fn level_action_to_u8(action: LevelAction) -> u8 {
+    match action {
+        LevelAction::New => level_action::NEW,
+        LevelAction::Change => level_action::CHANGE,
+        LevelAction::Delete => level_action::DELETE,
+        LevelAction::DeleteThru => level_action::DELETE_THRU,
+        LevelAction::DeleteFrom => level_action::DELETE_FROM,
+        LevelAction::Overlay => level_action::OVERLAY,
+    }
+}
+
 #[inline]
 pub fn map_event_to_obo_parts(ev: &Event) -> (Option<u32>, Option<OboEventV1>) {
     match *ev {
@@ -28,6 +43,7 @@ pub fn map_event_to_obo_parts(ev: &Event) -> (Option<u32>, Option<OboEventV1>) {
             px,
             qty,
             side,
+            ..
         } => {
             (
                 Some(instr),
@@ -40,7 +56,7 @@ pub fn map_event_to_obo_parts(ev: &Event) -> (Option<u32>, Option<OboEventV1>) {
                 })),
             )
         }
-        Event::Mod { order_id, qty } => {
+        Event::Mod { order_id, qty, .. } => {
             // qty-only modify; leave price unchanged (encode as 0 with a flag)
             (
                 None,
@@ -52,7 +68,7 @@ pub fn map_event_to_obo_parts(ev: &Event) -> (Option<u32>, Option<OboEventV1>) {
                 })),
             )
         }
-        Event::Del { order_id } => (
+        Event::Del { order_id, .. } => (
             None,
             Some(OboEventV1::Cancel(OboCancelV1 {
                 order_id,
@@ -66,6 +82,7 @@ pub fn map_event_to_obo_parts(ev: &Event) -> (Option<u32>, Option<OboEventV1>) {
             qty,
             maker_order_id,
             taker_side,
+            ..
         } => {
             if let Some(maker) = maker_order_id {
                 let side = taker_side.map(side_to_u8).unwrap_or(0);
@@ -83,6 +100,29 @@ pub fn map_event_to_obo_parts(ev: &Event) -> (Option<u32>, Option<OboEventV1>) {
                 (Some(instr), None)
             }
         }
+        Event::TradingStatus { instr, .. } => (Some(instr), None),
+        Event::Level {
+            instr,
+            side,
+            level,
+            px,
+            qty,
+            orders,
+            action,
+            implied,
+            ..
+        } => (
+            Some(instr),
+            Some(OboEventV1::Level(OboLevelV1 {
+                price_e8: px,
+                qty: qty.max(0) as u64,
+                orders: orders.max(0) as u32,
+                side: side_to_u8(side),
+                level,
+                action: level_action_to_u8(action),
+                flags: implied as u8,
+            })),
+        ),
         Event::Heartbeat => (None, None),
     }
 }
@@ -1,5 +1,5 @@
 // src/orderbook.rs Numan Thabit: extended with export/import
-use crate::parser::{Event, Side};
+use crate::parser::{Event, LevelAction, Side};
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 use slab::Slab;
@@ -638,9 +638,65 @@ impl InstrumentBook {
     }
 }
 
+/// One level of a venue-maintained price book (MBP)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceLevel {
+    pub px: i64,
+    pub qty: i64,
+    pub orders: i32,
+}
+
+/// Price book of one instrument as the venue sends it: levels by 1-based position
+/// per side, implied levels apart from outright ones.
+#[derive(Default)]
+struct LevelBook {
+    /// [outright, implied] x [bid, ask]
+    sides: [[Vec<PriceLevel>; 2]; 2],
+}
+
+impl LevelBook {
+    /// Apply one update; levels pushed past `depth` fall off (venues do not delete them).
+    fn apply(
+        &mut self,
+        side: Side,
+        level: u8,
+        lvl: PriceLevel,
+        action: LevelAction,
+        implied: bool,
+        depth: usize,
+    ) {
+        let v = &mut self.sides[implied as usize][side as usize];
+        let i = (level.max(1) - 1) as usize;
+        match action {
+            LevelAction::New => {
+                v.insert(i.min(v.len()), lvl);
+                v.truncate(depth.max(1));
+            }
+            LevelAction::Change | LevelAction::Overlay => {
+                if i < v.len() {
+                    v[i] = lvl;
+                } else if i == v.len() {
+                    v.push(lvl);
+                }
+            }
+            LevelAction::Delete => {
+                if i < v.len() {
+                    v.remove(i);
+                }
+            }
+            LevelAction::DeleteThru => {
+                v.drain(..(i + 1).min(v.len()));
+            }
+            LevelAction::DeleteFrom => v.truncate(i),
+        }
+    }
+}
+
 pub struct OrderBook {
-    _depth_for_reporting: usize,
+    depth_for_reporting: usize,
     books: HashMap<u32, InstrumentBook>,
+    /// Venue price books (MBP), kept apart from the order books built from MBO events
+    levels: HashMap<u32, LevelBook>,
     index: HashMap<u64, (u32, Handle)>,
     last_instr: Option<u32>,
     consume_trades: bool,
@@ -652,8 +708,9 @@ pub struct OrderBook {
 impl OrderBook {
     pub fn new(depth_for_reporting: usize) -> Self {
         Self {
-            _depth_for_reporting: depth_for_reporting,
+            depth_for_reporting,
             books: HashMap::new(),
+            levels: HashMap::new(),
             index: HashMap::new(),
             last_instr: None,
             consume_trades: false,
@@ -666,8 +723,9 @@ impl OrderBook {
     #[allow(dead_code)]
     pub fn new_with_options(depth_for_reporting: usize, consume_trades: bool) -> Self {
         Self {
-            _depth_for_reporting: depth_for_reporting,
+            depth_for_reporting,
             books: HashMap::new(),
+            levels: HashMap::new(),
             index: HashMap::new(),
             last_instr: None,
             consume_trades,
@@ -684,8 +742,9 @@ impl OrderBook {
         default_slab_capacity: usize,
     ) -> Self {
         Self {
-            _depth_for_reporting: depth_for_reporting,
+            depth_for_reporting,
             books: HashMap::new(),
+            levels: HashMap::new(),
             index: HashMap::new(),
             last_instr: None,
             consume_trades,
@@ -704,8 +763,9 @@ impl OrderBook {
         grid_span: usize,
     ) -> Self {
         Self {
-            _depth_for_reporting: depth_for_reporting,
+            depth_for_reporting,
             books: HashMap::new(),
+            levels: HashMap::new(),
             index: HashMap::new(),
             last_instr: None,
             consume_trades,
@@ -738,13 +798,14 @@ impl OrderBook {
                 px,
                 qty,
                 side,
+                ..
             } => {
                 let book = self.book_mut(instr);
                 let h = book.add(px, qty, side);
                 self.index.insert(order_id, (instr, h));
                 self.last_instr = Some(instr);
             }
-            Event::Mod { order_id, qty } => {
+            Event::Mod { order_id, qty, .. } => {
                 if let Some((instr, h)) = self.index.get(&order_id).copied() {
                     let book = self.book_mut(instr);
                     if qty > 0 {
@@ -756,7 +817,7 @@ impl OrderBook {
                     self.last_instr = Some(instr);
                 }
             }
-            Event::Del { order_id } => {
+            Event::Del { order_id, .. } => {
                 if let Some((instr, h)) = self.index.remove(&order_id) {
                     let book = self.book_mut(instr);
                     book.cancel(h);
@@ -788,7 +849,25 @@ impl OrderBook {
                     }
                 }
             }
-            Event::Heartbeat => {}
+            Event::Level {
+                instr,
+                side,
+                level,
+                px,
+                qty,
+                orders,
+                action,
+                implied,
+                ..
+            } => {
+                let lvl = PriceLevel { px, qty, orders };
+                let depth = self.depth_for_reporting;
+                self.levels
+                    .entry(instr)
+                    .or_default()
+                    .apply(side, level, lvl, action, implied, depth);
+            }
+            Event::TradingStatus { .. } | Event::Heartbeat => {}
         }
     }
 
@@ -805,6 +884,7 @@ impl OrderBook {
                     px,
                     qty,
                     side,
+                    ..
                 } if ev_instr == instr => {
                     let h = {
                         let b = self.book_mut(instr);
@@ -813,7 +893,7 @@ impl OrderBook {
                     self.index.insert(order_id, (instr, h));
                     self.last_instr = Some(instr);
                 }
-                Event::Mod { order_id, qty } => {
+                Event::Mod { order_id, qty, .. } => {
                     if let Some((mi, h)) = self.index.get(&order_id).copied() {
                         if mi == instr {
                             if qty > 0 {
@@ -830,7 +910,7 @@ impl OrderBook {
                         }
                     }
                 }
-                Event::Del { order_id } => {
+                Event::Del { order_id, .. } => {
                     if let Some((mi, h)) = self.index.remove(&order_id) {
                         if mi == instr {
                             let b = self.book_mut(instr);
@@ -897,6 +977,33 @@ impl OrderBook {
         self.books.get(&instr).map(|b| b.top_n(n))
     }
 
+    /// Outright (or implied) price levels of `side`, best first
+    #[allow(dead_code)]
+    pub fn price_levels(&self, instr: u32, side: Side, implied: bool) -> &[PriceLevel] {
+        self.levels
+            .get(&instr)
+            .map_or(&[], |b| &b.sides[implied as usize][side as usize])
+    }
+
+    /// Instruments with a book, in no particular order
+    #[allow(dead_code)]
+    pub fn instruments(&self) -> impl Iterator<Item = u32> + '_ {
+        let level_only = self.levels.keys().filter(|i| !self.books.contains_key(*i));
+        self.books.keys().chain(level_only).copied()
+    }
+
+    /// Drop an instrument's orders, e.g. before a snapshot resync.
+    #[allow(dead_code)]
+    pub fn clear_instrument(&mut self, instr: u32) {
+        if self.books.remove(&instr).is_some() {
+            self.index.retain(|_, (i, _)| *i != instr);
+        }
+        self.levels.remove(&instr);
+        if self.last_instr == Some(instr) {
+            self.last_instr = None;
+        }
+    }
+
     pub fn order_count(&self) -> usize {
         self.index.len()
     }
@@ -1058,6 +1165,63 @@ mod tests {
         b.cancel(h1);
         assert!(b.get_level(Side::Ask, 101).is_none());
     }
+
+    #[test]
+    fn clear_instrument_drops_only_its_orders() {
+        let mut ob = OrderBook::new(5);
+        for (order_id, instr) in [(1, 7), (2, 7), (3, 9)] {
+            let h = ob.book_mut(instr).add(100, 10, Side::Bid);
+            ob.index.insert(order_id, (instr, h));
+        }
+        ob.clear_instrument(7);
+        assert_eq!(ob.order_count(), 1);
+        assert_eq!(ob.instrument_for_order(3), Some(9));
+        assert_eq!(ob.instruments().collect::<Vec<_>>(), vec![9]);
+    }
+
+    #[test]
+    fn level_updates_keep_the_venue_price_book() {
+        let mut ob = OrderBook::new(3);
+        let ev = |level: u8, px: i64, action: LevelAction, implied: bool| Event::Level {
+            instr: 5,
+            side: Side::Bid,
+            level,
+            px,
+            qty: px / 10,
+            orders: 1,
+            action,
+            implied,
+            exch_ts_ns: None,
+        };
+        let pxs = |ob: &OrderBook, implied: bool| -> Vec<i64> {
+            ob.price_levels(5, Side::Bid, implied)
+                .iter()
+                .map(|l| l.px)
+                .collect()
+        };
+        for (level, px) in [(1, 100), (2, 90), (3, 80)] {
+            ob.apply(&ev(level, px, LevelAction::New, false));
+        }
+        // A new best level pushes the deepest one out of a depth-3 book
+        ob.apply(&ev(1, 110, LevelAction::New, false));
+        assert_eq!(pxs(&ob, false), vec![110, 100, 90]);
+        ob.apply(&ev(2, 105, LevelAction::Change, false));
+        ob.apply(&ev(1, 0, LevelAction::Delete, false));
+        assert_eq!(pxs(&ob, false), vec![105, 90]);
+        assert_eq!(ob.price_levels(5, Side::Bid, false)[0].qty, 10);
+
+        // Implied levels are a book of their own
+        ob.apply(&ev(1, 95, LevelAction::New, true));
+        assert_eq!(pxs(&ob, true), vec![95]);
+        ob.apply(&ev(2, 0, LevelAction::DeleteFrom, false));
+        assert_eq!(pxs(&ob, false), vec![105]);
+        ob.apply(&ev(1, 0, LevelAction::DeleteThru, true));
+        assert!(pxs(&ob, true).is_empty());
+
+        assert_eq!(ob.instruments().collect::<Vec<_>>(), vec![5]);
+        ob.clear_instrument(5);
+        assert!(pxs(&ob, false).is_empty());
+    }
 }
 
 /// Serializable snapshot format (coarse-grained; not in hot path).
@@ -2,19 +2,48 @@
 use crate::config::{Endian, ParserKind};
 use crate::decoder_eobi::EobiSbeDecoder;
 use crate::decoder_fast::FastEmdiDecoder;
-use crate::decoder_itch::Itch50Decoder;
+use crate::decoder_itch::{Itch50Decoder, MoldSeq, MOLD_HEADER_LEN};
+use crate::decoder_mdp3::{CmeMdp3Decoder, Mdp3PacketSeq};
+use crate::decoder_pitch::{CboePitchDecoder, PitchUnitSeq};
+use hashbrown::HashMap;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 #[derive(Clone)]
 pub struct SeqCfg {
     pub offset: u16,
     pub length: u8, // 4 or 8
     pub endian: Endian,
+    pub unit: Option<u8>,          // PITCH: restrict to one Sequenced Unit
+    pub stream: Option<(u16, u8)>, // (offset, length) of a stream id next to the sequence
+    pub moldudp64: bool,           // ITCH: sequence and message count from the MoldUDP64 header
 }
 
 pub trait SeqExtractor: Send + Sync + 'static {
     fn extract_seq(&self, pkt: &[u8]) -> Option<u64>;
+
+    /// `(stream, seq)` for feeds that multiplex several sequence spaces on one group;
+    /// merge keeps separate reorder state per stream. Single-stream feeds use stream 0.
+    #[inline]
+    fn extract_stream_seq(&self, pkt: &[u8]) -> Option<(u32, u64)> {
+        self.extract_seq(pkt).map(|s| (0, s))
+    }
+
+    /// Number of sequence numbers consumed by `pkt`. Per-packet feeds use 1;
+    /// feeds that number individual messages advance by the message count.
+    #[inline]
+    fn seq_span(&self, _pkt: &[u8]) -> u32 {
+        1
+    }
+
+    /// True if `pkt` explicitly restarts the sequence or the venue's book (new session,
+    /// channel reset): merge re-anchors at its sequence instead of dropping it as a
+    /// duplicate. Feeds without such messages rely on `merge.reset_backjump`.
+    #[inline]
+    fn is_seq_reset(&self, _pkt: &[u8]) -> bool {
+        false
+    }
 }
 
 #[allow(dead_code)]
@@ -28,6 +57,8 @@ pub enum Side {
     Ask,
 }
 
+/// Decoded venue event. `exch_ts_ns` is the venue's own timestamp for the message
+/// (ns since Unix epoch) when the protocol carries one.
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum Event {
@@ -37,13 +68,16 @@ pub enum Event {
         px: i64,
         qty: i64,
         side: Side,
+        exch_ts_ns: Option<u64>,
     },
     Mod {
         order_id: u64,
         qty: i64,
+        exch_ts_ns: Option<u64>,
     },
     Del {
         order_id: u64,
+        exch_ts_ns: Option<u64>,
     },
     Trade {
         instr: u32,
@@ -51,14 +85,64 @@ pub enum Event {
         qty: i64,
         maker_order_id: Option<u64>,
         taker_side: Option<Side>,
+        exch_ts_ns: Option<u64>,
+    },
+    /// Venue trading state change for an instrument (raw venue status code).
+    TradingStatus {
+        instr: u32,
+        status: u8,
+        exch_ts_ns: Option<u64>,
+    },
+    /// Market-by-price level update (MDP 3.0). `level` is the 1-based book depth;
+    /// implied-book levels are flagged so consumers can keep them apart.
+    Level {
+        instr: u32,
+        side: Side,
+        level: u8,
+        px: i64,
+        qty: i64,
+        orders: i32,
+        action: LevelAction,
+        implied: bool,
+        exch_ts_ns: Option<u64>,
     },
     Heartbeat,
 }
 
+impl Event {
+    #[inline]
+    pub fn exch_ts_ns(&self) -> Option<u64> {
+        match *self {
+            Event::Add { exch_ts_ns, .. }
+            | Event::Mod { exch_ts_ns, .. }
+            | Event::Del { exch_ts_ns, .. }
+            | Event::Trade { exch_ts_ns, .. }
+            | Event::TradingStatus { exch_ts_ns, .. }
+            | Event::Level { exch_ts_ns, .. } => exch_ts_ns,
+            Event::Heartbeat => None,
+        }
+    }
+}
+
+/// MBP level update action as sent by the venue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelAction {
+    New,
+    Change,
+    Delete,
+    /// Delete every level from 1 through `level` on the side
+    DeleteThru,
+    /// Delete `level` and every level behind it on the side
+    DeleteFrom,
+    Overlay,
+}
+
 #[derive(Clone)]
 pub struct Parser {
     seq: Arc<dyn SeqExtractor>,
     dec: DecoderImpl,
+    /// Packet header ahead of the first message (MoldUDP64)
+    frame_len: usize,
     pub max_messages_per_packet: usize,
 }
 
@@ -67,6 +151,9 @@ enum DecoderImpl {
     Fixed(EobiSbeDecoder), // FixedBinary -> EOBI/SBE-like
     Fast(FastEmdiDecoder),
     Itch(Itch50Decoder),
+    Pitch(CboePitchDecoder),
+    Mdp3(CmeMdp3Decoder),
+    Custom(Arc<dyn MessageDecoder>),
 }
 
 impl DecoderImpl {
@@ -76,6 +163,9 @@ impl DecoderImpl {
             DecoderImpl::Fixed(d) => d.decode_messages(payload, out),
             DecoderImpl::Fast(d) => d.decode_messages(payload, out),
             DecoderImpl::Itch(d) => d.decode_messages(payload, out),
+            DecoderImpl::Pitch(d) => d.decode_messages(payload, out),
+            DecoderImpl::Mdp3(d) => d.decode_messages(payload, out),
+            DecoderImpl::Custom(d) => d.decode_messages(payload, out),
         }
     }
 }
@@ -85,28 +175,107 @@ impl Parser {
     pub fn seq_extractor(&self) -> Arc<dyn SeqExtractor> {
         self.seq.clone()
     }
+    /// Decode the messages of a packet as received.
     #[inline]
     pub fn decode_into(&self, payload: &[u8], out: &mut Vec<Event>) {
-        self.dec.decode(payload, out)
+        self.decode_messages_into(payload.get(self.frame_len..).unwrap_or_default(), out)
+    }
+    /// Decode messages without the packet header (e.g. ITCH from a SoupBinTCP snapshot).
+    #[inline]
+    pub fn decode_messages_into(&self, messages: &[u8], out: &mut Vec<Event>) {
+        self.dec.decode(messages, out)
     }
 }
 
+/// Decoder and sequence extractor supplied by embedding code for `kind = "custom:<name>"`.
+#[allow(dead_code)] // built by library embedders, not the receiver
+pub struct CustomDecoder {
+    pub decoder: Arc<dyn MessageDecoder>,
+    pub seq: Arc<dyn SeqExtractor>,
+}
+
+type DecoderFactory = Arc<dyn Fn(&SeqCfg) -> CustomDecoder + Send + Sync>;
+
+static CUSTOM_DECODERS: Lazy<RwLock<HashMap<String, DecoderFactory>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Register a custom decoder under `name`, selectable as `parser.kind = "custom:<name>"`.
+/// The factory runs once per `build_parser` call so stateful decoders start fresh.
+/// Must be called before the parser is built; duplicate names are rejected.
+#[allow(dead_code)] // called by library embedders, not the receiver
+pub fn register_decoder<F>(name: &str, factory: F) -> anyhow::Result<()>
+where
+    F: Fn(&SeqCfg) -> CustomDecoder + Send + Sync + 'static,
+{
+    let mut reg = CUSTOM_DECODERS.write().unwrap();
+    if reg.contains_key(name) {
+        anyhow::bail!("decoder \"{name}\" is already registered");
+    }
+    reg.insert(name.to_string(), Arc::new(factory));
+    Ok(())
+}
+
+/// Whether a decoder is registered as `name` (in this crate's registry).
+pub fn decoder_registered(name: &str) -> bool {
+    CUSTOM_DECODERS.read().unwrap().contains_key(name)
+}
+
+/// `session_utc_offset_s` anchors protocols that stamp messages relative to the venue's
+/// local midnight (ITCH, PITCH) so their exchange timestamps come out as Unix epoch ns.
 pub fn build_parser(
     kind: ParserKind,
     seq: SeqCfg,
     max_per_packet: usize,
+    session_utc_offset_s: i32,
 ) -> anyhow::Result<Parser> {
-    let seq_impl: Arc<dyn SeqExtractor> = Arc::new(FixedSeq { cfg: seq.clone() });
+    // Custom decoders bring their own sequence extractor
+    if let ParserKind::Custom(name) = &kind {
+        let factory = CUSTOM_DECODERS
+            .read()
+            .unwrap()
+            .get(name.as_str())
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no decoder registered as \"custom:{name}\""))?;
+        let custom = factory(&seq);
+        return Ok(Parser {
+            seq: custom.seq,
+            dec: DecoderImpl::Custom(custom.decoder),
+            frame_len: 0,
+            max_messages_per_packet: max_per_packet.max(1),
+        });
+    }
+
+    let seq_impl: Arc<dyn SeqExtractor> = match kind {
+        // PITCH sequences live in the Sequenced Unit Header, not at a fixed offset
+        ParserKind::Pitch => Arc::new(PitchUnitSeq::new(seq.unit)),
+        // MDP 3.0 sequences live in the Binary Packet Header
+        ParserKind::Mdp3 => Arc::new(Mdp3PacketSeq),
+        // MoldUDP64 numbers messages, so a packet spans its message count
+        ParserKind::Itch50 if seq.moldudp64 => Arc::new(MoldSeq),
+        _ => Arc::new(FixedSeq { cfg: seq.clone() }),
+    };
+    let frame_len = match kind {
+        ParserKind::Itch50 if seq.moldudp64 => MOLD_HEADER_LEN,
+        _ => 0,
+    };
 
     let dec_impl: DecoderImpl = match kind {
         ParserKind::FixedBinary => DecoderImpl::Fixed(EobiSbeDecoder::new()),
         ParserKind::FastLike => DecoderImpl::Fast(FastEmdiDecoder::new()),
-        ParserKind::Itch50 => DecoderImpl::Itch(Itch50Decoder::new()),
+        ParserKind::Itch50 => {
+            DecoderImpl::Itch(Itch50Decoder::with_session_offset(session_utc_offset_s))
+        }
+        ParserKind::Pitch => {
+            DecoderImpl::Pitch(CboePitchDecoder::with_session_offset(session_utc_offset_s))
+        }
+        ParserKind::Mdp3 => DecoderImpl::Mdp3(CmeMdp3Decoder::new()),
+        ParserKind::Custom(_) => unreachable!("handled above"),
     };
 
     Ok(Parser {
         seq: seq_impl,
         dec: dec_impl,
+        frame_len,
         max_messages_per_packet: max_per_packet.max(1),
     })
 }
@@ -146,9 +315,144 @@ impl SeqExtractor for FixedSeq {
             _ => None,
         }
     }
+
+    #[inline]
+    fn extract_stream_seq(&self, pkt: &[u8]) -> Option<(u32, u64)> {
+        let seq = self.extract_seq(pkt)?;
+        let Some((off, len)) = self.cfg.stream else {
+            return Some((0, seq));
+        };
+        let b = pkt.get(off as usize..off as usize + len as usize)?;
+        let stream = match (len, &self.cfg.endian) {
+            (1, _) => b[0] as u32,
+            (2, Endian::Be) => u16::from_be_bytes([b[0], b[1]]) as u32,
+            (2, Endian::Le) => u16::from_le_bytes([b[0], b[1]]) as u32,
+            (4, Endian::Be) => u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            (4, Endian::Le) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            _ => return None,
+        };
+        Some((stream, seq))
+    }
 }
 
 // FixedBinaryDecoder was a synthetic format used for bring-up. It has been
 // replaced by a real EOBI/SBE-like implementation in `decoder_eobi.rs`.
 
 // FastLike is implemented by FastEmdiDecoder in decoder_fast.rs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FirstByteSeq;
+    impl SeqExtractor for FirstByteSeq {
+        fn extract_seq(&self, pkt: &[u8]) -> Option<u64> {
+            pkt.first().map(|b| *b as u64)
+        }
+    }
+
+    struct HeartbeatPerByte;
+    impl MessageDecoder for HeartbeatPerByte {
+        fn decode_messages(&self, payload: &[u8], out: &mut Vec<Event>) {
+            out.extend(payload.iter().map(|_| Event::Heartbeat));
+        }
+    }
+
+    fn seq_cfg() -> SeqCfg {
+        SeqCfg {
+            offset: 0,
+            length: 4,
+            endian: Endian::Le,
+            unit: None,
+            stream: None,
+            moldudp64: false,
+        }
+    }
+
+    #[test]
+    fn custom_decoder_selected_by_name() {
+        register_decoder("test_hb", |_| CustomDecoder {
+            decoder: Arc::new(HeartbeatPerByte),
+            seq: Arc::new(FirstByteSeq),
+        })
+        .unwrap();
+        assert!(register_decoder("test_hb", |_| CustomDecoder {
+            decoder: Arc::new(HeartbeatPerByte),
+            seq: Arc::new(FirstByteSeq),
+        })
+        .is_err());
+
+        let p = build_parser(ParserKind::Custom("test_hb".into()), seq_cfg(), 16, 0).unwrap();
+        assert_eq!(p.seq_extractor().extract_seq(&[7, 0, 0]), Some(7));
+        let mut out = Vec::new();
+        p.decode_into(&[1, 2, 3], &mut out);
+        assert_eq!(out.len(), 3);
+
+        assert!(build_parser(ParserKind::Custom("missing".into()), seq_cfg(), 16, 0).is_err());
+    }
+
+    #[test]
+    fn moldudp64_itch_reads_header_and_decodes_messages() {
+        let cfg = SeqCfg {
+            moldudp64: true,
+            ..seq_cfg()
+        };
+        let p = build_parser(ParserKind::Itch50, cfg, 16, 0).unwrap();
+        let mut pkt = b"SESSION001".to_vec();
+        pkt.extend_from_slice(&41u64.to_be_bytes());
+        pkt.extend_from_slice(&2u16.to_be_bytes());
+        for order in [1u64, 2] {
+            let mut m = vec![b'A', 0, 7, 0, 0, 0, 0, 0, 0, 0, 0];
+            m.extend_from_slice(&order.to_be_bytes());
+            m.push(b'B');
+            m.extend_from_slice(&100u32.to_be_bytes());
+            m.extend_from_slice(b"AAPL    ");
+            m.extend_from_slice(&1_500_000u32.to_be_bytes());
+            pkt.extend_from_slice(&(m.len() as u16).to_be_bytes());
+            pkt.extend_from_slice(&m);
+        }
+        let seq = p.seq_extractor();
+        assert_eq!(seq.extract_seq(&pkt), Some(41));
+        assert_eq!(seq.seq_span(&pkt), 2);
+        let mut out = Vec::new();
+        p.decode_into(&pkt, &mut out);
+        assert_eq!(out.len(), 2);
+
+        // Heartbeats carry the next sequence but no messages
+        let hb = &mut pkt[..20];
+        hb[18..20].copy_from_slice(&0u16.to_be_bytes());
+        assert_eq!(seq.extract_seq(hb), None);
+    }
+
+    #[test]
+    fn fixed_seq_reads_stream_id() {
+        let cfg = SeqCfg {
+            stream: Some((4, 2)),
+            ..seq_cfg()
+        };
+        let p = build_parser(ParserKind::FixedBinary, cfg, 16, 0).unwrap();
+        let pkt = [9, 0, 0, 0, 0x02, 0x01];
+        assert_eq!(
+            p.seq_extractor().extract_stream_seq(&pkt),
+            Some((0x0102, 9))
+        );
+        // Too short for the stream id
+        assert_eq!(p.seq_extractor().extract_stream_seq(&pkt[..5]), None);
+        let plain = build_parser(ParserKind::FixedBinary, seq_cfg(), 16, 0).unwrap();
+        assert_eq!(plain.seq_extractor().extract_stream_seq(&pkt), Some((0, 9)));
+    }
+
+    #[test]
+    fn parser_kind_from_config_string() {
+        assert!(matches!(
+            ParserKind::try_from("mdp3".to_string()),
+            Ok(ParserKind::Mdp3)
+        ));
+        assert!(matches!(
+            ParserKind::try_from("custom:venue_x".to_string()),
+            Ok(ParserKind::Custom(n)) if n == "venue_x"
+        ));
+        assert!(ParserKind::try_from("custom:".to_string()).is_err());
+        assert!(ParserKind::try_from("nope".to_string()).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 // src/pool.rs
 use bytes::BytesMut;
 use crossbeam::queue::ArrayQueue;
+use std::alloc::{self, Layout};
 use std::slice;
 use std::sync::Arc;
 
@@ -33,6 +34,12 @@ impl PacketPool {
         }
     }
 
+    #[inline]
+    #[allow(dead_code)] // only the io_uring receiver sizes buffers up front
+    pub fn max_packet_size(&self) -> usize {
+        self.max_packet_size
+    }
+
     #[inline]
     pub fn put(&self, mut buf: BytesMut) {
         buf.truncate(0);
@@ -50,6 +57,118 @@ pub enum TsKind {
     HwRaw = 3,
 }
 
+/// Page-aligned packet buffer area shared with the kernel by an AF_XDP socket.
+///
+/// Frames handed to the pipeline are leased as `UmemFrame`s; dropping a lease pushes
+/// the frame address onto `returned`, which the owning RX loop drains into its fill ring.
+/// The area outlives every lease, so payload pointers stay valid after the socket closes.
+#[allow(dead_code)] // only the AF_XDP receiver creates areas
+pub struct UmemArea {
+    base: *mut u8,
+    layout: Layout,
+    frame_size: u32,
+    returned: ArrayQueue<u64>,
+}
+
+// Safety: the area is only written by the kernel into frames owned by the fill/RX
+// rings; userspace reads a frame only while it holds the corresponding lease.
+unsafe impl Send for UmemArea {}
+unsafe impl Sync for UmemArea {}
+
+#[allow(dead_code)]
+impl UmemArea {
+    pub fn new(frame_count: u32, frame_size: u32) -> anyhow::Result<Arc<Self>> {
+        if frame_count == 0 || !frame_size.is_power_of_two() {
+            anyhow::bail!("UMEM needs frames > 0 and a power-of-two frame size");
+        }
+        let len = frame_count as usize * frame_size as usize;
+        let layout = Layout::from_size_align(len, 4096)?;
+        let base = unsafe { alloc::alloc_zeroed(layout) };
+        if base.is_null() {
+            anyhow::bail!("UMEM allocation of {len} bytes failed");
+        }
+        Ok(Arc::new(Self {
+            base,
+            layout,
+            frame_size,
+            returned: ArrayQueue::new(frame_count as usize),
+        }))
+    }
+
+    #[inline]
+    pub fn base(&self) -> *mut u8 {
+        self.base
+    }
+
+    #[inline]
+    pub fn byte_len(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Lease the frame containing `addr` and expose `len` bytes at `data_off` (offset into the area).
+    ///
+    /// # Safety
+    /// The caller must own the frame (consumed from the RX ring, not yet refilled) and
+    /// `data_off + len` must lie inside it.
+    #[inline]
+    pub unsafe fn lease(self: &Arc<Self>, addr: u64, data_off: usize, len: usize) -> PktBuf {
+        PktBuf::Umem {
+            ptr: self.base.add(data_off),
+            len,
+            frame: UmemFrame {
+                addr: addr & !(self.frame_size as u64 - 1),
+                area: self.clone(),
+            },
+        }
+    }
+
+    /// Frame address returned by a dropped lease, ready for the fill ring.
+    #[inline]
+    pub fn pop_returned(&self) -> Option<u64> {
+        self.returned.pop()
+    }
+
+    /// Hand a frame back without leasing it (e.g. filtered packets).
+    #[inline]
+    pub fn give_back(&self, addr: u64) {
+        let _ = self.returned.push(addr & !(self.frame_size as u64 - 1));
+    }
+}
+
+impl Drop for UmemArea {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.base, self.layout) }
+    }
+}
+
+/// Ownership of one UMEM frame while its packet travels through the pipeline.
+pub struct UmemFrame {
+    addr: u64,
+    area: Arc<UmemArea>,
+}
+
+impl UmemFrame {
+    #[allow(dead_code)]
+    pub fn addr(&self) -> u64 {
+        self.addr
+    }
+}
+
+impl Drop for UmemFrame {
+    fn drop(&mut self) {
+        // Capacity equals the frame count, so this cannot fail
+        let _ = self.area.returned.push(self.addr);
+    }
+}
+
+impl std::fmt::Debug for UmemFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UmemFrame")
+            .field("addr", &self.addr)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum PktBuf {
     Bytes(BytesMut),
@@ -57,16 +176,29 @@ pub enum PktBuf {
     Umem {
         ptr: *mut u8,
         len: usize,
-        frame_idx: u32,
+        frame: UmemFrame,
     },
 }
 
+/// `Pkt.chan` of packets injected by the recovery client
+pub const CHAN_RECOVERY: u8 = u8::MAX;
+/// `Pkt.chan` of merge control packets; the payload is a `codec_raw::FeedGapV1`
+pub const CHAN_CONTROL: u8 = u8::MAX - 1;
+
 #[derive(Debug)]
 pub struct Pkt {
     pub buf: PktBuf,
     pub len: usize,
     pub seq: u64,
+    /// Sequence stream `seq` belongs to, for feeds multiplexing several on one group (else 0)
+    pub stream: u32,
+    /// Sequence numbers covered by this packet (1 unless the feed numbers messages)
+    pub seq_span: u32,
+    /// The sequence extractor flagged this packet as an explicit sequence reset
+    pub seq_reset: bool,
     pub ts_nanos: u64,
+    /// Index of the receiving line in its partition (0 = A, 1 = B, then `extra`),
+    /// `CHAN_RECOVERY` for replayed packets or `CHAN_CONTROL` for merge control
     pub chan: u8,
     pub _ts_kind: TsKind,
     /// Timestamp when merge forwarded the packet to decode queue
@@ -74,8 +206,8 @@ pub struct Pkt {
 }
 
 // Safety: Packet buffers are transferred across threads via SPSC queues.
-// BytesMut is Send. The UMEM pointer represents a frame owned by the runtime
-// which is reclaimed out of band; we treat it as Send here.
+// BytesMut is Send. The UMEM pointer is backed by the `UmemFrame` lease stored
+// alongside it, which keeps the area alive and the frame out of the fill ring.
 unsafe impl Send for Pkt {}
 
 impl Pkt {
@@ -93,7 +225,41 @@ impl Pkt {
     pub fn recycle(self, pool: &PacketPool) {
         match self.buf {
             PktBuf::Bytes(b) => pool.put(b),
-            PktBuf::Umem { .. } => { /* TODO: return to UMEM completion ring */ }
+            // Dropping the lease queues the frame for the RX loop's fill ring
+            PktBuf::Umem { frame, .. } => drop(frame),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn umem_frame_returns_on_recycle_and_drop() {
+        let area = UmemArea::new(4, 2048).unwrap();
+        let pool = PacketPool::new(1, 64).unwrap();
+        unsafe { area.base().add(2048 + 64).write(0xAB) };
+        let mk = |addr: u64, off: usize| Pkt {
+            buf: unsafe { area.lease(addr, off, 1) },
+            len: 1,
+            seq: 1,
+            stream: 0,
+            seq_span: 1,
+            seq_reset: false,
+            ts_nanos: 0,
+            chan: 0,
+            _ts_kind: TsKind::None,
+            merge_emit_ns: 0,
+        };
+        let p = mk(2048 + 64, 2048 + 64);
+        assert_eq!(p.payload(), &[0xAB]);
+        assert!(area.pop_returned().is_none());
+        p.recycle(&pool);
+        assert_eq!(area.pop_returned(), Some(2048));
+        // Dropped without recycle (e.g. merge discarding a duplicate)
+        drop(mk(3 * 2048 + 10, 3 * 2048 + 10));
+        assert_eq!(area.pop_returned(), Some(3 * 2048));
+        assert!(area.pop_returned().is_none());
+    }
+}
@@ -3,7 +3,7 @@ use hashbrown::HashMap;
 use std::collections::VecDeque;
 use std::sync::{Arc, Condvar, Mutex};
 
-use crate::codec_raw::{self, FrameHeaderV1};
+use crate::codec_raw::{self, FrameHeaderV2};
 use crate::util::now_nanos;
 use zerocopy::AsBytes;
 
@@ -81,19 +81,21 @@ impl Publisher {
         channel_id: u32,
         instrument_id: u64,
         sequence: u64,
+        exchange_time_ns: u64,
         payload: &[u8],
     ) {
         let mut frame =
-            BytesMut::with_capacity(std::mem::size_of::<FrameHeaderV1>() + payload.len());
-        let hdr = FrameHeaderV1 {
+            BytesMut::with_capacity(std::mem::size_of::<FrameHeaderV2>() + payload.len());
+        let hdr = FrameHeaderV2 {
             magic: codec_raw::MAGIC,
-            version: codec_raw::VERSION_V1,
+            version: codec_raw::VERSION_V2,
             codec: codec_raw::codec::RAW_V1,
             message_type,
             channel_id,
             instrument_id,
             sequence,
             send_time_ns: now_nanos(),
+            exchange_time_ns,
             payload_len: payload.len() as u32,
         };
         frame.extend_from_slice(hdr.as_bytes());
@@ -1,31 +1,113 @@
 // src/recovery.rs
+use crate::config::RecoveryCfg;
 use crate::metrics;
 use bytes::BufMut;
-use crossbeam_channel::{Receiver, Sender};
-use std::fs::OpenOptions;
-use std::io::Write as IoWrite;
-use std::sync::Arc;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, TrySendError};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
+
+/// How often a recovery thread looks at the spill and due retries while idle
+const TICK: Duration = Duration::from_millis(5);
+/// Spilled requests kept before new ones are dropped (after coalescing)
+const SPILL_MAX: usize = 4096;
 
 #[derive(Debug, Clone)]
 pub enum RecoveryRequest {
-    /// Request to recover [from, to] inclusive range (sequence numbers).
-    Gap { from: u64, to: u64 },
+    /// Request to recover [from, to] inclusive range (sequence numbers) of `stream`.
+    Gap { stream: u32, from: u64, to: u64 },
+    /// Merge gave up on a gap under `on_give_up = "resync"`. The decode stage rebuilds the
+    /// books from the configured snapshot source (`glimpse`, `snapshot_feed`) on the
+    /// in-band FEED_GAP; replay transports only take note.
+    Snapshot,
 }
 
 pub struct Client {
     tx: Sender<RecoveryRequest>,
+    spill: Arc<Spill>,
 }
 
 impl Client {
+    fn new(tx: Sender<RecoveryRequest>) -> (Self, Arc<Spill>) {
+        let spill = Arc::new(Spill::default());
+        (
+            Self {
+                tx,
+                spill: spill.clone(),
+            },
+            spill,
+        )
+    }
+
     pub fn notify_gap(&self, from: u64, to: u64) {
-        let _ = self.tx.try_send(RecoveryRequest::Gap { from, to });
+        self.notify_stream_gap(0, from, to);
+    }
+
+    pub fn notify_stream_gap(&self, stream: u32, from: u64, to: u64) {
+        self.send(RecoveryRequest::Gap { stream, from, to });
+    }
+
+    pub fn request_snapshot(&self) {
+        self.send(RecoveryRequest::Snapshot);
+    }
+
+    /// Never blocks the merge: a full channel spills instead of dropping the request.
+    #[inline]
+    fn send(&self, req: RecoveryRequest) {
+        if let Err(TrySendError::Full(req)) = self.tx.try_send(req) {
+            self.spill.push(req);
+        }
+    }
+}
+
+/// Requests that found the channel full, coalesced per stream until the recovery
+/// thread picks them up on its next tick.
+#[derive(Default)]
+struct Spill(Mutex<Vec<RecoveryRequest>>);
+
+impl Spill {
+    #[cold]
+    fn push(&self, req: RecoveryRequest) {
+        metrics::inc_recovery_spilled();
+        let mut v = self.0.lock().unwrap();
+        if let RecoveryRequest::Gap { stream, from, to } = req {
+            if let Some(RecoveryRequest::Gap {
+                stream: s,
+                from: lo,
+                to: hi,
+            }) = v.last_mut()
+            {
+                if *s == stream && from <= hi.saturating_add(1) && to >= lo.saturating_sub(1) {
+                    *lo = (*lo).min(from);
+                    *hi = (*hi).max(to);
+                    return;
+                }
+            }
+        } else if v.iter().any(|r| matches!(r, RecoveryRequest::Snapshot)) {
+            return;
+        }
+        if v.len() >= SPILL_MAX {
+            metrics::inc_recovery_dropped();
+            return;
+        }
+        v.push(req);
+    }
+
+    fn take(&self) -> Vec<RecoveryRequest> {
+        std::mem::take(&mut *self.0.lock().unwrap())
     }
 }
 
 /// Trait for pluggable replayers to unify gap notifications across components.
 pub trait Replayer: Send + Sync {
     fn notify_gap(&self, from: u64, to: u64);
+    /// Gap in one of several sequence streams multiplexed on the feed; stream 0 is the
+    /// only stream of single-stream feeds.
+    fn notify_stream_gap(&self, _stream: u32, from: u64, to: u64) {
+        self.notify_gap(from, to);
+    }
+    /// Ask for a full snapshot after merge abandoned a gap; no-op unless supported.
+    fn request_snapshot(&self) {}
 }
 
 pub type RecoveryClient = Arc<dyn Replayer>;
@@ -35,6 +117,14 @@ impl Replayer for Client {
     fn notify_gap(&self, from: u64, to: u64) {
         self.notify_gap(from, to);
     }
+    #[inline]
+    fn notify_stream_gap(&self, stream: u32, from: u64, to: u64) {
+        self.notify_stream_gap(stream, from, to);
+    }
+    #[inline]
+    fn request_snapshot(&self) {
+        self.request_snapshot();
+    }
 }
 
 pub struct RecoveryHandle {
@@ -52,130 +142,647 @@ impl RecoveryHandle {
 /// Replace internals with exchange-specific replay logic.
 pub fn spawn_logger() -> (RecoveryClient, RecoveryHandle) {
     let (tx, rx) = crossbeam_channel::bounded::<RecoveryRequest>(1024);
+    let (cli, spill) = Client::new(tx);
     let join = std::thread::Builder::new()
         .name("recovery".into())
-        .spawn(move || run(rx))
+        .spawn(move || run(rx, spill))
         .expect("spawn recovery");
-    let client: RecoveryClient = Arc::new(Client { tx });
+    let client: RecoveryClient = Arc::new(cli);
     (client, RecoveryHandle { _join: join })
 }
 
-fn run(rx: Receiver<RecoveryRequest>) {
+fn run(rx: Receiver<RecoveryRequest>, spill: Arc<Spill>) {
     log::info!("recovery manager running (logger mode)");
     let mut last_log_ns: u64 = 0;
-    while let Ok(req) = rx.recv() {
-        match req {
-            RecoveryRequest::Gap { from, to } => {
-                let now = crate::util::now_nanos();
-                if now.saturating_sub(last_log_ns) >= 100_000_000 {
-                    last_log_ns = now;
-                    log::warn!("GAP detected; recommend out-of-band recovery for [{from}..{to}]");
-                }
-                // TODO: integrate TCP/unicast replay client here.
+    loop {
+        let mut reqs = spill.take();
+        match rx.recv_timeout(TICK) {
+            Ok(req) => reqs.push(req),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        for req in reqs {
+            log_request(req, &mut last_log_ns);
+        }
+    }
+}
+
+fn log_request(req: RecoveryRequest, last_log_ns: &mut u64) {
+    match req {
+        RecoveryRequest::Gap { stream, from, to } => {
+            let now = crate::util::now_nanos();
+            if now.saturating_sub(*last_log_ns) >= 100_000_000 {
+                *last_log_ns = now;
+                log::warn!(
+                    "GAP detected; recommend out-of-band recovery for [{from}..{to}] (stream {stream})"
+                );
             }
         }
+        RecoveryRequest::Snapshot => {
+            log::warn!("snapshot resync requested; books are rebuilt from the snapshot source");
+        }
     }
 }
 
 // -------------------- Optional: TCP replay injector --------------------
-// Feed recovered sequences directly into the merged decode queue. Keeps
+// Feed recovered sequences directly into the recovery->merge queue. Keeps
 // the Pkt contract intact. The on-wire replay protocol is venue-specific;
 // replace the body of `fetch_and_inject` accordingly.
+//
+// Requested ranges stay in an outstanding-gap table until every sequence in them
+// came back. Requests go over one persistent connection (TCP keepalive) with a
+// per-request deadline; whatever a response left out is re-requested with
+// exponential backoff, on the next endpoint if the connection failed, and given up
+// after `max_attempts`.
 
 use crate::pool::{PacketPool, Pkt, PktBuf, TsKind};
+use crate::recovery_journal::Journal;
 use crate::spsc::SpscQueue;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Instant;
 
-pub fn spawn_tcp_injector<A: std::net::ToSocketAddrs + Send + 'static>(
-    addr: A,
+/// TCP injector settings for one partition, from `[recovery]`.
+pub struct InjectorCfg {
+    /// Replay endpoints (host:port), tried in order on failure
+    pub endpoints: Vec<String>,
+    pub connect_timeout: Duration,
+    /// Deadline for a whole response; what did not arrive by then is retried
+    pub request_timeout: Duration,
+    /// Requests per range before it is abandoned
+    pub max_attempts: u32,
+    /// First retry delay, doubled per attempt up to `backoff_max`
+    pub backoff: Duration,
+    pub backoff_max: Duration,
+    /// TCP keepalive idle time on the replay connection (None = off)
+    pub keepalive: Option<Duration>,
+    /// Ranges tracked at once; gaps beyond this are abandoned straight away
+    pub max_outstanding: usize,
+    /// Optional path to the replay journal (see recovery_journal.rs)
+    pub backlog_path: Option<String>,
+    /// Re-request ranges the journal left unresolved at start; else report and close them
+    pub resume_backlog: bool,
+    /// Venue session the journaled ranges belong to; ranges of another session are
+    /// never re-requested (see `session_key`)
+    pub session: String,
+    /// Partition label for metrics and logs
+    pub partition: String,
+}
+
+impl InjectorCfg {
+    /// Defaults for the given endpoints.
+    pub fn new(endpoints: Vec<String>, partition: &str) -> Self {
+        Self {
+            endpoints,
+            connect_timeout: Duration::from_millis(100),
+            request_timeout: Duration::from_millis(200),
+            max_attempts: 5,
+            backoff: Duration::from_millis(10),
+            backoff_max: Duration::from_millis(500),
+            keepalive: Some(Duration::from_secs(5)),
+            max_outstanding: 4096,
+            backlog_path: None,
+            resume_backlog: false,
+            session: String::new(),
+            partition: partition.to_string(),
+        }
+    }
+
+    /// Build from `[recovery]`; `primary` (the partition's `recovery_endpoint`) replaces
+    /// `endpoint`. With several partitions each backlog goes to `<path>.<partition>`.
+    /// `session_utc_offset_s` (`parser.session_utc_offset_s`) dates the venue session.
+    pub fn from_cfg(
+        cfg: &RecoveryCfg,
+        primary: Option<&str>,
+        partition: &str,
+        n_partitions: usize,
+        session_utc_offset_s: i32,
+    ) -> Self {
+        let mut endpoints = vec![primary.unwrap_or(&cfg.endpoint).to_string()];
+        endpoints.extend(cfg.fallback_endpoints.iter().cloned());
+        Self {
+            connect_timeout: Duration::from_millis(cfg.connect_timeout_ms),
+            request_timeout: Duration::from_millis(cfg.request_timeout_ms),
+            max_attempts: cfg.max_attempts,
+            backoff: Duration::from_millis(cfg.retry_backoff_ms),
+            backoff_max: Duration::from_millis(cfg.retry_backoff_max_ms),
+            keepalive: (cfg.keepalive_ms > 0).then(|| Duration::from_millis(cfg.keepalive_ms)),
+            max_outstanding: cfg.max_outstanding,
+            // Partitions have independent sequence spaces; keep their backlogs apart
+            backlog_path: cfg.backlog_path.as_ref().map(|p| {
+                if n_partitions > 1 {
+                    format!("{p}.{partition}")
+                } else {
+                    p.clone()
+                }
+            }),
+            resume_backlog: cfg.resume_backlog,
+            session: session_key(
+                session_utc_offset_s,
+                cfg.moldudp64.as_ref().map(|m| m.session.as_str()),
+            ),
+            ..Self::new(endpoints, partition)
+        }
+    }
+}
+
+/// Journal session key: the venue's session date (`YYYYMMDD`, venue local time) and,
+/// for MoldUDP64, the session name. Venues restart their sequences with either.
+pub fn session_key(session_utc_offset_s: i32, mold_session: Option<&str>) -> String {
+    let local = crate::util::session_midnight_ns(session_utc_offset_s) as i64
+        + session_utc_offset_s as i64 * 1_000_000_000;
+    let date = chrono::DateTime::from_timestamp_nanos(local).format("%Y%m%d");
+    match mold_session {
+        Some(s) => format!("{date}/{}", s.trim().replace(' ', "_")),
+        None => date.to_string(),
+    }
+}
+
+pub fn spawn_tcp_injector(
+    cfg: InjectorCfg,
     q_recovery: Arc<SpscQueue<Pkt>>, // dedicated recovery->merge SPSC queue
     pool: Arc<PacketPool>,
-    backlog_path: Option<String>,
+) -> (RecoveryClient, RecoveryHandle) {
+    let fetch = TcpFetch {
+        conn: None,
+        connect_timeout: cfg.connect_timeout,
+        keepalive: cfg.keepalive,
+        q_recovery,
+        pool,
+        partition: cfg.partition.clone(),
+    };
+    spawn_injector("recovery-tcp", cfg, fetch)
+}
+
+/// How an injector asks its replay service for a range: the TCP `REPLAY` protocol
+/// here, MoldUDP64 re-requests in `recovery_mold.rs`.
+pub(crate) trait Fetch: Send + 'static {
+    /// Transport name for logs
+    const KIND: &'static str;
+
+    /// Request `[from, to]` of `stream` from `endpoint` and inject what arrives before
+    /// `deadline`, noting every sequence received in `got`.
+    fn fetch(
+        &mut self,
+        endpoint: &str,
+        stream: u32,
+        from: u64,
+        to: u64,
+        deadline: Instant,
+        got: &mut Vec<u64>,
+    ) -> anyhow::Result<()>;
+
+    /// Most sequences one `fetch` is handed; the rest of a longer gap stays in the
+    /// table and is requested next.
+    fn max_range(&self) -> u64 {
+        u64::MAX
+    }
+
+    /// Drop any connection state after a failed fetch.
+    fn reset(&mut self);
+}
+
+/// Run an outstanding-gap manager over `fetch` on its own thread.
+pub(crate) fn spawn_injector<F: Fetch>(
+    name: &str,
+    cfg: InjectorCfg,
+    fetch: F,
 ) -> (RecoveryClient, RecoveryHandle) {
     let (tx, rx) = crossbeam_channel::bounded::<RecoveryRequest>(1024);
+    let (cli, spill) = Client::new(tx);
     let join = std::thread::Builder::new()
-        .name("recovery-tcp".into())
-        .spawn(move || run_injector(addr, q_recovery, pool, rx, backlog_path))
+        .name(name.into())
+        .spawn(move || Injector::new(cfg, fetch, spill).run(rx))
         .expect("spawn recovery injector");
-    let client: RecoveryClient = Arc::new(Client { tx });
+    let client: RecoveryClient = Arc::new(cli);
     (client, RecoveryHandle { _join: join })
 }
 
-fn run_injector<A: std::net::ToSocketAddrs>(
-    addr: A,
-    q_recovery: Arc<SpscQueue<Pkt>>, // recovery->merge input
-    pool: Arc<PacketPool>,
-    rx: Receiver<RecoveryRequest>,
-    backlog_path: Option<String>,
-) {
-    log::info!(
-        "recovery injector running (tcp={:?})",
-        addr.to_socket_addrs().ok().and_then(|mut it| it.next())
-    );
-    let mut backlog =
-        backlog_path.and_then(|p| OpenOptions::new().create(true).append(true).open(p).ok());
-    // Simple coalescing of pending gaps: on each received gap, drain additional
-    // requests non-blockingly and merge overlapping/adjacent ranges before fetch.
-    while let Ok(first) = rx.recv() {
-        let (mut lo, mut hi) = match first {
-            RecoveryRequest::Gap { from, to } => (from, to),
-        };
-        if lo > hi {
-            continue;
+/// A range of one stream still (partly) missing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Gap {
+    stream: u32,
+    from: u64,
+    to: u64,
+    /// When merge reported it; fill latency is measured from here
+    since_ns: u64,
+    attempts: u32,
+    due_ns: u64,
+}
+
+/// Outstanding gaps, disjoint within each stream
+struct GapTable {
+    gaps: Vec<Gap>,
+    max: usize,
+}
+
+impl GapTable {
+    fn new(max: usize) -> Self {
+        Self {
+            gaps: Vec::new(),
+            max,
         }
-        // Drain more and coalesce
-        while let Ok(next) = rx.try_recv() {
-            let (from, to) = match next {
-                RecoveryRequest::Gap { from, to } => (from, to),
-            };
-            if from <= hi.saturating_add(1) && to >= lo.saturating_sub(1) {
-                // overlap or adjacent
-                if from < lo {
-                    lo = from;
-                }
-                if to > hi {
-                    hi = to;
-                }
+    }
+
+    /// Track `[from, to]` of `stream` minus what is already outstanding; new ranges
+    /// next to a gap not requested yet are folded into it. Returns ranges that did not
+    /// fit under `max`.
+    fn insert(&mut self, stream: u32, from: u64, to: u64, now: u64) -> Vec<(u64, u64)> {
+        let mut overflow = Vec::new();
+        for (lo, hi) in self.uncovered(stream, from, to) {
+            let fresh = |g: &Gap| g.stream == stream && g.attempts == 0;
+            let after = self.gaps.iter().position(|g| fresh(g) && g.to + 1 == lo);
+            let before = self.gaps.iter().position(|g| fresh(g) && hi + 1 == g.from);
+            if let Some(i) = after {
+                self.gaps[i].to = hi;
+            } else if let Some(i) = before {
+                self.gaps[i].from = lo;
+            } else if self.gaps.len() < self.max {
+                self.gaps.push(Gap {
+                    stream,
+                    from: lo,
+                    to: hi,
+                    since_ns: now,
+                    attempts: 0,
+                    due_ns: now,
+                });
             } else {
-                // Non-overlapping; log individually
-                if let Some(f) = backlog.as_mut() {
-                    let _ = writeln!(f, "gap {} {}", from, to);
-                    let _ = f.flush();
-                }
+                overflow.push((lo, hi));
             }
         }
-        if let Some(f) = backlog.as_mut() {
-            let _ = writeln!(f, "gap {} {}", lo, hi);
-            let _ = f.flush();
+        overflow
+    }
+
+    /// Parts of `[from, to]` of `stream` not outstanding yet.
+    fn uncovered(&self, stream: u32, from: u64, to: u64) -> Vec<(u64, u64)> {
+        let mut pieces = vec![(from, to)];
+        for g in self.gaps.iter().filter(|g| g.stream == stream) {
+            pieces = pieces
+                .into_iter()
+                .flat_map(|(lo, hi)| {
+                    if hi < g.from || lo > g.to {
+                        return vec![(lo, hi)];
+                    }
+                    let mut rest = Vec::new();
+                    if lo < g.from {
+                        rest.push((lo, g.from - 1));
+                    }
+                    if hi > g.to {
+                        rest.push((g.to + 1, hi));
+                    }
+                    rest
+                })
+                .collect();
+        }
+        pieces
+    }
+
+    /// Put back what is left of a requested gap.
+    fn requeue(&mut self, gap: Gap) {
+        self.gaps.push(gap);
+    }
+
+    /// Remove and return the gap due first, if it is due by `now`.
+    fn take_due(&mut self, now: u64) -> Option<Gap> {
+        let (i, _) = self
+            .gaps
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.due_ns <= now)
+            .min_by_key(|(_, g)| (g.due_ns, g.stream, g.from))?;
+        Some(self.gaps.swap_remove(i))
+    }
+
+    fn next_due(&self) -> Option<u64> {
+        self.gaps.iter().map(|g| g.due_ns).min()
+    }
+
+    fn len(&self) -> usize {
+        self.gaps.len()
+    }
+}
+
+/// Sub-ranges of `[from, to]` not in `got`.
+fn missing_ranges(from: u64, to: u64, got: &mut [u64]) -> Vec<(u64, u64)> {
+    got.sort_unstable();
+    let mut missing = Vec::new();
+    let mut next = from;
+    for &s in got.iter() {
+        if s < next || s > to {
+            continue;
         }
-        if let Err(e) = fetch_and_inject(&addr, lo, hi, &q_recovery, &pool) {
-            log::error!("replay fetch failed: {e:?}");
+        if s > next {
+            missing.push((next, s - 1));
         }
+        next = s + 1;
+    }
+    if next <= to {
+        missing.push((next, to));
     }
+    missing
 }
 
-fn fetch_and_inject<A: std::net::ToSocketAddrs>(
-    addr: &A,
+struct Injector<F> {
+    cfg: InjectorCfg,
+    fetch: F,
+    spill: Arc<Spill>,
+    table: GapTable,
+    endpoint: usize,
+    journal: Journal,
+}
+
+impl<F: Fetch> Injector<F> {
+    fn new(cfg: InjectorCfg, fetch: F, spill: Arc<Spill>) -> Self {
+        let journal = Journal::open(cfg.backlog_path.as_deref(), &cfg.partition, &cfg.session);
+        let mut inj = Self {
+            table: GapTable::new(cfg.max_outstanding.max(1)),
+            cfg,
+            fetch,
+            spill,
+            endpoint: 0,
+            journal,
+        };
+        inj.resume();
+        inj
+    }
+
+    /// Pick up what the journal left unresolved before a restart.
+    fn resume(&mut self) {
+        let left = self.journal.unresolved();
+        if left.is_empty() {
+            return;
+        }
+        let now = crate::util::now_nanos();
+        for (stream, from, to) in left {
+            if !self.cfg.resume_backlog {
+                log::warn!(
+                    "[{}] replay of {from}..={to} (stream {stream}) was unresolved at shutdown",
+                    self.cfg.partition
+                );
+                self.journal.abandoned(stream, from, to, "restart");
+                continue;
+            }
+            for (lo, hi) in self.table.insert(stream, from, to, now) {
+                self.journal.abandoned(stream, lo, hi, "table_full");
+            }
+        }
+        if self.cfg.resume_backlog {
+            log::info!(
+                "[{}] re-requesting {} ranges left in the recovery journal",
+                self.cfg.partition,
+                self.table.len()
+            );
+        }
+    }
+
+    fn run(mut self, rx: Receiver<RecoveryRequest>) {
+        log::info!(
+            "[{}] recovery injector running ({}={})",
+            self.cfg.partition,
+            F::KIND,
+            self.cfg.endpoints.join(", ")
+        );
+        loop {
+            let now = crate::util::now_nanos();
+            let wait = self.table.next_due().map_or(TICK, |due| {
+                Duration::from_nanos(due.saturating_sub(now)).min(TICK)
+            });
+            match rx.recv_timeout(wait) {
+                Ok(req) => self.accept(req),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            while let Ok(req) = rx.try_recv() {
+                self.accept(req);
+            }
+            for req in self.spill.take() {
+                self.accept(req);
+            }
+            // One request per pass so new gaps are taken in between
+            if let Some(gap) = self.table.take_due(crate::util::now_nanos()) {
+                self.request(gap);
+            }
+            metrics::set_recovery_outstanding(&self.cfg.partition, self.table.len());
+        }
+    }
+
+    fn accept(&mut self, req: RecoveryRequest) {
+        let RecoveryRequest::Gap { stream, from, to } = req else {
+            self.journal.snapshot();
+            return;
+        };
+        if from > to {
+            return;
+        }
+        for (lo, hi) in self.table.uncovered(stream, from, to) {
+            self.journal.requested(stream, lo, hi);
+        }
+        let now = crate::util::now_nanos();
+        for (lo, hi) in self.table.insert(stream, from, to, now) {
+            self.journal.abandoned(stream, lo, hi, "table_full");
+            log::warn!(
+                "[{}] recovery table full ({} gaps); abandoning {lo}..={hi} (stream {stream})",
+                self.cfg.partition,
+                self.cfg.max_outstanding
+            );
+            metrics::inc_recovery_abandoned(&self.cfg.partition);
+        }
+    }
+
+    fn request(&mut self, mut gap: Gap) {
+        let limit = self.fetch.max_range().max(1);
+        if gap.to - gap.from >= limit {
+            self.table.requeue(Gap {
+                from: gap.from + limit,
+                ..gap
+            });
+            gap.to = gap.from + limit - 1;
+        }
+        gap.attempts += 1;
+        if gap.attempts > 1 {
+            metrics::inc_recovery_retry(&self.cfg.partition);
+        }
+        let mut got = Vec::new();
+        let deadline = Instant::now() + self.cfg.request_timeout;
+        let res = self.fetch.fetch(
+            &self.cfg.endpoints[self.endpoint],
+            gap.stream,
+            gap.from,
+            gap.to,
+            deadline,
+            &mut got,
+        );
+        if let Err(e) = res {
+            log::warn!(
+                "[{}] replay {}..={} via {} failed (attempt {}): {e:#}",
+                self.cfg.partition,
+                gap.from,
+                gap.to,
+                self.cfg.endpoints[self.endpoint],
+                gap.attempts
+            );
+            // The connection is in an unknown state mid-response; start over elsewhere
+            self.fetch.reset();
+            self.failover();
+        }
+        let now = crate::util::now_nanos();
+        let missing = missing_ranges(gap.from, gap.to, &mut got);
+        // What came back is the complement of `missing`
+        let fill_ns = now.saturating_sub(gap.since_ns);
+        let mut next = gap.from;
+        for &(lo, hi) in &missing {
+            if lo > next {
+                self.journal.filled(gap.stream, next, lo - 1, fill_ns);
+            }
+            next = hi + 1;
+        }
+        if next <= gap.to {
+            self.journal.filled(gap.stream, next, gap.to, fill_ns);
+        }
+        if missing.is_empty() {
+            metrics::observe_recovery_gap_fill_ns(&self.cfg.partition, fill_ns);
+            return;
+        }
+        if !got.is_empty() {
+            metrics::inc_recovery_partial_fill(&self.cfg.partition);
+        }
+        if gap.attempts >= self.cfg.max_attempts {
+            for (lo, hi) in missing {
+                log::error!(
+                    "[{}] giving up on replay of {lo}..={hi} (stream {}) after {} attempts",
+                    self.cfg.partition,
+                    gap.stream,
+                    gap.attempts
+                );
+                metrics::inc_recovery_abandoned(&self.cfg.partition);
+                self.journal.abandoned(gap.stream, lo, hi, "attempts");
+            }
+            return;
+        }
+        let backoff = self
+            .cfg
+            .backoff
+            .saturating_mul(1 << (gap.attempts - 1).min(16))
+            .min(self.cfg.backoff_max);
+        for (lo, hi) in missing {
+            self.table.requeue(Gap {
+                from: lo,
+                to: hi,
+                due_ns: now + backoff.as_nanos() as u64,
+                ..gap
+            });
+        }
+    }
+
+    fn failover(&mut self) {
+        if self.cfg.endpoints.len() > 1 {
+            self.endpoint = (self.endpoint + 1) % self.cfg.endpoints.len();
+            metrics::inc_recovery_failover(&self.cfg.partition);
+            log::warn!(
+                "[{}] recovery failing over to {}",
+                self.cfg.partition,
+                self.cfg.endpoints[self.endpoint]
+            );
+        }
+    }
+}
+
+/// TCP replay: one persistent connection to the current endpoint
+struct TcpFetch {
+    conn: Option<TcpStream>,
+    connect_timeout: Duration,
+    keepalive: Option<Duration>,
+    q_recovery: Arc<SpscQueue<Pkt>>, // recovery->merge input
+    pool: Arc<PacketPool>,
+    partition: String,
+}
+
+impl Fetch for TcpFetch {
+    const KIND: &'static str = "tcp";
+
+    fn fetch(
+        &mut self,
+        endpoint: &str,
+        stream: u32,
+        from: u64,
+        to: u64,
+        deadline: Instant,
+        got: &mut Vec<u64>,
+    ) -> anyhow::Result<()> {
+        if self.conn.is_none() {
+            let addr = endpoint
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("{endpoint} does not resolve"))?;
+            let conn = TcpStream::connect_timeout(&addr, self.connect_timeout)?;
+            conn.set_nodelay(true).ok();
+            if let Some(idle) = self.keepalive {
+                let ka = socket2::TcpKeepalive::new().with_time(idle);
+                socket2::SockRef::from(&conn).set_tcp_keepalive(&ka)?;
+            }
+            log::info!("[{}] recovery connected to {endpoint}", self.partition);
+            self.conn = Some(conn);
+        }
+        let conn = self.conn.as_mut().expect("connected above");
+        fetch_and_inject(
+            conn,
+            stream,
+            from,
+            to,
+            deadline,
+            &self.q_recovery,
+            &self.pool,
+            got,
+        )
+    }
+
+    fn reset(&mut self) {
+        self.conn = None;
+    }
+}
+
+/// Request `[from, to]` of `stream_id` and inject what comes back, noting each sequence
+/// in `got`. A response that is not complete by `deadline` is an error.
+#[allow(clippy::too_many_arguments)]
+fn fetch_and_inject(
+    stream: &mut TcpStream,
+    stream_id: u32,
     from: u64,
     to: u64,
+    deadline: Instant,
     q_recovery: &Arc<SpscQueue<Pkt>>, // recovery->merge input
     pool: &Arc<PacketPool>,
+    got: &mut Vec<u64>,
 ) -> anyhow::Result<()> {
     use std::io::{Read, Write};
-    use std::net::TcpStream;
-    // Establish TCP to replay service
-    let mut stream = TcpStream::connect(addr)?;
-    stream.set_nodelay(true).ok();
-    // Example control request: "REPLAY from to\n" (replace with real venue protocol)
-    let req = format!("REPLAY {} {}\n", from, to);
+    // Example control request: "REPLAY from to [stream]\n" (replace with real venue protocol)
+    let req = if stream_id == 0 {
+        format!("REPLAY {} {}\n", from, to)
+    } else {
+        format!("REPLAY {} {} {}\n", from, to, stream_id)
+    };
     stream.write_all(req.as_bytes())?;
     stream.flush().ok();
 
-    // Example payload framing: [u32 len][u64 seq][bytes...]
+    // Every read gets what is left of the request's deadline
+    let read_exact = |stream: &mut TcpStream, buf: &mut [u8]| -> anyhow::Result<()> {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            anyhow::bail!("request timed out");
+        }
+        stream.set_read_timeout(Some(left))?;
+        stream.read_exact(buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => {
+                anyhow::anyhow!("request timed out")
+            }
+            std::io::ErrorKind::UnexpectedEof => anyhow::anyhow!("replay server closed"),
+            _ => e.into(),
+        })
+    };
+
+    // Example payload framing: [u32 len][u64 seq][bytes...], ended by len = 0
     let mut hdr = [0u8; 12];
     loop {
-        if stream.read_exact(&mut hdr).is_err() {
-            break;
-        }
+        read_exact(stream, &mut hdr)?;
         let len = u32::from_be_bytes([hdr[0], hdr[1], hdr[2], hdr[3]]) as usize;
         let seq = u64::from_be_bytes([
             hdr[4], hdr[5], hdr[6], hdr[7], hdr[8], hdr[9], hdr[10], hdr[11],
@@ -192,14 +799,7 @@ fn fetch_and_inject<A: std::net::ToSocketAddrs>(
         if len > dst.len() {
             anyhow::bail!("replay packet too large: {}", len);
         }
-        let mut read_so_far = 0usize;
-        while read_so_far < len {
-            let n = stream.read(&mut dst[read_so_far..len])?;
-            if n == 0 {
-                anyhow::bail!("unexpected EOF from replay server");
-            }
-            read_so_far += n;
-        }
+        read_exact(stream, &mut dst[..len])?;
         unsafe {
             bufm.advance_mut(len);
         }
@@ -207,14 +807,199 @@ fn fetch_and_inject<A: std::net::ToSocketAddrs>(
             buf: PktBuf::Bytes(bufm),
             len,
             seq,
+            stream: stream_id,
+            seq_span: 1, // replay frames are addressed one sequence at a time
+            seq_reset: false,
             ts_nanos: crate::util::now_nanos(),
-            chan: b'R',
+            chan: crate::pool::CHAN_RECOVERY,
             _ts_kind: TsKind::Sw,
             merge_emit_ns: 0,
         };
         q_recovery.push_blocking(pkt);
         metrics::inc_decode_pkts();
+        got.push(seq);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn gap_table_tracks_only_new_sequences() {
+        let mut t = GapTable::new(8);
+        assert!(t.insert(0, 10, 20, 1).is_empty());
+        // Only 21..=25 is new; it folds into the range not requested yet
+        t.insert(0, 15, 25, 2);
+        // The same numbers in another stream are another range
+        t.insert(1, 10, 12, 3);
+        let g = t.take_due(5).unwrap();
+        assert_eq!((g.stream, g.from, g.to, g.since_ns), (0, 10, 25, 1));
+        t.requeue(Gap {
+            attempts: 1,
+            due_ns: 100,
+            ..g
+        });
+        // A range already requested is not extended
+        t.insert(0, 26, 26, 6);
+        assert_eq!(t.len(), 3);
+        assert_eq!(t.take_due(10).map(|g| (g.stream, g.from)), Some((1, 10)));
+        assert_eq!(t.take_due(10).map(|g| (g.stream, g.from)), Some((0, 26)));
+        assert_eq!(t.take_due(10), None);
+        assert_eq!(t.next_due(), Some(100));
+
+        let mut got = vec![20, 10, 11, 15, 15];
+        assert_eq!(missing_ranges(10, 20, &mut got), vec![(12, 14), (16, 19)]);
+        assert_eq!(missing_ranges(10, 12, &mut []), vec![(10, 12)]);
+        let mut full = GapTable::new(1);
+        full.insert(0, 1, 1, 0);
+        assert_eq!(full.insert(0, 5, 6, 0), vec![(5, 6)]);
+    }
+
+    /// Fills every range it is handed, at most `.0` sequences at a time
+    struct Windowed(u64, crossbeam_channel::Sender<(u64, u64)>);
+
+    impl Fetch for Windowed {
+        const KIND: &'static str = "test";
+
+        fn fetch(
+            &mut self,
+            _endpoint: &str,
+            _stream: u32,
+            from: u64,
+            to: u64,
+            _deadline: Instant,
+            got: &mut Vec<u64>,
+        ) -> anyhow::Result<()> {
+            self.1.send((from, to)).unwrap();
+            got.extend(from..=to);
+            Ok(())
+        }
+
+        fn max_range(&self) -> u64 {
+            self.0
+        }
+
+        fn reset(&mut self) {}
+    }
+
+    #[test]
+    fn injector_hands_long_gaps_to_fetch_in_windows() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let cfg = InjectorCfg::new(vec!["test".into()], "window_test");
+        let (cli, _handle) = spawn_injector("window-test", cfg, Windowed(4, tx));
+        cli.notify_gap(1, 10);
+        let fetched: Vec<_> = (0..3)
+            .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        assert_eq!(fetched, vec![(1, 4), (5, 8), (9, 10)]);
+        assert!(rx.recv_timeout(Duration::from_millis(20)).is_err());
+    }
+
+    #[test]
+    fn injector_fails_over_and_re_requests_what_a_response_left_out() {
+        // Nothing listens on the first endpoint
+        let dead = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let live = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::<String>::new()));
+        let seen = requests.clone();
+        std::thread::spawn(move || {
+            // One persistent connection carries every request
+            let (conn, _) = listener.accept().unwrap();
+            let mut w = conn.try_clone().unwrap();
+            for line in BufReader::new(conn).lines() {
+                let line = line.unwrap();
+                let r: Vec<u64> = line
+                    .split(' ')
+                    .skip(1)
+                    .map(|x| x.parse().unwrap())
+                    .collect();
+                let first = seen.lock().unwrap().is_empty();
+                seen.lock().unwrap().push(line);
+                for seq in r[0]..=r[1] {
+                    // The first response leaves out 3 and 4
+                    if first && (3..=4).contains(&seq) {
+                        continue;
+                    }
+                    w.write_all(&1u32.to_be_bytes()).unwrap();
+                    w.write_all(&seq.to_be_bytes()).unwrap();
+                    w.write_all(&[seq as u8]).unwrap();
+                }
+                w.write_all(&[0u8; 12]).unwrap();
+            }
+        });
+
+        let mut cfg = InjectorCfg::new(vec![dead.to_string(), live.to_string()], "recovery_test");
+        cfg.backoff = Duration::from_millis(1);
+        let q = Arc::new(SpscQueue::new(64));
+        let pool = Arc::new(PacketPool::new(8, 64).unwrap());
+        let (cli, _handle) = spawn_tcp_injector(cfg, q.clone(), pool);
+        cli.notify_gap(1, 5);
+
+        let mut got = Vec::new();
+        let until = Instant::now() + Duration::from_secs(5);
+        while got.len() < 5 && Instant::now() < until {
+            match q.pop() {
+                Some(p) => got.push(p.seq),
+                None => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        assert_eq!(got, vec![1, 2, 5, 3, 4]);
+        assert_eq!(*requests.lock().unwrap(), vec!["REPLAY 1 5", "REPLAY 3 4"]);
+    }
+
+    #[test]
+    fn injector_re_requests_what_the_journal_left_unresolved() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (conn, _) = listener.accept().unwrap();
+            let mut w = conn.try_clone().unwrap();
+            for line in BufReader::new(conn).lines() {
+                assert_eq!(line.unwrap(), "REPLAY 7 8");
+                for seq in 7u64..=8 {
+                    w.write_all(&1u32.to_be_bytes()).unwrap();
+                    w.write_all(&seq.to_be_bytes()).unwrap();
+                    w.write_all(&[seq as u8]).unwrap();
+                }
+                w.write_all(&[0u8; 12]).unwrap();
+            }
+        });
+        let path = std::env::temp_dir().join(format!("resume_test_{}", std::process::id()));
+        // Requested before the restart; 5..=6 came back, 7..=8 did not
+        std::fs::write(&path, "1 requested 0 5 8\n2 filled 0 5 6 10\n").unwrap();
+
+        let mut cfg = InjectorCfg::new(vec![addr.to_string()], "resume_test");
+        cfg.backlog_path = Some(path.to_str().unwrap().into());
+        cfg.resume_backlog = true;
+        let q = Arc::new(SpscQueue::new(64));
+        let pool = Arc::new(PacketPool::new(8, 64).unwrap());
+        let (_cli, _handle) = spawn_tcp_injector(cfg, q.clone(), pool);
+
+        let mut got = Vec::new();
+        let until = Instant::now() + Duration::from_secs(5);
+        while got.len() < 2 && Instant::now() < until {
+            match q.pop() {
+                Some(p) => got.push(p.seq),
+                None => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        assert_eq!(got, vec![7, 8]);
+        let until = Instant::now() + Duration::from_secs(5);
+        while !crate::recovery_journal::report(Some("filled")).contains("resume_test 0 5 8")
+            && Instant::now() < until
+        {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(crate::recovery_journal::report(None).contains("resume_test 0 5 8 filled"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,438 @@
+// src/recovery_journal.rs
+// Replay backlog journal.
+//
+// Each partition's injector appends one line to `recovery.backlog_path` per range it
+// requests, gets filled or abandons:
+//   <unix ns> requested <stream> <from> <to>
+//   <unix ns> filled <stream> <from> <to> <fill ns>
+//   <unix ns> abandoned <stream> <from> <to> <reason>
+//   <unix ns> snapshot
+//   <unix ns> session <key>
+// Filled/abandoned lines name sub-ranges of earlier requests (retries split them).
+// Reading the file back gives every requested range with what is still missing. The
+// `session` line names the venue session the sequences belong to (session date, plus
+// the MoldUDP64 session); ranges left from another session are closed at open, since
+// the venue restarted its sequence. The file is then rewritten with only the unresolved
+// ranges, so it does not grow across restarts. At start the injector re-requests them
+// with `recovery.resume_backlog = true`, else reports and closes them. Lines of the
+// older `gap <from> <to> [stream]` format read as requests of an unnamed session. The
+// recent history of every partition is served as text at /recovery/gaps on the metrics
+// listener.
+
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex};
+
+/// Resolved ranges kept for the admin query; unresolved ones are always kept
+const HISTORY: usize = 1024;
+
+type Shared = Arc<Mutex<History>>;
+
+/// Histories of every open journal, by partition
+static JOURNALS: Lazy<Mutex<Vec<(String, Shared)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// One requested range and what became of it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub stream: u32,
+    pub from: u64,
+    pub to: u64,
+    pub requested_ns: u64,
+    /// When the last missing sequence was filled or abandoned (0 while unresolved)
+    pub resolved_ns: u64,
+    pub filled: u64,
+    pub abandoned: u64,
+    /// Slowest fill among its sub-ranges
+    pub fill_ns: Option<u64>,
+    /// Sub-ranges neither filled nor abandoned yet
+    pub missing: Vec<(u64, u64)>,
+}
+
+impl Record {
+    pub fn status(&self) -> &'static str {
+        match (self.missing.is_empty(), self.filled, self.abandoned) {
+            (false, _, _) => "outstanding",
+            (true, _, 0) => "filled",
+            (true, 0, _) => "abandoned",
+            (true, _, _) => "partial",
+        }
+    }
+
+    /// Take `[from, to]` off what is missing; returns how many sequences that was.
+    fn resolve(&mut self, from: u64, to: u64) -> u64 {
+        let mut n = 0;
+        self.missing = self
+            .missing
+            .iter()
+            .flat_map(|&(lo, hi)| {
+                if hi < from || lo > to {
+                    return vec![(lo, hi)];
+                }
+                n += hi.min(to) - lo.max(from) + 1;
+                let mut rest = Vec::new();
+                if lo < from {
+                    rest.push((lo, from - 1));
+                }
+                if hi > to {
+                    rest.push((to + 1, hi));
+                }
+                rest
+            })
+            .collect();
+        n
+    }
+}
+
+/// Requested ranges in order, unresolved ones plus the most recent resolved ones
+#[derive(Default)]
+pub struct History {
+    records: VecDeque<Record>,
+    /// Session of the ranges read last (`session` line)
+    session: String,
+}
+
+impl History {
+    fn apply(&mut self, line: &str) {
+        let mut f = line.split_ascii_whitespace();
+        let first = f.next();
+        // Older backlog: `gap from to [stream]`
+        if first == Some("gap") {
+            let n: Vec<u64> = f.filter_map(|x| x.parse().ok()).collect();
+            if let [from, to, rest @ ..] = n.as_slice() {
+                let stream = rest.first().copied().unwrap_or(0) as u32;
+                self.requested(0, stream, *from, *to);
+            }
+            return;
+        }
+        let Some(ts) = first.and_then(|x| x.parse::<u64>().ok()) else {
+            return;
+        };
+        let kind = f.next();
+        if kind == Some("session") {
+            let key = f.next().unwrap_or_default();
+            self.new_session(ts, key);
+            return;
+        }
+        let n: Vec<u64> = f.clone().take(3).filter_map(|x| x.parse().ok()).collect();
+        let [stream, from, to] = n[..] else {
+            return;
+        };
+        let stream = stream as u32;
+        match kind {
+            Some("requested") => self.requested(ts, stream, from, to),
+            Some("filled") => {
+                let fill_ns = f.nth(3).and_then(|x| x.parse().ok());
+                self.filled(ts, stream, from, to, fill_ns);
+            }
+            Some("abandoned") => self.abandoned(ts, stream, from, to),
+            _ => {}
+        }
+    }
+
+    /// Switch to session `key`; what the previous one left unresolved can no longer be
+    /// requested and is closed. Returns the ranges closed.
+    fn new_session(&mut self, ts: u64, key: &str) -> Vec<(u32, u64, u64)> {
+        if self.session == key {
+            return Vec::new();
+        }
+        let left = self.unresolved();
+        for &(stream, from, to) in &left {
+            self.abandoned(ts, stream, from, to);
+        }
+        self.session = key.to_string();
+        left
+    }
+
+    fn requested(&mut self, ts: u64, stream: u32, from: u64, to: u64) {
+        self.records.push_back(Record {
+            stream,
+            from,
+            to,
+            requested_ns: ts,
+            resolved_ns: 0,
+            filled: 0,
+            abandoned: 0,
+            fill_ns: None,
+            missing: vec![(from, to)],
+        });
+        self.trim();
+    }
+
+    fn filled(&mut self, ts: u64, stream: u32, from: u64, to: u64, fill_ns: Option<u64>) {
+        self.resolve(ts, stream, from, to, |r, n| {
+            r.filled += n;
+            r.fill_ns = r.fill_ns.max(fill_ns);
+        });
+    }
+
+    fn abandoned(&mut self, ts: u64, stream: u32, from: u64, to: u64) {
+        self.resolve(ts, stream, from, to, |r, n| r.abandoned += n);
+    }
+
+    fn resolve(
+        &mut self,
+        ts: u64,
+        stream: u32,
+        from: u64,
+        to: u64,
+        mut note: impl FnMut(&mut Record, u64),
+    ) {
+        for r in self.records.iter_mut() {
+            if r.stream != stream || r.missing.is_empty() || r.to < from || r.from > to {
+                continue;
+            }
+            let n = r.resolve(from, to);
+            if n > 0 {
+                note(r, n);
+                if r.missing.is_empty() {
+                    r.resolved_ns = ts;
+                }
+            }
+        }
+    }
+
+    /// Drop the oldest resolved records beyond `HISTORY`.
+    fn trim(&mut self) {
+        let resolved = self.records.iter().filter(|r| r.missing.is_empty()).count();
+        let mut excess = resolved.saturating_sub(HISTORY);
+        if excess > 0 {
+            self.records.retain(|r| {
+                let drop = excess > 0 && r.missing.is_empty();
+                excess -= drop as usize;
+                !drop
+            });
+        }
+    }
+
+    /// `(stream, from, to)` of every sequence still missing.
+    pub fn unresolved(&self) -> Vec<(u32, u64, u64)> {
+        self.records
+            .iter()
+            .flat_map(|r| r.missing.iter().map(move |&(lo, hi)| (r.stream, lo, hi)))
+            .collect()
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.records.iter()
+    }
+}
+
+/// Append-only journal of one partition's replay requests
+pub struct Journal {
+    file: Option<File>,
+    history: Shared,
+}
+
+impl Journal {
+    /// Read `path` back (if any), close what another venue session left unresolved,
+    /// rewrite the file with the unresolved ranges of `session` and keep appending to it.
+    /// The history is registered for the admin query under `partition`.
+    pub fn open(path: Option<&str>, partition: &str, session: &str) -> Self {
+        let mut history = History::default();
+        if let Some(p) = path {
+            if let Ok(f) = File::open(p) {
+                for line in BufReader::new(f).lines().map_while(Result::ok) {
+                    history.apply(&line);
+                }
+            }
+        }
+        let ts = unix_ns();
+        for (stream, from, to) in history.new_session(ts, session) {
+            log::warn!(
+                "[{partition}] replay of {from}..={to} (stream {stream}) dropped: it belongs to \
+                 an earlier venue session"
+            );
+        }
+        if let Some(p) = path {
+            if let Err(e) = compact(p, ts, session, &history) {
+                log::error!("[{partition}] rewriting recovery journal {p}: {e}");
+            }
+        }
+        let file = path.and_then(|p| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(p)
+                .map_err(|e| log::error!("[{partition}] recovery journal {p}: {e}"))
+                .ok()
+        });
+        let history = Arc::new(Mutex::new(history));
+        let mut all = JOURNALS.lock().unwrap();
+        all.retain(|(p, _)| p != partition);
+        all.push((partition.to_string(), history.clone()));
+        Self { file, history }
+    }
+
+    pub fn unresolved(&self) -> Vec<(u32, u64, u64)> {
+        self.history.lock().unwrap().unresolved()
+    }
+
+    pub fn requested(&mut self, stream: u32, from: u64, to: u64) {
+        let ts = unix_ns();
+        self.append(format_args!("{ts} requested {stream} {from} {to}"));
+        self.history.lock().unwrap().requested(ts, stream, from, to);
+    }
+
+    pub fn filled(&mut self, stream: u32, from: u64, to: u64, fill_ns: u64) {
+        let ts = unix_ns();
+        self.append(format_args!("{ts} filled {stream} {from} {to} {fill_ns}"));
+        self.history
+            .lock()
+            .unwrap()
+            .filled(ts, stream, from, to, Some(fill_ns));
+    }
+
+    /// `reason`: attempts, table_full or restart
+    pub fn abandoned(&mut self, stream: u32, from: u64, to: u64, reason: &str) {
+        let ts = unix_ns();
+        self.append(format_args!("{ts} abandoned {stream} {from} {to} {reason}"));
+        self.history.lock().unwrap().abandoned(ts, stream, from, to);
+    }
+
+    /// The replay protocol here has no snapshot request (decode rebuilds the books from
+    /// the snapshot source); record it for an operator.
+    pub fn snapshot(&mut self) {
+        log::warn!("snapshot resync requested; books are rebuilt from the snapshot source");
+        let ts = unix_ns();
+        self.append(format_args!("{ts} snapshot"));
+    }
+
+    fn append(&mut self, line: std::fmt::Arguments) {
+        if let Some(f) = self.file.as_mut() {
+            let _ = writeln!(f, "{line}");
+            let _ = f.flush();
+        }
+    }
+}
+
+/// Rewrite `path` with the session line and the missing sub-ranges of each unresolved
+/// record (as requests at their original time); resolved history is not carried over.
+fn compact(path: &str, ts: u64, session: &str, history: &History) -> std::io::Result<()> {
+    let tmp = format!("{path}.tmp");
+    let mut f = File::create(&tmp)?;
+    writeln!(f, "{ts} session {session}")?;
+    for r in history.records() {
+        for (lo, hi) in &r.missing {
+            writeln!(f, "{} requested {} {lo} {hi}", r.requested_ns, r.stream)?;
+        }
+    }
+    f.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Admin query: one line per requested range of every partition, oldest first;
+/// `status` keeps only outstanding, filled, partial or abandoned ones.
+pub fn report(status: Option<&str>) -> String {
+    let mut out = String::from(
+        "# partition stream from to status requested_ns resolved_ns filled abandoned fill_ns missing\n",
+    );
+    for (part, h) in JOURNALS.lock().unwrap().iter() {
+        for r in h.lock().unwrap().records() {
+            if status.is_some_and(|s| s != r.status()) {
+                continue;
+            }
+            let missing: Vec<String> = r
+                .missing
+                .iter()
+                .map(|(lo, hi)| format!("{lo}-{hi}"))
+                .collect();
+            let _ = writeln!(
+                out,
+                "{part} {} {} {} {} {} {} {} {} {} {}",
+                r.stream,
+                r.from,
+                r.to,
+                r.status(),
+                r.requested_ns,
+                r.resolved_ns,
+                r.filled,
+                r.abandoned,
+                r.fill_ns.map_or("-".into(), |n| n.to_string()),
+                if missing.is_empty() {
+                    "-".into()
+                } else {
+                    missing.join(",")
+                },
+            );
+        }
+    }
+    out
+}
+
+fn unix_ns() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_reads_back_unresolved_ranges_and_fill_status() {
+        let path = std::env::temp_dir().join(format!("journal_test_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "gap 1 3\n").unwrap();
+        {
+            let mut j = Journal::open(Some(path), "journal_test", "");
+            assert_eq!(j.unresolved(), vec![(0, 1, 3)]);
+            j.filled(0, 1, 3, 500);
+            j.requested(0, 10, 20);
+            j.requested(2, 10, 12);
+            // A retry split the range: 10..=14 came back, 15..=16 was given up
+            j.filled(0, 10, 14, 1_000);
+            j.abandoned(0, 15, 16, "attempts");
+            j.abandoned(2, 10, 12, "table_full");
+        }
+
+        let j = Journal::open(Some(path), "journal_test", "");
+        assert_eq!(j.unresolved(), vec![(0, 17, 20)]);
+        let h = j.history.lock().unwrap();
+        let status: Vec<_> = h.records().map(|r| (r.from, r.status())).collect();
+        assert_eq!(
+            status,
+            vec![(1, "filled"), (10, "outstanding"), (10, "abandoned")]
+        );
+        let r = h.records().nth(1).unwrap();
+        assert_eq!((r.filled, r.abandoned, r.fill_ns), (5, 2, Some(1_000)));
+        drop(h);
+
+        let text = report(Some("outstanding"));
+        assert!(text.contains("journal_test 0 10 20 outstanding"));
+        assert!(!text.contains("journal_test 2 "));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn journal_keeps_only_unresolved_ranges_of_the_current_session() {
+        let path = std::env::temp_dir().join(format!("journal_session_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(
+            path,
+            "1 session 20261015\n2 requested 0 5 9\n3 filled 0 5 6 10\n4 requested 0 20 21\n\
+             5 filled 0 20 21 10\n",
+        )
+        .unwrap();
+        {
+            let j = Journal::open(Some(path), "journal_session", "20261015");
+            assert_eq!(j.unresolved(), vec![(0, 7, 9)]);
+        }
+        // Rewritten with the missing part of the open request only
+        let text = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<_> = text.lines().map(|l| l.split_once(' ').unwrap().1).collect();
+        assert_eq!(lines, vec!["session 20261015", "requested 0 7 9"]);
+
+        // The venue restarted its sequence since: nothing is left to request
+        let j = Journal::open(Some(path), "journal_session", "20261016");
+        assert!(j.unresolved().is_empty());
+        let r = j.history.lock().unwrap().records().next().cloned().unwrap();
+        assert_eq!((r.from, r.status()), (7, "abandoned"));
+        let text = std::fs::read_to_string(path).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,439 @@
+// src/recovery_mold.rs
+// MoldUDP64 retransmission requests.
+//
+// ITCH-over-MoldUDP64 feeds recover gaps by sending request packets
+// `[session 10][sequence u64][count u16]` (big-endian) to the venue's unicast re-request
+// server, which resends the downstream packets `[session][sequence][count][messages]`
+// carrying those messages. Sequences number messages, so a downstream packet covers
+// `sequence..sequence + count`. Requests are split at the server's maximum message
+// count and paced to a request rate; resent packets go to the recovery queue exactly as
+// RX would have queued them (`sequence.moldudp64`, see `MoldSeq`). Outstanding gaps,
+// retries and failover are the injector's (see recovery.rs).
+
+use crate::config::MoldCfg;
+use crate::decoder_itch::{mold_header, MOLD_END_OF_SESSION, MOLD_HEARTBEAT};
+use crate::parser::SeqExtractor;
+use crate::pool::{PacketPool, Pkt, PktBuf, TsKind};
+use crate::recovery::{spawn_injector, Fetch, InjectorCfg, RecoveryClient, RecoveryHandle};
+use crate::spsc::SpscQueue;
+use bytes::BufMut;
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const REQUEST_LEN: usize = 20;
+/// Request packets per fetch; longer gaps are fetched in windows of this many batches
+const MAX_REQUESTS: u64 = 16;
+
+pub struct MoldParams {
+    /// Session name, space padded to 10 bytes
+    pub session: [u8; 10],
+    /// Most messages the re-request server serves per request
+    pub max_count: u16,
+    /// Minimum spacing between request packets
+    pub interval: Duration,
+}
+
+impl MoldParams {
+    pub fn from_cfg(cfg: &MoldCfg) -> Self {
+        let mut session = [b' '; 10];
+        let name = cfg.session.as_bytes();
+        session[..name.len().min(10)].copy_from_slice(&name[..name.len().min(10)]);
+        Self {
+            session,
+            max_count: cfg.max_request_count,
+            interval: Duration::from_nanos(1_000_000_000 / cfg.requests_per_sec.max(1) as u64),
+        }
+    }
+}
+
+/// Spawn a MoldUDP64 re-requester feeding `q_recovery`; `cfg.endpoints` are the
+/// re-request servers (host:port).
+pub fn spawn_mold_requester(
+    cfg: InjectorCfg,
+    mold: MoldParams,
+    seq: Arc<dyn SeqExtractor>,
+    q_recovery: Arc<SpscQueue<Pkt>>, // dedicated recovery->merge SPSC queue
+    pool: Arc<PacketPool>,
+) -> (RecoveryClient, RecoveryHandle) {
+    let fetch = MoldFetch {
+        sock: None,
+        mold,
+        seq,
+        q_recovery,
+        pool,
+        next_send: Instant::now(),
+        seen: Vec::new(),
+    };
+    spawn_injector("recovery-mold", cfg, fetch)
+}
+
+/// Request packet for `count` messages from `seq`.
+pub fn request(session: &[u8; 10], seq: u64, count: u16) -> [u8; REQUEST_LEN] {
+    let mut p = [0u8; REQUEST_LEN];
+    p[..10].copy_from_slice(session);
+    p[10..18].copy_from_slice(&seq.to_be_bytes());
+    p[18..20].copy_from_slice(&count.to_be_bytes());
+    p
+}
+
+struct MoldFetch {
+    /// Connected to the current re-request server
+    sock: Option<UdpSocket>,
+    mold: MoldParams,
+    seq: Arc<dyn SeqExtractor>,
+    q_recovery: Arc<SpscQueue<Pkt>>, // recovery->merge input
+    pool: Arc<PacketPool>,
+    next_send: Instant,
+    /// Messages of the current window received so far, one bit each
+    seen: Vec<u64>,
+}
+
+impl MoldFetch {
+    /// Hold requests to `interval` apart; false if the next slot is past `deadline`.
+    fn pace(&mut self, deadline: Instant) -> bool {
+        let now = Instant::now();
+        let at = self.next_send.max(now);
+        if at >= deadline {
+            return false;
+        }
+        if at > now {
+            std::thread::sleep(at - now);
+        }
+        self.next_send = at + self.mold.interval;
+        true
+    }
+}
+
+impl Fetch for MoldFetch {
+    const KIND: &'static str = "moldudp64";
+
+    fn fetch(
+        &mut self,
+        endpoint: &str,
+        _stream: u32,
+        from: u64,
+        to: u64,
+        deadline: Instant,
+        got: &mut Vec<u64>,
+    ) -> anyhow::Result<()> {
+        if self.sock.is_none() {
+            let sock = UdpSocket::bind(if endpoint.starts_with('[') {
+                "[::]:0"
+            } else {
+                "0.0.0.0:0"
+            })?;
+            sock.connect(endpoint)?;
+            log::info!("MoldUDP64 re-requests go to {endpoint}");
+            self.sock = Some(sock);
+        }
+        // The injector hands out at most `max_range`; what is not requested in time
+        // is missing and retried
+        let to = to.min(from.saturating_add(self.max_range() - 1));
+        let mut seq = from;
+        while seq <= to {
+            let count = (to - seq + 1).min(self.mold.max_count as u64) as u16;
+            if !self.pace(deadline) {
+                break;
+            }
+            let sock = self.sock.as_ref().expect("bound above");
+            sock.send(&request(&self.mold.session, seq, count))?;
+            seq += count as u64;
+        }
+        if seq == from {
+            // Out of time before the first request; retried like a loss
+            return Ok(());
+        }
+
+        // Resent packets may overlap, repeat or go missing; note each message of
+        // `from..seq` once
+        let mut left = (seq - from) as usize;
+        self.seen.clear();
+        self.seen.resize(left.div_ceil(64), 0);
+        let sock = self.sock.as_ref().expect("bound above");
+        while left > 0 {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            if wait.is_zero() {
+                break;
+            }
+            sock.set_read_timeout(Some(wait))?;
+            let mut bufm = self.pool.get();
+            // Safety: buffer is at least pool's max packet size
+            let dst = unsafe {
+                let s = bufm.chunk_mut();
+                std::slice::from_raw_parts_mut(s.as_mut_ptr(), s.len())
+            };
+            let n = match sock.recv(dst) {
+                Ok(n) => n,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    self.pool.put(bufm);
+                    break;
+                }
+                Err(e) => {
+                    self.pool.put(bufm);
+                    return Err(e.into());
+                }
+            };
+            unsafe {
+                bufm.advance_mut(n);
+            }
+            let Some((session, first, count)) = mold_header(&bufm) else {
+                self.pool.put(bufm);
+                continue;
+            };
+            let last = first.saturating_add(count as u64).saturating_sub(1);
+            if session != self.mold.session
+                || count == MOLD_HEARTBEAT
+                || count == MOLD_END_OF_SESSION
+                || first >= seq
+                || last < from
+            {
+                self.pool.put(bufm);
+                continue;
+            }
+            let Some((stream, s)) = self.seq.extract_stream_seq(&bufm) else {
+                self.pool.put(bufm);
+                continue;
+            };
+            for m in first.max(from)..first.saturating_add(count as u64).min(seq) {
+                let i = (m - from) as usize;
+                let (word, bit) = (i / 64, 1u64 << (i % 64));
+                if self.seen[word] & bit == 0 {
+                    self.seen[word] |= bit;
+                    left -= 1;
+                    got.push(m);
+                }
+            }
+            let pkt = Pkt {
+                // A resent packet covers `sequence..sequence + count`
+                seq_span: count as u32,
+                buf: PktBuf::Bytes(bufm),
+                len: n,
+                seq: s,
+                stream,
+                seq_reset: false,
+                ts_nanos: crate::util::now_nanos(),
+                chan: crate::pool::CHAN_RECOVERY,
+                _ts_kind: TsKind::Sw,
+                merge_emit_ns: 0,
+            };
+            self.q_recovery.push_blocking(pkt);
+        }
+        // Losses are retried; total silence points at the server
+        if got.is_empty() {
+            anyhow::bail!("no response");
+        }
+        Ok(())
+    }
+
+    fn max_range(&self) -> u64 {
+        self.mold.max_count as u64 * MAX_REQUESTS
+    }
+
+    fn reset(&mut self) {
+        self.sock = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Endian;
+    use crate::merge::tests::{cfg as merge_cfg, lines};
+    use crate::merge::{merge_loop, MergeConfig};
+    use crate::parser::SeqCfg;
+    use crate::util::BarrierFlag;
+    use std::sync::Mutex;
+
+    /// Stand-in re-request server: answers with two messages per packet and loses
+    /// the packet carrying `lose` the first time.
+    fn serve(sock: UdpSocket, lose: u64, log: Arc<Mutex<Vec<(u64, u16)>>>) {
+        let mut lost = false;
+        let mut req = [0u8; 64];
+        sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        while let Ok((n, peer)) = sock.recv_from(&mut req) {
+            assert_eq!(n, REQUEST_LEN);
+            let (session, seq, count) = mold_header(&req[..n]).unwrap();
+            assert_eq!(session, b"TEST01    ");
+            log.lock().unwrap().push((seq, count));
+            let end = seq + count as u64;
+            let mut m = seq;
+            while m < end {
+                let k = (end - m).min(2) as u16;
+                if m <= lose && lose < m + k as u64 && !lost {
+                    lost = true;
+                    m += k as u64;
+                    continue;
+                }
+                sock.send_to(&downstream(m, k), peer).unwrap();
+                m += k as u64;
+            }
+        }
+    }
+
+    /// Downstream packet carrying `count` one-byte messages from `first`
+    fn downstream(first: u64, count: u16) -> Vec<u8> {
+        let mut p = request(b"TEST01    ", first, count).to_vec();
+        for i in 0..count as u64 {
+            p.extend_from_slice(&1u16.to_be_bytes());
+            p.push((first + i) as u8);
+        }
+        p
+    }
+
+    /// ITCH over MoldUDP64: sequence and message count from the downstream header
+    fn mold_seq() -> Arc<dyn SeqExtractor> {
+        let seq = SeqCfg {
+            offset: 10,
+            length: 8,
+            endian: Endian::Be,
+            unit: None,
+            stream: None,
+            moldudp64: true,
+        };
+        crate::parser::build_parser(crate::config::ParserKind::Itch50, seq, 16, 0)
+            .unwrap()
+            .seq_extractor()
+    }
+
+    #[test]
+    fn rerequests_in_batches_and_retries_lost_packets() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let seen = log.clone();
+        std::thread::spawn(move || serve(server, 3, seen));
+
+        let mut cfg = InjectorCfg::new(vec![addr.to_string()], "mold_test");
+        cfg.request_timeout = Duration::from_millis(50);
+        cfg.backoff = Duration::from_millis(1);
+        let mold = MoldParams::from_cfg(&MoldCfg {
+            session: "TEST01".into(),
+            max_request_count: 3,
+            requests_per_sec: 10_000,
+        });
+        let seq = mold_seq();
+        let q = Arc::new(SpscQueue::new(64));
+        let pool = Arc::new(PacketPool::new(8, 256).unwrap());
+        let (cli, _handle) = spawn_mold_requester(cfg, mold, seq, q.clone(), pool);
+        cli.notify_gap(1, 5);
+
+        let mut got = Vec::new();
+        let until = Instant::now() + Duration::from_secs(5);
+        while got.len() < 3 && Instant::now() < until {
+            match q.pop() {
+                Some(p) => got.push(p.seq),
+                None => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        // 3..=4 was lost in the first answer; only 3 is asked for again
+        assert_eq!(got, vec![1, 4, 3]);
+        assert_eq!(*log.lock().unwrap(), vec![(1, 3), (4, 2), (3, 1)]);
+    }
+
+    #[test]
+    fn merge_advances_by_message_count_of_resent_packets() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let seen = log.clone();
+        std::thread::spawn(move || serve(server, 0, seen));
+
+        let mut cfg = InjectorCfg::new(vec![addr.to_string()], "mold_merge_test");
+        cfg.request_timeout = Duration::from_millis(50);
+        let mold = MoldParams::from_cfg(&MoldCfg {
+            session: "TEST01".into(),
+            max_request_count: 3,
+            requests_per_sec: 10_000,
+        });
+        let seq = mold_seq();
+        let pool = Arc::new(PacketPool::new(16, 256).unwrap());
+        let q_rec = Arc::new(SpscQueue::new(64));
+        let (cli, _handle) =
+            spawn_mold_requester(cfg, mold, seq.clone(), q_rec.clone(), pool.clone());
+
+        // Line A has messages 1..=2 and 8..=9; 3..=7 went missing on both lines
+        let q_a = Arc::new(SpscQueue::new(64));
+        let q_b = Arc::new(SpscQueue::new(64));
+        for (first, count) in [(1, 2), (8, 2)] {
+            let p = downstream(first, count);
+            let mut buf = pool.get();
+            buf.extend_from_slice(&p);
+            let _ = q_a.push(Pkt {
+                buf: PktBuf::Bytes(buf),
+                len: p.len(),
+                seq: seq.extract_seq(&p).unwrap(),
+                stream: 0,
+                seq_span: seq.seq_span(&p),
+                seq_reset: false,
+                ts_nanos: 0,
+                chan: 0,
+                _ts_kind: TsKind::Sw,
+                merge_emit_ns: 0,
+            });
+        }
+        let q_out: Arc<SpscQueue<Pkt>> = Arc::new(SpscQueue::new(64));
+        let shutdown = Arc::new(BarrierFlag::default());
+        let (qo, sd) = (q_out.clone(), shutdown.clone());
+        let t = std::thread::spawn(move || {
+            let cfg = MergeConfig {
+                gap_timeout_ns: 2_000_000,
+                ..merge_cfg()
+            };
+            let _ = merge_loop(lines(vec![q_a, q_b]), qo, cfg, sd, Some(cli), Some(q_rec));
+        });
+        let until = Instant::now() + Duration::from_secs(5);
+        while q_out.len() < 5 && Instant::now() < until {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // Long enough for a merge that lost count to time out and ask again
+        std::thread::sleep(Duration::from_millis(30));
+        shutdown.raise();
+        let _ = t.join();
+
+        let mut out = Vec::new();
+        while let Some(p) = q_out.pop() {
+            out.push((p.seq, p.seq_span));
+        }
+        assert_eq!(out, vec![(1, 2), (3, 2), (5, 1), (6, 2), (8, 2)]);
+        // One request per batch of the declared gap, none repeated
+        assert_eq!(*log.lock().unwrap(), vec![(3, 3), (6, 2)]);
+    }
+
+    #[test]
+    fn fetch_requests_nothing_past_its_deadline() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let seen = log.clone();
+        std::thread::spawn(move || serve(server, 0, seen));
+
+        let mut fetch = MoldFetch {
+            sock: None,
+            mold: MoldParams::from_cfg(&MoldCfg {
+                session: "TEST01".into(),
+                max_request_count: 2,
+                requests_per_sec: 20,
+            }),
+            seq: mold_seq(),
+            q_recovery: Arc::new(SpscQueue::new(64)),
+            pool: Arc::new(PacketPool::new(8, 256).unwrap()),
+            next_send: Instant::now(),
+            seen: Vec::new(),
+        };
+        // Requests go out 50ms apart; by 75ms only two of the three were sent
+        let mut got = Vec::new();
+        let deadline = Instant::now() + Duration::from_millis(75);
+        fetch
+            .fetch(&addr.to_string(), 0, 1, 6, deadline, &mut got)
+            .unwrap();
+        assert_eq!(got, vec![1, 2, 3, 4]);
+        assert_eq!(*log.lock().unwrap(), vec![(1, 2), (3, 2)]);
+        assert_eq!(fetch.max_range(), 2 * MAX_REQUESTS);
+    }
+}
@@ -23,6 +23,13 @@ pub struct RxConfig {
     pub spin_loops_per_yield: u32,
     pub rx_batch: usize,
     pub ts_mode: Option<crate::config::TimestampingMode>,
+    pub backend: crate::config::RxBackend,
+    /// SQPOLL idle timeout for the io_uring backend; None keeps submission in-thread
+    pub sqpoll_idle_ms: Option<u32>,
+    /// Partition label for metrics
+    pub partition: String,
+    /// Line index stamped into `Pkt.chan`
+    pub line: u8,
 }
 
 // TODO: Group arguments into an RxConfig struct to reduce parameter count.
@@ -35,14 +42,20 @@ pub fn rx_loop(
     shutdown: Arc<crate::util::BarrierFlag>,
     cfg: RxConfig,
 ) -> anyhow::Result<()> {
+    if cfg.backend == crate::config::RxBackend::IoUring {
+        return crate::rx_uring::uring_rx_loop(chan_name, sock, seq, q_out, pool, shutdown, &cfg);
+    }
     let RxConfig {
         spin_loops_per_yield,
         rx_batch,
         ts_mode,
+        partition,
+        line,
+        ..
     } = cfg;
+    let part = partition.as_str();
     let fd = sock.as_raw_fd();
     let mut dropped: u64 = 0;
-    let chan_id = if chan_name == "A" { b'A' } else { b'B' };
 
     sock.set_nonblocking(true).context("set nonblocking")?;
 
@@ -52,7 +65,7 @@ pub fn rx_loop(
         .map(|m| matches!(m, crate::config::TimestampingMode::Off))
         .unwrap_or(true);
     #[cfg(target_os = "linux")]
-    let use_recvmmsg: bool = ts_off && batch > 1;
+    let use_recvmmsg: bool = batch > 1;
     #[cfg(not(target_os = "linux"))]
     let use_recvmmsg: bool = false;
 
@@ -74,6 +87,13 @@ pub fn rx_loop(
     } else {
         Vec::new()
     };
+    // One control area per message so every packet in the batch keeps its own timestamp
+    #[cfg(target_os = "linux")]
+    let mut ctrls: Vec<CmsgArea> = if use_recvmmsg && !ts_off {
+        vec![CmsgArea([0; CMSG_AREA_WORDS]); batch]
+    } else {
+        Vec::new()
+    };
     #[cfg(target_os = "linux")]
     let mut hdrs: Vec<libc::mmsghdr> = if use_recvmmsg {
         let mut v = Vec::with_capacity(batch);
@@ -83,8 +103,9 @@ pub fn rx_loop(
             mh.msg_namelen = 0;
             mh.msg_iov = &mut iovecs[i] as *mut libc::iovec;
             mh.msg_iovlen = 1;
-            mh.msg_control = std::ptr::null_mut();
-            mh.msg_controllen = 0;
+            if let Some(c) = ctrls.get_mut(i) {
+                mh.msg_control = c.0.as_mut_ptr() as *mut libc::c_void;
+            }
             mh.msg_flags = 0;
             v.push(libc::mmsghdr {
                 msg_hdr: mh,
@@ -96,7 +117,7 @@ pub fn rx_loop(
         Vec::new()
     };
 
-    let queue_label: &'static str = if chan_name == "A" { "rx_a" } else { "rx_b" };
+    let queue_label = format!("rx_{}_{part}", chan_name.to_ascii_lowercase());
     let mut iter: u64 = 0;
     let mut idle_iters: u32 = 0;
     loop {
@@ -122,6 +143,10 @@ pub fn rx_loop(
                     iovecs[i].iov_base = s.as_mut_ptr() as *mut libc::c_void;
                     iovecs[i].iov_len = s.len();
                     hdrs[i].msg_len = 0;
+                    // The kernel shrinks msg_controllen to what it wrote
+                    if !ts_off {
+                        hdrs[i].msg_hdr.msg_controllen = (CMSG_AREA_WORDS * 8) as _;
+                    }
                 }
 
                 let ret = libc::recvmmsg(
@@ -144,31 +169,45 @@ pub fn rx_loop(
                     }
                 } else if ret > 0 {
                     progressed = true;
-                    let ts = loop_now_cache.unwrap_or_else(now_nanos);
+                    let batch_now = loop_now_cache.unwrap_or_else(now_nanos);
                     let count = ret as usize;
                     for i in 0..count {
                         let n = hdrs[i].msg_len as usize;
+                        let (ts, kind) = if ts_off {
+                            (batch_now, TsKind::Sw)
+                        } else {
+                            let ctrl = std::slice::from_raw_parts(
+                                ctrls[i].0.as_ptr() as *const u8,
+                                hdrs[i].msg_hdr.msg_controllen as usize,
+                            );
+                            cmsg_timestamp(ctrl).unwrap_or((batch_now, TsKind::Sw))
+                        };
                         let mut buf = std::mem::take(&mut bufs[i]);
                         buf.advance_mut(n);
-                        let maybe_seq = seq.extract_seq(&buf);
-                        if let Some(sv) = maybe_seq {
+                        let maybe_seq = seq.extract_stream_seq(&buf);
+                        if let Some((stream, sv)) = maybe_seq {
+                            let span = seq.seq_span(&buf);
+                            let seq_reset = seq.is_seq_reset(&buf);
                             let pkt = Pkt {
                                 buf: PktBuf::Bytes(buf),
                                 len: n,
                                 seq: sv,
+                                stream,
+                                seq_span: span,
+                                seq_reset,
                                 ts_nanos: ts,
-                                chan: chan_id,
-                                _ts_kind: TsKind::Sw,
+                                chan: line,
+                                _ts_kind: kind,
                                 merge_emit_ns: 0,
                             };
                             if let Err(_full) = q_out.push(pkt) {
                                 dropped += 1;
-                                metrics::inc_rx_drop(chan_name);
+                                metrics::inc_rx_drop(part, chan_name);
                                 if dropped % 10_000 == 1 {
                                     debug!("{}_rx: queue full, dropped={}", chan_name, dropped);
                                 }
                             } else {
-                                metrics::inc_rx(chan_name, n);
+                                metrics::inc_rx(part, chan_name, n);
                             }
                         } else {
                             pool.put(buf);
@@ -214,19 +253,16 @@ pub fn rx_loop(
                                             kind = TsKind::Sw;
                                         }
                                         ControlMessageOwned::ScmTimestamping(tss) => {
+                                            // [software, hardware-sys, hardware-raw]: last non-zero slot wins
                                             let pick = tss
                                                 .iter()
+                                                .zip([TsKind::Sw, TsKind::HwSys, TsKind::HwRaw])
                                                 .rev()
-                                                .find(|t| t.tv_sec() != 0 || t.tv_nsec() != 0)
-                                                .copied();
-                                            if let Some(tv) = pick {
+                                                .find(|(t, _)| t.tv_sec() != 0 || t.tv_nsec() != 0);
+                                            if let Some((tv, k)) = pick {
                                                 ts_nanos = (tv.tv_sec() as u64) * 1_000_000_000
                                                     + (tv.tv_nsec() as u64);
-                                                kind = match ts_mode.as_ref() {
-                                                    Some(crate::config::TimestampingMode::HardwareRaw) => TsKind::HwRaw,
-                                                    Some(crate::config::TimestampingMode::Hardware) => TsKind::HwSys,
-                                                    _ => TsKind::HwSys,
-                                                };
+                                                kind = k;
                                             }
                                         }
                                         _ => {}
@@ -295,25 +331,30 @@ pub fn rx_loop(
                         unsafe {
                             buf.advance_mut(n);
                         }
-                        let maybe_seq = seq.extract_seq(&buf);
-                        if let Some(sv) = maybe_seq {
+                        let maybe_seq = seq.extract_stream_seq(&buf);
+                        if let Some((stream, sv)) = maybe_seq {
+                            let span = seq.seq_span(&buf);
+                            let seq_reset = seq.is_seq_reset(&buf);
                             let pkt = Pkt {
                                 buf: PktBuf::Bytes(buf),
                                 len: n,
                                 seq: sv,
+                                stream,
+                                seq_span: span,
+                                seq_reset,
                                 ts_nanos: ts,
-                                chan: chan_id,
+                                chan: line,
                                 _ts_kind: kind,
                                 merge_emit_ns: 0,
                             };
                             if let Err(_full) = q_out.push(pkt) {
                                 dropped += 1;
-                                metrics::inc_rx_drop(chan_name);
+                                metrics::inc_rx_drop(part, chan_name);
                                 if dropped % 10_000 == 1 {
                                     debug!("{}_rx: queue full, dropped={}", chan_name, dropped);
                                 }
                             } else {
-                                metrics::inc_rx(chan_name, n);
+                                metrics::inc_rx(part, chan_name, n);
                             }
                         } else {
                             pool.put(buf);
@@ -343,7 +384,7 @@ pub fn rx_loop(
 
         iter = iter.wrapping_add(1);
         if (iter & 0x3fff) == 0 {
-            metrics::set_queue_len(queue_label, q_out.len());
+            metrics::set_queue_len(&queue_label, q_out.len());
         }
     }
 
@@ -351,3 +392,110 @@ pub fn rx_loop(
 }
 
 // Removed unused legacy adapter `rx_loop_compat`. If needed, reintroduce via a small wrapper.
+
+/// Control buffer for one message: SCM_TIMESTAMPING (timespec[3]) plus headroom, cmsg-aligned.
+#[cfg(target_os = "linux")]
+const CMSG_AREA_WORDS: usize = 16;
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy)]
+#[repr(C, align(8))]
+struct CmsgArea([u64; CMSG_AREA_WORDS]);
+
+/// Kernel RX timestamp from a raw control area (recvmmsg / io_uring recvmsg).
+///
+/// SCM_TIMESTAMPING carries [software, hardware-sys, hardware-raw]; the last non-zero
+/// slot wins and decides the kind, so a software fallback is never tagged as hardware.
+#[cfg(target_os = "linux")]
+pub(crate) fn cmsg_timestamp(ctrl: &[u8]) -> Option<(u64, TsKind)> {
+    use std::mem::size_of;
+    const HDR: usize = size_of::<libc::cmsghdr>();
+    const TS: usize = size_of::<libc::timespec>();
+    let align = |n: usize| (n + size_of::<usize>() - 1) & !(size_of::<usize>() - 1);
+    let ts_ns = |b: &[u8]| -> u64 {
+        let ts = unsafe { (b.as_ptr() as *const libc::timespec).read_unaligned() };
+        ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+    };
+    let mut off = 0usize;
+    let mut out = None;
+    while off + HDR <= ctrl.len() {
+        let h = unsafe { (ctrl.as_ptr().add(off) as *const libc::cmsghdr).read_unaligned() };
+        #[allow(clippy::unnecessary_cast)] // u32 on musl
+        let len = h.cmsg_len as usize;
+        if len < HDR || off + len > ctrl.len() {
+            break;
+        }
+        let data = &ctrl[off + align(HDR)..off + len];
+        if h.cmsg_level == libc::SOL_SOCKET {
+            if h.cmsg_type == libc::SO_TIMESTAMPNS && data.len() >= TS {
+                out = Some((ts_ns(data), TsKind::Sw));
+            } else if h.cmsg_type == libc::SO_TIMESTAMPING && data.len() >= 3 * TS {
+                const KINDS: [TsKind; 3] = [TsKind::Sw, TsKind::HwSys, TsKind::HwRaw];
+                if let Some((ns, kind)) = (0..3)
+                    .rev()
+                    .map(|i| (ts_ns(&data[i * TS..]), KINDS[i]))
+                    .find(|&(v, _)| v != 0)
+                {
+                    out = Some((ns, kind));
+                }
+            }
+        }
+        off += align(len);
+    }
+    out
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::mem::size_of;
+
+    fn cmsg(level: i32, ty: i32, data: &[u8]) -> Vec<u8> {
+        let hdr = size_of::<libc::cmsghdr>();
+        let mut h: libc::cmsghdr = unsafe { std::mem::zeroed() };
+        h.cmsg_len = (hdr + data.len()) as _;
+        h.cmsg_level = level;
+        h.cmsg_type = ty;
+        let mut v =
+            unsafe { std::slice::from_raw_parts(&h as *const _ as *const u8, hdr).to_vec() };
+        v.extend_from_slice(data);
+        while v.len() % size_of::<usize>() != 0 {
+            v.push(0);
+        }
+        v
+    }
+
+    fn ts(sec: i64, nsec: i64) -> Vec<u8> {
+        let t = libc::timespec {
+            tv_sec: sec as _,
+            tv_nsec: nsec as _,
+        };
+        unsafe {
+            std::slice::from_raw_parts(&t as *const _ as *const u8, size_of::<libc::timespec>())
+                .to_vec()
+        }
+    }
+
+    #[test]
+    fn cmsg_timestamp_tags_kind_by_slot() {
+        let stamping = |slots: [(i64, i64); 3]| {
+            let data: Vec<u8> = slots.iter().flat_map(|&(s, n)| ts(s, n)).collect();
+            cmsg(libc::SOL_SOCKET, libc::SO_TIMESTAMPING, &data)
+        };
+        assert_eq!(
+            cmsg_timestamp(&stamping([(1, 5), (0, 0), (2, 7)])),
+            Some((2_000_000_007, TsKind::HwRaw))
+        );
+        assert_eq!(
+            cmsg_timestamp(&stamping([(1, 5), (4, 1), (0, 0)])),
+            Some((4_000_000_001, TsKind::HwSys))
+        );
+        // Hardware requested but the NIC did not stamp: software fallback stays Sw
+        assert_eq!(
+            cmsg_timestamp(&stamping([(1, 5), (0, 0), (0, 0)])),
+            Some((1_000_000_005, TsKind::Sw))
+        );
+        let ns = cmsg(libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, &ts(3, 9));
+        assert_eq!(cmsg_timestamp(&ns), Some((3_000_000_009, TsKind::Sw)));
+        assert_eq!(cmsg_timestamp(&[]), None);
+    }
+}
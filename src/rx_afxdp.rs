@@ -1,7 +1,17 @@
 // src/rx_afxdp.rs
 // Optional AF_XDP receiver. Integrate by spawning this loop instead of `rx::rx_loop`. Keeps the same Pkt
 // contract and queueing model.
+//
+// Each queue gets its own XSK socket and UMEM. A small bundled XDP program (attached once per
+// interface over rtnetlink) redirects IPv4 UDP for the channel port into the XSKMAP slot of the
+// receiving queue; everything else passes to the kernel stack. Frames travel downstream as
+// `PktBuf::Umem` leases without a copy and go back to the fill ring when the Pkt is recycled or
+// dropped. If any step of the XSK setup fails, the loop falls back to a TPACKET ring: V2
+// (per-frame slots) or V3 (whole blocks handed over per wakeup), per `afxdp.packet_ring`.
 
+use crate::config::AfxdpCfg;
+#[cfg(target_os = "linux")]
+use crate::config::{PacketRing, XdpMode};
 #[cfg(target_os = "linux")]
 use crate::metrics;
 use crate::parser::SeqExtractor;
@@ -16,13 +26,16 @@ use crate::util::BarrierFlag;
 use bytes::BufMut;
 use std::sync::Arc;
 
-/// Receive loop using a high-performance packet ring on Linux (TPACKET_V2 fallback if AF_XDP is unavailable).
+/// Receive loop using AF_XDP on Linux (TPACKET ring fallback if AF_XDP is unavailable).
 #[cfg(not(target_os = "linux"))]
+#[allow(clippy::too_many_arguments)]
 pub fn afxdp_loop(
-    _ifname: &str,
+    _cfg: &AfxdpCfg,
+    _udp_port: u16,
     _queue_id: u32,
     _seq: Arc<dyn SeqExtractor>,
     _chan_name: &str,
+    _partition: &str,
     _q_out: Arc<SpscQueue<Pkt>>,
     _pool: Arc<PacketPool>,
     _shutdown: Arc<BarrierFlag>,
@@ -30,23 +43,177 @@ pub fn afxdp_loop(
     Err(anyhow::anyhow!("AF_XDP is only supported on Linux"))
 }
 
+/// Receive loop using AF_XDP on Linux (TPACKET ring fallback if AF_XDP is unavailable or
+/// `xdp_mode = "off"`). `udp_port` restricts redirection to one destination port (0 = all IPv4 UDP).
 #[cfg(target_os = "linux")]
+#[allow(clippy::too_many_arguments)]
 pub fn afxdp_loop(
-    ifname: &str,
-    _queue_id: u32,
+    cfg: &AfxdpCfg,
+    udp_port: u16,
+    queue_id: u32,
     seq: Arc<dyn SeqExtractor>,
     chan_name: &str,
+    partition: &str,
     q_out: Arc<SpscQueue<Pkt>>,
     pool: Arc<PacketPool>,
     shutdown: Arc<BarrierFlag>,
 ) -> anyhow::Result<()> {
-    // Try AF_XDP? For portability, we fallback immediately to PACKET_RX_RING (TPACKET_V2),
-    // which is widely supported and provides mmap'ed zero-copy from kernel to userspace.
+    if cfg.xdp_mode == XdpMode::Off {
+        return packet_ring_loop(
+            cfg, queue_id, seq, chan_name, partition, q_out, pool, shutdown,
+        );
+    }
+    match xsk::XskSocket::open(
+        &cfg.ifname,
+        queue_id,
+        cfg.xdp_mode,
+        cfg.umem_frames,
+        udp_port,
+    ) {
+        Ok(sock) => {
+            log::info!(
+                "AF_XDP bound to {} queue {} ({})",
+                cfg.ifname,
+                queue_id,
+                if sock.zero_copy() {
+                    "zero-copy"
+                } else {
+                    "copy mode"
+                }
+            );
+            sock.run(seq, chan_name, partition, q_out, shutdown)
+        }
+        Err(e) => {
+            log::warn!(
+                "AF_XDP setup on {} queue {} failed: {e:#}; falling back to {:?}",
+                cfg.ifname,
+                queue_id,
+                cfg.packet_ring
+            );
+            packet_ring_loop(
+                cfg, queue_id, seq, chan_name, partition, q_out, pool, shutdown,
+            )
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[allow(clippy::too_many_arguments)]
+fn packet_ring_loop(
+    cfg: &AfxdpCfg,
+    queue_id: u32,
+    seq: Arc<dyn SeqExtractor>,
+    chan_name: &str,
+    partition: &str,
+    q_out: Arc<SpscQueue<Pkt>>,
+    pool: Arc<PacketPool>,
+    shutdown: Arc<BarrierFlag>,
+) -> anyhow::Result<()> {
+    let rx = PacketRx {
+        seq,
+        chan_name,
+        partition,
+        chan_id: if chan_name == "A" { 0 } else { 1 },
+        q_out,
+        pool,
+        vlan_id: cfg.vlan_id,
+    };
+    match cfg.packet_ring {
+        PacketRing::TpacketV2 => tpacket_v2_loop(&cfg.ifname, queue_id, &rx, shutdown),
+        PacketRing::TpacketV3 => {
+            tpacket_v3_loop(&cfg.ifname, queue_id, cfg.block_timeout_ms, &rx, shutdown)
+        }
+    }
+}
+
+/// Per-thread state shared by the TPACKET ring walkers.
+#[cfg(target_os = "linux")]
+struct PacketRx<'a> {
+    seq: Arc<dyn SeqExtractor>,
+    chan_name: &'a str,
+    partition: &'a str,
+    chan_id: u8,
+    q_out: Arc<SpscQueue<Pkt>>,
+    pool: Arc<PacketPool>,
+    vlan_id: Option<u16>,
+}
+
+#[cfg(target_os = "linux")]
+impl PacketRx<'_> {
+    /// Copy the UDP payload of an L2 frame into a pool buffer and forward it.
+    /// `vlan` is the tag reported out of band by the ring (stripped by VLAN offload).
+    #[inline]
+    fn forward(&self, frame: &[u8], vlan: Option<u16>, ts_nanos: u64) {
+        let Some((udp_payload, inline_vlan)) = parse_udp_payload_vlan(frame) else {
+            return;
+        };
+        if let Some(want) = self.vlan_id {
+            if vlan.or(inline_vlan) != Some(want) {
+                return;
+            }
+        }
+        let nbytes = udp_payload.len();
+        let mut buf = self.pool.get();
+        unsafe {
+            let dst = {
+                let s = buf.chunk_mut();
+                std::slice::from_raw_parts_mut(s.as_mut_ptr(), s.len())
+            };
+            if nbytes > dst.len() {
+                self.pool.put(buf);
+                return;
+            }
+            std::ptr::copy_nonoverlapping(udp_payload.as_ptr(), dst.as_mut_ptr(), nbytes);
+            buf.advance_mut(nbytes);
+        }
+        let Some((stream, sv)) = self.seq.extract_stream_seq(&buf) else {
+            self.pool.put(buf);
+            return;
+        };
+        let span = self.seq.seq_span(&buf);
+        let seq_reset = self.seq.is_seq_reset(&buf);
+        let pkt = Pkt {
+            buf: PktBuf::Bytes(buf),
+            len: nbytes,
+            seq: sv,
+            stream,
+            seq_span: span,
+            seq_reset,
+            ts_nanos,
+            chan: self.chan_id,
+            _ts_kind: TsKind::Sw,
+            merge_emit_ns: 0,
+        };
+        if let Err(_full) = self.q_out.push(pkt) {
+            metrics::inc_rx_drop(self.partition, self.chan_name);
+        } else {
+            metrics::inc_rx(self.partition, self.chan_name, nbytes);
+        }
+    }
+}
+
+/// Offset that maps kernel (CLOCK_REALTIME) packet stamps onto `now_nanos()`'s clock,
+/// so ring timestamps feed the same latency histograms as the socket path.
+#[cfg(target_os = "linux")]
+#[inline]
+fn realtime_to_mono_offset() -> u64 {
+    crate::util::now_realtime_nanos().saturating_sub(crate::util::now_nanos())
+}
+
+/// AF_PACKET socket with an mmap'ed RX ring of `ring_len` bytes, bound to `ifname` and
+/// joined to the fanout group of `queue_id`.
+#[cfg(target_os = "linux")]
+fn open_packet_ring<R>(
+    ifname: &str,
+    queue_id: u32,
+    version: libc::c_int,
+    req: &R,
+    ring_len: usize,
+) -> anyhow::Result<(libc::c_int, *mut libc::c_void)> {
     use std::ffi::CString;
     use std::mem::size_of;
     use std::ptr::null_mut;
 
-    // Open AF_PACKET raw socket (fallback path)
     let fd = unsafe {
         libc::socket(
             libc::AF_PACKET,
@@ -60,71 +227,46 @@ pub fn afxdp_loop(
             std::io::Error::last_os_error()
         ));
     }
+    let fail = |msg: String| {
+        unsafe {
+            libc::close(fd);
+        }
+        Err(anyhow::anyhow!(msg))
+    };
 
-    // Set TPACKET_V2
-    const TPACKET_V2: libc::c_int = 1;
-    let ver: libc::c_int = TPACKET_V2;
     let rc = unsafe {
         libc::setsockopt(
             fd,
             libc::SOL_PACKET,
             libc::PACKET_VERSION,
-            &ver as *const _ as *const libc::c_void,
+            &version as *const _ as *const libc::c_void,
             size_of::<libc::c_int>() as libc::socklen_t,
         )
     };
     if rc != 0 {
-        unsafe {
-            libc::close(fd);
-        }
-        return Err(anyhow::anyhow!("PACKET_VERSION set failed"));
+        return fail("PACKET_VERSION set failed".into());
     }
 
-    // Ring parameters
-    let frame_size: u32 = 2048; // typical MTU + headers; aligned
-    let block_size: u32 = frame_size * 1024; // 2MB per block
-    let block_nr: u32 = 4; // total 8MB
-    let frame_nr: u32 = (block_size / frame_size) * block_nr;
-
-    #[repr(C)]
-    struct TpacketReq {
-        tp_block_size: u32,
-        tp_block_nr: u32,
-        tp_frame_size: u32,
-        tp_frame_nr: u32,
-    }
-    let req = TpacketReq {
-        tp_block_size: block_size,
-        tp_block_nr: block_nr,
-        tp_frame_size: frame_size,
-        tp_frame_nr: frame_nr,
-    };
     let rc = unsafe {
         libc::setsockopt(
             fd,
             libc::SOL_PACKET,
             libc::PACKET_RX_RING,
-            &req as *const _ as *const libc::c_void,
-            size_of::<TpacketReq>() as libc::socklen_t,
+            req as *const R as *const libc::c_void,
+            size_of::<R>() as libc::socklen_t,
         )
     };
     if rc != 0 {
-        unsafe {
-            libc::close(fd);
-        }
-        return Err(anyhow::anyhow!(
+        return fail(format!(
             "PACKET_RX_RING set failed: {}",
             std::io::Error::last_os_error()
         ));
     }
 
     // Bind to interface
-    let if_index = unsafe { libc::if_nametoindex(CString::new(ifname).unwrap().as_ptr()) };
+    let if_index = unsafe { libc::if_nametoindex(CString::new(ifname)?.as_ptr()) };
     if if_index == 0 {
-        unsafe {
-            libc::close(fd);
-        }
-        return Err(anyhow::anyhow!("if_nametoindex failed for {}", ifname));
+        return fail(format!("if_nametoindex failed for {}", ifname));
     }
     let mut sll: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
     sll.sll_family = libc::AF_PACKET as u16;
@@ -138,10 +280,7 @@ pub fn afxdp_loop(
         )
     };
     if rc != 0 {
-        unsafe {
-            libc::close(fd);
-        }
-        return Err(anyhow::anyhow!("bind AF_PACKET failed"));
+        return fail("bind AF_PACKET failed".into());
     }
 
     // Enable PACKET_FANOUT to distribute frames across multiple sockets/threads
@@ -149,7 +288,7 @@ pub fn afxdp_loop(
     {
         const PACKET_FANOUT: libc::c_int = 18; // from linux/if_packet.h
         const PACKET_FANOUT_HASH: u16 = 0;
-        let group_id: u16 = (_queue_id as u16).wrapping_add(1);
+        let group_id: u16 = (queue_id as u16).wrapping_add(1);
         let val: u32 = ((group_id as u32) << 16) | (PACKET_FANOUT_HASH as u32);
         let _ = unsafe {
             libc::setsockopt(
@@ -163,7 +302,6 @@ pub fn afxdp_loop(
     }
 
     // Mmap ring
-    let ring_len = (block_size as usize) * (block_nr as usize);
     let ring = unsafe {
         libc::mmap(
             null_mut(),
@@ -175,11 +313,44 @@ pub fn afxdp_loop(
         )
     };
     if ring == libc::MAP_FAILED {
-        unsafe {
-            libc::close(fd);
-        }
-        return Err(anyhow::anyhow!("mmap RX_RING failed"));
+        return fail("mmap RX_RING failed".into());
+    }
+    Ok((fd, ring))
+}
+
+/// TPACKET_V2: one frame per slot, per-frame status polling.
+#[cfg(target_os = "linux")]
+fn tpacket_v2_loop(
+    ifname: &str,
+    queue_id: u32,
+    rx: &PacketRx,
+    shutdown: Arc<BarrierFlag>,
+) -> anyhow::Result<()> {
+    // PACKET_RX_RING (TPACKET_V2) is widely supported and provides an mmap'ed ring from
+    // kernel to userspace; payloads are copied once into pool buffers.
+    const TPACKET_V2: libc::c_int = 1;
+
+    // Ring parameters
+    let frame_size: u32 = 2048; // typical MTU + headers; aligned
+    let block_size: u32 = frame_size * 1024; // 2MB per block
+    let block_nr: u32 = 4; // total 8MB
+    let frame_nr: u32 = (block_size / frame_size) * block_nr;
+
+    #[repr(C)]
+    struct TpacketReq {
+        tp_block_size: u32,
+        tp_block_nr: u32,
+        tp_frame_size: u32,
+        tp_frame_nr: u32,
     }
+    let req = TpacketReq {
+        tp_block_size: block_size,
+        tp_block_nr: block_nr,
+        tp_frame_size: frame_size,
+        tp_frame_nr: frame_nr,
+    };
+    let ring_len = (block_size as usize) * (block_nr as usize);
+    let (fd, ring) = open_packet_ring(ifname, queue_id, TPACKET_V2, &req, ring_len)?;
 
     // Structures for TPACKET_V2 frames
     #[repr(C)]
@@ -197,65 +368,38 @@ pub fn afxdp_loop(
     }
 
     const TP_STATUS_USER: u32 = 1u32; // bit 0
+    const TP_STATUS_VLAN_VALID: u32 = 1 << 4;
+
+    // Idle spins between refreshes of the clock offset (it only moves with NTP slew)
+    const CLOCK_OFF_REFRESH_SPINS: u32 = 4096;
 
-    let chan_id = if chan_name == "A" { b'A' } else { b'B' };
     let mut frame_idx: u32 = 0;
-    let mut dropped: u64 = 0;
+    let mut clock_off = realtime_to_mono_offset();
+    let mut idle_spins = 0u32;
     while !shutdown.is_raised() {
         let off = (frame_idx as usize) * (frame_size as usize);
         let hdr_ptr = unsafe { (ring as *mut u8).add(off) as *mut Tpacket2Hdr };
         let status = unsafe { (*hdr_ptr).tp_status };
         if (status & TP_STATUS_USER) == 0 {
             crate::util::spin_wait(64);
+            idle_spins += 1;
+            if idle_spins == CLOCK_OFF_REFRESH_SPINS {
+                idle_spins = 0;
+                clock_off = realtime_to_mono_offset();
+            }
             continue;
         }
 
         // Determine packet bytes (L2.. payload)
-        let snap = unsafe { (*hdr_ptr).tp_snaplen } as usize;
-        let mac_off = unsafe { (*hdr_ptr).tp_mac } as usize;
-        let data_ptr = unsafe { (hdr_ptr as *mut u8).add(mac_off) };
+        let hdr = unsafe { &*hdr_ptr };
+        let snap = hdr.tp_snaplen as usize;
+        let data_ptr = unsafe { (hdr_ptr as *mut u8).add(hdr.tp_mac as usize) };
         let frame = unsafe { std::slice::from_raw_parts(data_ptr, snap) };
-
-        // Parse UDP payload offset (Ethernet + IPv4 + UDP), handle optional single VLAN
-        if let Some(udp_payload) = parse_udp_payload(frame) {
-            let nbytes = udp_payload.len();
-            // Use kernel-provided timestamp from TPACKET_V2 header
-            let ts_nanos = (unsafe { (*hdr_ptr).tp_sec } as u64) * 1_000_000_000u64
-                + (unsafe { (*hdr_ptr).tp_nsec } as u64);
-            let mut buf = pool.get();
-            unsafe {
-                let dst = {
-                    let s = buf.chunk_mut();
-                    std::slice::from_raw_parts_mut(s.as_mut_ptr() as *mut u8, s.len())
-                };
-                if nbytes <= dst.len() {
-                    std::ptr::copy_nonoverlapping(udp_payload.as_ptr(), dst.as_mut_ptr(), nbytes);
-                    buf.advance_mut(nbytes);
-                    let seqv = seq.extract_seq(&buf);
-                    if let Some(sv) = seqv {
-                        let pkt = Pkt {
-                            buf: PktBuf::Bytes(buf),
-                            len: nbytes,
-                            seq: sv,
-                            ts_nanos,
-                            chan: chan_id,
-                            _ts_kind: TsKind::Sw,
-                            merge_emit_ns: 0,
-                        };
-                        if let Err(_full) = q_out.push(pkt) {
-                            dropped += 1;
-                            metrics::inc_rx_drop(chan_name);
-                        } else {
-                            metrics::inc_rx(chan_name, nbytes);
-                        }
-                    } else {
-                        pool.put(buf);
-                    }
-                } else {
-                    pool.put(buf);
-                }
-            }
-        }
+        let vlan = (status & TP_STATUS_VLAN_VALID != 0).then_some(hdr.tp_vlan_tci & 0x0FFF);
+        // Kernel-provided timestamp from the TPACKET_V2 header
+        let ts_nanos =
+            (hdr.tp_sec as u64 * 1_000_000_000 + hdr.tp_nsec as u64).saturating_sub(clock_off);
+        rx.forward(frame, vlan, ts_nanos);
 
         // Release frame back to kernel
         unsafe {
@@ -271,8 +415,1041 @@ pub fn afxdp_loop(
     Ok(())
 }
 
+/// TPACKET_V3: the kernel fills variable-size blocks and hands over a whole block at a
+/// time (when full or after `block_timeout_ms`); each wakeup walks every packet in it.
+#[cfg(target_os = "linux")]
+fn tpacket_v3_loop(
+    ifname: &str,
+    queue_id: u32,
+    block_timeout_ms: u32,
+    rx: &PacketRx,
+    shutdown: Arc<BarrierFlag>,
+) -> anyhow::Result<()> {
+    const TPACKET_V3: libc::c_int = 2;
+    const TP_FT_REQ_FILL_RXHASH: u32 = 1;
+    const TP_STATUS_KERNEL: u32 = 0;
+    const TP_STATUS_USER: u32 = 1;
+    const TP_STATUS_VLAN_VALID: u32 = 1 << 4;
+
+    let block_size: u32 = 1 << 20;
+    let block_nr: u32 = 8;
+    let frame_size: u32 = 2048; // only used by the kernel for sanity checks in V3
+
+    #[repr(C)]
+    struct TpacketReq3 {
+        tp_block_size: u32,
+        tp_block_nr: u32,
+        tp_frame_size: u32,
+        tp_frame_nr: u32,
+        tp_retire_blk_tov: u32,
+        tp_sizeof_priv: u32,
+        tp_feature_req_word: u32,
+    }
+    let req = TpacketReq3 {
+        tp_block_size: block_size,
+        tp_block_nr: block_nr,
+        tp_frame_size: frame_size,
+        tp_frame_nr: (block_size / frame_size) * block_nr,
+        tp_retire_blk_tov: block_timeout_ms,
+        tp_sizeof_priv: 0,
+        tp_feature_req_word: TP_FT_REQ_FILL_RXHASH,
+    };
+    let ring_len = (block_size as usize) * (block_nr as usize);
+    let (fd, ring) = open_packet_ring(ifname, queue_id, TPACKET_V3, &req, ring_len)?;
+
+    // struct tpacket_block_desc with the tpacket_hdr_v1 header inlined
+    #[repr(C)]
+    struct BlockDesc {
+        version: u32,
+        offset_to_priv: u32,
+        block_status: u32,
+        num_pkts: u32,
+        offset_to_first_pkt: u32,
+        blk_len: u32,
+        // followed by seq_num and first/last packet timestamps
+    }
+
+    // struct tpacket3_hdr with tpacket_hdr_variant1 inlined
+    #[repr(C)]
+    struct Tpacket3Hdr {
+        tp_next_offset: u32,
+        tp_sec: u32,
+        tp_nsec: u32,
+        tp_snaplen: u32,
+        tp_len: u32,
+        tp_status: u32,
+        tp_mac: u16,
+        tp_net: u16,
+        tp_rxhash: u32,
+        tp_vlan_tci: u32,
+        tp_vlan_tpid: u16,
+        tp_padding: u16,
+    }
+
+    let mut flows = FlowTracker::new(rx.chan_name, queue_id);
+    let mut block_idx: u32 = 0;
+    let mut pfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN | libc::POLLERR,
+        revents: 0,
+    };
+    while !shutdown.is_raised() {
+        let bd_ptr = unsafe { (ring as *mut u8).add(block_idx as usize * block_size as usize) }
+            as *mut BlockDesc;
+        let status = unsafe { std::ptr::read_volatile(&(*bd_ptr).block_status) };
+        if status & TP_STATUS_USER == 0 {
+            // Sleep until the kernel retires a block; bounded so shutdown stays responsive
+            unsafe { libc::poll(&mut pfd, 1, 100) };
+            continue;
+        }
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Acquire);
+
+        let clock_off = realtime_to_mono_offset();
+        let bd = unsafe { &*bd_ptr };
+        let mut pkt_ptr = unsafe { (bd_ptr as *mut u8).add(bd.offset_to_first_pkt as usize) };
+        for _ in 0..bd.num_pkts {
+            let hdr = unsafe { &*(pkt_ptr as *const Tpacket3Hdr) };
+            let frame = unsafe {
+                std::slice::from_raw_parts(
+                    pkt_ptr.add(hdr.tp_mac as usize),
+                    hdr.tp_snaplen as usize,
+                )
+            };
+            let vlan = (hdr.tp_status & TP_STATUS_VLAN_VALID != 0)
+                .then_some((hdr.tp_vlan_tci & 0x0FFF) as u16);
+            flows.observe(hdr.tp_rxhash);
+            let ts_nanos =
+                (hdr.tp_sec as u64 * 1_000_000_000 + hdr.tp_nsec as u64).saturating_sub(clock_off);
+            rx.forward(frame, vlan, ts_nanos);
+            pkt_ptr = unsafe { pkt_ptr.add(hdr.tp_next_offset as usize) };
+        }
+
+        // Hand the whole block back to the kernel
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Release);
+        unsafe { std::ptr::write_volatile(&mut (*bd_ptr).block_status, TP_STATUS_KERNEL) };
+        block_idx = (block_idx + 1) % block_nr;
+    }
+
+    unsafe {
+        libc::munmap(ring, ring_len);
+        libc::close(fd);
+    }
+    Ok(())
+}
+
+/// Counts distinct RX flow hashes (`tp_rxhash`) seen by one ring worker, exported as
+/// `rx_flows{chan,queue}` to check fanout/RSS balance. Stops tracking past a small cap.
+#[cfg(target_os = "linux")]
+struct FlowTracker {
+    seen: Vec<u32>,
+    gauge: prometheus::IntGauge,
+}
+
+#[cfg(target_os = "linux")]
+impl FlowTracker {
+    const CAP: usize = 64;
+
+    fn new(chan: &str, queue_id: u32) -> Self {
+        Self {
+            seen: Vec::with_capacity(Self::CAP),
+            gauge: metrics::rx_flows_gauge(chan, queue_id),
+        }
+    }
+
+    #[inline]
+    fn observe(&mut self, rxhash: u32) {
+        if self.seen.len() < Self::CAP && rxhash != 0 && !self.seen.contains(&rxhash) {
+            self.seen.push(rxhash);
+            self.gauge.set(self.seen.len() as i64);
+        }
+    }
+}
+
+// -------------------- AF_XDP socket --------------------
+
+#[cfg(target_os = "linux")]
+mod xsk {
+    use super::parse_udp_payload;
+    use crate::config::XdpMode;
+    use crate::metrics;
+    use crate::parser::SeqExtractor;
+    use crate::pool::{Pkt, TsKind, UmemArea};
+    use crate::spsc::SpscQueue;
+    use crate::util::{now_nanos, BarrierFlag};
+    use anyhow::Context;
+    use once_cell::sync::Lazy;
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::io;
+    use std::mem::size_of;
+    use std::ptr::null_mut;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex, Weak};
+
+    // linux/if_xdp.h
+    const AF_XDP: libc::c_int = 44;
+    const SOL_XDP: libc::c_int = 283;
+    const XDP_MMAP_OFFSETS: libc::c_int = 1;
+    const XDP_RX_RING: libc::c_int = 2;
+    const XDP_UMEM_REG: libc::c_int = 4;
+    const XDP_UMEM_FILL_RING: libc::c_int = 5;
+    const XDP_UMEM_COMPLETION_RING: libc::c_int = 6;
+    const XDP_STATISTICS: libc::c_int = 7;
+    const XDP_OPTIONS: libc::c_int = 8;
+    const XDP_OPTIONS_ZEROCOPY: u32 = 1;
+    const XDP_COPY: u16 = 1 << 1;
+    const XDP_USE_NEED_WAKEUP: u16 = 1 << 3;
+    const XDP_RING_NEED_WAKEUP: u32 = 1;
+    const XDP_PGOFF_RX_RING: libc::off_t = 0;
+    const XDP_UMEM_PGOFF_FILL_RING: libc::off_t = 0x1_0000_0000;
+    const XDP_UMEM_PGOFF_COMPLETION_RING: libc::off_t = 0x1_8000_0000;
+
+    const FRAME_SIZE: u32 = 2048;
+    const COMPLETION_RING_SIZE: u32 = 64; // RX only; required by bind
+    const RX_BATCH: u32 = 64;
+
+    #[repr(C)]
+    #[derive(Default, Clone, Copy)]
+    struct XdpRingOffset {
+        producer: u64,
+        consumer: u64,
+        desc: u64,
+        flags: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct XdpMmapOffsets {
+        rx: XdpRingOffset,
+        tx: XdpRingOffset,
+        fr: XdpRingOffset,
+        cr: XdpRingOffset,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct XdpUmemReg {
+        addr: u64,
+        len: u64,
+        chunk_size: u32,
+        headroom: u32,
+        flags: u32,
+        tx_metadata_len: u32,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct SockaddrXdp {
+        sxdp_family: u16,
+        sxdp_flags: u16,
+        sxdp_ifindex: u32,
+        sxdp_queue_id: u32,
+        sxdp_shared_umem_fd: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct XdpDesc {
+        addr: u64,
+        len: u32,
+        options: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default, Debug)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct XdpStatistics {
+        rx_dropped: u64,
+        rx_invalid_descs: u64,
+        tx_invalid_descs: u64,
+        rx_ring_full: u64,
+        rx_fill_ring_empty_descs: u64,
+        tx_ring_empty_descs: u64,
+    }
+
+    fn os_err(what: &str) -> anyhow::Error {
+        anyhow::anyhow!("{what}: {}", io::Error::last_os_error())
+    }
+
+    fn setsockopt<T>(fd: i32, level: i32, name: i32, val: &T, what: &str) -> anyhow::Result<()> {
+        let rc = unsafe {
+            libc::setsockopt(
+                fd,
+                level,
+                name,
+                val as *const T as *const libc::c_void,
+                size_of::<T>() as libc::socklen_t,
+            )
+        };
+        if rc != 0 {
+            return Err(os_err(what));
+        }
+        Ok(())
+    }
+
+    fn getsockopt<T: Default>(fd: i32, name: i32, what: &str) -> anyhow::Result<T> {
+        let mut v = T::default();
+        let mut len = size_of::<T>() as libc::socklen_t;
+        let rc = unsafe {
+            libc::getsockopt(
+                fd,
+                SOL_XDP,
+                name,
+                &mut v as *mut T as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if rc != 0 {
+            return Err(os_err(what));
+        }
+        if len as usize != size_of::<T>() {
+            anyhow::bail!("{what}: unexpected option size {len} (kernel too old?)");
+        }
+        Ok(v)
+    }
+
+    /// Single-producer/single-consumer ring shared with the kernel.
+    struct Ring {
+        map: *mut libc::c_void,
+        map_len: usize,
+        producer: *const AtomicU32,
+        consumer: *const AtomicU32,
+        flags: *const AtomicU32,
+        descs: *mut u8,
+        size: u32,
+    }
+
+    impl Ring {
+        fn map(
+            fd: i32,
+            off: &XdpRingOffset,
+            size: u32,
+            elem: usize,
+            pgoff: libc::off_t,
+        ) -> anyhow::Result<Self> {
+            let map_len = off.desc as usize + size as usize * elem;
+            let map = unsafe {
+                libc::mmap(
+                    null_mut(),
+                    map_len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED | libc::MAP_POPULATE,
+                    fd,
+                    pgoff,
+                )
+            };
+            if map == libc::MAP_FAILED {
+                return Err(os_err("mmap XDP ring"));
+            }
+            let at = |o: u64| unsafe { (map as *mut u8).add(o as usize) };
+            Ok(Self {
+                map,
+                map_len,
+                producer: at(off.producer) as *const AtomicU32,
+                consumer: at(off.consumer) as *const AtomicU32,
+                flags: at(off.flags) as *const AtomicU32,
+                descs: at(off.desc),
+                size,
+            })
+        }
+
+        #[inline]
+        fn prod(&self) -> &AtomicU32 {
+            unsafe { &*self.producer }
+        }
+
+        #[inline]
+        fn cons(&self) -> &AtomicU32 {
+            unsafe { &*self.consumer }
+        }
+
+        // ---- producer side (fill ring) ----
+
+        #[inline]
+        fn free_entries(&self) -> u32 {
+            let used = self
+                .prod()
+                .load(Ordering::Relaxed)
+                .wrapping_sub(self.cons().load(Ordering::Acquire));
+            self.size - used
+        }
+
+        /// Write `addr` at producer position + `k`; becomes visible on `submit`.
+        #[inline]
+        fn put_addr(&self, k: u32, addr: u64) {
+            let idx = self.prod().load(Ordering::Relaxed).wrapping_add(k) & (self.size - 1);
+            unsafe { (self.descs as *mut u64).add(idx as usize).write(addr) };
+        }
+
+        #[inline]
+        fn submit(&self, n: u32) {
+            let p = self.prod().load(Ordering::Relaxed);
+            self.prod().store(p.wrapping_add(n), Ordering::Release);
+        }
+
+        #[inline]
+        fn needs_wakeup(&self) -> bool {
+            unsafe { (*self.flags).load(Ordering::Relaxed) & XDP_RING_NEED_WAKEUP != 0 }
+        }
+
+        // ---- consumer side (RX ring) ----
+
+        #[inline]
+        fn available(&self) -> u32 {
+            self.prod()
+                .load(Ordering::Acquire)
+                .wrapping_sub(self.cons().load(Ordering::Relaxed))
+        }
+
+        #[inline]
+        fn desc(&self, k: u32) -> XdpDesc {
+            let idx = self.cons().load(Ordering::Relaxed).wrapping_add(k) & (self.size - 1);
+            unsafe { (self.descs as *const XdpDesc).add(idx as usize).read() }
+        }
+
+        #[inline]
+        fn release(&self, n: u32) {
+            let c = self.cons().load(Ordering::Relaxed);
+            self.cons().store(c.wrapping_add(n), Ordering::Release);
+        }
+    }
+
+    impl Drop for Ring {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.map, self.map_len) };
+        }
+    }
+
+    pub struct XskSocket {
+        fd: i32,
+        area: Arc<UmemArea>,
+        fill: Ring,
+        _comp: Ring,
+        rx: Ring,
+        zero_copy: bool,
+        _prog: Arc<XdpProg>,
+    }
+
+    impl XskSocket {
+        /// Create UMEM + rings, bind to `ifname`/`queue_id` and register in the interface's XSKMAP.
+        pub fn open(
+            ifname: &str,
+            queue_id: u32,
+            mode: XdpMode,
+            frames: u32,
+            udp_port: u16,
+        ) -> anyhow::Result<Self> {
+            let ifindex = unsafe { libc::if_nametoindex(CString::new(ifname)?.as_ptr()) };
+            if ifindex == 0 {
+                anyhow::bail!("if_nametoindex failed for {ifname}");
+            }
+            let fd = unsafe { libc::socket(AF_XDP, libc::SOCK_RAW | libc::SOCK_CLOEXEC, 0) };
+            if fd < 0 {
+                return Err(os_err("AF_XDP socket"));
+            }
+            // From here on, dropping `guard` closes the fd on error
+            struct FdGuard(i32);
+            impl Drop for FdGuard {
+                fn drop(&mut self) {
+                    if self.0 >= 0 {
+                        unsafe { libc::close(self.0) };
+                    }
+                }
+            }
+            let mut guard = FdGuard(fd);
+
+            let area = UmemArea::new(frames, FRAME_SIZE)?;
+            let reg = XdpUmemReg {
+                addr: area.base() as u64,
+                len: area.byte_len() as u64,
+                chunk_size: FRAME_SIZE,
+                headroom: 0,
+                flags: 0,
+                tx_metadata_len: 0,
+            };
+            setsockopt(fd, SOL_XDP, XDP_UMEM_REG, &reg, "XDP_UMEM_REG")?;
+            setsockopt(
+                fd,
+                SOL_XDP,
+                XDP_UMEM_FILL_RING,
+                &frames,
+                "XDP_UMEM_FILL_RING",
+            )?;
+            setsockopt(
+                fd,
+                SOL_XDP,
+                XDP_UMEM_COMPLETION_RING,
+                &COMPLETION_RING_SIZE,
+                "XDP_UMEM_COMPLETION_RING",
+            )?;
+            setsockopt(fd, SOL_XDP, XDP_RX_RING, &frames, "XDP_RX_RING")?;
+
+            let off: XdpMmapOffsets = getsockopt(fd, XDP_MMAP_OFFSETS, "XDP_MMAP_OFFSETS")?;
+            let fill = Ring::map(fd, &off.fr, frames, 8, XDP_UMEM_PGOFF_FILL_RING)?;
+            let comp = Ring::map(
+                fd,
+                &off.cr,
+                COMPLETION_RING_SIZE,
+                8,
+                XDP_UMEM_PGOFF_COMPLETION_RING,
+            )?;
+            let rx = Ring::map(fd, &off.rx, frames, size_of::<XdpDesc>(), XDP_PGOFF_RX_RING)?;
+
+            // Hand every frame to the kernel up front
+            for i in 0..frames {
+                fill.put_addr(i, i as u64 * FRAME_SIZE as u64);
+            }
+            fill.submit(frames);
+
+            let mut flags = XDP_USE_NEED_WAKEUP;
+            if mode == XdpMode::Skb {
+                // Generic XDP cannot do zero-copy
+                flags |= XDP_COPY;
+            }
+            let sa = SockaddrXdp {
+                sxdp_family: AF_XDP as u16,
+                sxdp_flags: flags,
+                sxdp_ifindex: ifindex,
+                sxdp_queue_id: queue_id,
+                sxdp_shared_umem_fd: 0,
+            };
+            let rc = unsafe {
+                libc::bind(
+                    fd,
+                    &sa as *const SockaddrXdp as *const libc::sockaddr,
+                    size_of::<SockaddrXdp>() as libc::socklen_t,
+                )
+            };
+            if rc != 0 {
+                return Err(os_err("bind AF_XDP"));
+            }
+            let opts: u32 = getsockopt(fd, XDP_OPTIONS, "XDP_OPTIONS").unwrap_or(0);
+
+            let prog = XdpProg::attach(ifindex, mode, udp_port).context("attach XDP program")?;
+            prog.register(queue_id, fd)?;
+
+            guard.0 = -1;
+            Ok(Self {
+                fd,
+                area,
+                fill,
+                _comp: comp,
+                rx,
+                zero_copy: opts & XDP_OPTIONS_ZEROCOPY != 0,
+                _prog: prog,
+            })
+        }
+
+        pub fn zero_copy(&self) -> bool {
+            self.zero_copy
+        }
+
+        /// Move frames released by downstream stages back into the fill ring.
+        #[inline]
+        fn refill(&self) {
+            let free = self.fill.free_entries();
+            let mut k = 0u32;
+            while k < free {
+                match self.area.pop_returned() {
+                    Some(addr) => {
+                        self.fill.put_addr(k, addr);
+                        k += 1;
+                    }
+                    None => break,
+                }
+            }
+            if k > 0 {
+                self.fill.submit(k);
+            }
+        }
+
+        #[inline]
+        fn kick(&self) {
+            unsafe {
+                libc::recvfrom(
+                    self.fd,
+                    null_mut(),
+                    0,
+                    libc::MSG_DONTWAIT,
+                    null_mut(),
+                    null_mut(),
+                );
+            }
+        }
+
+        pub fn run(
+            self,
+            seq: Arc<dyn SeqExtractor>,
+            chan_name: &str,
+            partition: &str,
+            q_out: Arc<SpscQueue<Pkt>>,
+            shutdown: Arc<BarrierFlag>,
+        ) -> anyhow::Result<()> {
+            let chan_id = if chan_name == "A" { 0 } else { 1 };
+            let base = self.area.base();
+            let mut idle_iters: u32 = 0;
+            while !shutdown.is_raised() {
+                self.refill();
+                let n = self.rx.available().min(RX_BATCH);
+                if n == 0 {
+                    if self.fill.needs_wakeup() {
+                        self.kick();
+                    }
+                    crate::util::adaptive_wait(&mut idle_iters, 64);
+                    continue;
+                }
+                idle_iters = 0;
+                let ts_nanos = now_nanos();
+                for k in 0..n {
+                    let d = self.rx.desc(k);
+                    let frame = unsafe {
+                        std::slice::from_raw_parts(base.add(d.addr as usize), d.len as usize)
+                    };
+                    let Some(payload) = parse_udp_payload(frame) else {
+                        self.area.give_back(d.addr);
+                        continue;
+                    };
+                    let Some((stream, sv)) = seq.extract_stream_seq(payload) else {
+                        self.area.give_back(d.addr);
+                        continue;
+                    };
+                    let span = seq.seq_span(payload);
+                    let seq_reset = seq.is_seq_reset(payload);
+                    let nbytes = payload.len();
+                    let data_off = d.addr as usize + (frame.len() - nbytes);
+                    let pkt = Pkt {
+                        // Safety: the descriptor was just consumed from the RX ring
+                        buf: unsafe { self.area.lease(d.addr, data_off, nbytes) },
+                        len: nbytes,
+                        seq: sv,
+                        stream,
+                        seq_span: span,
+                        seq_reset,
+                        ts_nanos,
+                        chan: chan_id,
+                        _ts_kind: TsKind::Sw,
+                        merge_emit_ns: 0,
+                    };
+                    // A rejected Pkt is dropped here, which returns its frame
+                    if q_out.push(pkt).is_err() {
+                        metrics::inc_rx_drop(partition, chan_name);
+                    } else {
+                        metrics::inc_rx(partition, chan_name, nbytes);
+                    }
+                }
+                self.rx.release(n);
+            }
+            if let Ok(st) = getsockopt::<XdpStatistics>(self.fd, XDP_STATISTICS, "XDP_STATISTICS") {
+                log::info!("AF_XDP {chan_name} statistics: {st:?}");
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for XskSocket {
+        fn drop(&mut self) {
+            // Closing the socket also removes it from the XSKMAP
+            unsafe { libc::close(self.fd) };
+        }
+    }
+
+    // -------------------- XDP program --------------------
+
+    const BPF_MAP_CREATE: libc::c_long = 0;
+    const BPF_MAP_UPDATE_ELEM: libc::c_long = 2;
+    const BPF_PROG_LOAD: libc::c_long = 5;
+    const BPF_MAP_TYPE_XSKMAP: u32 = 17;
+    const BPF_PROG_TYPE_XDP: u32 = 6;
+    const XSKMAP_ENTRIES: u32 = 64;
+
+    const XDP_FLAGS_UPDATE_IF_NOEXIST: u32 = 1;
+    const XDP_FLAGS_SKB_MODE: u32 = 1 << 1;
+    const XDP_FLAGS_DRV_MODE: u32 = 1 << 2;
+
+    #[repr(C)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct MapCreateAttr {
+        map_type: u32,
+        key_size: u32,
+        value_size: u32,
+        max_entries: u32,
+        map_flags: u32,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct MapUpdateAttr {
+        map_fd: u32,
+        _pad: u32,
+        key: u64,
+        value: u64,
+        flags: u64,
+    }
+
+    #[repr(C)]
+    #[allow(dead_code)] // kernel ABI layout
+    struct ProgLoadAttr {
+        prog_type: u32,
+        insn_cnt: u32,
+        insns: u64,
+        license: u64,
+        log_level: u32,
+        log_size: u32,
+        log_buf: u64,
+        kern_version: u32,
+        prog_flags: u32,
+    }
+
+    fn bpf<T>(cmd: libc::c_long, attr: &mut T) -> io::Result<i32> {
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_bpf,
+                cmd,
+                attr as *mut T as *mut libc::c_void,
+                size_of::<T>() as libc::c_uint,
+            )
+        };
+        if rc < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(rc as i32)
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub(super) struct BpfInsn {
+        code: u8,
+        regs: u8, // dst in low nibble, src in high nibble
+        off: i16,
+        imm: i32,
+    }
+
+    const fn insn(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> BpfInsn {
+        BpfInsn {
+            code,
+            regs: dst | (src << 4),
+            off,
+            imm,
+        }
+    }
+
+    // Opcodes used by the redirect program (linux/bpf.h)
+    const LDX_W: u8 = 0x61;
+    const LDX_H: u8 = 0x69;
+    const LDX_B: u8 = 0x71;
+    const LD_DW_IMM: u8 = 0x18;
+    const MOV64_IMM: u8 = 0xb7;
+    const MOV64_REG: u8 = 0xbf;
+    const ADD64_IMM: u8 = 0x07;
+    const JGT_REG: u8 = 0x2d;
+    const JNE_IMM: u8 = 0x55;
+    const CALL: u8 = 0x85;
+    const EXIT: u8 = 0x95;
+    const PSEUDO_MAP_FD: u8 = 1;
+    const FN_REDIRECT_MAP: i32 = 51;
+    const XDP_PASS: i32 = 2;
+
+    /// Redirect untagged IPv4/UDP (no IP options) to the XSK bound on the RX queue; pass the rest.
+    /// `udp_port` = 0 redirects any destination port. Needs kernel 5.3+ for the
+    /// `bpf_redirect_map` fallback action.
+    pub(super) fn redirect_program(map_fd: i32, udp_port: u16) -> Vec<BpfInsn> {
+        let ethertype_ipv4 = u16::from_ne_bytes(0x0800u16.to_be_bytes()) as i32;
+        let port = u16::from_ne_bytes(udp_port.to_be_bytes()) as i32;
+        let mut p = vec![
+            insn(LDX_W, 2, 1, 0, 0), // r2 = ctx->data
+            insn(LDX_W, 3, 1, 4, 0), // r3 = ctx->data_end
+            insn(MOV64_REG, 4, 2, 0, 0),
+        ];
+        let mut to_pass = Vec::new();
+        let mut check = |p: &mut Vec<BpfInsn>, load: BpfInsn, jump: BpfInsn| {
+            p.push(load);
+            to_pass.push(p.len());
+            p.push(jump);
+        };
+        // if data + 42 > data_end goto pass (bounds check for everything below)
+        check(
+            &mut p,
+            insn(ADD64_IMM, 4, 0, 0, 14 + 20 + 8),
+            insn(JGT_REG, 4, 3, 0, 0),
+        );
+        check(
+            &mut p,
+            insn(LDX_H, 5, 2, 12, 0),
+            insn(JNE_IMM, 5, 0, 0, ethertype_ipv4),
+        );
+        check(
+            &mut p,
+            insn(LDX_B, 5, 2, 14, 0),
+            insn(JNE_IMM, 5, 0, 0, 0x45),
+        );
+        check(&mut p, insn(LDX_B, 5, 2, 23, 0), insn(JNE_IMM, 5, 0, 0, 17));
+        if udp_port != 0 {
+            check(
+                &mut p,
+                insn(LDX_H, 5, 2, 36, 0),
+                insn(JNE_IMM, 5, 0, 0, port),
+            );
+        }
+        p.extend_from_slice(&[
+            insn(LDX_W, 2, 1, 16, 0), // r2 = ctx->rx_queue_index
+            insn(LD_DW_IMM, 1, PSEUDO_MAP_FD, 0, map_fd),
+            insn(0, 0, 0, 0, 0),
+            insn(MOV64_IMM, 3, 0, 0, XDP_PASS), // fallback when no socket on this queue
+            insn(CALL, 0, 0, 0, FN_REDIRECT_MAP),
+            insn(EXIT, 0, 0, 0, 0),
+        ]);
+        let pass = p.len();
+        p.push(insn(MOV64_IMM, 0, 0, 0, XDP_PASS));
+        p.push(insn(EXIT, 0, 0, 0, 0));
+        for i in to_pass {
+            p[i].off = (pass - i - 1) as i16;
+        }
+        p
+    }
+
+    /// XSKMAP + redirect program attached to one interface, shared by all its queues.
+    /// Detached when the last socket using it goes away.
+    pub struct XdpProg {
+        ifindex: u32,
+        map_fd: i32,
+        prog_fd: i32,
+        attach_flags: u32,
+    }
+
+    static ATTACHED: Lazy<Mutex<HashMap<u32, Weak<XdpProg>>>> =
+        Lazy::new(|| Mutex::new(HashMap::new()));
+
+    impl XdpProg {
+        fn attach(ifindex: u32, mode: XdpMode, udp_port: u16) -> anyhow::Result<Arc<Self>> {
+            let mut attached = ATTACHED.lock().unwrap();
+            if let Some(p) = attached.get(&ifindex).and_then(Weak::upgrade) {
+                return Ok(p);
+            }
+            let mut m = MapCreateAttr {
+                map_type: BPF_MAP_TYPE_XSKMAP,
+                key_size: 4,
+                value_size: 4,
+                max_entries: XSKMAP_ENTRIES,
+                map_flags: 0,
+            };
+            let map_fd = bpf(BPF_MAP_CREATE, &mut m).context("create XSKMAP")?;
+            let mut prog = XdpProg {
+                ifindex,
+                map_fd,
+                prog_fd: -1,
+                attach_flags: 0,
+            };
+
+            let insns = redirect_program(map_fd, udp_port);
+            let license = b"GPL\0";
+            let mut log_buf = vec![0u8; 64 * 1024];
+            let mut load = |log: bool| {
+                let mut a = ProgLoadAttr {
+                    prog_type: BPF_PROG_TYPE_XDP,
+                    insn_cnt: insns.len() as u32,
+                    insns: insns.as_ptr() as u64,
+                    license: license.as_ptr() as u64,
+                    log_level: log as u32,
+                    log_size: if log { log_buf.len() as u32 } else { 0 },
+                    log_buf: if log { log_buf.as_mut_ptr() as u64 } else { 0 },
+                    kern_version: 0,
+                    prog_flags: 0,
+                };
+                bpf(BPF_PROG_LOAD, &mut a)
+            };
+            prog.prog_fd = match load(false) {
+                Ok(fd) => fd,
+                Err(e) => {
+                    // Reload with the verifier log for a useful error
+                    let _ = load(true);
+                    let end = log_buf.iter().position(|&b| b == 0).unwrap_or(0);
+                    anyhow::bail!(
+                        "load XDP program: {e}; verifier: {}",
+                        String::from_utf8_lossy(&log_buf[..end])
+                    );
+                }
+            };
+
+            let modes: &[u32] = match mode {
+                XdpMode::Skb => &[XDP_FLAGS_SKB_MODE],
+                XdpMode::Drv => &[XDP_FLAGS_DRV_MODE],
+                XdpMode::Auto => &[XDP_FLAGS_DRV_MODE, XDP_FLAGS_SKB_MODE],
+                XdpMode::Off => anyhow::bail!("AF_XDP disabled (xdp_mode = \"off\")"),
+            };
+            let mut last_err = None;
+            for &m in modes {
+                let flags = XDP_FLAGS_UPDATE_IF_NOEXIST | m;
+                match netlink_set_xdp(ifindex, prog.prog_fd, flags) {
+                    Ok(()) => {
+                        prog.attach_flags = m;
+                        last_err = None;
+                        break;
+                    }
+                    Err(e) => last_err = Some(e),
+                }
+            }
+            if let Some(e) = last_err {
+                return Err(e).context("attach XDP program (is another one already loaded?)");
+            }
+
+            let prog = Arc::new(prog);
+            attached.insert(ifindex, Arc::downgrade(&prog));
+            Ok(prog)
+        }
+
+        fn register(&self, queue_id: u32, xsk_fd: i32) -> anyhow::Result<()> {
+            if queue_id >= XSKMAP_ENTRIES {
+                anyhow::bail!("queue id {queue_id} exceeds XSKMAP size {XSKMAP_ENTRIES}");
+            }
+            let key = queue_id;
+            let value = xsk_fd as u32;
+            let mut a = MapUpdateAttr {
+                map_fd: self.map_fd as u32,
+                _pad: 0,
+                key: &key as *const u32 as u64,
+                value: &value as *const u32 as u64,
+                flags: 0,
+            };
+            bpf(BPF_MAP_UPDATE_ELEM, &mut a).context("insert XSK into XSKMAP")?;
+            Ok(())
+        }
+    }
+
+    impl Drop for XdpProg {
+        fn drop(&mut self) {
+            if self.attach_flags != 0 {
+                if let Err(e) = netlink_set_xdp(self.ifindex, -1, self.attach_flags) {
+                    log::warn!("detach XDP program from ifindex {}: {e:#}", self.ifindex);
+                }
+            }
+            unsafe {
+                if self.prog_fd >= 0 {
+                    libc::close(self.prog_fd);
+                }
+                libc::close(self.map_fd);
+            }
+        }
+    }
+
+    // -------------------- rtnetlink attach --------------------
+
+    const RTM_SETLINK: u16 = 19;
+    const NLM_F_REQUEST: u16 = 1;
+    const NLM_F_ACK: u16 = 4;
+    const NLMSG_ERROR: u16 = 2;
+    const NLA_F_NESTED: u16 = 1 << 15;
+    const IFLA_XDP: u16 = 43;
+    const IFLA_XDP_FD: u16 = 1;
+    const IFLA_XDP_FLAGS: u16 = 3;
+
+    /// RTM_SETLINK with IFLA_XDP { FD, FLAGS }; `prog_fd` = -1 detaches.
+    fn netlink_set_xdp(ifindex: u32, prog_fd: i32, flags: u32) -> anyhow::Result<()> {
+        let mut msg: Vec<u8> = Vec::with_capacity(64);
+        // nlmsghdr: len (patched below), type, flags, seq, pid
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&RTM_SETLINK.to_ne_bytes());
+        msg.extend_from_slice(&(NLM_F_REQUEST | NLM_F_ACK).to_ne_bytes());
+        msg.extend_from_slice(&1u32.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        // ifinfomsg: family, pad, type, index, flags, change
+        msg.extend_from_slice(&[libc::AF_UNSPEC as u8, 0]);
+        msg.extend_from_slice(&0u16.to_ne_bytes());
+        msg.extend_from_slice(&(ifindex as i32).to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        // IFLA_XDP (nested) { IFLA_XDP_FD, IFLA_XDP_FLAGS }
+        msg.extend_from_slice(&(4u16 + 8 + 8).to_ne_bytes());
+        msg.extend_from_slice(&(IFLA_XDP | NLA_F_NESTED).to_ne_bytes());
+        msg.extend_from_slice(&8u16.to_ne_bytes());
+        msg.extend_from_slice(&IFLA_XDP_FD.to_ne_bytes());
+        msg.extend_from_slice(&prog_fd.to_ne_bytes());
+        msg.extend_from_slice(&8u16.to_ne_bytes());
+        msg.extend_from_slice(&IFLA_XDP_FLAGS.to_ne_bytes());
+        msg.extend_from_slice(&flags.to_ne_bytes());
+        let len = msg.len() as u32;
+        msg[0..4].copy_from_slice(&len.to_ne_bytes());
+
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(os_err("netlink socket"));
+        }
+        let res = (|| {
+            let mut sa: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+            sa.nl_family = libc::AF_NETLINK as u16;
+            let rc = unsafe {
+                libc::sendto(
+                    fd,
+                    msg.as_ptr() as *const libc::c_void,
+                    msg.len(),
+                    0,
+                    &sa as *const libc::sockaddr_nl as *const libc::sockaddr,
+                    size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+                )
+            };
+            if rc < 0 {
+                return Err(os_err("netlink send"));
+            }
+            let mut buf = [0u8; 4096];
+            let n = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if n < 20 {
+                return Err(os_err("netlink recv"));
+            }
+            let ty = u16::from_ne_bytes([buf[4], buf[5]]);
+            if ty != NLMSG_ERROR {
+                anyhow::bail!("unexpected netlink reply type {ty}");
+            }
+            let err = i32::from_ne_bytes([buf[16], buf[17], buf[18], buf[19]]);
+            if err != 0 {
+                return Err(io::Error::from_raw_os_error(-err).into());
+            }
+            Ok(())
+        })();
+        unsafe { libc::close(fd) };
+        res
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn redirect_program_jumps_land_on_pass() {
+            for port in [0u16, 5001] {
+                let p = redirect_program(7, port);
+                let pass = p.len() - 2;
+                assert_eq!(p[pass], insn(MOV64_IMM, 0, 0, 0, XDP_PASS));
+                assert_eq!(p[p.len() - 1].code, EXIT);
+                let jumps: Vec<usize> = (0..p.len())
+                    .filter(|&i| p[i].code == JGT_REG || p[i].code == JNE_IMM)
+                    .collect();
+                assert_eq!(jumps.len(), if port == 0 { 4 } else { 5 });
+                for i in jumps {
+                    assert_eq!(i + 1 + p[i].off as usize, pass);
+                }
+                let ld = p.iter().position(|i| i.code == LD_DW_IMM).unwrap();
+                assert_eq!((p[ld].regs >> 4, p[ld].imm), (PSEUDO_MAP_FD, 7));
+            }
+        }
+    }
+}
+
 #[cfg(target_os = "linux")]
 fn parse_udp_payload(frame: &[u8]) -> Option<&[u8]> {
+    parse_udp_payload_vlan(frame).map(|(p, _)| p)
+}
+
+/// Like `parse_udp_payload`, also returning the VLAN id of an in-frame 802.1Q/802.1ad tag.
+#[cfg(target_os = "linux")]
+fn parse_udp_payload_vlan(frame: &[u8]) -> Option<(&[u8], Option<u16>)> {
     if frame.len() < 14 {
         return None;
     }
@@ -280,10 +1457,12 @@ fn parse_udp_payload(frame: &[u8]) -> Option<&[u8]> {
     let ethertype = u16::from_be_bytes([frame[12], frame[13]]);
     off += 14;
     let mut et = ethertype;
+    let mut vlan = None;
     if et == 0x8100 || et == 0x88A8 {
         if frame.len() < off + 4 {
             return None;
         }
+        vlan = Some(u16::from_be_bytes([frame[off], frame[off + 1]]) & 0x0FFF);
         et = u16::from_be_bytes([frame[off + 2], frame[off + 3]]);
         off += 4;
     }
@@ -307,5 +1486,36 @@ fn parse_udp_payload(frame: &[u8]) -> Option<&[u8]> {
     if frame.len() < off {
         return None;
     }
-    Some(&frame[off..])
+    Some((&frame[off..], vlan))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn udp_frame(vlan: Option<u16>, payload: &[u8]) -> Vec<u8> {
+        let mut f = vec![0u8; 12];
+        if let Some(v) = vlan {
+            f.extend_from_slice(&[0x81, 0x00]);
+            f.extend_from_slice(&(0x2000 | v).to_be_bytes()); // PCP 1
+        }
+        f.extend_from_slice(&[0x08, 0x00]);
+        let mut ip = [0u8; 20];
+        ip[0] = 0x45;
+        ip[9] = 17;
+        f.extend_from_slice(&ip);
+        f.extend_from_slice(&[0u8; 8]);
+        f.extend_from_slice(payload);
+        f
+    }
+
+    #[test]
+    fn parse_udp_payload_reports_inline_vlan() {
+        let f = udp_frame(Some(42), b"hello");
+        assert_eq!(parse_udp_payload_vlan(&f), Some((&b"hello"[..], Some(42))));
+        let f = udp_frame(None, b"hi");
+        assert_eq!(parse_udp_payload_vlan(&f), Some((&b"hi"[..], None)));
+        assert_eq!(parse_udp_payload(&f), Some(&b"hi"[..]));
+        assert_eq!(parse_udp_payload_vlan(&f[..30]), None);
+    }
 }
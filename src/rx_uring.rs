@@ -0,0 +1,277 @@
+// src/rx_uring.rs
+// io_uring RX backend. One multishot RECVMSG stays armed per socket; the kernel picks a
+// PacketPool buffer from a provided-buffer ring for every datagram and writes the recvmsg
+// header, control messages and payload into it. The payload is forwarded in place, so a
+// single completion drain yields a batch of packets each with its kernel timestamp.
+
+use crate::pool::{PacketPool, Pkt};
+use crate::rx::RxConfig;
+use crate::spsc::SpscQueue;
+use std::net::UdpSocket;
+use std::sync::Arc;
+
+#[cfg(not(target_os = "linux"))]
+pub fn uring_rx_loop(
+    _chan_name: &str,
+    _sock: &UdpSocket,
+    _seq: Arc<dyn crate::parser::SeqExtractor>,
+    _q_out: Arc<SpscQueue<Pkt>>,
+    _pool: Arc<PacketPool>,
+    _shutdown: Arc<crate::util::BarrierFlag>,
+    _cfg: &RxConfig,
+) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("io_uring RX is only supported on Linux"))
+}
+
+#[cfg(target_os = "linux")]
+pub use linux::uring_rx_loop;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use crate::config::TimestampingMode;
+    use crate::metrics;
+    use crate::parser::SeqExtractor;
+    use crate::pool::{PktBuf, TsKind};
+    use crate::util::{now_nanos, BarrierFlag};
+    use anyhow::Context;
+    use bytes::{Buf, BytesMut};
+    use io_uring::types::{BufRingEntry, Fd, RecvMsgOut};
+    use io_uring::{cqueue, opcode, squeue, IoUring};
+    use log::debug;
+    use nix::libc;
+    use std::alloc::{self, Layout};
+    use std::mem::size_of;
+    use std::os::fd::AsRawFd;
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    const BUF_GROUP: u16 = 0;
+    const RING_ENTRIES: u16 = 1024;
+    const RECV_USER_DATA: u64 = 1;
+    /// Room for SCM_TIMESTAMPNS or SCM_TIMESTAMPING (timespec[3]) plus alignment
+    const CONTROL_LEN: usize = 128;
+    /// io_uring_recvmsg_out header written ahead of the control data
+    const OUT_HDR_LEN: usize = 16;
+
+    /// Provided-buffer ring whose slots hold PacketPool buffers owned by the kernel
+    /// until a completion names their buffer id.
+    struct BufRing {
+        base: *mut BufRingEntry,
+        layout: Layout,
+        tail: u16,
+        slots: Vec<Option<BytesMut>>,
+        buf_len: usize,
+    }
+
+    impl BufRing {
+        fn new(buf_len: usize) -> anyhow::Result<Self> {
+            let layout =
+                Layout::from_size_align(RING_ENTRIES as usize * size_of::<BufRingEntry>(), 4096)?;
+            let base = unsafe { alloc::alloc_zeroed(layout) } as *mut BufRingEntry;
+            if base.is_null() {
+                anyhow::bail!("buffer ring allocation failed");
+            }
+            Ok(Self {
+                base,
+                layout,
+                tail: 0,
+                slots: (0..RING_ENTRIES).map(|_| None).collect(),
+                buf_len,
+            })
+        }
+
+        /// Queue `buf` under id `bid`; visible to the kernel after `publish`.
+        fn provide(&mut self, bid: u16, mut buf: BytesMut) {
+            buf.truncate(0);
+            // Reclaims space consumed by a previous in-place payload split before allocating
+            buf.reserve(self.buf_len.saturating_sub(buf.len()));
+            // The kernel writes up to `buf_len` bytes at this address
+            assert!(buf.capacity() >= self.buf_len);
+            let e = unsafe { &mut *self.base.add((self.tail & (RING_ENTRIES - 1)) as usize) };
+            e.set_addr(buf.as_mut_ptr() as u64);
+            e.set_len(self.buf_len as u32);
+            e.set_bid(bid);
+            self.slots[bid as usize] = Some(buf);
+            self.tail = self.tail.wrapping_add(1);
+        }
+
+        fn publish(&self) {
+            unsafe {
+                let tail = BufRingEntry::tail(self.base) as *const AtomicU16;
+                (*tail).store(self.tail, Ordering::Release);
+            }
+        }
+
+        fn take(&mut self, bid: u16) -> Option<BytesMut> {
+            self.slots.get_mut(bid as usize).and_then(Option::take)
+        }
+    }
+
+    impl Drop for BufRing {
+        fn drop(&mut self) {
+            unsafe { alloc::dealloc(self.base as *mut u8, self.layout) }
+        }
+    }
+
+    pub fn uring_rx_loop(
+        chan_name: &str,
+        sock: &UdpSocket,
+        seq: Arc<dyn SeqExtractor>,
+        q_out: Arc<SpscQueue<Pkt>>,
+        pool: Arc<PacketPool>,
+        shutdown: Arc<BarrierFlag>,
+        cfg: &RxConfig,
+    ) -> anyhow::Result<()> {
+        let fd = sock.as_raw_fd();
+        let chan_id = cfg.line;
+        let ts_off = cfg
+            .ts_mode
+            .as_ref()
+            .map(|m| matches!(m, TimestampingMode::Off))
+            .unwrap_or(true);
+
+        let mut builder = IoUring::<squeue::Entry, cqueue::Entry>::builder();
+        // A CQ overflow ends the multishot op; size it for every provided buffer completing at once
+        builder.setup_cqsize(RING_ENTRIES as u32);
+        if let Some(idle_ms) = cfg.sqpoll_idle_ms {
+            builder.setup_sqpoll(idle_ms);
+        }
+        let mut ring = builder.build(64).context("io_uring setup")?;
+
+        // Template for every multishot completion: no source address, room for timestamps
+        let mut msghdr: libc::msghdr = unsafe { std::mem::zeroed() };
+        msghdr.msg_controllen = CONTROL_LEN as _;
+
+        let mut bufs = BufRing::new(OUT_HDR_LEN + CONTROL_LEN + pool.max_packet_size())?;
+        for bid in 0..RING_ENTRIES {
+            bufs.provide(bid, pool.get());
+        }
+        bufs.publish();
+        unsafe {
+            ring.submitter()
+                .register_buf_ring_with_flags(bufs.base as u64, RING_ENTRIES, BUF_GROUP, 0)
+                .context("register provided buffer ring (kernel 5.19+)")?;
+        }
+
+        let recv = opcode::RecvMsgMulti::new(Fd(fd), &msghdr, BUF_GROUP)
+            .build()
+            .user_data(RECV_USER_DATA);
+        let mut armed = false;
+        let mut dropped: u64 = 0;
+        let mut idle_iters: u32 = 0;
+
+        while !shutdown.is_raised() {
+            if !armed {
+                unsafe {
+                    ring.submission()
+                        .push(&recv)
+                        .map_err(|_| anyhow::anyhow!("io_uring submission queue full"))?;
+                }
+                armed = true;
+            }
+            // Non-waiting enter: submits the re-arm and runs completion task work
+            ring.submit().context("io_uring_enter")?;
+
+            let mut progressed = false;
+            let batch_now = if ts_off { now_nanos() } else { 0 };
+            let mut cq = ring.completion();
+            cq.sync();
+            for cqe in &mut cq {
+                if !cqueue::more(cqe.flags()) {
+                    armed = false;
+                }
+                let res = cqe.result();
+                if res < 0 {
+                    let err = std::io::Error::from_raw_os_error(-res);
+                    if -res == libc::ENOBUFS {
+                        // Every buffer is downstream; the kernel dropped the datagram
+                        metrics::inc_rx_drop(&cfg.partition, chan_name);
+                        continue;
+                    }
+                    return Err(anyhow::anyhow!("io_uring recvmsg error: {err}"));
+                }
+                let Some(bid) = cqueue::buffer_select(cqe.flags()) else {
+                    continue;
+                };
+                let Some(mut buf) = bufs.take(bid) else {
+                    continue;
+                };
+                progressed = true;
+                unsafe { buf.set_len(res as usize) };
+                let parsed = RecvMsgOut::parse(&buf, &msghdr).ok().and_then(|out| {
+                    if out.is_payload_truncated() {
+                        return None;
+                    }
+                    let payload = out.payload_data();
+                    let off = payload.as_ptr() as usize - buf.as_ptr() as usize;
+                    let ts = if ts_off {
+                        None
+                    } else {
+                        crate::rx::cmsg_timestamp(out.control_data())
+                    };
+                    Some((off, payload.len(), ts))
+                });
+                let Some((off, n, ts)) = parsed else {
+                    debug!("{chan_name}_rx: malformed or truncated io_uring completion");
+                    bufs.provide(bid, buf);
+                    continue;
+                };
+                buf.advance(off);
+                buf.truncate(n);
+                // Refill the slot right away so the kernel never runs dry under load
+                bufs.provide(bid, pool.get());
+
+                let Some((stream, sv)) = seq.extract_stream_seq(&buf) else {
+                    pool.put(buf);
+                    continue;
+                };
+                let (ts_nanos, kind) = ts
+                    .unwrap_or_else(|| (if ts_off { batch_now } else { now_nanos() }, TsKind::Sw));
+                let span = seq.seq_span(&buf);
+                let seq_reset = seq.is_seq_reset(&buf);
+                let pkt = Pkt {
+                    buf: PktBuf::Bytes(buf),
+                    len: n,
+                    seq: sv,
+                    stream,
+                    seq_span: span,
+                    seq_reset,
+                    ts_nanos,
+                    chan: chan_id,
+                    _ts_kind: kind,
+                    merge_emit_ns: 0,
+                };
+                if let Err(full) = q_out.push(pkt) {
+                    dropped += 1;
+                    metrics::inc_rx_drop(&cfg.partition, chan_name);
+                    if dropped % 10_000 == 1 {
+                        debug!("{}_rx: queue full, dropped={}", chan_name, dropped);
+                    }
+                    full.recycle(&pool);
+                } else {
+                    metrics::inc_rx(&cfg.partition, chan_name, n);
+                }
+            }
+            drop(cq);
+            bufs.publish();
+
+            if progressed {
+                idle_iters = 0;
+            } else {
+                crate::util::adaptive_wait(&mut idle_iters, cfg.spin_loops_per_yield);
+            }
+        }
+
+        // Tear down: cancel the multishot op before the buffers go away
+        let _ = unsafe {
+            ring.submission()
+                .push(&opcode::AsyncCancel::new(RECV_USER_DATA).build())
+        };
+        let _ = ring.submit_and_wait(1);
+        let _ = ring.submitter().unregister_buf_ring(BUF_GROUP);
+        for b in bufs.slots.iter_mut().filter_map(Option::take) {
+            pool.put(b);
+        }
+        Ok(())
+    }
+}
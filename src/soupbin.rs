@@ -0,0 +1,384 @@
+// src/soupbin.rs
+// SoupBinTCP client and Glimpse snapshot bootstrap for ITCH feeds.
+//
+// SoupBinTCP 4.0 frames are `[u16 length][type][payload]` (big-endian, the length covers
+// type and payload). The client logs in with 'L' (username 6, password 10, session 10,
+// sequence 20; alphanumerics right padded, the sequence left padded with spaces) and is
+// answered by 'A' (session, next sequence) or 'J' (reject reason). Then the server sends
+// sequenced ('S') and unsequenced ('U') data, heartbeats ('H'), debug text ('+') and
+// finally end of session ('Z'); the client sends unsequenced data ('U'), heartbeats ('R')
+// and logout ('O'). Each side heartbeats after a second of silence and drops a peer it
+// has not heard from in 15.
+//
+// Glimpse serves the current book as ITCH 5.0 messages, one per sequenced packet,
+// closed by End of Snapshot ('G' + 20 ASCII digits): the sequence at which the live
+// feed continues. The download is kept as raw messages; the decode stage runs them
+// through its own `Parser` (`src/glimpse.rs`), so the ITCH decoder keeps the order
+// state that later executes and deletes refer to.
+
+use crate::config::GlimpseCfg;
+use crate::orderbook::OrderBook;
+use crate::parser::{Event, Parser};
+use anyhow::Context;
+use hashbrown::HashSet;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+const PEER_TIMEOUT: Duration = Duration::from_secs(15);
+/// Glimpse End of Snapshot message type
+const END_OF_SNAPSHOT: u8 = b'G';
+
+/// One packet from the server; data borrows the client's read buffer.
+#[derive(Debug, PartialEq)]
+pub enum Packet<'a> {
+    Sequenced(&'a [u8]),
+    Unsequenced(&'a [u8]),
+    Heartbeat,
+    Debug(&'a [u8]),
+    EndOfSession,
+}
+
+pub struct Login<'a> {
+    pub username: &'a str,
+    pub password: &'a str,
+    /// Empty for the current session
+    pub session: &'a str,
+    /// First sequenced message wanted; 0 for none already sent
+    pub sequence: u64,
+}
+
+pub struct SoupClient {
+    stream: TcpStream,
+    buf: Vec<u8>,
+    /// Length of the frame handed out by the last `recv`
+    consumed: usize,
+    last_sent: Instant,
+    last_heard: Instant,
+    /// Session the server logged us into
+    pub session: String,
+    /// Sequence number of the next sequenced message
+    pub next_seq: u64,
+}
+
+impl SoupClient {
+    /// Connect and log in; fails on reject or when no answer arrives within `timeout`.
+    pub fn login(endpoint: &str, login: &Login, timeout: Duration) -> anyhow::Result<Self> {
+        let addr = endpoint
+            .to_socket_addrs()?
+            .next()
+            .with_context(|| format!("cannot resolve {endpoint}"))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(HEARTBEAT_INTERVAL.min(timeout)))?;
+        let now = Instant::now();
+        let mut c = Self {
+            stream,
+            buf: Vec::with_capacity(1 << 16),
+            consumed: 0,
+            last_sent: now,
+            last_heard: now,
+            session: String::new(),
+            next_seq: 0,
+        };
+
+        let mut p = Vec::with_capacity(46);
+        p.extend_from_slice(format!("{:<6}", login.username).as_bytes());
+        p.extend_from_slice(format!("{:<10}", login.password).as_bytes());
+        p.extend_from_slice(format!("{:<10}", login.session).as_bytes());
+        p.extend_from_slice(format!("{:>20}", login.sequence).as_bytes());
+        c.send(b'L', &p)?;
+
+        let deadline = now + timeout;
+        loop {
+            let len = c.next_frame(Some(deadline))?;
+            let (typ, body) = (c.buf[2], &c.buf[3..2 + len]);
+            match typ {
+                b'A' if body.len() >= 30 => {
+                    let seq = std::str::from_utf8(&body[10..30])?.trim();
+                    c.next_seq = seq
+                        .parse()
+                        .with_context(|| format!("bad login accepted sequence {seq:?}"))?;
+                    c.session = String::from_utf8_lossy(&body[..10]).trim().to_string();
+                    log::info!(
+                        "SoupBinTCP: logged into session {:?} at {endpoint}, next sequence {}",
+                        c.session,
+                        c.next_seq
+                    );
+                    return Ok(c);
+                }
+                b'J' => {
+                    let why = match body.first() {
+                        Some(b'A') => "not authorized",
+                        Some(b'S') => "session not available",
+                        _ => "unknown reason",
+                    };
+                    anyhow::bail!("SoupBinTCP login to {endpoint} rejected: {why}");
+                }
+                b'H' | b'+' => {}
+                t => anyhow::bail!("unexpected SoupBinTCP packet {:?} before login", t as char),
+            }
+        }
+    }
+
+    /// Next packet from the server; heartbeats are sent while waiting.
+    pub fn recv(&mut self) -> anyhow::Result<Packet<'_>> {
+        let len = self.next_frame(None)?;
+        let body = &self.buf[3..2 + len];
+        Ok(match self.buf[2] {
+            b'S' => {
+                self.next_seq += 1;
+                Packet::Sequenced(body)
+            }
+            b'U' => Packet::Unsequenced(body),
+            b'H' => Packet::Heartbeat,
+            b'+' => Packet::Debug(body),
+            b'Z' => Packet::EndOfSession,
+            t => anyhow::bail!("unexpected SoupBinTCP packet {:?}", t as char),
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn send_unsequenced(&mut self, msg: &[u8]) -> anyhow::Result<()> {
+        self.send(b'U', msg)
+    }
+
+    pub fn logout(mut self) -> anyhow::Result<()> {
+        self.send(b'O', &[])
+    }
+
+    fn send(&mut self, typ: u8, payload: &[u8]) -> anyhow::Result<()> {
+        let len = u16::try_from(payload.len() + 1).context("SoupBinTCP payload too long")?;
+        let mut p = Vec::with_capacity(payload.len() + 3);
+        p.extend_from_slice(&len.to_be_bytes());
+        p.push(typ);
+        p.extend_from_slice(payload);
+        self.stream.write_all(&p)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    /// Read until a whole frame sits at the front of `buf`; returns its length field.
+    fn next_frame(&mut self, deadline: Option<Instant>) -> anyhow::Result<usize> {
+        self.buf.drain(..self.consumed);
+        self.consumed = 0;
+        let mut chunk = [0u8; 8192];
+        loop {
+            if self.buf.len() >= 2 {
+                let len = u16::from_be_bytes([self.buf[0], self.buf[1]]) as usize;
+                if len == 0 {
+                    anyhow::bail!("empty SoupBinTCP packet");
+                }
+                if self.buf.len() >= 2 + len {
+                    self.consumed = 2 + len;
+                    self.last_heard = Instant::now();
+                    return Ok(len);
+                }
+            }
+            let now = Instant::now();
+            if deadline.is_some_and(|d| now >= d) {
+                anyhow::bail!("SoupBinTCP: timed out waiting for the server");
+            }
+            if now.duration_since(self.last_heard) >= PEER_TIMEOUT {
+                anyhow::bail!("SoupBinTCP: server silent for {PEER_TIMEOUT:?}");
+            }
+            if now.duration_since(self.last_sent) >= HEARTBEAT_INTERVAL {
+                self.send(b'R', &[])?;
+            }
+            match self.stream.read(&mut chunk) {
+                Ok(0) => anyhow::bail!("SoupBinTCP: server closed the connection"),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Messages downloaded from Glimpse and the live sequence they are current up to.
+pub struct GlimpseSnapshot {
+    /// `[u16 length][message]` back to back, as in a MoldUDP64 packet
+    pub data: Vec<u8>,
+    /// First live sequence not covered by the snapshot
+    pub next_seq: u64,
+    pub messages: u64,
+}
+
+impl GlimpseSnapshot {
+    /// Build the book from the snapshot through `parser`, which must be the one that
+    /// goes on decoding the live feed. Instruments with orders are added to `instrs`.
+    pub fn apply(&self, parser: &Parser, book: &mut OrderBook, instrs: &mut HashSet<u32>) {
+        let mut events: Vec<Event> = Vec::with_capacity(parser.max_messages_per_packet);
+        let mut off = 0;
+        while off + 2 <= self.data.len() {
+            let end = off + 2 + u16::from_be_bytes([self.data[off], self.data[off + 1]]) as usize;
+            events.clear();
+            parser.decode_messages_into(&self.data[off..end], &mut events);
+            for ev in &events {
+                book.apply(ev);
+                if let Event::Add { instr, .. } = *ev {
+                    instrs.insert(instr);
+                }
+            }
+            off = end;
+        }
+    }
+}
+
+/// Download the Glimpse snapshot.
+pub fn glimpse_download(cfg: &GlimpseCfg) -> anyhow::Result<GlimpseSnapshot> {
+    let deadline = Instant::now() + Duration::from_millis(cfg.timeout_ms);
+    let mut client = SoupClient::login(
+        &cfg.endpoint,
+        &Login {
+            username: &cfg.username,
+            password: &cfg.password,
+            session: &cfg.session,
+            sequence: 1,
+        },
+        Duration::from_millis(cfg.connect_timeout_ms),
+    )?;
+    let mut data = Vec::with_capacity(1 << 20);
+    let mut messages = 0u64;
+    loop {
+        if Instant::now() >= deadline {
+            anyhow::bail!(
+                "Glimpse: no End of Snapshot within {}ms ({messages} messages)",
+                cfg.timeout_ms
+            );
+        }
+        match client.recv()? {
+            Packet::Sequenced(msg) if msg.first() == Some(&END_OF_SNAPSHOT) => {
+                let seq = msg
+                    .get(1..21)
+                    .and_then(|s| std::str::from_utf8(s).ok())
+                    .map(str::trim)
+                    .and_then(|s| s.parse().ok())
+                    .context("Glimpse: malformed End of Snapshot")?;
+                let _ = client.logout();
+                return Ok(GlimpseSnapshot {
+                    data,
+                    next_seq: seq,
+                    messages,
+                });
+            }
+            Packet::Sequenced(msg) => {
+                // The decoder takes ITCH messages length prefixed, as in MoldUDP64
+                data.extend_from_slice(&(msg.len() as u16).to_be_bytes());
+                data.extend_from_slice(msg);
+                messages += 1;
+            }
+            Packet::Debug(text) => {
+                log::debug!("Glimpse: {}", String::from_utf8_lossy(text));
+            }
+            Packet::Unsequenced(_) | Packet::Heartbeat => {}
+            Packet::EndOfSession => anyhow::bail!("Glimpse: session ended before End of Snapshot"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Endian, ParserKind};
+    use crate::parser::{build_parser, SeqCfg};
+    use std::net::TcpListener;
+
+    fn frame(typ: u8, payload: &[u8]) -> Vec<u8> {
+        let mut p = ((payload.len() + 1) as u16).to_be_bytes().to_vec();
+        p.push(typ);
+        p.extend_from_slice(payload);
+        p
+    }
+
+    fn add_order(order: u64, side: u8, qty: u32, px: u32) -> Vec<u8> {
+        let mut m = vec![b'A'];
+        m.extend_from_slice(&7u16.to_be_bytes()); // locate
+        m.extend_from_slice(&0u16.to_be_bytes()); // tracking
+        m.extend_from_slice(&[0; 6]); // timestamp
+        m.extend_from_slice(&order.to_be_bytes());
+        m.push(side);
+        m.extend_from_slice(&qty.to_be_bytes());
+        m.extend_from_slice(b"AAPL    ");
+        m.extend_from_slice(&px.to_be_bytes());
+        m
+    }
+
+    #[test]
+    fn bootstraps_book_and_sequence_from_glimpse() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut login = [0u8; 49];
+            s.read_exact(&mut login).unwrap();
+            assert_eq!(&login[..3], &[0, 47, b'L']);
+            assert_eq!(&login[3..19], b"USER01secret    ");
+            assert_eq!(&login[29..49], b"                   1");
+            let mut accept = b"GLMPS1    ".to_vec();
+            accept.extend_from_slice(format!("{:>20}", 1).as_bytes());
+            s.write_all(&frame(b'A', &accept)).unwrap();
+            s.write_all(&frame(b'H', &[])).unwrap();
+            s.write_all(&frame(b'S', &add_order(1, b'B', 100, 1_500_000)))
+                .unwrap();
+            s.write_all(&frame(b'+', b"halfway")).unwrap();
+            s.write_all(&frame(b'S', &add_order(2, b'S', 50, 1_510_000)))
+                .unwrap();
+            let mut end = vec![END_OF_SNAPSHOT];
+            end.extend_from_slice(format!("{:>20}", 12_345).as_bytes());
+            s.write_all(&frame(b'S', &end)).unwrap();
+            let mut logout = [0u8; 3];
+            s.read_exact(&mut logout).unwrap();
+            assert_eq!(logout, [0, 1, b'O']);
+        });
+
+        let parser = build_parser(
+            ParserKind::Itch50,
+            SeqCfg {
+                offset: 10,
+                length: 8,
+                endian: Endian::Be,
+                unit: None,
+                stream: None,
+                moldudp64: false,
+            },
+            16,
+            0,
+        )
+        .unwrap();
+        let cfg = GlimpseCfg {
+            endpoint: addr.to_string(),
+            username: "USER01".into(),
+            password: "secret".into(),
+            session: String::new(),
+            partition: None,
+            connect_timeout_ms: 1000,
+            timeout_ms: 5000,
+            max_buffered_packets: 16,
+        };
+        let snap = glimpse_download(&cfg).unwrap();
+        server.join().unwrap();
+        assert_eq!(snap.next_seq, 12_345);
+        assert_eq!(snap.messages, 2);
+        let mut book = OrderBook::new(10);
+        let mut instrs = HashSet::new();
+        snap.apply(&parser, &mut book, &mut instrs);
+        assert_eq!(book.order_count(), 2);
+        assert_eq!(instrs.into_iter().collect::<Vec<_>>(), vec![7]);
+
+        // The live feed deletes a snapshot order: the parser still knows it
+        let mut del = vec![0, 19, b'D'];
+        del.extend_from_slice(&7u16.to_be_bytes());
+        del.extend_from_slice(&[0; 8]);
+        del.extend_from_slice(&1u64.to_be_bytes());
+        let mut events = Vec::new();
+        parser.decode_into(&del, &mut events);
+        events.iter().for_each(|e| book.apply(e));
+        assert_eq!(book.order_count(), 1);
+    }
+}
@@ -66,6 +66,81 @@ pub fn now_nanos() -> u64 {
     START.elapsed().as_nanos() as u64
 }
 
+/// Wall-clock ns since Unix epoch (unlike `now_nanos`, comparable to venue timestamps).
+#[inline]
+pub fn now_realtime_nanos() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+const DAY_NS: u64 = 86_400 * 1_000_000_000;
+
+/// Unix epoch ns of the most recent midnight in a venue whose local time is
+/// UTC + `utc_offset_s`.
+pub fn session_midnight_ns(utc_offset_s: i32) -> u64 {
+    session_midnight_at(utc_offset_s, now_realtime_nanos())
+}
+
+/// Venue midnight at or before `now_ns` (Unix epoch ns).
+pub fn session_midnight_at(utc_offset_s: i32, now_ns: u64) -> u64 {
+    let offset_ns = utc_offset_s as i64 * 1_000_000_000;
+    let local = now_ns as i64 + offset_ns;
+    (local - local.rem_euclid(DAY_NS as i64) - offset_ns).max(0) as u64
+}
+
+/// Anchors "ns since midnight" protocol timestamps (ITCH, PITCH) at the venue's
+/// midnight, following the wall clock from one session day to the next.
+#[derive(Clone, Copy, Default)]
+pub struct SessionClock {
+    utc_offset_s: i32,
+    midnight_ns: u64,
+    now_ns: u64,
+}
+
+impl SessionClock {
+    pub fn new(utc_offset_s: i32) -> Self {
+        let mut c = Self {
+            utc_offset_s,
+            ..Self::default()
+        };
+        c.tick();
+        c
+    }
+
+    /// Read the wall clock; once per packet.
+    #[inline]
+    pub fn tick(&mut self) {
+        self.tick_at(now_realtime_nanos());
+    }
+
+    #[inline]
+    pub fn tick_at(&mut self, now_ns: u64) {
+        self.now_ns = now_ns;
+        if now_ns >= self.midnight_ns + DAY_NS {
+            self.midnight_ns = session_midnight_at(self.utc_offset_s, now_ns);
+        }
+    }
+
+    /// Unix epoch ns of `since_midnight_ns`. A message stamped just before midnight
+    /// and decoded just after it stays on its own day.
+    #[inline]
+    pub fn at(&self, since_midnight_ns: u64) -> u64 {
+        let ts = self.midnight_ns + since_midnight_ns;
+        if ts > self.now_ns + DAY_NS / 2 {
+            ts.saturating_sub(DAY_NS)
+        } else {
+            ts
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn midnight_ns(&self) -> u64 {
+        self.midnight_ns
+    }
+}
+
 #[inline]
 pub fn lock_all_memory_if(cfg: bool) {
     if !cfg {}